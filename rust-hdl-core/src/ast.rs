@@ -1,6 +1,9 @@
 use crate::bits::Bits;
+use crate::fixed::{max, Fixed, FixedPoint, Overflow, Rounding, UFixed};
 use crate::signed::Signed;
-use num_bigint::{BigInt, Sign};
+use crate::synth::Synth;
+use crate::type_descriptor::{TypeDescriptor, TypeKind};
+use num_bigint::{BigInt, BigUint, Sign};
 use std::fmt::{Display, Formatter, LowerHex};
 
 /// The BlackBox struct provides a way to wrap a blackbox,
@@ -230,12 +233,11 @@ use std::fmt::{Display, Formatter, LowerHex};
 /// is called so it can refer to it in the generated code.
 ///
 /// In general RustHDL tries to avoid contention between modules with the same
-/// name by automatically namespacing them.  That means that if you have a
-/// module that is used in two different places in your code, it will get
-/// two different names.  This is because of the parametric nature of the
-/// generated code.  RustHDL does not assume (or know) that your two modules
-/// will generate identical Verilog.  So it assumes they will be different
-/// and creates two different named instances.
+/// name by automatically namespacing them.  The module for a block is named after
+/// the path to the block (e.g., `top$counter`).  Blocks that have the same structure
+/// (up to the values of constants and the widths of signals, which become parameters
+/// of the module) share a single module, which is named after the type of the blocks.
+/// Blocks of the same type that cannot share a module get different names.
 ///
 /// To see how that works, let's create a minimum example.  For test, we will
 /// use a single bit inverter.
@@ -284,10 +286,9 @@ use std::fmt::{Display, Formatter, LowerHex};
 /// x.connect_all();
 /// let v = generate_verilog(&x);
 /// // If you examine the generated code, you will see it contains
-/// // two instances of modules, one named `top$knot_1` and the
-/// assert!(v.contains("top$knot_1 knot_1"));
-/// // and the second is `top$knot_2`.
-/// assert!(v.contains("top$knot_2 knot_2"));
+/// // two instances of the module named `Inverter`.
+/// assert!(v.contains("Inverter knot_1"));
+/// assert!(v.contains("Inverter knot_2"));
 /// ```
 /// The problem arises when you use a [BlackBox] Verilog declaration.
 /// In particular, RustHDL does not wrap your declaration (the Verilog is
@@ -297,7 +298,11 @@ use std::fmt::{Display, Formatter, LowerHex};
 /// In that case, it is up to you to rename the different IP cores so that
 /// they do not conflict.  A better way around this is to use the [Wrapper]
 /// variant, since that is easier to use in most cases.
-#[derive(Debug, Clone)]
+///
+/// If you have the Verilog for the module, the `black_box` attribute can write the
+/// struct, the `connect` method and the [BlackBox] (or [Wrapper]) for you, using the
+/// ports and parameters declared in the module header.
+#[derive(Debug, Clone, PartialEq)]
 pub struct BlackBox {
     /// The Verilog code to create the black box in your firmware
     pub code: String,
@@ -551,7 +556,7 @@ pub struct BlackBox {
 /// # Ok::<(), SynthError>(())
 /// ```
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Wrapper {
     /// The Verilog code to instantiate the black box core, and connect
     /// its inputs to the argument of the current LogicBlock kernel.
@@ -563,7 +568,7 @@ pub struct Wrapper {
 /// The [Verilog] type is used to represent the Verilog translation of a
 /// RustHDL kernel.  You will only need it if implementing blackbox cores
 /// or wrapping external Verilog code.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Verilog {
     /// Use [Empty] when you do not want a module represented in Verilog at all
     Empty,
@@ -583,11 +588,25 @@ impl Default for Verilog {
     }
 }
 
+/// The hardware description languages that RustHDL can generate.  See
+/// [generate_hdl](crate::module_defines::generate_hdl).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum HDLLanguage {
+    /// Verilog-2001 (the same output as [generate_verilog](crate::module_defines::generate_verilog))
+    #[default]
+    Verilog,
+    /// SystemVerilog, with `always_comb`/`always_ff` blocks and `typedef`s for
+    /// enums and structs
+    SystemVerilog,
+    /// VHDL-2008
+    VHDL,
+}
+
 #[doc(hidden)]
 pub type VerilogBlock = Vec<VerilogStatement>;
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerilogStatement {
     Assignment(VerilogExpression, VerilogExpression),
     SliceAssignment {
@@ -596,16 +615,61 @@ pub enum VerilogStatement {
         offset: VerilogExpression,
         replacement: VerilogExpression,
     },
+    ElementAssignment(VerilogElement, VerilogExpression),
     If(VerilogConditional),
     Match(VerilogMatch),
     Loop(VerilogLoop),
     Comment(String),
     Link(Vec<VerilogLink>),
     Macro(VerilogBlock),
+    Property(VerilogProperty),
+    Let(VerilogLet),
+    Source(SourceLocation),
+}
+
+/// The line of Rust source that the statements of an HDL kernel come from.  A
+/// [VerilogStatement::Source] marks the statements that follow it (up to the next one).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    /// The source file (as given by `file!()`)
+    pub file: String,
+    /// The line in the source file (starting at 1)
+    pub line: u32,
+}
+
+impl SourceLocation {
+    pub fn new(file: &str, line: u32) -> Self {
+        Self {
+            file: file.into(),
+            line,
+        }
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerilogPropertyKind {
+    Assert,
+    Assume,
+    Cover,
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerilogProperty {
+    pub kind: VerilogPropertyKind,
+    pub test: VerilogExpression,
+    pub text: String,
+}
+
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerilogLink {
     Forward(VerilogLinkDetails),
     Backward(VerilogLinkDetails),
@@ -613,7 +677,7 @@ pub enum VerilogLink {
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VerilogLinkDetails {
     pub my_name: String,
     pub owner_name: String,
@@ -621,23 +685,88 @@ pub struct VerilogLinkDetails {
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VerilogIndexAssignment {
     pub target: VerilogExpression,
     pub index: VerilogExpression,
     pub value: VerilogExpression,
 }
 
+/// An element of an array-valued signal (e.g., `x.val()[i][j]`).  Each index picks an
+/// element (of the given width in bits) out of the array picked by the indices before it.
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerilogElement {
+    pub array: VerilogExpression,
+    pub indices: Vec<(VerilogExpression, usize)>,
+}
+
+impl VerilogElement {
+    pub fn width(&self) -> usize {
+        self.indices.last().map(|x| x.1).unwrap_or_default()
+    }
+    /// The offset (in bits) of the element in the packed array, which holds element 0
+    /// in the low bits
+    pub fn offset(&self) -> VerilogExpression {
+        self.indices
+            .iter()
+            .map(|(index, width)| {
+                VerilogExpression::Binary(
+                    Box::new(VerilogExpression::Paren(Box::new(index.clone()))),
+                    VerilogOp::Mul,
+                    Box::new(VerilogExpression::Literal((*width as u32).into())),
+                )
+            })
+            .reduce(|offset, x| {
+                VerilogExpression::Binary(Box::new(offset), VerilogOp::Add, Box::new(x))
+            })
+            .unwrap_or(VerilogExpression::Literal(0_u32.into()))
+    }
+    /// The element as a slice of the packed array
+    pub fn packed(&self) -> VerilogExpression {
+        VerilogExpression::Slice(
+            Box::new(self.array.clone()),
+            self.width(),
+            Box::new(self.offset()),
+        )
+    }
+}
+
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VerilogConditional {
     pub test: VerilogExpression,
     pub then: VerilogBlock,
     pub otherwise: VerilogBlockOrConditional,
 }
 
+/// A `let` binding in an HDL kernel.  These are lowered to local (automatically named)
+/// signals in the generated code, with the width and signedness of the bound type.
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerilogLet {
+    pub name: String,
+    pub width: usize,
+    pub signed: bool,
+    pub descriptor: TypeDescriptor,
+    pub value: VerilogExpression,
+}
+
+impl VerilogLet {
+    pub fn new<T: Synth>(_x: &T, name: &str, value: VerilogExpression) -> Self {
+        let descriptor = T::descriptor();
+        Self {
+            name: name.into(),
+            width: T::BITS,
+            signed: descriptor.kind.is_signed(),
+            descriptor,
+            value,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VerilogLoop {
     pub index: String,
     pub from: VerilogLiteral,
@@ -646,7 +775,7 @@ pub struct VerilogLoop {
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerilogBlockOrConditional {
     Block(VerilogBlock),
     Conditional(Box<VerilogStatement>),
@@ -654,27 +783,51 @@ pub enum VerilogBlockOrConditional {
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VerilogMatch {
     pub test: VerilogExpression,
     pub cases: Vec<VerilogCase>,
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VerilogCase {
     pub condition: String,
     pub block: VerilogBlock,
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VerilogLiteral {
     val: BigInt,
     bits: usize,
 }
 
 impl VerilogLiteral {
+    // A literal holding the two's complement form of a (possibly negative) value
+    fn twos_complement(val: BigInt, bits: usize) -> Self {
+        let val = if val.sign() == Sign::Minus {
+            val + (BigInt::from(1) << bits)
+        } else {
+            val
+        };
+        VerilogLiteral { val, bits }
+    }
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+    /// Returns the (two's complement) value of the literal as a set of
+    /// 32 bit words, least significant word first.
+    pub fn to_u32_words(&self) -> Vec<u32> {
+        let val = if self.val.sign() == Sign::Minus {
+            &self.val + (BigInt::from(1) << self.bits)
+        } else {
+            self.val.clone()
+        };
+        let mut words = val.to_u32_digits().1;
+        words.resize(self.bits.div_ceil(32), 0);
+        words
+    }
     pub fn as_usize(&self) -> usize {
         let m = self.val.to_u32_digits();
         assert!(m.0 != Sign::Minus);
@@ -729,6 +882,20 @@ impl<const N: usize> From<Bits<N>> for VerilogLiteral {
     }
 }
 
+impl<T: Synth, const N: usize> From<[T; N]> for VerilogLiteral {
+    fn from(x: [T; N]) -> Self {
+        let mut z = BigInt::default();
+        for element in x.iter().rev() {
+            let bits = BigUint::from_slice(&element.verilog().to_u32_words());
+            z = (z << T::BITS) | BigInt::from(bits);
+        }
+        VerilogLiteral {
+            val: z,
+            bits: N * T::BITS,
+        }
+    }
+}
+
 impl<const N: usize> From<Signed<N>> for VerilogLiteral {
     fn from(x: Signed<N>) -> Self {
         VerilogLiteral {
@@ -738,6 +905,19 @@ impl<const N: usize> From<Signed<N>> for VerilogLiteral {
     }
 }
 
+macro_rules! define_literal_from_fixed {
+    ($name: ident) => {
+        impl<const I: usize, const F: usize> From<$name<I, F>> for VerilogLiteral {
+            fn from(x: $name<I, F>) -> Self {
+                VerilogLiteral::twos_complement(x.raw().into(), I + F)
+            }
+        }
+    };
+}
+
+define_literal_from_fixed!(Fixed);
+define_literal_from_fixed!(UFixed);
+
 impl Display for VerilogLiteral {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let bits = self.bits;
@@ -763,7 +943,7 @@ impl LowerHex for VerilogLiteral {
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerilogExpression {
     Signal(String),
     Literal(VerilogLiteral),
@@ -775,15 +955,408 @@ pub enum VerilogExpression {
     Unary(VerilogOpUnary, Box<VerilogExpression>),
     Index(Box<VerilogExpression>, Box<VerilogExpression>),
     Slice(Box<VerilogExpression>, usize, Box<VerilogExpression>),
+    Element(Box<VerilogElement>),
     IndexReplace(
         Box<VerilogExpression>,
         Box<VerilogExpression>,
         Box<VerilogExpression>,
     ),
+    Call(VerilogCall),
+}
+
+impl VerilogExpression {
+    /// The expression tested by a `match` on a value of type `T`.  A tagged union is
+    /// matched on its tag (which is held in the low bits).  Anything else is matched
+    /// as is.
+    pub fn match_tag<T: Synth>(_x: &T, test: VerilogExpression) -> VerilogExpression {
+        match T::TAG_BITS {
+            Some(bits) => VerilogExpression::Slice(
+                Box::new(test),
+                bits,
+                Box::new(VerilogExpression::Literal(0_u32.into())),
+            ),
+            None => test,
+        }
+    }
+}
+
+/// The width of the elements of an array.  The array is never computed - the closure
+/// is only there so that the types can be inferred from an expression in an HDL kernel.
+#[doc(hidden)]
+pub fn array_element_bits<T: Synth, const N: usize>(_x: impl FnOnce() -> [T; N]) -> usize {
+    T::BITS
+}
+
+/// A call to a function defined with `#[hdl_function]`.  The definition of the function
+/// travels with the call, so that it can be emitted into the module that calls it.
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerilogCall {
+    pub function: Box<VerilogFunction>,
+    pub args: Vec<VerilogExpression>,
+}
+
+impl VerilogCall {
+    /// A call with arguments given by name (e.g., the fields of a struct literal),
+    /// which are put in the order the function expects.
+    pub fn named(function: VerilogFunction, mut args: Vec<(&str, VerilogExpression)>) -> Self {
+        let args = function
+            .args
+            .iter()
+            .map(|arg| {
+                let ndx = args
+                    .iter()
+                    .position(|(name, _)| *name == arg.name)
+                    .unwrap_or_else(|| panic!("No value given for argument {}", arg.name));
+                args.remove(ndx).1
+            })
+            .collect();
+        Self {
+            function: Box::new(function),
+            args,
+        }
+    }
+}
+
+/// A pure (combinatorial) function, lowered to a Verilog `function`.  The body assigns
+/// the result to the name of the function.
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerilogFunction {
+    pub name: String,
+    pub width: usize,
+    pub signed: bool,
+    pub args: Vec<VerilogFunctionArg>,
+    pub block: VerilogBlock,
+}
+
+impl VerilogFunction {
+    pub fn new<T: Synth>(name: &str, args: Vec<VerilogFunctionArg>, block: VerilogBlock) -> Self {
+        Self {
+            name: name.into(),
+            width: T::BITS,
+            signed: T::descriptor().kind.is_signed(),
+            args,
+            block,
+        }
+    }
+    /// The constructor for a variant of a tagged union.  The tag is placed in the
+    /// low bits, and each argument is shifted up to the given offset.
+    pub fn variant<T: Synth>(
+        name: &str,
+        tag: usize,
+        args: Vec<(VerilogFunctionArg, usize)>,
+    ) -> Self {
+        let value = args.iter().fold(
+            VerilogExpression::Literal(VerilogLiteral {
+                val: tag.into(),
+                bits: T::BITS,
+            }),
+            |value, (arg, offset)| {
+                let field = VerilogExpression::Cast(
+                    Box::new(VerilogExpression::Signal(arg.name.clone())),
+                    T::BITS,
+                );
+                let field = VerilogExpression::Binary(
+                    Box::new(field),
+                    VerilogOp::Shl,
+                    Box::new(VerilogExpression::Literal((*offset as u32).into())),
+                );
+                VerilogExpression::Binary(Box::new(value), VerilogOp::BitOr, Box::new(field))
+            },
+        );
+        Self {
+            name: name.into(),
+            width: T::BITS,
+            signed: false,
+            args: args.into_iter().map(|(arg, _)| arg).collect(),
+            block: vec![VerilogStatement::Assignment(
+                VerilogExpression::Signal(name.into()),
+                value,
+            )],
+        }
+    }
+    /// The conversion of a fixed point value of type `T` to the format of `S` (as done by
+    /// [Fixed::round] and friends).  The value is sign extended into a working register
+    /// that is wide enough for both formats, and the dropped fraction bits are then sliced
+    /// off, which rounds towards -infinity (like an arithmetic shift does).
+    pub fn fixed_convert<T: FixedPoint, S: FixedPoint>(
+        _from: impl FnOnce() -> T,
+        _to: impl FnOnce() -> S,
+        rounding: Rounding,
+        overflow: Overflow,
+    ) -> Self {
+        let signed = T::SIGNED;
+        let name = format!(
+            "fixed_{}{}_{}_to_{}_{}_{}{}",
+            if signed { "s" } else { "u" },
+            T::INTEGER,
+            T::FRACTION,
+            S::INTEGER,
+            S::FRACTION,
+            match rounding {
+                Rounding::Truncate => "trunc",
+                Rounding::Nearest => "round",
+            },
+            match overflow {
+                Overflow::Wrap => "",
+                Overflow::Saturate => "_sat",
+            }
+        );
+        let x = VerilogFunctionArg {
+            name: "x".into(),
+            width: T::BITS,
+            signed,
+        };
+        // One extra bit, so that rounding up cannot overflow
+        let wide = max(T::INTEGER, S::INTEGER) + max(T::FRACTION, S::FRACTION) + 1;
+        let mut block = vec![fixed_let("wide", wide, signed, signal(&x.name))];
+        let aligned = if S::FRACTION >= T::FRACTION {
+            fixed_let(
+                "aligned",
+                wide,
+                signed,
+                shift_left(signal("wide"), S::FRACTION - T::FRACTION),
+            )
+        } else {
+            let shift = T::FRACTION - S::FRACTION;
+            let source = if rounding == Rounding::Nearest {
+                let half = VerilogLiteral::twos_complement(BigInt::from(1) << (shift - 1), wide);
+                block.push(fixed_let(
+                    "rounded",
+                    wide,
+                    signed,
+                    VerilogExpression::Binary(
+                        Box::new(signal("wide")),
+                        VerilogOp::Add,
+                        Box::new(VerilogExpression::Literal(half)),
+                    ),
+                ));
+                "rounded"
+            } else {
+                "wide"
+            };
+            fixed_let(
+                "aligned",
+                wide - shift,
+                signed,
+                VerilogExpression::Slice(
+                    Box::new(signal(source)),
+                    wide - shift,
+                    Box::new(VerilogExpression::Literal((shift as u32).into())),
+                ),
+            )
+        };
+        let aligned_width = match &aligned {
+            VerilogStatement::Let(x) => x.width,
+            _ => unreachable!(),
+        };
+        block.push(aligned);
+        let result =
+            |value: VerilogExpression| vec![VerilogStatement::Assignment(signal(&name), value)];
+        let wrapped = result(VerilogExpression::Slice(
+            Box::new(signal("aligned")),
+            S::BITS,
+            Box::new(VerilogExpression::Literal(0_u32.into())),
+        ));
+        match overflow {
+            Overflow::Wrap => block.extend(wrapped),
+            Overflow::Saturate => {
+                let (min, max) = crate::fixed::raw_range(S::BITS, signed);
+                let limit = |val: BigInt, width: usize| {
+                    let literal =
+                        VerilogExpression::Literal(VerilogLiteral::twos_complement(val, width));
+                    if signed {
+                        VerilogExpression::Signed(Box::new(literal))
+                    } else {
+                        literal
+                    }
+                };
+                let compare = |op: VerilogOp, val: &BigInt| {
+                    VerilogExpression::Binary(
+                        Box::new(signal("aligned")),
+                        op,
+                        Box::new(limit(val.clone(), aligned_width)),
+                    )
+                };
+                let otherwise = if signed {
+                    VerilogBlockOrConditional::Conditional(Box::new(VerilogStatement::If(
+                        VerilogConditional {
+                            test: compare(VerilogOp::Lt, &min),
+                            then: result(limit(min.clone(), S::BITS)),
+                            otherwise: VerilogBlockOrConditional::Block(wrapped),
+                        },
+                    )))
+                } else {
+                    VerilogBlockOrConditional::Block(wrapped)
+                };
+                block.push(VerilogStatement::If(VerilogConditional {
+                    test: compare(VerilogOp::Gt, &max),
+                    then: result(limit(max.clone(), S::BITS)),
+                    otherwise,
+                }));
+            }
+        }
+        Self {
+            name,
+            width: S::BITS,
+            signed,
+            args: vec![x],
+            block,
+        }
+    }
+    /// The full precision sum, difference or product of two fixed point values (as done
+    /// by [Fixed::wide_add], [Fixed::wide_sub] and [Fixed::wide_mul], or the equivalent
+    /// methods on [Bits] and [Signed]).  Both arguments are extended to the width of the
+    /// result (and aligned on the binary point) first.
+    pub fn fixed_wide<T: FixedPoint, U: FixedPoint>(
+        _lhs: impl FnOnce() -> T,
+        _rhs: impl FnOnce() -> U,
+        op: VerilogOp,
+    ) -> Self {
+        let signed = T::SIGNED;
+        let (integer, fraction, op_name) = match op {
+            VerilogOp::Add | VerilogOp::Sub => (
+                max(T::INTEGER, U::INTEGER) + 1,
+                max(T::FRACTION, U::FRACTION),
+                if matches!(op, VerilogOp::Add) {
+                    "add"
+                } else {
+                    "sub"
+                },
+            ),
+            VerilogOp::Mul => (T::INTEGER + U::INTEGER, T::FRACTION + U::FRACTION, "mul"),
+            _ => panic!(
+                "Unsupported operation {:?} for a wide fixed point result",
+                op
+            ),
+        };
+        let width = integer + fraction;
+        let sign = if signed { "s" } else { "u" };
+        let name = if T::FRACTION == 0 && U::FRACTION == 0 {
+            // Integers (i.e., Bits and Signed)
+            format!("wide_{}{}_{}_{}", sign, T::INTEGER, op_name, U::INTEGER)
+        } else {
+            format!(
+                "fixed_{}{}_{}_{}_{}_{}",
+                sign,
+                T::INTEGER,
+                T::FRACTION,
+                op_name,
+                U::INTEGER,
+                U::FRACTION
+            )
+        };
+        let args = vec![
+            VerilogFunctionArg {
+                name: "x".into(),
+                width: T::BITS,
+                signed,
+            },
+            VerilogFunctionArg {
+                name: "y".into(),
+                width: U::BITS,
+                signed,
+            },
+        ];
+        let mut block = vec![];
+        let mut operands = vec![];
+        for (arg, arg_fraction) in args.iter().zip([T::FRACTION, U::FRACTION]) {
+            let ext = format!("{}_ext", arg.name);
+            block.push(fixed_let(&ext, width, signed, signal(&arg.name)));
+            let shift = if matches!(op, VerilogOp::Mul) {
+                0
+            } else {
+                fraction - arg_fraction
+            };
+            if shift == 0 {
+                operands.push(signal(&ext));
+            } else {
+                let aligned = format!("{}_aligned", arg.name);
+                block.push(fixed_let(
+                    &aligned,
+                    width,
+                    signed,
+                    shift_left(signal(&ext), shift),
+                ));
+                operands.push(signal(&aligned));
+            }
+        }
+        let rhs = operands.pop().unwrap();
+        let lhs = operands.pop().unwrap();
+        block.push(VerilogStatement::Assignment(
+            signal(&name),
+            VerilogExpression::Binary(Box::new(lhs), op, Box::new(rhs)),
+        ));
+        Self {
+            name,
+            width,
+            signed,
+            args,
+            block,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerilogFunctionArg {
+    pub name: String,
+    pub width: usize,
+    pub signed: bool,
+}
+
+impl VerilogFunctionArg {
+    pub fn new<T: Synth>(_x: &T, name: &str) -> Self {
+        Self {
+            name: name.into(),
+            width: T::BITS,
+            signed: T::descriptor().kind.is_signed(),
+        }
+    }
+}
+
+fn signal(name: &str) -> VerilogExpression {
+    VerilogExpression::Signal(name.into())
+}
+
+fn shift_left(x: VerilogExpression, shift: usize) -> VerilogExpression {
+    if shift == 0 {
+        x
+    } else {
+        VerilogExpression::Binary(
+            Box::new(x),
+            VerilogOp::Shl,
+            Box::new(VerilogExpression::Literal((shift as u32).into())),
+        )
+    }
+}
+
+// A let binding of a plain (signed or unsigned) vector, as used in the bodies
+// of the generated fixed point functions
+fn fixed_let(name: &str, width: usize, signed: bool, value: VerilogExpression) -> VerilogStatement {
+    let descriptor = if signed {
+        TypeDescriptor {
+            name: format!("Signed::<{}>", width),
+            kind: TypeKind::Signed(width),
+        }
+    } else {
+        TypeDescriptor {
+            name: format!("Bits::<{}>", width),
+            kind: TypeKind::Bits(width),
+        }
+    };
+    VerilogStatement::Let(VerilogLet {
+        name: name.into(),
+        width,
+        signed,
+        descriptor,
+        value,
+    })
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerilogOp {
     Add,
     Sub,
@@ -804,7 +1377,7 @@ pub enum VerilogOp {
 }
 
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerilogOpUnary {
     Not,
     Neg,
@@ -44,12 +44,19 @@ pub trait Atom {
     fn constraints(&self) -> Vec<PinConstraint>;
 }
 
+#[doc(hidden)]
+pub trait AtomMut: Atom {
+    /// Set the (settled) value of the atom from its VCD representation.  Returns
+    /// `false` if the value does not describe the type of the atom.
+    fn set_vcd(&mut self, val: &VCDValue) -> bool;
+}
+
 pub fn is_atom_an_enum(atom: &dyn Atom) -> bool {
     matches!(atom.descriptor().kind, TypeKind::Enum(_))
 }
 
 pub fn is_atom_signed(atom: &dyn Atom) -> bool {
-    matches!(atom.descriptor().kind, TypeKind::Signed(_))
+    atom.descriptor().kind.is_signed()
 }
 
 pub fn get_atom_typename(atom: &dyn Atom) -> String {
@@ -333,6 +333,7 @@
 //! ```
 
 use crate::bitvec::BitVec;
+use crate::fixed::WideCheck;
 use crate::short_bit_vec::{ShortBitVec, ShortType, SHORT_BITS};
 use crate::synth::VCDValue;
 use num_bigint::BigUint;
@@ -1106,6 +1107,40 @@ impl<const N: usize> Bits<N> {
             Bits::Long(x) => x.to_u128(),
         }
     }
+    /// Add two bit vectors without overflow.  The result must be one bit wider than
+    /// the wider of the two arguments (this is checked at compile time), so that the
+    /// carry is kept.  This can also be used in an HDL kernel.
+    /// ```
+    /// # use rust_hdl_core::prelude::*;
+    /// let x: Bits<8> = 200.into();
+    /// let y: Bits<4> = 15.into();
+    /// let z: Bits<9> = x.wide_add(y);
+    /// assert_eq!(z, 215);
+    /// ```
+    pub fn wide_add<const M: usize, const O: usize>(self, rhs: Bits<M>) -> Bits<O> {
+        #[allow(clippy::let_unit_value)]
+        let _ = WideCheck::<N, 0, M, 0, O, 0>::SUM;
+        bit_cast::<O, N>(self) + bit_cast::<O, M>(rhs)
+    }
+    /// Multiply two bit vectors without overflow.  The width of the result must be the
+    /// sum of the widths of the arguments (this is checked at compile time).  This can
+    /// also be used in an HDL kernel.
+    /// ```
+    /// # use rust_hdl_core::prelude::*;
+    /// let x: Bits<8> = 200.into();
+    /// let y: Bits<4> = 15.into();
+    /// let z: Bits<12> = x.wide_mul(y);
+    /// assert_eq!(z, 3000);
+    /// ```
+    pub fn wide_mul<const M: usize, const O: usize>(self, rhs: Bits<M>) -> Bits<O> {
+        #[allow(clippy::let_unit_value)]
+        let _ = WideCheck::<N, 0, M, 0, O, 0>::PRODUCT;
+        if O <= LITERAL_BITS {
+            (self.to_u64() * rhs.to_u64()).into()
+        } else {
+            (BigUint::from(self) * BigUint::from(rhs)).into()
+        }
+    }
 }
 
 impl From<bool> for Bits<1> {
@@ -2086,6 +2121,22 @@ mod tests {
         }
         test_cmp_with_values!(gt);
     }
+
+    #[test]
+    fn test_wide_ops() {
+        for _iters in 0..10 {
+            let y: Bits<40> = random_bits();
+            let z: Bits<30> = random_bits();
+            let y1: BigUint = y.into();
+            let z1: BigUint = z.into();
+            let sum: Bits<41> = y.wide_add(z);
+            assert_eq!(BigUint::from(sum), &y1 + &z1);
+            let product: Bits<70> = y.wide_mul(z);
+            assert_eq!(BigUint::from(product), &y1 * &z1);
+            let short: Bits<60> = z.wide_mul(z);
+            assert_eq!(BigUint::from(short), &z1 * &z1);
+        }
+    }
 }
 
 /// A type alias for a simple bool.  You can use them interchangeably.
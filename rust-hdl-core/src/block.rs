@@ -1,5 +1,6 @@
 use crate::logic::Logic;
-use crate::probe::Probe;
+use crate::probe::{Probe, ProbeMut};
+use crate::sensitivity::Sensitivity;
 
 /// The [Block] trait is required for all circuitry that
 /// can be simulated by RustHDL.  If you want to be able
@@ -16,6 +17,43 @@ pub trait Block: Logic {
     fn has_changed(&self) -> bool;
     /// The visitor pattern - allows a circuit to be probed by a [Probe] struct.
     fn accept(&self, name: &str, probe: &mut dyn Probe);
+    /// The mutable version of [Block::accept].  It must visit the same scopes and atoms
+    /// (minus any constants) in the same order, so that the state of the circuit can be
+    /// restored from a [Snapshot](crate::snapshot::Snapshot).  The default implementation
+    /// tells the probe that the block cannot be visited, so that snapshots of circuits
+    /// that contain it fail with a [SimError::SnapshotMismatch](crate::simulate::SimError::SnapshotMismatch).
+    fn accept_mut(&mut self, name: &str, probe: &mut dyn ProbeMut) {
+        probe.visit_unsupported(name);
+    }
+    /// Propogate changes from inputs to outputs within the circuit, but only re-evaluate
+    /// the parts of the circuit that can be affected by a change.  See [Sensitivity] for
+    /// details.  The `dirty` flag is set if the parent block was evaluated on this pass.
+    /// Returns `true` if anything in the circuit has changed.  The default implementation
+    /// falls back to [Block::update_all], which is fine for anything that does not
+    /// open a scope in [Block::accept].
+    fn update_sensitive(&mut self, _sens: &mut Sensitivity, _dirty: bool) -> bool {
+        self.update_all();
+        self.has_changed()
+    }
+    /// Latch the new values of the inputs of the circuit (i.e., the signals driven by
+    /// its parent), so that its next update sees them.  [Block::update_sensitive] uses this
+    /// to propagate changes into combinational logic without waiting for another pass.
+    fn latch_inputs(&mut self) {}
+    /// Returns `true` if any of the signals that make up the interface of the circuit
+    /// (i.e., the ones its parent can see) changed on the last update.
+    fn ports_changed(&self) -> bool {
+        self.has_changed()
+    }
+    /// Returns `true` if any of the signals reachable without entering a sub-block
+    /// changed on the last update.
+    fn atoms_changed(&self) -> bool {
+        self.has_changed()
+    }
+    /// The name of the type of the circuit.  The generated HDL names a module that is
+    /// shared by several circuits after their type.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 impl<B: Block> Block for Vec<B> {
@@ -46,6 +84,35 @@ impl<B: Block> Block for Vec<B> {
             x.1.accept(&name, probe);
         }
     }
+
+    fn accept_mut(&mut self, name: &str, probe: &mut dyn ProbeMut) {
+        for x in self.iter_mut().enumerate() {
+            let name = format!("{}${}", name, x.0);
+            x.1.accept_mut(&name, probe);
+        }
+    }
+
+    fn update_sensitive(&mut self, sens: &mut Sensitivity, dirty: bool) -> bool {
+        let mut changed = false;
+        for x in self {
+            changed |= x.update_sensitive(sens, dirty);
+        }
+        changed
+    }
+
+    fn latch_inputs(&mut self) {
+        for x in self {
+            x.latch_inputs();
+        }
+    }
+
+    fn ports_changed(&self) -> bool {
+        self.iter().any(|x| x.ports_changed())
+    }
+
+    fn atoms_changed(&self) -> bool {
+        self.iter().any(|x| x.atoms_changed())
+    }
 }
 
 impl<B: Block, const P: usize> Block for [B; P] {
@@ -76,4 +143,33 @@ impl<B: Block, const P: usize> Block for [B; P] {
             x.1.accept(&name, probe);
         }
     }
+
+    fn accept_mut(&mut self, name: &str, probe: &mut dyn ProbeMut) {
+        for x in self.iter_mut().enumerate() {
+            let name = format!("{}${}", name, x.0);
+            x.1.accept_mut(&name, probe);
+        }
+    }
+
+    fn update_sensitive(&mut self, sens: &mut Sensitivity, dirty: bool) -> bool {
+        let mut changed = false;
+        for x in self {
+            changed |= x.update_sensitive(sens, dirty);
+        }
+        changed
+    }
+
+    fn latch_inputs(&mut self) {
+        for x in self {
+            x.latch_inputs();
+        }
+    }
+
+    fn ports_changed(&self) -> bool {
+        self.iter().any(|x| x.ports_changed())
+    }
+
+    fn atoms_changed(&self) -> bool {
+        self.iter().any(|x| x.atoms_changed())
+    }
 }
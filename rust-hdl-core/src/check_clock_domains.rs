@@ -0,0 +1,114 @@
+use crate::ast::SourceLocation;
+use crate::block::Block;
+use crate::check_error::{CheckError, ClockCrossing, PathedName};
+use crate::check_timing::{
+    register_clocks, signal_graph, DelayModel, SignalEdgeKind, SignalNodeKind,
+};
+use petgraph::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// Split a node of the signal graph (e.g., `top$a$q`) into the path of the block and
+// the name of the signal, in the same form as the other checks.
+fn crossing_name(name: &str, sources: &HashMap<String, SourceLocation>) -> PathedName {
+    let source = sources.get(name).cloned();
+    match name.rsplit_once('$') {
+        Some((path, name)) => PathedName {
+            path: path.into(),
+            name: name.into(),
+            source,
+        },
+        None => PathedName {
+            path: Default::default(),
+            name: name.into(),
+            source,
+        },
+    }
+}
+
+/// Check a circuit for signals that cross clock domains without being synchronized.
+/// This check is run as part of [check_all](crate::check_error::check_all).
+/// The clock domain of each register (e.g., a `DFF`) is inferred from the signal that drives
+/// its clock (so the `clock!` and `dff_setup!` macros put registers in the domain of
+/// the clock they are given).  Any path from the output of a register in one domain to
+/// the input of a register in another domain is flagged, unless it passes through an input
+/// that is declared as a crossing point with [Logic::clock_crossings](crate::logic::Logic::clock_crossings).
+/// The synchronizers in the widgets library (`BitSynchronizer`, `SyncSender`/`SyncReceiver`,
+/// `VectorSynchronizer` and `AsynchronousFIFO`) are all legal crossing points.
+///
+/// Each [ClockCrossing] in the error gives the full hierarchical path of the
+/// register output and input at either end of the crossing.
+/// ```rust
+/// use rust_hdl_core::prelude::*;
+///
+/// #[derive(LogicBlock, Default)]
+/// struct Circuit {
+///    pub in1: Signal<In, Bit>,
+///    pub out1: Signal<Out, Bit>,
+/// }
+///
+/// impl Logic for Circuit {
+///    #[hdl_gen]
+///    fn update(&mut self) {
+///         self.out1.next = !self.in1.val();
+///    }
+/// }
+///
+/// let mut uut = Circuit::default();  uut.connect_all();
+/// assert!(check_clock_domains(&uut).is_ok());
+/// ```
+pub fn check_clock_domains(uut: &dyn Block) -> Result<(), CheckError> {
+    let (g, crossings, sources) = signal_graph(uut, &DelayModel::default());
+    let clocks = register_clocks(&g);
+    let mut violations: Vec<ClockCrossing> = vec![];
+    for sink in g.node_indices() {
+        if g[sink].kind != SignalNodeKind::Sink {
+            continue;
+        }
+        let Some(clock) = clocks.get(&g[sink].name) else {
+            continue;
+        };
+        for input in g
+            .edges_directed(sink, Incoming)
+            .filter(|x| x.weight().kind == SignalEdgeKind::Input)
+        {
+            // Walk back from the register input to the registers that drive it,
+            // stopping at any declared crossing points.
+            let start = input.source();
+            let mut seen: HashSet<NodeIndex> = HashSet::new();
+            let mut pending = vec![start];
+            while let Some(node) = pending.pop() {
+                if !seen.insert(node) || crossings.contains(&g[node].name) {
+                    continue;
+                }
+                for edge in g.edges_directed(node, Incoming) {
+                    if edge.weight().kind == SignalEdgeKind::Clock {
+                        continue;
+                    }
+                    let source = edge.source();
+                    if g[source].kind != SignalNodeKind::Source {
+                        pending.push(source);
+                        continue;
+                    }
+                    match clocks.get(&g[source].name) {
+                        Some(other) if other != clock => {
+                            let crossing = ClockCrossing {
+                                from: crossing_name(&g[node].name, &sources),
+                                to: crossing_name(&g[start].name, &sources),
+                            };
+                            if !violations.contains(&crossing) {
+                                violations.push(crossing);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        violations.sort_by_key(|x| (x.to.path.clone(), x.to.name.clone(), x.from.path.clone()));
+        Err(CheckError::ClockDomainCrossings(violations))
+    }
+}
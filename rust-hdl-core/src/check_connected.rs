@@ -1,7 +1,7 @@
 use crate::atom::Atom;
 use crate::atom::AtomKind;
 use crate::block::Block;
-use crate::check_error::{CheckError, OpenMap, PathedName};
+use crate::check_error::{CheckError, OpenMap, PathedName, SignalSources};
 use crate::named_path::NamedPath;
 use crate::probe::Probe;
 
@@ -10,12 +10,14 @@ struct CheckConnected {
     path: NamedPath,
     namespace: NamedPath,
     failures: OpenMap,
+    sources: Vec<SignalSources>,
 }
 
 impl Probe for CheckConnected {
-    fn visit_start_scope(&mut self, name: &str, _node: &dyn Block) {
+    fn visit_start_scope(&mut self, name: &str, node: &dyn Block) {
         self.path.push(name);
         self.namespace.reset();
+        self.sources.push(SignalSources::new(node));
     }
 
     fn visit_start_namespace(&mut self, name: &str, _node: &dyn Block) {
@@ -29,15 +31,17 @@ impl Probe for CheckConnected {
             [AtomKind::InputParameter, AtomKind::InOutParameter].contains(&signal.kind());
         if !(signal_is_connected | (signal_is_input && is_top_scope)) {
             dbg!(&signal.kind());
+            let name = if self.namespace.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}${name}", self.namespace.to_string())
+            };
             self.failures.insert(
                 signal.id(),
                 dbg!(PathedName {
                     path: self.path.to_string(),
-                    name: if self.namespace.is_empty() {
-                        name.to_string()
-                    } else {
-                        format!("{}${name}", self.namespace.to_string())
-                    }
+                    source: self.sources.last().unwrap().read(&name),
+                    name,
                 }),
             );
         }
@@ -49,6 +53,7 @@ impl Probe for CheckConnected {
 
     fn visit_end_scope(&mut self, _name: &str, _node: &dyn Block) {
         self.path.pop();
+        self.sources.pop();
     }
 }
 
@@ -1,7 +1,10 @@
+use crate::ast::{SourceLocation, Verilog, VerilogConditional, VerilogExpression, VerilogMatch};
 use crate::block::Block;
+use crate::check_clock_domains::check_clock_domains;
 use crate::check_connected::check_connected;
 use crate::check_logic_loops::check_logic_loops;
 use crate::check_write_inputs::check_inputs_not_written;
+use crate::verilog_visitor::VerilogVisitor;
 
 use std::collections::HashMap;
 
@@ -9,17 +12,45 @@ use std::collections::HashMap;
 pub type OpenMap = HashMap<usize, PathedName>;
 
 /// Struct to capture a signal in the design for human consumption
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct PathedName {
     /// The path to the signal (i.e., the hierarchical namespace such as `uut:flasher:blah`)
     pub path: String,
     /// The name of the signal that is being referenced, such as `pulse_in`.
     pub name: String,
+    /// The line of the HDL kernel that refers to the signal (if it can be found).
+    pub source: Option<SourceLocation>,
+}
+
+// The source is informational, and two names refer to the same signal regardless of it
+impl PartialEq for PathedName {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.name == other.name
+    }
+}
+
+impl std::fmt::Display for PathedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::{}", self.path, self.name)?;
+        if let Some(source) = &self.source {
+            write!(f, " ({})", source)?;
+        }
+        Ok(())
+    }
 }
 
 /// A list of [PathedName]
 pub type PathedNameList = Vec<PathedName>;
 
+/// A signal that crosses from one clock domain to another without a synchronizer
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClockCrossing {
+    /// The output of the register that launches the signal
+    pub from: PathedName,
+    /// The input of the register (in another clock domain) that captures the signal
+    pub to: PathedName,
+}
+
 /// The enum models the errors that can be returned from "checking"
 /// a circuit using [check_all].
 #[derive(Debug, Clone, PartialEq)]
@@ -30,10 +61,120 @@ pub enum CheckError {
     LogicLoops(PathedNameList),
     /// The circuit attempts to write to the inputs, which is not allowed in RustHDL.
     WritesToInputs(PathedNameList),
+    /// The circuit moves signals between clock domains without synchronizing them
+    ClockDomainCrossings(Vec<ClockCrossing>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Ignore,
+    Read,
+    Write,
+}
+
+// Collects the lines of the HDL kernel of a block that read and write each signal,
+// so that a check can point at the line that is at fault.
+pub(crate) struct SignalSources {
+    source: Option<SourceLocation>,
+    mode: Mode,
+    reads: HashMap<String, SourceLocation>,
+    writes: HashMap<String, SourceLocation>,
+}
+
+impl SignalSources {
+    pub(crate) fn new(uut: &dyn Block) -> Self {
+        let mut sources = Self {
+            source: None,
+            mode: Mode::Ignore,
+            reads: Default::default(),
+            writes: Default::default(),
+        };
+        if let Verilog::Combinatorial(code) = &uut.hdl() {
+            sources.visit_block(code);
+        }
+        sources
+    }
+    // The first line that reads the signal (or failing that, writes it)
+    pub(crate) fn read(&self, name: &str) -> Option<SourceLocation> {
+        self.reads
+            .get(name)
+            .or_else(|| self.writes.get(name))
+            .cloned()
+    }
+    // The first line that writes the signal (or failing that, reads it)
+    pub(crate) fn write(&self, name: &str) -> Option<SourceLocation> {
+        self.writes
+            .get(name)
+            .or_else(|| self.reads.get(name))
+            .cloned()
+    }
+}
+
+impl VerilogVisitor for SignalSources {
+    fn visit_source(&mut self, s: &SourceLocation) {
+        self.source = Some(s.clone());
+    }
+
+    fn visit_conditional(&mut self, c: &VerilogConditional) {
+        let current_mode = self.mode;
+        self.mode = Mode::Read;
+        self.visit_expression(&c.test);
+        self.mode = current_mode;
+        self.visit_block(&c.then);
+        self.visit_block_or_conditional(&c.otherwise);
+    }
+
+    fn visit_match(&mut self, m: &VerilogMatch) {
+        let current_mode = self.mode;
+        self.mode = Mode::Read;
+        self.visit_expression(&m.test);
+        self.mode = current_mode;
+        for case in &m.cases {
+            self.visit_case(case);
+        }
+    }
+
+    fn visit_slice_assignment(
+        &mut self,
+        base: &VerilogExpression,
+        _width: &usize,
+        offset: &VerilogExpression,
+        replacement: &VerilogExpression,
+    ) {
+        let current_mode = self.mode;
+        self.mode = Mode::Read;
+        self.visit_expression(offset);
+        self.visit_expression(replacement);
+        self.mode = Mode::Write;
+        self.visit_expression(base);
+        self.mode = current_mode;
+    }
+
+    fn visit_signal(&mut self, c: &str) {
+        let myname = c.replace("$next", "");
+        let Some(source) = &self.source else {
+            return;
+        };
+        let map = match self.mode {
+            Mode::Ignore => return,
+            Mode::Read => &mut self.reads,
+            Mode::Write => &mut self.writes,
+        };
+        map.entry(myname).or_insert_with(|| source.clone());
+    }
+
+    fn visit_assignment(&mut self, l: &VerilogExpression, r: &VerilogExpression) {
+        let current_mode = self.mode;
+        self.mode = Mode::Read;
+        self.visit_expression(r);
+        self.mode = Mode::Write;
+        self.visit_expression(l);
+        self.mode = current_mode;
+    }
 }
 
-/// This is a helper function used to check a [Block] for connection, loops, and
-/// writes to the inputs.  
+/// This is a helper function used to check a [Block] for connection, loops,
+/// writes to the inputs, and unsynchronized clock domain crossings.
 /// ```rust
 /// use rust_hdl_core::prelude::*;
 ///
@@ -57,5 +198,6 @@ pub fn check_all(uut: &dyn Block) -> Result<(), CheckError> {
     check_connected(uut)?;
     check_logic_loops(uut)?;
     check_inputs_not_written(uut)?;
+    check_clock_domains(uut)?;
     Ok(())
 }
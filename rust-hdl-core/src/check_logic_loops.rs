@@ -1,7 +1,7 @@
 use crate::ast::{Verilog, VerilogExpression};
 use crate::atom::{Atom, AtomKind};
 use crate::block::Block;
-use crate::check_error::{CheckError, PathedName, PathedNameList};
+use crate::check_error::{CheckError, PathedName, PathedNameList, SignalSources};
 use crate::named_path::NamedPath;
 use crate::probe::Probe;
 use crate::verilog_visitor::VerilogVisitor;
@@ -95,12 +95,14 @@ struct LocalVars {
 }
 
 impl LocalVars {
-    fn update_loops(&mut self, candidates: &[String]) {
+    fn update_loops(&mut self, node: &dyn Block, candidates: &[String]) {
+        let sources = SignalSources::new(node);
         for candidate in candidates {
             if self.names.last().unwrap().contains(candidate) {
                 self.loops.push(PathedName {
                     path: self.path.to_string(),
                     name: candidate.to_string(),
+                    source: sources.read(candidate),
                 })
             }
         }
@@ -133,7 +135,7 @@ impl Probe for LocalVars {
     }
 
     fn visit_end_scope(&mut self, _name: &str, node: &dyn Block) {
-        self.update_loops(&get_logic_loop_candidates(node));
+        self.update_loops(node, &get_logic_loop_candidates(node));
         self.path.pop();
         self.names.pop();
     }
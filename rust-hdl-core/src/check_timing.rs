@@ -1,21 +1,109 @@
 use crate::ast::{
-    Verilog, VerilogConditional, VerilogExpression, VerilogLink, VerilogLinkDetails, VerilogMatch,
+    SourceLocation, Verilog, VerilogCall, VerilogConditional, VerilogExpression, VerilogLet,
+    VerilogLink, VerilogLinkDetails, VerilogLoop, VerilogMatch, VerilogOp, VerilogOpUnary,
 };
 use crate::atom::{Atom, AtomKind};
 use crate::block::Block;
+use crate::check_error::{CheckError, PathedName};
 use crate::named_path::NamedPath;
 use crate::probe::Probe;
-use crate::type_descriptor::TypeKind;
-use crate::verilog_visitor::VerilogVisitor;
-use petgraph::algo::{connected_components, is_cyclic_directed};
+use crate::type_descriptor::{TypeDescriptor, TypeKind};
+use crate::verilog_gen::{ident_fixup, LoopVariable};
+use crate::verilog_visitor::{
+    walk_binop, walk_block, walk_call, walk_index, walk_index_replacement, walk_let, walk_slice,
+    walk_slice_replace, walk_unop, VerilogVisitor,
+};
+use petgraph::algo::{connected_components, is_cyclic_directed, toposort};
 use petgraph::dot::Dot;
 use petgraph::prelude::*;
 use petgraph::unionfind::UnionFind;
 use petgraph::visit::NodeIndexable;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// The delay model used by [estimate_timing].  It is a (very) rough model of an FPGA
+/// fabric built from LUTs with a dedicated carry chain, and all delays are in picoseconds.
+/// The defaults are in the right ballpark for a mid-range part, but the estimate is only
+/// meant to flag paths that are unlikely to close timing - it does not replace the
+/// timing analysis of the vendor tools.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelayModel {
+    /// Delay through one LUT, including the local routing to the next one
+    pub lut: u64,
+    /// The number of inputs to each LUT
+    pub lut_inputs: usize,
+    /// Delay per bit of the carry chain (used by adders, subtractors and magnitude comparisons)
+    pub carry_per_bit: u64,
+    /// The clock to output delay of a register
+    pub clock_to_out: u64,
+    /// The setup time of a register
+    pub setup: u64,
+}
+
+impl Default for DelayModel {
+    fn default() -> Self {
+        Self {
+            lut: 400,
+            lut_inputs: 6,
+            carry_per_bit: 25,
+            clock_to_out: 450,
+            setup: 100,
+        }
+    }
+}
+
+impl DelayModel {
+    // Number of LUT levels needed to combine `inputs` signals into one
+    fn levels(&self, inputs: usize, per_lut: usize) -> u64 {
+        let per_lut = per_lut.max(2);
+        let mut levels = 0;
+        let mut remaining = inputs;
+        while remaining > 1 {
+            remaining = remaining.div_ceil(per_lut);
+            levels += 1;
+        }
+        levels
+    }
+    /// Delay through a LUT tree that reduces `bits` inputs (e.g., `x.any()` or `a == b`)
+    pub fn reduce(&self, bits: usize) -> u64 {
+        self.levels(bits, self.lut_inputs).max(1) * self.lut
+    }
+    /// Delay through a multiplexer with `fanin` data inputs.  Each LUT selects one of
+    /// `lut_inputs - 2` inputs (so a LUT6 is a 4:1 mux).
+    pub fn mux(&self, fanin: usize) -> u64 {
+        self.levels(fanin, self.lut_inputs.saturating_sub(2)) * self.lut
+    }
+    /// Delay through a `bits` wide carry chain (e.g., an adder)
+    pub fn carry(&self, bits: usize) -> u64 {
+        self.lut + self.carry_per_bit * bits as u64
+    }
+    /// Delay through a `bits` wide multiplier, modelled as a tree of adders
+    pub fn multiply(&self, bits: usize) -> u64 {
+        self.levels(bits, 2).max(1) * self.carry(2 * bits)
+    }
+}
+
+/// The longest (estimated) register to register path in one clock domain, as
+/// reported by [estimate_timing].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingPath {
+    /// The clock signal that drives the registers at both ends of the path
+    pub clock: String,
+    /// The estimated delay of the path (including clock to out and setup) in picoseconds
+    pub delay: u64,
+    /// The signals along the path.  The first entry is the launching register,
+    /// and the last is the capturing register.
+    pub path: Vec<String>,
+}
+
+impl TimingPath {
+    /// The highest clock frequency (in Hz) this path can support
+    pub fn max_frequency(&self) -> f64 {
+        1.0e12 / (self.delay.max(1) as f64)
+    }
+}
 
 #[derive(Clone, Debug, Copy, PartialEq)]
-enum SignalNodeKind {
+pub(crate) enum SignalNodeKind {
     Normal,
     Bidirectional,
     Source,
@@ -23,7 +111,7 @@ enum SignalNodeKind {
 }
 
 #[derive(Clone, Debug, Copy, PartialEq)]
-enum SignalEdgeKind {
+pub(crate) enum SignalEdgeKind {
     Assign,
     Input,
     Clock,
@@ -34,14 +122,20 @@ enum SignalEdgeKind {
 }
 
 #[derive(Clone, Debug, PartialEq)]
-struct SignalNode {
+pub(crate) struct SignalNode {
     pub name: String,
     pub kind: SignalNodeKind,
 }
 
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub(crate) struct SignalEdge {
+    pub kind: SignalEdgeKind,
+    pub delay: u64,
+}
+
 #[derive(Clone, Debug, Default)]
 struct SignalGraph {
-    pub graph: Graph<SignalNode, SignalEdgeKind, Directed>,
+    pub graph: Graph<SignalNode, SignalEdge, Directed>,
 }
 
 impl SignalGraph {
@@ -55,10 +149,23 @@ impl SignalGraph {
             None => self.graph.add_node(node.clone()),
         }
     }
-    fn add_signal_edge(&mut self, from: &SignalNode, to: NodeIndex, kind: SignalEdgeKind) {
+    fn add_signal_edge(
+        &mut self,
+        from: &SignalNode,
+        to: NodeIndex,
+        kind: SignalEdgeKind,
+        delay: u64,
+    ) {
         let from_index = self.add_signal_node(from);
-        if !self.graph.contains_edge(from_index, to) {
-            self.graph.add_edge(from_index, to, kind);
+        match self.graph.find_edge(from_index, to) {
+            Some(edge) => {
+                let weight = &mut self.graph[edge];
+                weight.delay = weight.delay.max(delay);
+            }
+            None => {
+                self.graph
+                    .add_edge(from_index, to, SignalEdge { kind, delay });
+            }
         }
     }
 }
@@ -69,7 +176,8 @@ enum ExpressionMode {
     Read,
 }
 
-type ReadScope = Vec<SignalNode>;
+// Each read carries the delay from the signal to the written value
+type ReadScope = Vec<(SignalNode, u64)>;
 
 struct TimingChecker {
     path: NamedPath,
@@ -77,6 +185,14 @@ struct TimingChecker {
     mode: ExpressionMode,
     write_name: String,
     read_names: Vec<ReadScope>,
+    model: DelayModel,
+    widths: HashMap<String, usize>,
+    delay: u64,
+    muxes: Vec<u64>,
+    loops: Vec<LoopVariable>,
+    source: Option<SourceLocation>,
+    pub crossings: HashSet<String>,
+    pub sources: HashMap<String, SourceLocation>,
     pub graph: SignalGraph,
 }
 
@@ -88,6 +204,14 @@ impl Default for TimingChecker {
             mode: ExpressionMode::Write,
             write_name: "".to_string(),
             read_names: vec![],
+            model: Default::default(),
+            widths: Default::default(),
+            delay: 0,
+            muxes: vec![],
+            loops: vec![],
+            source: None,
+            crossings: Default::default(),
+            sources: Default::default(),
             graph: Default::default(),
         }
     }
@@ -114,13 +238,123 @@ impl TimingChecker {
         if self.read_names.is_empty() {
             self.read_names.push(ReadScope::default());
         }
-        self.read_names.last_mut().unwrap().push(SignalNode {
-            name: name.into(),
-            kind: kind,
-        })
+        // A read passes through the operators above it in the expression, and
+        // through the muxes of every enclosing conditional.
+        let delay = self.delay + self.muxes.iter().sum::<u64>();
+        self.read_names.last_mut().unwrap().push((
+            SignalNode {
+                name: name.into(),
+                kind: kind,
+            },
+            delay,
+        ))
+    }
+    fn signal_name(&self, c: &str) -> String {
+        format!("{}${}", self.path.to_string(), ident_fixup(&self.loops, c)).replace("$next", "")
+    }
+    // The width of a signal.  Fields of structs are not atoms, so they fall
+    // back to the width of the enclosing signal.
+    fn signal_width(&self, c: &str) -> usize {
+        let mut name = self.signal_name(c);
+        loop {
+            if let Some(width) = self.widths.get(&name) {
+                return *width;
+            }
+            match name.rfind('$') {
+                Some(ndx) => name.truncate(ndx),
+                None => return 1,
+            }
+        }
+    }
+    // The width of the operands of a binary operator.  Literals are 32 bits
+    // wide, so they only count if both sides are literals.
+    fn operand_width(&self, l: &VerilogExpression, r: &VerilogExpression) -> usize {
+        match (l, r) {
+            (VerilogExpression::Literal(_), VerilogExpression::Literal(_)) => {
+                self.width(l).max(self.width(r))
+            }
+            (VerilogExpression::Literal(_), _) => self.width(r),
+            (_, VerilogExpression::Literal(_)) => self.width(l),
+            _ => self.width(l).max(self.width(r)),
+        }
+    }
+    fn width(&self, e: &VerilogExpression) -> usize {
+        match e {
+            VerilogExpression::Signal(s) => self.signal_width(s),
+            VerilogExpression::Literal(l) => l.bits(),
+            VerilogExpression::Cast(_, bits) => *bits,
+            VerilogExpression::Paren(x)
+            | VerilogExpression::Signed(x)
+            | VerilogExpression::Unsigned(x) => self.width(x),
+            VerilogExpression::Binary(l, op, r) => match op {
+                VerilogOp::Eq
+                | VerilogOp::Ne
+                | VerilogOp::Lt
+                | VerilogOp::Le
+                | VerilogOp::Gt
+                | VerilogOp::Ge
+                | VerilogOp::LogicalAnd
+                | VerilogOp::LogicalOr => 1,
+                VerilogOp::Shl | VerilogOp::Shr => self.width(l),
+                _ => self.operand_width(l, r),
+            },
+            VerilogExpression::Unary(op, x) => match op {
+                VerilogOpUnary::Not | VerilogOpUnary::Neg => self.width(x),
+                _ => 1,
+            },
+            VerilogExpression::Index(_, _) => 1,
+            VerilogExpression::Slice(_, bits, _) => *bits,
+            VerilogExpression::Element(e) => e.width(),
+            VerilogExpression::IndexReplace(a, _, _) => self.width(a),
+            VerilogExpression::Call(c) => c.function.width,
+        }
+    }
+    fn binop_delay(&self, l: &VerilogExpression, op: &VerilogOp, r: &VerilogExpression) -> u64 {
+        let width = self.operand_width(l, r);
+        match op {
+            VerilogOp::Add
+            | VerilogOp::Sub
+            | VerilogOp::Lt
+            | VerilogOp::Le
+            | VerilogOp::Gt
+            | VerilogOp::Ge => self.model.carry(width),
+            VerilogOp::Mul => self.model.multiply(width),
+            VerilogOp::Eq | VerilogOp::Ne => self.model.reduce(2 * width),
+            VerilogOp::Shl | VerilogOp::Shr => match r {
+                VerilogExpression::Literal(_) => 0,
+                _ => self.model.mux(self.width(l)),
+            },
+            VerilogOp::LogicalAnd
+            | VerilogOp::LogicalOr
+            | VerilogOp::BitXor
+            | VerilogOp::BitAnd
+            | VerilogOp::BitOr => self.model.lut,
+        }
+    }
+    fn unop_delay(&self, op: &VerilogOpUnary, x: &VerilogExpression) -> u64 {
+        match op {
+            VerilogOpUnary::Not => self.model.lut,
+            VerilogOpUnary::Neg => self.model.carry(self.width(x)),
+            VerilogOpUnary::All | VerilogOpUnary::Any | VerilogOpUnary::Xor => {
+                self.model.reduce(self.width(x))
+            }
+        }
+    }
+    // Selecting a bit (or a slice) with a variable index is a mux
+    fn select_delay(&self, a: &VerilogExpression, index: &VerilogExpression) -> u64 {
+        match index {
+            VerilogExpression::Literal(_) => 0,
+            _ => self.model.mux(self.width(a)),
+        }
+    }
+    fn with_delay<F: FnOnce(&mut Self)>(&mut self, delay: u64, f: F) {
+        self.delay += delay;
+        f(self);
+        self.delay -= delay;
     }
     fn add_code(&mut self, module: &str, code: Verilog) {
         if let Verilog::Combinatorial(code) = &code {
+            self.source = None;
             self.visit_block(code);
         }
     }
@@ -131,14 +365,19 @@ impl TimingChecker {
             kind,
         };
         let write_id = self.graph.add_signal_node(&write_node);
+        if let Some(source) = &self.source {
+            self.sources
+                .entry(write_name.into())
+                .or_insert_with(|| source.clone());
+        }
         for scope in &self.read_names {
-            for read in scope {
-                println!("Adding edge from {:?} -> {:?}", read, write_node);
-                self.graph.add_signal_edge(read, write_id, edge);
+            for (read, delay) in scope {
+                self.graph.add_signal_edge(read, write_id, edge, *delay);
             }
         }
     }
     fn link_fixup(&self, x: &VerilogLinkDetails) -> (String, String) {
+        let x = crate::verilog_gen::link_fixup(&self.loops, x);
         let v1 = format!(
             "{}${}${}",
             self.path.to_string(),
@@ -156,20 +395,70 @@ impl TimingChecker {
 }
 
 impl VerilogVisitor for TimingChecker {
+    fn visit_source(&mut self, s: &SourceLocation) {
+        self.source = Some(s.clone());
+    }
+
     fn visit_conditional(&mut self, c: &VerilogConditional) {
+        self.muxes.push(self.model.mux(2));
         self.push_read_scope();
         self.visit_expression(&c.test);
         self.visit_block(&c.then);
         self.visit_block_or_conditional(&c.otherwise);
         self.pop_read_scope();
+        self.muxes.pop();
     }
     fn visit_match(&mut self, m: &VerilogMatch) {
-        self.visit_expression(&m.test);
+        self.muxes.push(self.model.mux(m.cases.len()));
         self.push_read_scope();
+        self.visit_expression(&m.test);
         for case in &m.cases {
             self.visit_case(case);
         }
         self.pop_read_scope();
+        self.muxes.pop();
+    }
+    fn visit_binop(&mut self, l: &VerilogExpression, o: &VerilogOp, r: &VerilogExpression) {
+        let delay = self.binop_delay(l, o, r);
+        self.with_delay(delay, |x| walk_binop(x, l, o, r));
+    }
+    fn visit_unop(&mut self, o: &VerilogOpUnary, ex: &VerilogExpression) {
+        let delay = self.unop_delay(o, ex);
+        self.with_delay(delay, |x| walk_unop(x, o, ex));
+    }
+    // The body of a function is not analyzed.  It is estimated as a tree of LUTs
+    // that reduces all of the bits of the arguments.
+    fn visit_call(&mut self, c: &VerilogCall) {
+        let bits = c.args.iter().map(|x| self.width(x)).sum();
+        let delay = self.model.reduce(bits);
+        self.with_delay(delay, |x| walk_call(x, c));
+    }
+    fn visit_index(&mut self, a: &VerilogExpression, b: &VerilogExpression) {
+        let delay = self.select_delay(a, b);
+        self.with_delay(delay, |x| walk_index(x, a, b));
+    }
+    fn visit_slice(&mut self, a: &VerilogExpression, b: &usize, c: &VerilogExpression) {
+        let delay = self.select_delay(a, c);
+        self.with_delay(delay, |x| walk_slice(x, a, b, c));
+    }
+    fn visit_slice_replace(
+        &mut self,
+        a: &VerilogExpression,
+        b: &usize,
+        c: &VerilogExpression,
+        d: &VerilogExpression,
+    ) {
+        let delay = self.select_delay(a, c);
+        self.with_delay(delay, |x| walk_slice_replace(x, a, b, c, d));
+    }
+    fn visit_index_replace(
+        &mut self,
+        a: &VerilogExpression,
+        b: &VerilogExpression,
+        c: &VerilogExpression,
+    ) {
+        let delay = self.select_delay(a, b);
+        self.with_delay(delay, |x| walk_index_replacement(x, a, b, c));
     }
     fn visit_assignment(&mut self, l: &VerilogExpression, r: &VerilogExpression) {
         self.push_read_scope();
@@ -200,8 +489,27 @@ impl VerilogVisitor for TimingChecker {
         self.add_write(&write_name, SignalNodeKind::Normal, SignalEdgeKind::Assign);
         self.pop_read_scope();
     }
+    fn visit_loop(&mut self, a: &VerilogLoop) {
+        for i in a.from.as_usize()..a.to.as_usize() {
+            self.loops.push(LoopVariable {
+                variable: a.index.clone(),
+                value: i,
+            });
+            walk_block(self, &a.block);
+            self.loops.pop();
+        }
+    }
+    fn visit_let(&mut self, l: &VerilogLet) {
+        let name = self.signal_name(&l.name);
+        self.widths.insert(name, l.width);
+        walk_let(self, l);
+    }
     fn visit_signal(&mut self, c: &str) {
-        let c = format!("{}${}", self.path.to_string(), c).replace("$next", "");
+        // Loop indices are not signals
+        if self.loops.iter().any(|x| x.variable == c) {
+            return;
+        }
+        let c = self.signal_name(c);
         match self.mode {
             ExpressionMode::Write => self.write_name = c,
             ExpressionMode::Read => self.add_read(&c, SignalNodeKind::Normal),
@@ -238,10 +546,12 @@ impl VerilogVisitor for TimingChecker {
 
 impl Probe for TimingChecker {
     fn visit_start_scope(&mut self, name: &str, node: &dyn Block) {
-        println!("Start scope {}", name);
         self.path.push(name);
         self.namespace.reset();
-        self.add_code(&self.path.to_string(), node.hdl());
+        for input in node.clock_crossings() {
+            self.crossings
+                .insert(format!("{}${}", self.path.to_string(), input));
+        }
         for info in &node.timing() {
             // The timing info represents a register.  A register
             // adds a write dependency based on the clock
@@ -282,7 +592,6 @@ impl Probe for TimingChecker {
         self.clear_scope();
     }
     fn visit_start_namespace(&mut self, name: &str, _node: &dyn Block) {
-        println!("Start Namespace {}", name);
         self.namespace.push(name);
     }
     fn visit_atom(&mut self, name: &str, signal: &dyn Atom) {
@@ -296,6 +605,8 @@ impl Probe for TimingChecker {
         // Add an async source for all input parameters at the top scope
         let is_top_scope = self.path.to_string().eq("top");
         let global_signal_name = format!("{}${}", self.path.to_string(), name);
+        self.widths
+            .insert(global_signal_name.clone(), signal.bits());
         match signal.kind() {
             AtomKind::InputParameter | AtomKind::InOutParameter => {
                 if is_top_scope {
@@ -310,6 +621,7 @@ impl Probe for TimingChecker {
                         },
                         my_id,
                         SignalEdgeKind::Extern,
+                        0,
                     );
                 }
             }
@@ -325,49 +637,68 @@ impl Probe for TimingChecker {
                     },
                     my_id,
                     SignalEdgeKind::Constant,
+                    0,
                 );
             }
             _ => {}
         }
         let descriptor = &signal.descriptor();
-        if let TypeKind::Enum(x) = &descriptor.kind {
-            for label in x {
-                let label = label.replace("::", "$");
-                let my_id = self.graph.add_signal_node(&SignalNode {
-                    name: format!("{}${}", module_path, label),
-                    kind: SignalNodeKind::Normal,
-                });
-                self.graph.add_signal_edge(
-                    &SignalNode {
-                        name: format!("const${}", label),
-                        kind: SignalNodeKind::Source,
-                    },
-                    my_id,
-                    SignalEdgeKind::Constant,
-                );
-                let my_id = self.graph.add_signal_node(&SignalNode {
-                    name: format!("{}${}", self.path.parent(), label),
-                    kind: SignalNodeKind::Normal,
-                });
-                self.graph.add_signal_edge(
-                    &SignalNode {
-                        name: format!("const${}", label),
-                        kind: SignalNodeKind::Source,
-                    },
-                    my_id,
-                    SignalEdgeKind::Constant,
-                );
-            }
+        for label in enum_labels(descriptor) {
+            let label = label.replace("::", "$");
+            let my_id = self.graph.add_signal_node(&SignalNode {
+                name: format!("{}${}", module_path, label),
+                kind: SignalNodeKind::Normal,
+            });
+            self.graph.add_signal_edge(
+                &SignalNode {
+                    name: format!("const${}", label),
+                    kind: SignalNodeKind::Source,
+                },
+                my_id,
+                SignalEdgeKind::Constant,
+                0,
+            );
+            let my_id = self.graph.add_signal_node(&SignalNode {
+                name: format!("{}${}", self.path.parent(), label),
+                kind: SignalNodeKind::Normal,
+            });
+            self.graph.add_signal_edge(
+                &SignalNode {
+                    name: format!("const${}", label),
+                    kind: SignalNodeKind::Source,
+                },
+                my_id,
+                SignalEdgeKind::Constant,
+                0,
+            );
         }
     }
     fn visit_end_namespace(&mut self, _name: &str, _node: &dyn Block) {
         self.namespace.pop();
     }
-    fn visit_end_scope(&mut self, _name: &str, _node: &dyn Block) {
+    fn visit_end_scope(&mut self, _name: &str, node: &dyn Block) {
+        // The code is analyzed once all of the signals in (and below) this
+        // scope have been seen, so that their widths are known.
+        self.clear_scope();
+        self.add_code(&self.path.to_string(), node.hdl());
         self.path.pop();
     }
 }
 
+// The labels of any enums in the type (including the fields of a struct, or the
+// tag of a tagged union)
+fn enum_labels(descriptor: &TypeDescriptor) -> Vec<String> {
+    match &descriptor.kind {
+        TypeKind::Enum(x) => x.clone(),
+        TypeKind::Composite(fields) => fields
+            .iter()
+            .flat_map(|field| enum_labels(&field.kind))
+            .collect(),
+        TypeKind::Array { element, .. } => enum_labels(element),
+        _ => vec![],
+    }
+}
+
 pub fn check_timing<U: Block>(uut: &U) {
     let mut scan = TimingChecker::default();
     uut.accept("top", &mut scan);
@@ -391,7 +722,7 @@ pub fn check_timing<U: Block>(uut: &U) {
     // For each label, we extract the vertices and build a new graph
     for subgraph in unique_labels {
         let mut remap: HashMap<_, _> = Default::default();
-        let mut s: Graph<SignalNode, SignalEdgeKind> = Graph::default();
+        let mut s: Graph<SignalNode, SignalEdge> = Graph::default();
         for (ndx, label) in labels.iter().enumerate() {
             if *label == subgraph {
                 let old_index = g.from_index(ndx);
@@ -418,3 +749,163 @@ pub fn check_timing<U: Block>(uut: &U) {
     }
     //std::fs::write("dag.dot", dot).unwrap();
 }
+
+// The name of a node in the signal graph, with the line of the kernel that writes it
+fn pathed_name(name: &str, sources: &HashMap<String, SourceLocation>) -> PathedName {
+    let source = sources.get(name).cloned();
+    let name = name.replace('$', ".");
+    match name.rsplit_once('.') {
+        Some((path, name)) => PathedName {
+            path: path.into(),
+            name: name.into(),
+            source,
+        },
+        None => PathedName {
+            path: Default::default(),
+            name,
+            source,
+        },
+    }
+}
+
+pub(crate) type SignalGraphType = Graph<SignalNode, SignalEdge>;
+
+// Build the signal graph of the circuit (with delays from the model), and collect the
+// inputs that are declared as clock domain crossings, and the line of the kernel that
+// writes each signal.
+pub(crate) fn signal_graph(
+    uut: &dyn Block,
+    model: &DelayModel,
+) -> (
+    SignalGraphType,
+    HashSet<String>,
+    HashMap<String, SourceLocation>,
+) {
+    let mut scan = TimingChecker {
+        model: model.clone(),
+        ..Default::default()
+    };
+    uut.accept("top", &mut scan);
+    (scan.graph.graph, scan.crossings, scan.sources)
+}
+
+// Each register has a sink (for its inputs and clock) and a source (for its
+// outputs) of the same name.  The clock domain of both is the root of the clock
+// of the sink.
+pub(crate) fn register_clocks(g: &SignalGraphType) -> HashMap<String, NodeIndex> {
+    let mut domains: HashMap<String, NodeIndex> = Default::default();
+    for node in g.node_indices() {
+        if g[node].kind != SignalNodeKind::Sink {
+            continue;
+        }
+        if let Some(clock) = g
+            .edges_directed(node, Incoming)
+            .find(|x| x.weight().kind == SignalEdgeKind::Clock)
+        {
+            domains.insert(g[node].name.clone(), clock_root(g, clock.source()));
+        }
+    }
+    domains
+}
+
+// Follow the clock back through any assignments to the signal that drives it.  A
+// clock that is assigned in more than one place (e.g., by `clock!` and then again
+// by hand) follows them all, as long as they lead back to the same clock.
+fn clock_root(g: &SignalGraphType, clock: NodeIndex) -> NodeIndex {
+    // The path holds the signals between the clock and the current one, to stop at loops
+    fn walk(g: &SignalGraphType, clock: NodeIndex, path: &mut Vec<NodeIndex>) -> NodeIndex {
+        let mut roots = g
+            .edges_directed(clock, Incoming)
+            .filter(|x| x.weight().kind == SignalEdgeKind::Assign)
+            .map(|x| x.source())
+            .collect::<Vec<_>>();
+        if roots.is_empty() || roots.iter().any(|x| path.contains(x) || *x == clock) {
+            return clock;
+        }
+        path.push(clock);
+        for root in roots.iter_mut() {
+            *root = walk(g, *root, path);
+        }
+        path.pop();
+        roots.sort();
+        roots.dedup();
+        match roots[..] {
+            [root] => root,
+            _ => clock,
+        }
+    }
+    walk(g, clock, &mut vec![])
+}
+
+/// Estimate the longest combinatorial path between registers in each clock domain
+/// of the circuit.  Each operator in the HDL is assigned a delay using the [DelayModel]
+/// (adders and comparisons by their width, muxes by their fan-in, etc.), and the
+/// delays are summed along the signal graph from the output of one register to
+/// the input of the next.  The result has one [TimingPath] for each clock that
+/// drives a register to register path, sorted by the name of the clock.  Paths
+/// from the inputs of the circuit (or that cross between clock domains) are not
+/// included.
+///
+/// Note that this is an estimate made from the HDL, before synthesis, and is meant
+/// to spot designs that are unlikely to close timing.  Blocks with custom Verilog
+/// (other than their registers) are treated as if they have no delay.
+pub fn estimate_timing<U: Block>(
+    uut: &U,
+    model: &DelayModel,
+) -> Result<Vec<TimingPath>, CheckError> {
+    let (g, _, sources) = signal_graph(uut, model);
+    let g = &g;
+    let order = toposort(g, None).map_err(|cycle| {
+        CheckError::LogicLoops(vec![pathed_name(&g[cycle.node_id()].name, &sources)])
+    })?;
+    let domains = register_clocks(g);
+    let mut clocks: Vec<NodeIndex> = domains.values().copied().collect();
+    clocks.sort_by_key(|x| g[*x].name.clone());
+    clocks.dedup();
+    let mut paths = vec![];
+    for clock in clocks {
+        let in_domain = |node: NodeIndex| domains.get(&g[node].name) == Some(&clock);
+        let mut arrival: Vec<Option<u64>> = vec![None; g.node_count()];
+        let mut previous: Vec<Option<NodeIndex>> = vec![None; g.node_count()];
+        for node in g.node_indices() {
+            if g[node].kind == SignalNodeKind::Source && in_domain(node) {
+                arrival[node.index()] = Some(model.clock_to_out);
+            }
+        }
+        for node in &order {
+            let Some(time) = arrival[node.index()] else {
+                continue;
+            };
+            for edge in g.edges_directed(*node, Outgoing) {
+                if edge.weight().kind == SignalEdgeKind::Clock {
+                    continue;
+                }
+                let next = edge.target().index();
+                let time = time + edge.weight().delay;
+                if arrival[next].map(|x| time > x).unwrap_or(true) {
+                    arrival[next] = Some(time);
+                    previous[next] = Some(*node);
+                }
+            }
+        }
+        let worst = g
+            .node_indices()
+            .filter(|x| g[*x].kind == SignalNodeKind::Sink && in_domain(*x))
+            .filter_map(|x| arrival[x.index()].map(|t| (t, x)))
+            .max_by_key(|(t, _)| *t);
+        if let Some((time, mut node)) = worst {
+            let mut path = vec![g[node].name.replace('$', ".")];
+            while let Some(prev) = previous[node.index()] {
+                path.push(g[prev].name.replace('$', "."));
+                node = prev;
+            }
+            path.reverse();
+            paths.push(TimingPath {
+                clock: g[clock].name.replace('$', "."),
+                delay: time + model.setup,
+                path,
+            });
+        }
+    }
+    Ok(paths)
+}
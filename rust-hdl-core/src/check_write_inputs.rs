@@ -1,7 +1,7 @@
 use crate::ast::{Verilog, VerilogExpression};
 use crate::atom::{Atom, AtomKind};
 use crate::block::Block;
-use crate::check_error::{CheckError, PathedName, PathedNameList};
+use crate::check_error::{CheckError, PathedName, PathedNameList, SignalSources};
 use crate::named_path::NamedPath;
 use crate::probe::Probe;
 use crate::verilog_visitor::VerilogVisitor;
@@ -119,11 +119,13 @@ impl Probe for CheckInputsNotDriven {
     fn visit_end_scope(&mut self, _name: &str, node: &dyn Block) {
         let written = get_write_list(node);
         let my_params = self.input_parameters.last().unwrap();
+        let sources = SignalSources::new(node);
         for param in my_params {
             if written.contains(param) {
                 self.failures.push(PathedName {
                     path: self.path.to_string(),
                     name: param.to_owned(),
+                    source: sources.write(param),
                 })
             }
         }
@@ -4,7 +4,7 @@ use crate::bits::Bits;
 use crate::block::Block;
 use crate::constraint::PinConstraint;
 use crate::logic::Logic;
-use crate::probe::Probe;
+use crate::probe::{Probe, ProbeMut};
 use crate::signal::{get_signal_id, Signal};
 use crate::sim_assert_eq;
 use crate::simulate::{Sim, Simulation};
@@ -139,4 +139,7 @@ impl<T: Synth> Block for Constant<T> {
     fn accept(&self, name: &str, probe: &mut dyn Probe) {
         probe.visit_atom(name, self);
     }
+
+    // Constants never change, so there is nothing to restore
+    fn accept_mut(&mut self, _name: &str, _probe: &mut dyn ProbeMut) {}
 }
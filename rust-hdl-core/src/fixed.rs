@@ -0,0 +1,378 @@
+//! Fixed point numbers for DSP style datapaths.
+//!
+//! A [Fixed] (or [UFixed]) number is held as an integer number of LSBs, with a binary
+//! point at a fixed position.  Arithmetic between two values of the same type wraps
+//! (just like [Bits](crate::bits::Bits) and [Signed](crate::signed::Signed)), while the
+//! `wide_*` methods return a type that is large enough to hold the exact result.  To
+//! move the binary point (or to drop bits), use one of the explicit conversions:
+//! [Fixed::truncate], [Fixed::round], [Fixed::saturate] or [Fixed::round_saturate].
+//! All of these can be used in an `#[hdl_gen]` kernel, where they produce the same
+//! bits in hardware as they do in simulation.
+//!
+//! ```
+//! # use rust_hdl_core::prelude::*;
+//! let c = Fixed::<2, 6>::from_f64(0.3);
+//! assert_eq!(c.raw(), 19); // 0.3 * 64 = 19.2
+//! let x = Fixed::<4, 4>::from_f64(-1.25);
+//! let p: Fixed<6, 10> = c.wide_mul(x);
+//! assert_eq!(p.to_f64(), -0.37109375);
+//! assert_eq!(p.round::<4, 4>().to_f64(), -0.375);
+//! assert_eq!(p.truncate::<4, 4>().to_f64(), -0.375);
+//! ```
+use std::fmt::{Display, Formatter};
+
+use num_bigint::BigInt;
+
+use crate::bits::Bits;
+use crate::signed::Signed;
+use crate::synth::Synth;
+
+/// A fixed point number type.  The [Synth] types that implement this trait have
+/// a binary point at a fixed position - `FRACTION` bits up from the least significant
+/// bit.
+pub trait FixedPoint: Synth {
+    /// The number of bits above the binary point (including the sign bit for signed types)
+    const INTEGER: usize;
+    /// The number of bits below the binary point
+    const FRACTION: usize;
+    /// True if the number is held in two's complement form
+    const SIGNED: bool;
+}
+
+// Integers are fixed point numbers without any fraction bits
+impl<const N: usize> FixedPoint for Bits<N> {
+    const INTEGER: usize = N;
+    const FRACTION: usize = 0;
+    const SIGNED: bool = false;
+}
+
+impl<const N: usize> FixedPoint for Signed<N> {
+    const INTEGER: usize = N;
+    const FRACTION: usize = 0;
+    const SIGNED: bool = true;
+}
+
+/// How the fraction bits that are dropped by a conversion are handled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// The dropped bits are discarded (i.e., the value is rounded towards -infinity)
+    Truncate,
+    /// The value is rounded to the nearest representable value (with halves rounded up)
+    Nearest,
+}
+
+/// How values that do not fit in the integer bits of a conversion are handled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// The high bits are dropped (so the value wraps around)
+    Wrap,
+    /// The value is clamped to the largest (or smallest) representable value
+    Saturate,
+}
+
+#[doc(hidden)]
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+// Checks (at compile time) that the result of a widening operation on an `<I, F>`
+// and an `<I2, F2>` value has the right size
+pub(crate) struct WideCheck<
+    const I: usize,
+    const F: usize,
+    const I2: usize,
+    const F2: usize,
+    const IO: usize,
+    const FO: usize,
+>;
+
+impl<
+        const I: usize,
+        const F: usize,
+        const I2: usize,
+        const F2: usize,
+        const IO: usize,
+        const FO: usize,
+    > WideCheck<I, F, I2, F2, IO, FO>
+{
+    pub(crate) const SUM: () = assert!(
+        IO == max(I, I2) + 1 && FO == max(F, F2),
+        "The result of a wide sum must have one more integer bit than the wider argument, and the fraction bits of the finer one"
+    );
+    pub(crate) const PRODUCT: () = assert!(
+        IO == I + I2 && FO == F + F2,
+        "The result of a wide product must have the integer (and fraction) bits of both arguments"
+    );
+}
+
+/// The smallest and largest raw values that fit into the given number of bits.
+pub(crate) fn raw_range(bits: usize, signed: bool) -> (BigInt, BigInt) {
+    if signed {
+        (
+            -(BigInt::from(1) << (bits - 1)),
+            (BigInt::from(1) << (bits - 1)) - 1,
+        )
+    } else {
+        (BigInt::from(0), (BigInt::from(1) << bits) - 1)
+    }
+}
+
+/// Move the binary point of a raw value from `fraction` bits to `to_fraction` bits,
+/// and fit the result into `to_bits` bits.
+fn convert_raw(
+    raw: i128,
+    fraction: usize,
+    to_fraction: usize,
+    to_bits: usize,
+    signed: bool,
+    rounding: Rounding,
+    overflow: Overflow,
+) -> i128 {
+    let raw = BigInt::from(raw);
+    let raw = if to_fraction >= fraction {
+        raw << (to_fraction - fraction)
+    } else {
+        let shift = fraction - to_fraction;
+        let raw = match rounding {
+            Rounding::Truncate => raw,
+            Rounding::Nearest => raw + (BigInt::from(1) << (shift - 1)),
+        };
+        // Shifting a BigInt rounds towards -infinity, as an arithmetic shift does
+        raw >> shift
+    };
+    let (min, max) = raw_range(to_bits, signed);
+    let raw = match overflow {
+        Overflow::Saturate => raw.clamp(min, max),
+        Overflow::Wrap => {
+            let modulus = BigInt::from(1) << to_bits;
+            let raw = ((raw % &modulus) + &modulus) % &modulus;
+            if raw > max {
+                raw - modulus
+            } else {
+                raw
+            }
+        }
+    };
+    i128::try_from(raw).unwrap()
+}
+
+macro_rules! define_fixed {
+    ($name: ident, $signed: expr, $doc: expr) => {
+        #[doc = $doc]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+        pub struct $name<const I: usize, const F: usize>(i128);
+
+        impl<const I: usize, const F: usize> $name<I, F> {
+            const VALID: () = assert!(
+                I + F > 0 && I + F <= 64,
+                "Fixed point numbers must have between 1 and 64 bits"
+            );
+            fn wrap(raw: i128) -> Self {
+                Self::convert_from(raw, F, Rounding::Truncate, Overflow::Wrap)
+            }
+            fn convert_from(
+                raw: i128,
+                fraction: usize,
+                rounding: Rounding,
+                overflow: Overflow,
+            ) -> Self {
+                #[allow(clippy::let_unit_value)]
+                let _ = Self::VALID;
+                Self(convert_raw(
+                    raw,
+                    fraction,
+                    F,
+                    I + F,
+                    $signed,
+                    rounding,
+                    overflow,
+                ))
+            }
+            /// Create a value from its raw representation (i.e., the value times 2^F).
+            /// Bits that do not fit are dropped.
+            pub fn from_raw(raw: i128) -> Self {
+                Self::wrap(raw)
+            }
+            /// The raw representation of the value (i.e., the value times 2^F)
+            pub fn raw(self) -> i128 {
+                self.0
+            }
+            /// Convert a floating point value (e.g., a filter coefficient), rounding to the
+            /// nearest representable value.  Values that are out of range are saturated.
+            pub fn from_f64(x: f64) -> Self {
+                // Convert with one extra fraction bit, so that the rounding matches the
+                // conversions between fixed point types
+                let scaled = (x * 2.0_f64.powi(F as i32 + 1)).floor();
+                let raw = if scaled.is_nan() {
+                    0
+                } else {
+                    scaled.clamp(-(2.0_f64.powi(66)), 2.0_f64.powi(66)) as i128
+                };
+                Self::convert_from(raw, F + 1, Rounding::Nearest, Overflow::Saturate)
+            }
+            /// The value as a floating point number
+            pub fn to_f64(self) -> f64 {
+                (self.0 as f64) / 2.0_f64.powi(F as i32)
+            }
+            /// The smallest value that can be represented
+            pub fn min_value() -> Self {
+                Self::convert_from(i128::MIN, F, Rounding::Truncate, Overflow::Saturate)
+            }
+            /// The largest value that can be represented
+            pub fn max_value() -> Self {
+                Self::convert_from(i128::MAX, F, Rounding::Truncate, Overflow::Saturate)
+            }
+            /// Convert to a different format, dropping any extra fraction bits, and
+            /// wrapping if the value does not fit.
+            pub fn truncate<const I2: usize, const F2: usize>(self) -> $name<I2, F2> {
+                $name::convert_from(self.0, F, Rounding::Truncate, Overflow::Wrap)
+            }
+            /// Convert to a different format, rounding to the nearest value, and
+            /// wrapping if the value does not fit.
+            pub fn round<const I2: usize, const F2: usize>(self) -> $name<I2, F2> {
+                $name::convert_from(self.0, F, Rounding::Nearest, Overflow::Wrap)
+            }
+            /// Convert to a different format, dropping any extra fraction bits, and
+            /// saturating if the value does not fit.
+            pub fn saturate<const I2: usize, const F2: usize>(self) -> $name<I2, F2> {
+                $name::convert_from(self.0, F, Rounding::Truncate, Overflow::Saturate)
+            }
+            /// Convert to a different format, rounding to the nearest value, and
+            /// saturating if the value does not fit.
+            pub fn round_saturate<const I2: usize, const F2: usize>(self) -> $name<I2, F2> {
+                $name::convert_from(self.0, F, Rounding::Nearest, Overflow::Saturate)
+            }
+            /// Add without overflow.  The result has one more integer bit than the
+            /// wider of the arguments, and as many fraction bits as the finer of the two.
+            pub fn wide_add<const I2: usize, const F2: usize, const IO: usize, const FO: usize>(
+                self,
+                rhs: $name<I2, F2>,
+            ) -> $name<IO, FO> {
+                #[allow(clippy::let_unit_value)]
+                let _ = WideCheck::<I, F, I2, F2, IO, FO>::SUM;
+                $name::wrap((self.0 << (FO - F)) + (rhs.0 << (FO - F2)))
+            }
+            /// Subtract without overflow.  The result has the same format as for
+            /// `wide_add`.
+            pub fn wide_sub<const I2: usize, const F2: usize, const IO: usize, const FO: usize>(
+                self,
+                rhs: $name<I2, F2>,
+            ) -> $name<IO, FO> {
+                #[allow(clippy::let_unit_value)]
+                let _ = WideCheck::<I, F, I2, F2, IO, FO>::SUM;
+                $name::wrap((self.0 << (FO - F)) - (rhs.0 << (FO - F2)))
+            }
+            /// Multiply without overflow or rounding.  The integer and fraction bits of the
+            /// result are the sums of those of the arguments.
+            pub fn wide_mul<const I2: usize, const F2: usize, const IO: usize, const FO: usize>(
+                self,
+                rhs: $name<I2, F2>,
+            ) -> $name<IO, FO> {
+                #[allow(clippy::let_unit_value)]
+                let _ = WideCheck::<I, F, I2, F2, IO, FO>::PRODUCT;
+                $name::wrap(self.0 * rhs.0)
+            }
+        }
+
+        impl<const I: usize, const F: usize> FixedPoint for $name<I, F> {
+            const INTEGER: usize = I;
+            const FRACTION: usize = F;
+            const SIGNED: bool = $signed;
+        }
+
+        impl<const I: usize, const F: usize> Display for $name<I, F> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                Display::fmt(&self.to_f64(), f)
+            }
+        }
+
+        impl<const I: usize, const F: usize> std::ops::Add for $name<I, F> {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self {
+                Self::wrap(self.0 + rhs.0)
+            }
+        }
+
+        impl<const I: usize, const F: usize> std::ops::Sub for $name<I, F> {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self {
+                Self::wrap(self.0 - rhs.0)
+            }
+        }
+    };
+}
+
+define_fixed!(
+    Fixed,
+    true,
+    "A signed (two's complement) fixed point number with `I` integer bits (including \
+     the sign bit) and `F` fraction bits, for a total of up to 64 bits.  So `Fixed<2, 14>` \
+     holds values from -2 up to (just under) 2 in steps of 2^-14.  See the \
+     [module](crate::fixed) documentation for details."
+);
+define_fixed!(
+    UFixed,
+    false,
+    "An unsigned fixed point number with `I` integer bits and `F` fraction bits.  \
+     It supports the same operations as [Fixed]."
+);
+
+impl<const I: usize, const F: usize> std::ops::Neg for Fixed<I, F> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::wrap(-self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_f64_rounds_and_saturates() {
+        assert_eq!(Fixed::<2, 4>::from_f64(0.5).raw(), 8);
+        assert_eq!(Fixed::<2, 4>::from_f64(-0.53).raw(), -8);
+        assert_eq!(Fixed::<2, 4>::from_f64(5.0), Fixed::<2, 4>::max_value());
+        assert_eq!(Fixed::<2, 4>::from_f64(-5.0).to_f64(), -2.0);
+        assert_eq!(UFixed::<2, 4>::from_f64(-1.0).raw(), 0);
+        assert_eq!(UFixed::<2, 4>::max_value().to_f64(), 3.9375);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let x = Fixed::<4, 4>::from_f64(-2.5625);
+        assert_eq!(x.truncate::<4, 2>().to_f64(), -2.75);
+        assert_eq!(x.round::<4, 2>().to_f64(), -2.5);
+        assert_eq!(x.truncate::<6, 6>().to_f64(), -2.5625);
+        let y = Fixed::<4, 4>::from_f64(7.5);
+        assert_eq!(y.truncate::<2, 4>().to_f64(), -0.5);
+        assert_eq!(y.saturate::<2, 4>(), Fixed::<2, 4>::max_value());
+        assert_eq!((-y).round_saturate::<2, 2>().to_f64(), -2.0);
+        assert_eq!(UFixed::<4, 4>::from_f64(15.9).round::<4, 0>().raw(), 0);
+        assert_eq!(
+            UFixed::<4, 4>::from_f64(15.9)
+                .round_saturate::<4, 0>()
+                .raw(),
+            15
+        );
+    }
+
+    #[test]
+    fn test_wide_arithmetic() {
+        let a = Fixed::<4, 4>::max_value();
+        let b = Fixed::<2, 6>::min_value();
+        let s: Fixed<5, 6> = a.wide_add(b);
+        assert_eq!(s.to_f64(), a.to_f64() + b.to_f64());
+        let d: Fixed<5, 6> = b.wide_sub(a);
+        assert_eq!(d.to_f64(), b.to_f64() - a.to_f64());
+        let p: Fixed<6, 10> = a.wide_mul(b);
+        assert_eq!(p.to_f64(), a.to_f64() * b.to_f64());
+        let u: UFixed<16, 0> = UFixed::<8, 0>::max_value().wide_mul(UFixed::<8, 0>::max_value());
+        assert_eq!(u.raw(), 255 * 255);
+        assert_eq!(a + a, Fixed::<4, 4>::from_raw(-2));
+    }
+}
@@ -0,0 +1,306 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::env::temp_dir;
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::ast::VerilogPropertyKind;
+use crate::block::Block;
+use crate::module_defines::generate_verilog;
+use crate::yosys::SynthError;
+
+// Properties are only checked once the circuit has settled, since the intermediate
+// values seen while the circuit converges can (legitimately) violate them.  So the
+// result of each property is recorded every time the block that holds it is
+// evaluated, and only the latest results are checked after the circuit settles.
+// A block that is not re-evaluated on a pass has the same inputs as before, so its
+// latest results still hold.  The results are keyed by the index the block has in
+// the [Sensitivity](crate::sensitivity::Sensitivity) map of the simulation (or by 0,
+// if the simulation updates the whole circuit on every pass).
+#[derive(Default)]
+struct PropertyMonitor {
+    block: usize,
+    results: BTreeMap<(usize, &'static str), (&'static str, bool)>,
+}
+
+thread_local! {
+    static MONITOR: RefCell<PropertyMonitor> = RefCell::new(PropertyMonitor::default());
+}
+
+#[doc(hidden)]
+pub fn check_property<F: FnOnce() -> bool>(kind: VerilogPropertyKind, test: F, text: &'static str) {
+    let label = match kind {
+        VerilogPropertyKind::Assert => "assertion",
+        VerilogPropertyKind::Assume => "assumption",
+        VerilogPropertyKind::Cover => return,
+    };
+    let holds = test();
+    MONITOR.with(|m| {
+        let mut m = m.borrow_mut();
+        let block = m.block;
+        m.results.insert((block, text), (label, holds));
+    });
+}
+
+// Forget the results of all properties.  Called when a simulation starts.
+pub(crate) fn reset_properties() {
+    MONITOR.with(|m| *m.borrow_mut() = PropertyMonitor::default());
+}
+
+// Called before the block with the given index is evaluated, so that its new
+// results replace the old ones.
+pub(crate) fn begin_block(block: usize) {
+    MONITOR.with(|m| {
+        let mut m = m.borrow_mut();
+        m.block = block;
+        if !m.results.is_empty() {
+            m.results.retain(|(b, _), _| *b != block);
+        }
+    });
+}
+
+/// Check the `hdl_assert!` and `hdl_assume!` properties of a (settled) circuit, using
+/// the results recorded while it settled.  Returns a description of the first property
+/// that does not hold.  The simulation calls this after every step, so you should not
+/// need to.
+pub fn check_properties() -> Option<String> {
+    MONITOR.with(|m| {
+        m.borrow()
+            .results
+            .iter()
+            .find(|(_, (_, holds))| !holds)
+            .map(|((_, text), (label, _))| format!("{} {}", label, text))
+    })
+}
+
+/// Assert that a condition holds in every (settled) state of the circuit.  This can be
+/// used in a `#[hdl_gen]` update function.  In simulation, a failed assertion stops the
+/// simulation with a [SimError::PropertyFailed](crate::simulate::SimError::PropertyFailed).
+/// In the generated Verilog, it becomes an `assert property` that can be proven with
+/// [formal_check].
+#[macro_export]
+macro_rules! hdl_assert {
+    ($test: expr) => {
+        $crate::formal::check_property(
+            $crate::ast::VerilogPropertyKind::Assert,
+            || $test,
+            concat!("`", stringify!($test), "` at ", file!(), ":", line!()),
+        )
+    };
+}
+
+/// Assume that a condition holds.  Formal tools will only consider the behaviors of
+/// the circuit in which it does, so this is generally used to constrain the inputs.
+/// In simulation, it is checked like [hdl_assert!], since a failure means the testbench
+/// is driving the circuit in a way that was ruled out.
+#[macro_export]
+macro_rules! hdl_assume {
+    ($test: expr) => {
+        $crate::formal::check_property(
+            $crate::ast::VerilogPropertyKind::Assume,
+            || $test,
+            concat!("`", stringify!($test), "` at ", file!(), ":", line!()),
+        )
+    };
+}
+
+/// Ask the formal tools to show that a condition can be reached.  It has no effect
+/// in simulation.
+#[macro_export]
+macro_rules! hdl_cover {
+    ($test: expr) => {
+        $crate::formal::check_property(
+            $crate::ast::VerilogPropertyKind::Cover,
+            || $test,
+            concat!("`", stringify!($test), "` at ", file!(), ":", line!()),
+        )
+    };
+}
+
+/// The kind of check run by SymbiYosys.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FormalMode {
+    /// Bounded model check - look for a failing assertion in the first `depth` cycles
+    BMC,
+    /// Prove the assertions hold (using k-induction with the given depth)
+    Prove,
+}
+
+/// The outcome of a [formal_check]
+#[derive(Clone, Debug, PartialEq)]
+pub enum FormalResult {
+    /// The assertions hold
+    Pass,
+    /// An assertion can fail.  The counterexample is a VCD trace (using the same
+    /// names as a RustHDL simulation trace) leading to the failure.
+    Fail { counterexample: Vec<u8> },
+    /// The induction did not succeed, usually because the depth is too small
+    Unknown,
+}
+
+/// Returns the contents of the SymbiYosys `.sby` file used to check the top level
+/// module in `top.v`.
+pub fn sby_file(mode: FormalMode, depth: usize) -> String {
+    let mode = match mode {
+        FormalMode::BMC => "bmc",
+        FormalMode::Prove => "prove",
+    };
+    format!(
+        "\
+[options]
+mode {mode}
+depth {depth}
+multiclock on
+
+[engines]
+smtbmc
+
+[script]
+read -formal top.v
+prep -top top
+
+[files]
+top.v
+"
+    )
+}
+
+/// Use SymbiYosys to check the `hdl_assert!` properties of a circuit, with the
+/// `hdl_assume!` properties as constraints.  The `prefix` names the (temporary)
+/// directory in which the check is run.  Requires `sby`, `yosys` and an SMT solver
+/// to be installed.
+pub fn formal_check<U: Block>(
+    prefix: &str,
+    uut: &U,
+    mode: FormalMode,
+    depth: usize,
+) -> Result<FormalResult, SynthError> {
+    let dir = temp_dir().as_path().join(prefix);
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir)?;
+    write!(
+        File::create(dir.join("top.v"))?,
+        "{}",
+        generate_verilog(uut)
+    )?;
+    write!(
+        File::create(dir.join("top.sby"))?,
+        "{}",
+        sby_file(mode, depth)
+    )?;
+    let output = Command::new("sby")
+        .current_dir(dir.clone())
+        .args(["-f", "top.sby"])
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    {
+        let mut debug = File::create(dir.join("sby.stdout"))?;
+        write!(debug, "{}", stdout)?;
+        write!(debug, "{}", stderr)?;
+    }
+    if stdout.contains("DONE (PASS") {
+        Ok(FormalResult::Pass)
+    } else if stdout.contains("DONE (FAIL") {
+        Ok(FormalResult::Fail {
+            counterexample: read_counterexample(&dir.join("top").join("engine_0"))?,
+        })
+    } else if stdout.contains("DONE (UNKNOWN") {
+        Ok(FormalResult::Unknown)
+    } else {
+        Err(SynthError::SynthesisFailed {
+            stdout,
+            stderr,
+            source: None,
+        })
+    }
+}
+
+fn read_counterexample(dir: &Path) -> Result<Vec<u8>, SynthError> {
+    let mut vcd = vec![];
+    for name in ["trace.vcd", "trace_induct.vcd"] {
+        if let Ok(file) = File::open(dir.join(name)) {
+            counterexample_to_vcd(file, &mut vcd)?;
+            break;
+        }
+    }
+    Ok(vcd)
+}
+
+#[derive(Default)]
+struct TraceScope {
+    vars: Vec<(String, vcd::Var)>,
+    children: BTreeMap<String, TraceScope>,
+}
+
+fn write_trace_scope<W: Write>(
+    scope: &TraceScope,
+    writer: &mut vcd::Writer<W>,
+) -> std::io::Result<()> {
+    for (name, var) in &scope.vars {
+        writer.var_def(var.var_type, var.size, var.code, name, var.index)?;
+    }
+    for (name, child) in &scope.children {
+        writer.add_module(name)?;
+        write_trace_scope(child, writer)?;
+        writer.upscope()?;
+    }
+    Ok(())
+}
+
+/// Convert a VCD trace of the `top` module (such as a counterexample written by
+/// SymbiYosys) into one that uses the hierarchy of the RustHDL circuit.  The flattened
+/// names of the generated Verilog (e.g., `counter$q`) become nested scopes
+/// (e.g., `uut.counter.q`), and signals internal to the formal tools are dropped.
+pub fn counterexample_to_vcd<R: Read, W: Write>(trace: R, out: W) -> std::io::Result<()> {
+    let mut parser = vcd::Parser::new(trace);
+    let header = parser.parse_header()?;
+    let top = header.find_scope(&["top"]).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "No top module in trace")
+    })?;
+    let mut root = TraceScope::default();
+    let mut keep = HashSet::new();
+    for item in &top.children {
+        if let vcd::ScopeItem::Var(var) = item {
+            let path = var.reference.split('$').collect::<Vec<_>>();
+            if path.iter().any(|x| x.is_empty()) || var.reference.starts_with("_witness_") {
+                continue;
+            }
+            let (name, scopes) = path.split_last().unwrap();
+            let mut scope = &mut root;
+            for x in scopes {
+                scope = scope.children.entry(x.to_string()).or_default();
+            }
+            scope.vars.push((name.to_string(), var.clone()));
+            keep.insert(var.code);
+        }
+    }
+    let mut writer = vcd::Writer::new(out);
+    if let Some((ts, unit)) = header.timescale {
+        writer.timescale(ts, unit)?;
+    }
+    writer.add_module("uut")?;
+    write_trace_scope(&root, &mut writer)?;
+    writer.upscope()?;
+    writer.enddefinitions()?;
+    for command in parser {
+        let command = command?;
+        match &command {
+            vcd::Command::ChangeScalar(id, _)
+            | vcd::Command::ChangeVector(id, _)
+            | vcd::Command::ChangeReal(id, _)
+            | vcd::Command::ChangeString(id, _)
+                if keep.contains(id) =>
+            {
+                writer.command(&command)?;
+            }
+            vcd::Command::Timestamp(_) | vcd::Command::Begin(_) | vcd::Command::End(_) => {
+                writer.command(&command)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::io::{BufWriter, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Writes a trace in FST (the compressed waveform format used by GTKWave).  The VCD
+/// trace written to it is piped through GTKWave's `vcd2fst` as the simulation runs,
+/// so the (much larger) VCD never touches the disk.  Requires `vcd2fst` to be installed.
+///
+/// Call [FSTWriter::finish] once the trace is complete, to wait for the conversion.
+pub struct FSTWriter {
+    stdin: Option<BufWriter<ChildStdin>>,
+    child: Child,
+}
+
+impl FSTWriter {
+    /// Start writing an FST trace to the file `name`.
+    pub fn new(name: &str) -> std::io::Result<FSTWriter> {
+        let mut child = Command::new("vcd2fst")
+            .args(["-v", "-", "-f", name])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().map(BufWriter::new);
+        Ok(FSTWriter { stdin, child })
+    }
+
+    /// Close the trace, and wait for `vcd2fst` to finish writing the file.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        if let Some(mut stdin) = self.stdin.take() {
+            stdin.flush()?;
+        }
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!(
+                "vcd2fst failed with {}",
+                status
+            )));
+        }
+        Ok(())
+    }
+
+    fn stdin(&mut self) -> std::io::Result<&mut BufWriter<ChildStdin>> {
+        self.stdin.as_mut().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "FST trace is closed")
+        })
+    }
+}
+
+impl Write for FSTWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stdin()?.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stdin()?.flush()
+    }
+}
@@ -6,6 +6,7 @@ pub mod bits;
 #[doc(hidden)]
 pub mod bitvec;
 pub mod block;
+pub mod check_clock_domains;
 pub mod check_connected;
 pub mod check_error;
 pub mod check_logic_loops;
@@ -16,22 +17,33 @@ pub mod code_writer;
 pub mod constant;
 pub mod constraint;
 pub mod direction;
+pub mod fixed;
+pub mod formal;
+pub mod fst;
 pub mod logic;
 pub mod module_defines;
 pub mod named_path;
 pub mod path_tools;
 pub mod prelude;
 pub mod probe;
+pub mod reset;
+pub mod sensitivity;
 #[doc(hidden)]
 pub mod short_bit_vec;
 pub mod signal;
 pub mod signed;
 pub mod simulate;
+pub mod simulate_async;
+pub mod snapshot;
 pub mod synth;
 pub mod timing;
+pub mod top_ports;
 pub mod top_wrap;
 pub mod type_descriptor;
 pub mod vcd_probe;
+pub mod verilator;
 pub mod verilog_gen;
+pub mod verilog_testbench;
 pub mod verilog_visitor;
+pub mod vhdl_gen;
 pub mod yosys;
@@ -1,4 +1,5 @@
-use crate::ast::{Verilog, VerilogLink};
+use crate::ast::{HDLLanguage, Verilog, VerilogLink};
+use crate::synth::VCDValue;
 use crate::timing::TimingInfo;
 
 pub trait Logic {
@@ -7,9 +8,33 @@ pub trait Logic {
     fn hdl(&self) -> Verilog {
         Verilog::Empty
     }
+    /// If [Logic::hdl] returns [Verilog::Custom] code, this can provide the equivalent
+    /// code in another language for [generate_hdl](crate::module_defines::generate_hdl).
+    /// Custom Verilog is valid SystemVerilog, so that translation falls back to it.  Generating
+    /// VHDL for a kernel with custom Verilog and no translation is an error.
+    fn hdl_custom(&self, _lang: HDLLanguage) -> Option<String> {
+        None
+    }
     fn timing(&self) -> Vec<TimingInfo> {
         vec![]
     }
+    /// The inputs of the block that may be driven from another clock domain, because the
+    /// block synchronizes them (e.g., the input of a `BitSynchronizer`).  These are the legal
+    /// crossing points for [check_clock_domains](crate::check_clock_domains::check_clock_domains).
+    fn clock_crossings(&self) -> Vec<String> {
+        vec![]
+    }
+    /// Simulation-only state of the block that is not held in a signal (e.g., the contents
+    /// of a `RAM`).  It is saved in a [Snapshot](crate::snapshot::Snapshot) along with the
+    /// signals, and handed to [Logic::set_sim_state] when the snapshot is restored.
+    fn sim_state(&self) -> Vec<VCDValue> {
+        vec![]
+    }
+    /// Replace the simulation-only state of the block with one returned by [Logic::sim_state]
+    /// (which is empty if the block had none).  Returns `false` if the state does not fit the block.
+    fn set_sim_state(&mut self, state: &[VCDValue]) -> bool {
+        state.is_empty()
+    }
 }
 
 pub fn logic_connect_fn<L: Logic>(x: &mut L) {
@@ -1,44 +1,73 @@
-use crate::ast::{Verilog, VerilogLink, VerilogLiteral};
+use crate::ast::{HDLLanguage, Verilog, VerilogLet, VerilogLink, VerilogLiteral};
 use crate::atom::AtomKind::{StubInputSignal, StubOutputSignal};
 use crate::atom::{is_atom_signed, Atom, AtomKind};
+use crate::bits::clog2;
 use crate::block::Block;
 use crate::check_error::check_all;
 use crate::code_writer::CodeWriter;
 use crate::named_path::NamedPath;
 use crate::probe::Probe;
 use crate::type_descriptor::{TypeDescriptor, TypeKind};
-use crate::verilog_gen::{verilog_combinatorial, verilog_link_extraction};
-use std::collections::BTreeMap;
+use crate::verilog_gen::{
+    combinatorial, verilog_array_extraction, verilog_function, verilog_function_extraction,
+    verilog_let_extraction, verilog_link_extraction, PackedArray, UnpackedArray,
+};
+use crate::vhdl_gen::vhdl_defines;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
 
 #[derive(Clone, Debug, Default)]
-struct SubModuleInvocation {
-    kind: String,
-    name: String,
+pub(crate) struct SubModuleInvocation {
+    pub(crate) kind: String,
+    pub(crate) name: String,
 }
 
 #[derive(Clone, Debug, Default)]
-struct ModuleDetails {
-    atoms: Vec<AtomDetails>,
-    sub_modules: Vec<SubModuleInvocation>,
-    enums: Vec<EnumDefinition>,
-    code: Verilog,
-    links: Vec<VerilogLink>,
+pub(crate) struct ModuleDetails {
+    pub(crate) atoms: Vec<AtomDetails>,
+    pub(crate) sub_modules: Vec<SubModuleInvocation>,
+    pub(crate) enums: Vec<EnumDefinition>,
+    pub(crate) code: Verilog,
+    // Replaces custom Verilog code when generating another language
+    pub(crate) custom: Option<String>,
+    pub(crate) links: Vec<VerilogLink>,
+    pub(crate) lets: Vec<VerilogLet>,
+    // The name of the type of the block, without the module paths (see [block_type_name])
+    pub(crate) block_type: String,
 }
 
 #[derive(Clone, Debug, PartialEq)]
-struct EnumDefinition {
+pub(crate) struct EnumDefinition {
     pub type_name: String,
     pub discriminant: String,
     pub value: usize,
 }
 
+/// The parameters of a module that is shared by several blocks: the constants whose values,
+/// and the signals whose widths, differ between the blocks.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ModuleParameters {
+    pub(crate) constants: BTreeSet<String>,
+    pub(crate) widths: BTreeSet<String>,
+}
+
+/// How a sub-module is instantiated: the (possibly shared) module it uses, and the
+/// parameters of that module that are given values other than their defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ModuleInstance {
+    pub(crate) module: String,
+    pub(crate) overrides: Vec<(String, String)>,
+}
+
 #[derive(Clone, Debug)]
-struct AtomDetails {
-    name: String,
-    kind: AtomKind,
-    width: usize,
-    const_val: VerilogLiteral,
-    signed: bool,
+pub(crate) struct AtomDetails {
+    pub(crate) name: String,
+    pub(crate) kind: AtomKind,
+    pub(crate) width: usize,
+    pub(crate) const_val: VerilogLiteral,
+    pub(crate) signed: bool,
+    pub(crate) descriptor: TypeDescriptor,
 }
 
 fn verilog_atom_name(x: &AtomKind) -> &str {
@@ -54,15 +83,22 @@ fn verilog_atom_name(x: &AtomKind) -> &str {
     }
 }
 
-fn decl(x: &AtomDetails) -> String {
+fn decl(x: &AtomDetails, parameters: &ModuleParameters) -> String {
     let signed = if x.signed { "signed" } else { "" };
     if x.kind == AtomKind::Constant {
+        let keyword = if parameters.constants.contains(&x.name) {
+            "parameter"
+        } else {
+            verilog_atom_name(&x.kind)
+        };
+        format!("{} {} {} = {};", keyword, signed, x.name, x.const_val)
+    } else if parameters.widths.contains(&x.name) {
         format!(
-            "{} {} {} = {};",
+            "{} {} [{}-1:0] {};",
             verilog_atom_name(&x.kind),
             signed,
-            x.name,
-            x.const_val
+            width_parameter(&x.name),
+            x.name
         )
     } else {
         if x.width == 1 {
@@ -79,14 +115,163 @@ fn decl(x: &AtomDetails) -> String {
     }
 }
 
+fn systemverilog_type(descriptor: &TypeDescriptor, width: usize, signed: bool) -> String {
+    match &descriptor.kind {
+        TypeKind::Enum(_) | TypeKind::Composite(_) => descriptor.name.clone(),
+        _ => {
+            let signed = if signed { " signed" } else { "" };
+            if width == 1 {
+                format!("logic{}", signed)
+            } else {
+                format!("logic{} [{}:0]", signed, width - 1)
+            }
+        }
+    }
+}
+
+fn systemverilog_decl(x: &AtomDetails, parameters: &ModuleParameters) -> String {
+    let kind = if parameters.widths.contains(&x.name) {
+        let signed = if x.signed { " signed" } else { "" };
+        format!("logic{} [{}-1:0]", signed, width_parameter(&x.name))
+    } else {
+        systemverilog_type(&x.descriptor, x.width, x.signed)
+    };
+    if x.kind == AtomKind::Constant {
+        let keyword = if parameters.constants.contains(&x.name) {
+            "parameter"
+        } else {
+            "localparam"
+        };
+        return format!("{} {} {} = {};", keyword, kind, x.name, x.const_val);
+    }
+    let prefix = match x.kind {
+        AtomKind::InputParameter => "input ",
+        AtomKind::OutputParameter => "output ",
+        AtomKind::StubOutputSignal => "wire ",
+        AtomKind::InOutParameter => "inout wire ",
+        AtomKind::OutputPassthrough => "output wire ",
+        _ => "",
+    };
+    format!("{}{} {};", prefix, kind, x.name)
+}
+
+fn unpacked_decl(lang: HDLLanguage, x: &UnpackedArray) -> String {
+    let kind = if lang == HDLLanguage::SystemVerilog {
+        let descriptor = TypeDescriptor {
+            name: Default::default(),
+            kind: TypeKind::Bits(x.width),
+        };
+        systemverilog_type(&descriptor, x.width, x.signed)
+    } else {
+        let signed = if x.signed { "signed" } else { "" };
+        if x.width == 1 {
+            format!("reg {}", signed)
+        } else {
+            format!("reg {} [{}:0]", signed, x.width - 1)
+        }
+    };
+    format!("{} {} [0:{}];", kind, x.name, x.count - 1)
+}
+
+// Let bindings are declared as plain vectors (even if they hold an enum or a struct),
+// since they are cleared to zero at the start of the kernel.
+fn let_atom(x: &VerilogLet) -> AtomDetails {
+    let kind = if x.signed {
+        TypeKind::Signed(x.width)
+    } else {
+        TypeKind::Bits(x.width)
+    };
+    AtomDetails {
+        name: x.name.clone(),
+        kind: AtomKind::LocalSignal,
+        width: x.width,
+        const_val: VerilogLiteral::from(0_u32),
+        signed: x.signed,
+        descriptor: TypeDescriptor {
+            name: x.descriptor.name.clone(),
+            kind,
+        },
+    }
+}
+
+fn enum_width(labels: &[String]) -> usize {
+    clog2(labels.len()).max(1)
+}
+
+// Emit the typedefs for the enum and struct types used by the design.  Struct fields
+// are listed from the most significant down, so they match the packing of a LogicStruct.
+fn systemverilog_typedef(descriptor: &TypeDescriptor, done: &mut Vec<String>, io: &mut CodeWriter) {
+    match &descriptor.kind {
+        TypeKind::Enum(labels) => {
+            if done.contains(&descriptor.name) {
+                return;
+            }
+            let width = enum_width(labels);
+            let values = labels
+                .iter()
+                .enumerate()
+                .map(|(ndx, label)| format!("{} = {}'d{}", label.replace("::", "$"), width, ndx))
+                .collect::<Vec<_>>()
+                .join(", ");
+            io.add(format!(
+                "typedef enum logic [{}:0] {{{}}} {};",
+                width - 1,
+                values,
+                descriptor.name
+            ));
+            done.push(descriptor.name.clone());
+        }
+        TypeKind::Composite(fields) => {
+            if done.contains(&descriptor.name) {
+                return;
+            }
+            for field in fields {
+                systemverilog_typedef(&field.kind, done, io);
+            }
+            io.add("typedef struct packed {");
+            io.push();
+            for field in fields.iter().rev() {
+                let width = match &field.kind.kind {
+                    TypeKind::Bits(w) | TypeKind::Signed(w) => *w,
+                    TypeKind::Enum(labels) => enum_width(labels),
+                    TypeKind::Composite(_) => 0,
+                    TypeKind::Array { width, count, .. } => width * count,
+                    TypeKind::Fixed {
+                        integer, fraction, ..
+                    } => integer + fraction,
+                };
+                let signed = field.kind.kind.is_signed();
+                io.add(format!(
+                    "{} {};",
+                    systemverilog_type(&field.kind, width, signed),
+                    field.fieldname
+                ));
+            }
+            io.pop();
+            io.add(format!("}} {};", descriptor.name));
+            done.push(descriptor.name.clone());
+        }
+        TypeKind::Array { element, .. } => systemverilog_typedef(element, done, io),
+        _ => {}
+    }
+}
+
 #[derive(Default)]
 pub struct ModuleDefines {
     path: NamedPath,
     namespace: NamedPath,
-    details: BTreeMap<String, ModuleDetails>,
+    pub(crate) details: BTreeMap<String, ModuleDetails>,
+    lang: HDLLanguage,
 }
 
 impl ModuleDefines {
+    /// Create a [ModuleDefines] that will generate code in the given language
+    pub fn new(lang: HDLLanguage) -> ModuleDefines {
+        ModuleDefines {
+            lang,
+            ..Default::default()
+        }
+    }
     fn add_atom(&mut self, module: &str, atom: AtomDetails) {
         let entry = self.details.entry(module.into()).or_default();
         entry.atoms.push(atom)
@@ -119,18 +304,29 @@ impl ModuleDefines {
                     self.add_enums(module, &item.kind);
                 }
             }
+            TypeKind::Array { element, .. } => self.add_enums(module, element),
             _ => {}
         }
     }
-    fn add_code(&mut self, module: &str, code: Verilog) {
+    fn add_code(&mut self, module: &str, code: Verilog, custom: Option<String>) {
         let entry = self.details.entry(module.into()).or_default();
+        entry.custom = custom;
         entry.links = match &code {
             Verilog::Combinatorial(code) => verilog_link_extraction(code),
             _ => {
                 vec![]
             }
         };
+        entry.lets = match &code {
+            Verilog::Combinatorial(code) => verilog_let_extraction(code),
+            _ => {
+                vec![]
+            }
+        };
         entry.code = code;
+        for x in entry.lets.clone() {
+            self.add_enums(module, &x.descriptor);
+        }
     }
 }
 
@@ -140,7 +336,15 @@ impl Probe for ModuleDefines {
         self.path.push(name);
         self.namespace.reset();
         self.add_submodule(&top_level, name, &self.path.to_string());
-        self.add_code(&self.path.to_string(), node.hdl());
+        let custom = match self.lang {
+            HDLLanguage::Verilog => None,
+            lang => node.hdl_custom(lang),
+        };
+        self.add_code(&self.path.to_string(), node.hdl(), custom);
+        self.details
+            .entry(self.path.to_string())
+            .or_default()
+            .block_type = block_type_name(node.type_name());
     }
 
     fn visit_start_namespace(&mut self, name: &str, _node: &dyn Block) {
@@ -162,6 +366,7 @@ impl Probe for ModuleDefines {
             width: signal.bits(),
             const_val: signal.verilog(),
             signed: is_atom_signed(signal),
+            descriptor: signal.descriptor(),
         };
         if param.kind.is_parameter() {
             let kind = if param.kind == AtomKind::InputParameter {
@@ -175,6 +380,7 @@ impl Probe for ModuleDefines {
                 width: signal.bits(),
                 const_val: signal.verilog(),
                 signed: is_atom_signed(signal),
+                descriptor: signal.descriptor(),
             };
             let parent_name = self.path.parent();
             self.add_atom(&parent_name, parent_param);
@@ -193,7 +399,7 @@ impl Probe for ModuleDefines {
     }
 }
 
-fn get_link_equivalence(link: &VerilogLink) -> (String, String) {
+pub(crate) fn get_link_equivalence(link: &VerilogLink) -> (String, String) {
     match link {
         VerilogLink::Forward(link) => (
             format!("{}${}", link.other_name, link.my_name),
@@ -217,15 +423,18 @@ fn get_link_equivalence(link: &VerilogLink) -> (String, String) {
 }
 
 impl ModuleDefines {
-    fn sub_module_invocation(
+    pub(crate) fn sub_module_invocation(
         &self,
         module_details: &ModuleDetails,
         child: &SubModuleInvocation,
+        instances: &BTreeMap<String, ModuleInstance>,
         io: &mut CodeWriter,
     ) {
         let entry = self.details.get(&child.kind).unwrap();
-        let submodule_kind = match &entry.code {
-            Verilog::Blackbox(b) => &b.name,
+        let instance = instances.get(&child.kind);
+        let submodule_kind = match (&entry.code, instance) {
+            (Verilog::Blackbox(b), _) => &b.name,
+            (_, Some(instance)) => &instance.module,
             _ => &child.kind,
         };
         let child_args = entry
@@ -244,13 +453,29 @@ impl ModuleDefines {
             })
             .collect::<Vec<_>>()
             .join(",\n");
-        io.add(format!("{} {}(\n", submodule_kind, child.name));
+        match instance {
+            Some(instance) if !instance.overrides.is_empty() => {
+                io.add(format!("{} #(", submodule_kind));
+                io.push();
+                io.add(
+                    instance
+                        .overrides
+                        .iter()
+                        .map(|(name, value)| format!(".{}({})", name, value))
+                        .collect::<Vec<_>>()
+                        .join(",\n"),
+                );
+                io.pop();
+                io.add(format!(") {}(\n", child.name));
+            }
+            _ => io.add(format!("{} {}(\n", submodule_kind, child.name)),
+        }
         io.push();
         io.add(child_args);
         io.pop();
         io.add(");\n");
     }
-    fn module_argument_is_passed_through_to_submodule(
+    pub(crate) fn module_argument_is_passed_through_to_submodule(
         &self,
         module_details: &ModuleDetails,
         module_arg_name: &str,
@@ -266,7 +491,11 @@ impl ModuleDefines {
         }
         false
     }
-    fn get_linked_argument_name(&self, module_details: &ModuleDetails, arg_name: &str) -> String {
+    pub(crate) fn get_linked_argument_name(
+        &self,
+        module_details: &ModuleDetails,
+        arg_name: &str,
+    ) -> String {
         for link in &module_details.links {
             let equiv = get_link_equivalence(link);
             if arg_name == equiv.0 {
@@ -278,7 +507,7 @@ impl ModuleDefines {
         }
         arg_name.to_string()
     }
-    fn signal_name_is_module_argument(
+    pub(crate) fn signal_name_is_module_argument(
         &self,
         module_details: &ModuleDetails,
         signal_name: &str,
@@ -291,7 +520,7 @@ impl ModuleDefines {
         }
         false
     }
-    fn stub_is_linked_to_module_argument(
+    pub(crate) fn stub_is_linked_to_module_argument(
         &self,
         module_details: &ModuleDetails,
         atom_name: &str,
@@ -311,10 +540,73 @@ impl ModuleDefines {
         }
         false
     }
+    fn decl(&self, x: &AtomDetails, parameters: &ModuleParameters) -> String {
+        if self.lang == HDLLanguage::SystemVerilog {
+            systemverilog_decl(x, parameters)
+        } else {
+            decl(x, parameters)
+        }
+    }
+    // The array-valued signals that the kernel indexes are unpacked (see [UnpackedArray])
+    fn unpacked_arrays(&self, module_details: &ModuleDetails) -> Vec<UnpackedArray> {
+        let indexed = match &module_details.code {
+            Verilog::Combinatorial(code) => verilog_array_extraction(code),
+            _ => return vec![],
+        };
+        module_details
+            .atoms
+            .iter()
+            .filter(|x| x.kind != AtomKind::Constant && indexed.contains(&x.name))
+            .filter(|x| {
+                !(x.kind.is_stub()
+                    && self.stub_is_linked_to_module_argument(module_details, &x.name))
+            })
+            .filter_map(|x| {
+                let mut element = None;
+                let mut kind = &x.descriptor.kind;
+                while let TypeKind::Array {
+                    element: inner,
+                    width,
+                    ..
+                } = kind
+                {
+                    element = Some((*width, inner.kind.is_signed()));
+                    kind = &inner.kind;
+                }
+                let (width, signed) = element?;
+                let name = x.name.clone();
+                let (name, packed) = match x.kind {
+                    AtomKind::LocalSignal => (name, PackedArray::None),
+                    // An output that a sub-module drives is a net, which the kernel only reads
+                    AtomKind::OutputParameter
+                        if self.module_argument_is_passed_through_to_submodule(
+                            module_details,
+                            &name,
+                        ) =>
+                    {
+                        (format!("{}$array", name), PackedArray::Source(name))
+                    }
+                    AtomKind::OutputParameter | AtomKind::StubInputSignal => {
+                        (format!("{}$array", name), PackedArray::Sink(name))
+                    }
+                    _ => (format!("{}$array", name), PackedArray::Source(name)),
+                };
+                Some(UnpackedArray {
+                    name,
+                    width,
+                    count: x.width / width,
+                    signed,
+                    packed,
+                })
+            })
+            .collect()
+    }
     fn process_module(
         &self,
         module_name: &str,
         module_details: &ModuleDetails,
+        parameters: &ModuleParameters,
+        instances: &BTreeMap<String, ModuleInstance>,
         io: &mut CodeWriter,
     ) {
         // Remap the output parameters to pass through (net type) in case we have a wrapper
@@ -363,28 +655,43 @@ impl ModuleDefines {
             .join(",");
         io.add(format!("\n\nmodule {}({});", module_name, module_args));
         io.push();
+        // The widths are declared ahead of the module arguments (which use them)
+        if !parameters.widths.is_empty() {
+            io.add("\n// Parameters");
+            atoms
+                .iter()
+                .filter(|x| parameters.widths.contains(&x.name))
+                .for_each(|x| {
+                    io.add(format!(
+                        "parameter {} = {};",
+                        width_parameter(&x.name),
+                        x.width
+                    ))
+                });
+        }
         if !args.is_empty() {
             io.add("\n// Module arguments");
             args.iter().for_each(|x| {
                 if !self.module_argument_is_passed_through_to_submodule(module_details, &x.name)
                     || x.kind != AtomKind::OutputParameter
                 {
-                    io.add(decl(x))
+                    io.add(self.decl(x, parameters))
                 } else {
                     // For some synthesis engines, you cannot pass a module argument
                     // to a child module if it is of reg type
                     let mut x = (*x).clone();
                     x.kind = AtomKind::OutputPassthrough;
-                    io.add(decl(&x))
+                    io.add(self.decl(&x, parameters))
                 }
             });
         }
         let submodules = &module_details.sub_modules;
         if !consts.is_empty() {
             io.add("\n// Constant declarations");
-            consts.iter().for_each(|x| io.add(decl(x)));
+            consts.iter().for_each(|x| io.add(self.decl(x, parameters)));
         }
-        if !module_details.enums.is_empty() & !wrapper_mode {
+        // SystemVerilog uses the (global) typedefs for the enums
+        if !module_details.enums.is_empty() & !wrapper_mode & (self.lang == HDLLanguage::Verilog) {
             io.add("\n// Enums");
             module_details.enums.iter().for_each(|x| {
                 io.add(format!(
@@ -398,28 +705,61 @@ impl ModuleDefines {
             io.add("\n// Stub signals");
             stubs.iter().for_each(|x| {
                 if !self.stub_is_linked_to_module_argument(module_details, &x.name) {
-                    io.add(decl(x))
+                    io.add(self.decl(x, parameters))
                 }
             });
         }
+        let arrays = self.unpacked_arrays(module_details);
         if !locals.is_empty() & !wrapper_mode {
             io.add("\n// Local signals");
-            locals.iter().for_each(|x| io.add(decl(x)));
+            locals
+                .iter()
+                .for_each(|x| match arrays.iter().find(|array| array.name == x.name) {
+                    Some(array) => io.add(unpacked_decl(self.lang, array)),
+                    None => io.add(self.decl(x, parameters)),
+                });
+        }
+        let copies = arrays
+            .iter()
+            .filter(|x| !matches!(x.packed, PackedArray::None))
+            .collect::<Vec<_>>();
+        if !copies.is_empty() {
+            io.add("\n// Unpacked arrays");
+            copies
+                .iter()
+                .for_each(|x| io.add(unpacked_decl(self.lang, x)));
+        }
+        if !module_details.lets.is_empty() & !wrapper_mode {
+            io.add("\n// Let bindings");
+            module_details
+                .lets
+                .iter()
+                .for_each(|x| io.add(self.decl(&let_atom(x), parameters)));
         }
         if !submodules.is_empty() & !wrapper_mode {
             io.add("\n// Sub module instances");
             for child in submodules {
-                self.sub_module_invocation(module_details, child, io);
+                self.sub_module_invocation(module_details, child, instances, io);
             }
         }
         match &module_details.code {
             Verilog::Combinatorial(code) => {
+                let functions = verilog_function_extraction(code);
+                if !functions.is_empty() {
+                    io.add("\n// Functions");
+                    functions.iter().for_each(|x| io.add(verilog_function(x)));
+                }
                 io.add("\n// Update code");
-                io.add(verilog_combinatorial(code));
+                let always = if self.lang == HDLLanguage::SystemVerilog {
+                    "always_comb"
+                } else {
+                    "always @(*)"
+                };
+                io.add(combinatorial(always, code, &arrays));
             }
             Verilog::Custom(code) => {
                 io.add("\n// Update code (custom)");
-                io.add(code);
+                io.add(module_details.custom.as_ref().unwrap_or(code));
             }
             Verilog::Wrapper(c) => {
                 io.add("\n// Update code (wrapper)");
@@ -428,6 +768,11 @@ impl ModuleDefines {
             Verilog::Blackbox(_) => {}
             Verilog::Empty => {}
         }
+        let always_comb = if self.lang == HDLLanguage::SystemVerilog {
+            "always_comb"
+        } else {
+            "always @(*)"
+        };
         for x in &module_details.links {
             let equiv = get_link_equivalence(x);
             if !self.signal_name_is_module_argument(module_details, &equiv.0)
@@ -436,7 +781,8 @@ impl ModuleDefines {
                 let txt = match x {
                     VerilogLink::Forward(x) => {
                         format!(
-                            "always @(*) {}${} = {}${};",
+                            "{} {}${} = {}${};",
+                            always_comb,
                             x.other_name.replace("[", "$").replace("]", ""),
                             x.my_name,
                             x.owner_name.replace("[", "$").replace("]", ""),
@@ -445,7 +791,8 @@ impl ModuleDefines {
                     }
                     VerilogLink::Backward(x) => {
                         format!(
-                            "always @(*) {}${} = {}${};",
+                            "{} {}${} = {}${};",
+                            always_comb,
                             x.owner_name.replace("[", "$").replace("]", ""),
                             x.my_name,
                             x.other_name.replace("[", "$").replace("]", ""),
@@ -471,23 +818,289 @@ impl ModuleDefines {
     }
 
     pub fn defines(&self) -> String {
+        if self.lang == HDLLanguage::VHDL {
+            return vhdl_defines(self);
+        }
         let mut io = CodeWriter::default();
-        self.details
-            .iter()
-            .filter(|x| x.0.len() != 0)
-            .filter(|x| !matches!(x.1.code, Verilog::Blackbox(_)))
-            .for_each(|k| {
-                let module_name = k.0;
-                let module_details = k.1;
-                self.process_module(module_name, module_details, &mut io);
-            });
-        self.details.iter().for_each(|x| match &x.1.code {
-            Verilog::Blackbox(b) => io.add(&b.code),
-            Verilog::Wrapper(w) => io.add(&w.cores),
-            _ => {}
-        });
+        if self.lang == HDLLanguage::SystemVerilog {
+            let mut done = vec![];
+            for details in self.details.values() {
+                for atom in &details.atoms {
+                    systemverilog_typedef(&atom.descriptor, &mut done, &mut io);
+                }
+                for x in &details.lets {
+                    systemverilog_typedef(&x.descriptor, &mut done, &mut io);
+                }
+            }
+        }
+        for (_, code) in self.shared_modules() {
+            io.add(code);
+        }
+        // Several instances of the same black box only need one copy of its code
+        let mut cores: Vec<&String> = vec![];
+        for details in self.details.values() {
+            let code = match &details.code {
+                Verilog::Blackbox(b) => &b.code,
+                Verilog::Wrapper(w) => &w.cores,
+                _ => continue,
+            };
+            if !cores.contains(&code) {
+                io.add(code);
+                cores.push(code);
+            }
+        }
         io.to_string()
     }
+
+    fn post_order(&self, path: &str, order: &mut Vec<String>) {
+        for child in &self.details[path].sub_modules {
+            self.post_order(&child.kind, order);
+        }
+        order.push(path.into());
+    }
+
+    // Render the modules (other than black boxes), children first.  Sub-blocks with the
+    // same structure (code, sub-modules and signals) share a single module, named after
+    // their type.  The constants whose values, and the signals whose widths, differ between
+    // them become parameters of the module, which the instances override as needed.
+    fn shared_modules(&self) -> Vec<(String, String)> {
+        let roots = match self.details.get("") {
+            Some(root) => root.sub_modules.clone(),
+            None => vec![],
+        };
+        let is_root = |path: &str| roots.iter().any(|x| x.kind == path);
+        let mut order = vec![];
+        for root in &roots {
+            self.post_order(&root.kind, &mut order);
+        }
+        let mut shared: Vec<SharedModule> = vec![];
+        let mut uses: BTreeMap<String, ModuleUse> = Default::default();
+        for path in order {
+            let details = &self.details[&path];
+            if matches!(details.code, Verilog::Blackbox(_)) {
+                continue;
+            }
+            // The top level modules keep their names
+            let found = if is_root(&path) {
+                None
+            } else {
+                shared.iter_mut().enumerate().find_map(|(ndx, module)| {
+                    let reference = &self.details[&module.path];
+                    let parameters = self.module_parameters(reference, details, &uses)?;
+                    let overrides = overrides(details, &parameters);
+                    module.parameters.constants.extend(parameters.constants);
+                    module.parameters.widths.extend(parameters.widths);
+                    Some((ndx, overrides))
+                })
+            };
+            let found = found.unwrap_or_else(|| {
+                shared.push(SharedModule {
+                    path: path.clone(),
+                    parameters: Default::default(),
+                });
+                (shared.len() - 1, vec![])
+            });
+            uses.insert(path, found);
+        }
+        // The black boxes, wrapped cores and unshared modules keep their names, so the
+        // shared modules cannot use them
+        let mut reserved = roots
+            .iter()
+            .map(|x| x.kind.clone())
+            .collect::<BTreeSet<_>>();
+        let core_module = Regex::new(r"\bmodule\s+([A-Za-z_][A-Za-z0-9_$]*)").unwrap();
+        for details in self.details.values() {
+            match &details.code {
+                Verilog::Blackbox(b) => {
+                    reserved.insert(b.name.clone());
+                }
+                Verilog::Wrapper(w) => {
+                    reserved.extend(core_module.captures_iter(&w.cores).map(|x| x[1].into()))
+                }
+                _ => {}
+            }
+        }
+        // A module that is used by a single block keeps the name of that block.  A module
+        // that is shared is named after the type of the blocks (without its generic
+        // arguments unless they are needed to tell the modules apart).
+        let mut count = vec![0; shared.len()];
+        for (ndx, _) in uses.values() {
+            count[*ndx] += 1;
+        }
+        let types = shared
+            .iter()
+            .zip(&count)
+            .map(|(x, n)| {
+                (*n > 1 && !is_root(&x.path)).then(|| self.details[&x.path].block_type.as_str())
+            })
+            .collect::<Vec<_>>();
+        reserved.extend(
+            shared
+                .iter()
+                .zip(&types)
+                .filter(|(_, full)| full.is_none())
+                .map(|(x, _)| x.path.clone()),
+        );
+        let names = shared
+            .iter()
+            .zip(&types)
+            .enumerate()
+            .map(|(ndx, (module, full))| {
+                let full = match full {
+                    Some(full) => *full,
+                    None => return module.path.clone(),
+                };
+                let base = base_type_name(full);
+                let others = types.iter().flatten();
+                if others.clone().filter(|x| base_type_name(x) == base).count() == 1
+                    && !reserved.contains(base)
+                {
+                    base.to_string()
+                } else if others.filter(|x| **x == full).count() == 1 && !reserved.contains(full) {
+                    full.to_string()
+                } else {
+                    let count = types[..ndx]
+                        .iter()
+                        .flatten()
+                        .filter(|x| **x == full)
+                        .count();
+                    format!("{}${}", full, count)
+                }
+            })
+            .collect::<Vec<_>>();
+        let instances = uses
+            .into_iter()
+            .map(|(path, (ndx, overrides))| {
+                let instance = ModuleInstance {
+                    module: names[ndx].clone(),
+                    overrides,
+                };
+                (path, instance)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let mut modules = shared
+            .iter()
+            .zip(names)
+            .map(|(module, name)| {
+                let mut io = CodeWriter::default();
+                let details = &self.details[&module.path];
+                self.process_module(&name, details, &module.parameters, &instances, &mut io);
+                (name, io.to_string())
+            })
+            .collect::<Vec<_>>();
+        modules.sort_by(|a, b| a.0.cmp(&b.0));
+        modules
+    }
+
+    // The parameters that a module rendered from `reference` needs, so that it can also be
+    // used for a block with the given details.  Returns `None` if the two differ in more
+    // than the values of their constants and the widths of their (plain vector) signals.
+    fn module_parameters(
+        &self,
+        reference: &ModuleDetails,
+        details: &ModuleDetails,
+        uses: &BTreeMap<String, ModuleUse>,
+    ) -> Option<ModuleParameters> {
+        if base_type_name(&reference.block_type) != base_type_name(&details.block_type)
+            || reference.code != details.code
+            || reference.custom != details.custom
+            || reference.enums != details.enums
+            || reference.atoms.len() != details.atoms.len()
+            || reference.sub_modules.len() != details.sub_modules.len()
+        {
+            return None;
+        }
+        // The sub-modules must be the same (with the same parameters)
+        for (mine, theirs) in reference.sub_modules.iter().zip(&details.sub_modules) {
+            if mine.name != theirs.name {
+                return None;
+            }
+            match (uses.get(&mine.kind), uses.get(&theirs.kind)) {
+                (Some(x), Some(y)) if x == y => {}
+                (None, None)
+                    if self.details[&mine.kind].code == self.details[&theirs.kind].code => {}
+                _ => return None,
+            }
+        }
+        let mut parameters = ModuleParameters::default();
+        for (mine, theirs) in reference.atoms.iter().zip(&details.atoms) {
+            if mine.name != theirs.name || mine.kind != theirs.kind || mine.signed != theirs.signed
+            {
+                return None;
+            }
+            if mine.kind == AtomKind::Constant {
+                if mine.width != theirs.width || mine.descriptor != theirs.descriptor {
+                    return None;
+                }
+                if mine.const_val != theirs.const_val {
+                    parameters.constants.insert(mine.name.clone());
+                }
+            } else if mine.width != theirs.width || mine.descriptor != theirs.descriptor {
+                match (&mine.descriptor.kind, &theirs.descriptor.kind) {
+                    (TypeKind::Bits(_), TypeKind::Bits(_))
+                    | (TypeKind::Signed(_), TypeKind::Signed(_)) => {
+                        parameters.widths.insert(mine.name.clone());
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        Some(parameters)
+    }
+}
+
+// The shared module used by a block (as an index into the shared modules), and the values
+// it gives to the parameters of the module
+type ModuleUse = (usize, Vec<(String, String)>);
+
+// A module that is shared by all of the blocks with the same structure as the one at `path`
+struct SharedModule {
+    path: String,
+    parameters: ModuleParameters,
+}
+
+// The values a block gives to the parameters of the module it shares
+fn overrides(details: &ModuleDetails, parameters: &ModuleParameters) -> Vec<(String, String)> {
+    details
+        .atoms
+        .iter()
+        .filter_map(|x| {
+            if parameters.constants.contains(&x.name) {
+                Some((x.name.clone(), x.const_val.to_string()))
+            } else if parameters.widths.contains(&x.name) {
+                Some((width_parameter(&x.name), x.width.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn width_parameter(name: &str) -> String {
+    format!("{}$WIDTH", name)
+}
+
+// The name of a block type without the module paths, e.g., the name of
+// `rust_hdl_widgets::dff::DFF<rust_hdl_core::bits::Bits<8>>` is `DFF$Bits$8`
+fn block_type_name(type_name: &str) -> String {
+    static WORDS: OnceLock<Regex> = OnceLock::new();
+    let words = WORDS.get_or_init(|| Regex::new(r"[A-Za-z0-9_]+").unwrap());
+    let mut name: Vec<&str> = vec![];
+    let mut last = 0;
+    for word in words.find_iter(type_name) {
+        // A word that follows a `::` replaces the path before it
+        if !name.is_empty() && &type_name[last..word.start()] == "::" {
+            name.pop();
+        }
+        name.push(word.as_str());
+        last = word.end();
+    }
+    name.join("$")
+}
+
+// The name of a block type without its generic arguments
+fn base_type_name(name: &str) -> &str {
+    name.split('$').next().unwrap_or_default()
 }
 
 pub fn generate_verilog<U: Block>(uut: &U) -> String {
@@ -497,6 +1110,15 @@ pub fn generate_verilog<U: Block>(uut: &U) -> String {
     defines.defines()
 }
 
+/// Generate the code for a circuit in the given [HDLLanguage].  This performs the same
+/// checks as [generate_verilog], and for [HDLLanguage::Verilog] the output is identical.
+pub fn generate_hdl<U: Block>(lang: HDLLanguage, uut: &U) -> String {
+    let mut defines = ModuleDefines::new(lang);
+    check_all(uut).unwrap();
+    uut.accept("top", &mut defines);
+    defines.defines()
+}
+
 pub fn generate_verilog_unchecked<U: Block>(uut: &U) -> String {
     let mut defines = ModuleDefines::default();
     uut.accept("top", &mut defines);
@@ -1,9 +1,15 @@
 pub use crate::ast;
 pub use crate::ast::BlackBox;
+pub use crate::ast::HDLLanguage;
+pub use crate::ast::SourceLocation;
 pub use crate::ast::Verilog;
 pub use crate::ast::VerilogLiteral;
 pub use crate::ast::Wrapper;
 pub use crate::atom::{Atom, AtomKind};
+pub use crate::await_clock_cycle;
+pub use crate::await_clock_cycles;
+pub use crate::await_clock_false;
+pub use crate::await_clock_true;
 pub use crate::bits::bit_cast;
 pub use crate::bits::bits;
 pub use crate::bits::clog2;
@@ -12,9 +18,10 @@ pub use crate::bits::ToBits;
 pub use crate::bits::{Bit, Bits};
 pub use crate::block;
 pub use crate::block::Block;
+pub use crate::check_clock_domains::check_clock_domains;
 pub use crate::check_connected::check_connected;
 pub use crate::check_error::check_all;
-pub use crate::check_timing::check_timing;
+pub use crate::check_timing::{check_timing, estimate_timing, DelayModel, TimingPath};
 pub use crate::clock;
 pub use crate::clock::freq_hz_to_period_femto;
 pub use crate::clock::Clock;
@@ -23,15 +30,25 @@ pub use crate::constant::Constant;
 pub use crate::constraint::Timing::*;
 pub use crate::constraint::*;
 pub use crate::direction::{Direction, In, InOut, Local, Out};
+pub use crate::fixed;
+pub use crate::fixed::{Fixed, FixedPoint, UFixed};
+pub use crate::formal::{formal_check, FormalMode, FormalResult};
+pub use crate::fst::FSTWriter;
+pub use crate::hdl_assert;
+pub use crate::hdl_assume;
+pub use crate::hdl_cover;
 pub use crate::logic;
 pub use crate::logic::Logic;
 pub use crate::logic::LogicJoin;
 pub use crate::logic::LogicLink;
 pub use crate::module_defines::ModuleDefines;
-pub use crate::module_defines::{generate_verilog, generate_verilog_unchecked};
+pub use crate::module_defines::{generate_hdl, generate_verilog, generate_verilog_unchecked};
 pub use crate::named_path::NamedPath;
 pub use crate::probe;
 pub use crate::probe::Probe;
+pub use crate::reset::{Reset, ResetN, ResetType};
+pub use crate::sensitivity;
+pub use crate::sensitivity::Sensitivity;
 pub use crate::signal::Signal;
 pub use crate::signed::ToSignedBits;
 pub use crate::signed::{
@@ -44,6 +61,8 @@ pub use crate::simulate::sim_time;
 pub use crate::simulate::simulate;
 pub use crate::simulate::SIMULATION_TIME_ONE_SECOND;
 pub use crate::simulate::{Sim, SimError, Simulation};
+pub use crate::simulate_async::{AsyncSim, AsyncSimulation};
+pub use crate::snapshot::Snapshot;
 pub use crate::synth;
 pub use crate::synth::Synth;
 pub use crate::synth::VCDValue;
@@ -53,12 +72,19 @@ pub use crate::top_wrap::TopWrap;
 pub use crate::type_descriptor;
 pub use crate::type_descriptor::{TypeDescriptor, TypeField, TypeKind};
 pub use crate::vcd_path;
-pub use crate::vcd_probe::{write_vcd_change, write_vcd_dump, write_vcd_header};
+pub use crate::vcd_probe::{
+    write_vcd_change, write_vcd_dump, write_vcd_header, write_vcd_header_filtered, TraceFilter,
+};
+pub use crate::verilator::VerilatorCosim;
 pub use crate::verilog_gen::filter_blackbox_directives;
+pub use crate::verilog_testbench::{iverilog_run_testbench, VerilogTestbench};
 pub use crate::verilog_visitor::VerilogVisitor;
+pub use crate::vhdl_gen::{vhdl_bit_string, vhdl_ident, vhdl_type};
 pub use crate::wait_clock_cycle;
 pub use crate::wait_clock_cycles;
 pub use crate::wait_clock_false;
 pub use crate::wait_clock_true;
 pub use crate::yosys::*;
-pub use rust_hdl_macros::{hdl_gen, LogicBlock, LogicInterface, LogicState, LogicStruct};
+pub use rust_hdl_macros::{
+    black_box, hdl_function, hdl_gen, LogicBlock, LogicInterface, LogicState, LogicStruct,
+};
@@ -1,4 +1,4 @@
-use crate::atom::Atom;
+use crate::atom::{Atom, AtomMut};
 use crate::block::Block;
 
 pub trait Probe {
@@ -8,3 +8,17 @@ pub trait Probe {
     fn visit_end_namespace(&mut self, _name: &str, _node: &dyn Block) {}
     fn visit_end_scope(&mut self, _name: &str, _node: &dyn Block) {}
 }
+
+/// The mutable counterpart of [Probe], visited by [Block::accept_mut].  The scopes
+/// and names are the same as the ones seen by a [Probe], but the atoms can be changed.
+/// This is used to restore the state of a circuit from a [Snapshot](crate::snapshot::Snapshot).
+pub trait ProbeMut {
+    fn visit_start_scope(&mut self, _name: &str, _node: &mut dyn Block) {}
+    fn visit_start_namespace(&mut self, _name: &str) {}
+    fn visit_atom(&mut self, _name: &str, _signal: &mut dyn AtomMut) {}
+    fn visit_end_namespace(&mut self, _name: &str) {}
+    fn visit_end_scope(&mut self, _name: &str) {}
+    /// Visited in place of a block that does not implement [Block::accept_mut], and so
+    /// cannot be changed by the probe.
+    fn visit_unsupported(&mut self, _name: &str) {}
+}
@@ -0,0 +1,92 @@
+use crate::synth::Synth;
+
+/// A reset signal in RustHDL is (like a [Clock](crate::clock::Clock)) a transparent wrapper
+/// around a boolean valued signal.  Giving resets their own types means that a reset net
+/// cannot be accidentally connected to a data input (or vice versa), and that the polarity
+/// of the reset is part of its type.  [Reset] is active high, and [ResetN] is active low.
+///
+/// Blocks that are reset (like the `DFFWithReset` in the widgets crate) are generic over
+/// the [ResetType], so that the same block can be used with either polarity.
+pub trait ResetType: Synth + From<bool> {
+    /// True if the reset is asserted when the signal is low.
+    const ACTIVE_LOW: bool;
+    /// Returns true if the reset is currently asserted.
+    fn asserted(self) -> bool;
+    /// The value of the signal that asserts the reset.
+    fn assert() -> Self {
+        (!Self::ACTIVE_LOW).into()
+    }
+    /// The value of the signal that releases the reset.
+    fn release() -> Self {
+        Self::ACTIVE_LOW.into()
+    }
+}
+
+/// An active high reset.  The reset is asserted when `rst` is true.
+#[repr(transparent)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct Reset {
+    /// The reset signal itself.
+    pub rst: bool,
+}
+
+/// An active low reset.  The reset is asserted when `rst_n` is false.  Note that the
+/// default value of a [ResetN] is asserted.
+#[repr(transparent)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct ResetN {
+    /// The reset signal itself.
+    pub rst_n: bool,
+}
+
+impl From<bool> for Reset {
+    fn from(x: bool) -> Reset {
+        Reset { rst: x }
+    }
+}
+
+impl From<bool> for ResetN {
+    fn from(x: bool) -> ResetN {
+        ResetN { rst_n: x }
+    }
+}
+
+impl std::ops::Not for Reset {
+    type Output = Reset;
+
+    fn not(self) -> Self::Output {
+        Reset { rst: !self.rst }
+    }
+}
+
+impl std::ops::Not for ResetN {
+    type Output = ResetN;
+
+    fn not(self) -> Self::Output {
+        ResetN { rst_n: !self.rst_n }
+    }
+}
+
+impl ResetType for Reset {
+    const ACTIVE_LOW: bool = false;
+    fn asserted(self) -> bool {
+        self.rst
+    }
+}
+
+impl ResetType for ResetN {
+    const ACTIVE_LOW: bool = true;
+    fn asserted(self) -> bool {
+        !self.rst_n
+    }
+}
+
+#[test]
+fn test_reset_polarity() {
+    assert!(Reset::assert().asserted());
+    assert!(!Reset::release().asserted());
+    assert!(ResetN::assert().asserted());
+    assert!(!ResetN::release().asserted());
+    assert_eq!(ResetN::assert(), ResetN { rst_n: false });
+    assert!(!Reset::default().asserted());
+}
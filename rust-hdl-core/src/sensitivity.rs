@@ -0,0 +1,440 @@
+use crate::ast::{Verilog, VerilogConditional, VerilogExpression, VerilogLink, VerilogMatch};
+use crate::block::Block;
+use crate::formal::{begin_block, reset_properties};
+use crate::probe::Probe;
+use crate::verilog_visitor::{walk_conditional, walk_match, VerilogVisitor};
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::Graph;
+use std::collections::{HashMap, HashSet};
+
+// Collects the signal graph of a block's HDL kernel.  This is the same read/write
+// split that the logic loop check uses, except that here we keep the reads, and
+// which signals each write depends on (including the conditions it is made under).
+#[derive(Default)]
+struct SignalGraph {
+    writing: bool,
+    sources: Vec<HashSet<String>>,
+    reads: HashSet<String>,
+    writes: HashMap<String, HashSet<String>>,
+}
+
+impl SignalGraph {
+    fn depend(&mut self, target: &str, source: &str) {
+        self.writes
+            .entry(target.to_string())
+            .or_default()
+            .insert(source.to_string());
+    }
+}
+
+impl VerilogVisitor for SignalGraph {
+    fn visit_slice_assignment(
+        &mut self,
+        base: &VerilogExpression,
+        _width: &usize,
+        offset: &VerilogExpression,
+        replacement: &VerilogExpression,
+    ) {
+        // A slice assignment reads the base as well as writing it
+        self.sources.push(Default::default());
+        self.visit_expression(offset);
+        self.visit_expression(replacement);
+        self.visit_expression(base);
+        let writing = self.writing;
+        self.writing = true;
+        self.visit_expression(base);
+        self.writing = writing;
+        self.sources.pop();
+    }
+
+    fn visit_conditional(&mut self, c: &VerilogConditional) {
+        self.sources.push(Default::default());
+        walk_conditional(self, c);
+        self.sources.pop();
+    }
+
+    fn visit_match(&mut self, m: &VerilogMatch) {
+        self.sources.push(Default::default());
+        walk_match(self, m);
+        self.sources.pop();
+    }
+
+    fn visit_signal(&mut self, c: &str) {
+        let name = c.replace("$next", "");
+        if self.writing {
+            let sources = self.sources.iter().flatten().cloned().collect::<Vec<_>>();
+            for source in sources {
+                self.depend(&name, &source);
+            }
+        } else {
+            if let Some(sources) = self.sources.last_mut() {
+                sources.insert(name.clone());
+            }
+            self.reads.insert(name);
+        }
+    }
+
+    fn visit_link(&mut self, c: &[VerilogLink]) {
+        for link in c {
+            let details = match link {
+                VerilogLink::Forward(x)
+                | VerilogLink::Backward(x)
+                | VerilogLink::Bidirectional(x) => x,
+            };
+            self.reads.insert(details.owner_name.clone());
+            self.reads.insert(details.other_name.clone());
+            self.depend(&details.owner_name, &details.other_name);
+            self.depend(&details.other_name, &details.owner_name);
+        }
+    }
+
+    fn visit_assignment(&mut self, l: &VerilogExpression, r: &VerilogExpression) {
+        self.sources.push(Default::default());
+        self.visit_expression(r);
+        let writing = self.writing;
+        self.writing = true;
+        self.visit_expression(l);
+        self.writing = writing;
+        self.sources.pop();
+    }
+}
+
+fn get_signal_graph(uut: &dyn Block) -> Option<SignalGraph> {
+    match &uut.hdl() {
+        Verilog::Combinatorial(code) => {
+            let mut graph = SignalGraph::default();
+            graph.visit_block(code);
+            Some(graph)
+        }
+        _ => None,
+    }
+}
+
+fn field_owns(name: &str, field: &str) -> bool {
+    name == field
+        || (name.starts_with(field)
+            && matches!(name.as_bytes().get(field.len()), Some(b'$') | Some(b'[')))
+}
+
+// The fields of a block, in the order they are evaluated, and what is needed to
+// evaluate them in that order.
+#[derive(Clone, Debug)]
+struct Schedule {
+    // Which fields the kernel of the block reads
+    mask: Vec<bool>,
+    // The fields in topological order
+    order: Vec<usize>,
+    // The index of the first sub-block of each field, if it has one
+    start: Vec<Option<usize>>,
+    // The fields that each field depends on through the kernel of the block
+    preds: Vec<Vec<usize>>,
+    // The fields that the kernel should be re-evaluated for when one of their
+    // predecessors changes during a pass
+    refresh: Vec<bool>,
+    // The changed fields that the kernel has already seen on this pass
+    seen: Vec<bool>,
+}
+
+#[derive(Default)]
+struct SensitivityNode {
+    span: usize,
+    active: bool,
+    graph: Option<SignalGraph>,
+    combinational: bool,
+    children: Vec<(String, usize)>,
+    schedule: Option<Schedule>,
+}
+
+impl SensitivityNode {
+    fn schedule(&self, nodes: &[SensitivityNode], fields: &[&str]) -> Schedule {
+        let mask = match &self.graph {
+            Some(graph) => fields
+                .iter()
+                .map(|x| graph.reads.iter().any(|r| field_owns(r, x)))
+                .collect(),
+            None => vec![true; fields.len()],
+        };
+        let start = fields
+            .iter()
+            .map(|x| {
+                self.children
+                    .iter()
+                    .find(|(label, _)| field_owns(label, x))
+                    .map(|(_, id)| *id)
+            })
+            .collect::<Vec<_>>();
+        let combinational = fields
+            .iter()
+            .map(|x| {
+                self.children
+                    .iter()
+                    .filter(|(label, _)| field_owns(label, x))
+                    .all(|(_, id)| nodes[*id].combinational)
+            })
+            .collect::<Vec<_>>();
+        let preds = match &self.graph {
+            Some(graph) => field_dependencies(graph, fields),
+            None => vec![vec![]; fields.len()],
+        };
+        // The fields in topological order.  Fields that depend on each other (through
+        // a register, for example) keep the order in which they are declared.
+        let mut dependencies = Graph::<usize, ()>::new();
+        let ids = (0..fields.len())
+            .map(|x| dependencies.add_node(x))
+            .collect::<Vec<_>>();
+        for (field, preds) in preds.iter().enumerate() {
+            for pred in preds {
+                dependencies.add_edge(ids[*pred], ids[field], ());
+            }
+        }
+        let mut order = vec![];
+        for mut scc in tarjan_scc(&dependencies).into_iter().rev() {
+            scc.sort();
+            order.extend(scc.into_iter().map(|x| dependencies[x]));
+        }
+        let refresh = preds
+            .iter()
+            .zip(combinational.iter())
+            .map(|(preds, combinational)| {
+                self.graph.is_some() && *combinational && !preds.is_empty()
+            })
+            .collect();
+        Schedule {
+            mask,
+            order,
+            start,
+            preds,
+            refresh,
+            seen: vec![false; fields.len()],
+        }
+    }
+}
+
+// For each field of a block, the other fields that feed it through the kernel of the
+// block (possibly via local signals).
+fn field_dependencies(graph: &SignalGraph, fields: &[&str]) -> Vec<Vec<usize>> {
+    let mut fanout: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (target, sources) in &graph.writes {
+        for source in sources {
+            fanout.entry(source).or_default().push(target);
+        }
+    }
+    let mut preds = vec![vec![]; fields.len()];
+    for (ndx, field) in fields.iter().enumerate() {
+        let mut reached = HashSet::new();
+        let mut pending = fanout
+            .keys()
+            .filter(|x| field_owns(x, field))
+            .copied()
+            .collect::<Vec<_>>();
+        while let Some(name) = pending.pop() {
+            if let Some(targets) = fanout.get(name) {
+                for target in targets {
+                    if reached.insert(*target) {
+                        pending.push(target);
+                    }
+                }
+            }
+        }
+        for (other, other_field) in fields.iter().enumerate() {
+            if other != ndx && reached.iter().any(|x| field_owns(x, other_field)) {
+                preds[other].push(ndx);
+            }
+        }
+    }
+    preds
+}
+
+#[derive(Default)]
+struct SensitivityBuilder {
+    nodes: Vec<SensitivityNode>,
+    stack: Vec<usize>,
+    namespaces: Vec<Vec<String>>,
+}
+
+impl Probe for SensitivityBuilder {
+    fn visit_start_scope(&mut self, name: &str, node: &dyn Block) {
+        let id = self.nodes.len();
+        if let (Some(parent), Some(namespace)) = (self.stack.last(), self.namespaces.last()) {
+            // Sub-blocks inside of an interface belong to the field holding the interface
+            let label = namespace.first().map(|x| x.as_str()).unwrap_or(name);
+            self.nodes[*parent].children.push((label.to_string(), id));
+        }
+        self.stack.push(id);
+        self.namespaces.push(vec![]);
+        self.nodes.push(SensitivityNode {
+            graph: get_signal_graph(node),
+            ..Default::default()
+        });
+    }
+
+    fn visit_start_namespace(&mut self, name: &str, _node: &dyn Block) {
+        if let Some(namespace) = self.namespaces.last_mut() {
+            namespace.push(name.to_string());
+        }
+    }
+
+    fn visit_end_namespace(&mut self, _name: &str, _node: &dyn Block) {
+        if let Some(namespace) = self.namespaces.last_mut() {
+            namespace.pop();
+        }
+    }
+
+    fn visit_end_scope(&mut self, _name: &str, _node: &dyn Block) {
+        let id = self.stack.pop().unwrap();
+        self.namespaces.pop();
+        let combinational = self.nodes[id].graph.is_some()
+            && self.nodes[id]
+                .children
+                .iter()
+                .all(|(_, child)| self.nodes[*child].combinational);
+        let span = self.nodes.len() - id;
+        let node = &mut self.nodes[id];
+        node.span = span;
+        node.combinational = combinational;
+    }
+}
+
+/// The [Sensitivity] struct holds the bookkeeping needed to propagate changes
+/// through a circuit with [Block::update_sensitive] instead of [Block::update_all].
+///
+/// Each block in the circuit (i.e., each scope visited by [Block::accept]) gets an entry
+/// that records if anything inside of it changed on the last pass, and which of its
+/// fields the block actually reads.  The latter is derived from the signal graph of the
+/// HDL kernel of the block, using the same read/write analysis that
+/// [check_logic_loops](crate::check_logic_loops::check_logic_loops) uses.  Blocks that do
+/// not have a combinatorial HDL kernel (like a `DFF`, or a simulation model) are sensitive
+/// to all of their fields.
+///
+/// The signal graph also orders the fields of each block topologically, so that a
+/// sub-block is evaluated after the sub-blocks that feed it.  When a sub-block changes,
+/// the kernel of the block is re-evaluated before the purely combinational sub-blocks
+/// (and outputs) that depend on it, and those sub-blocks pick up their new inputs right
+/// away.  A chain of combinational logic then settles in a single pass.  Sub-blocks that
+/// hold state only ever see inputs from the previous pass, so that registers sample their
+/// inputs before a clock edge has propagated through them.
+///
+/// With this information, a pass through the circuit only re-evaluates a block
+/// if a signal it reads has changed, and skips entire sub-trees of the design in which
+/// nothing is happening.  The cost of settling the circuit after a change then scales with
+/// the amount of activity in the design rather than its size.
+///
+/// Changes made to the circuit from outside (by a testbench, for example) are not tracked.
+/// So after the circuit has been modified externally, the next pass must be run as a
+/// sweep (see [Sensitivity::sweep]), which visits every block to pick up the
+/// changes.  Generally, you will not need to use this directly, as the simulation
+/// takes care of it.
+pub struct Sensitivity {
+    nodes: Vec<SensitivityNode>,
+    cursor: usize,
+    sweep: bool,
+    force: bool,
+}
+
+impl Sensitivity {
+    /// Build the sensitivity information for a circuit.  The first pass through the
+    /// circuit will evaluate every block.
+    pub fn new(uut: &dyn Block) -> Sensitivity {
+        let mut builder = SensitivityBuilder::default();
+        uut.accept("uut", &mut builder);
+        reset_properties();
+        Sensitivity {
+            nodes: builder.nodes,
+            cursor: 0,
+            sweep: true,
+            force: true,
+        }
+    }
+    /// Request that the next pass visits every block in the circuit.  This is
+    /// needed if the circuit has been changed by something other than its own
+    /// `update` functions.
+    pub fn sweep(&mut self) {
+        self.sweep = true;
+    }
+    /// Propagate changes through the circuit once.  Returns `true` if anything changed.
+    pub fn update<B: Block + ?Sized>(&mut self, uut: &mut B) -> bool {
+        self.cursor = 0;
+        let changed = uut.update_sensitive(self, false);
+        self.sweep = false;
+        self.force = false;
+        changed
+    }
+    /// Called by a block at the start of [Block::update_sensitive].  The `dirty` flag
+    /// indicates that the parent block was evaluated on this pass, and so
+    /// the inputs of the block may have been changed.  Returns `None` if the block (and
+    /// everything inside of it) can be skipped.
+    pub fn enter(&mut self, dirty: bool) -> Option<usize> {
+        let id = self.cursor;
+        assert!(
+            id < self.nodes.len(),
+            "Block structure does not match the sensitivity map.  \
+             Blocks that implement accept by hand must also implement update_sensitive."
+        );
+        if self.sweep || dirty || self.nodes[id].active {
+            self.cursor += 1;
+            Some(id)
+        } else {
+            self.cursor += self.nodes[id].span;
+            None
+        }
+    }
+    /// Returns `true` if the block (and everything inside of it) is combinational, in
+    /// which case it should latch its inputs (see [Block::latch_inputs]) before it is
+    /// evaluated.
+    pub fn is_combinational(&self, id: usize) -> bool {
+        self.nodes[id].combinational
+    }
+    /// Returns `true` if the block needs to be re-evaluated.  The `changed` flags
+    /// indicate which of the block's fields have changed since the last pass, and
+    /// `fields` holds the names of those fields.
+    pub fn evaluate(&mut self, id: usize, changed: &[bool], fields: &[&str]) -> bool {
+        if self.nodes[id].schedule.is_none() {
+            let schedule = self.nodes[id].schedule(&self.nodes, fields);
+            self.nodes[id].schedule = Some(schedule);
+        }
+        let schedule = self.nodes[id].schedule.as_mut().unwrap();
+        schedule.seen.iter_mut().for_each(|x| *x = false);
+        let evaluate = self.force
+            || changed
+                .iter()
+                .zip(schedule.mask.iter())
+                .any(|(c, m)| *c && *m);
+        if evaluate {
+            begin_block(id);
+        }
+        evaluate
+    }
+    /// Returns the index of the field of the block that is evaluated at the given `step`.
+    pub fn field(&mut self, id: usize, step: usize) -> usize {
+        let schedule = self.nodes[id].schedule.as_ref().unwrap();
+        let field = schedule.order[step];
+        if let Some(start) = schedule.start[field] {
+            self.cursor = start;
+        }
+        field
+    }
+    /// Returns `true` if the block needs to be re-evaluated before the given field is,
+    /// because a field that feeds it changed on this pass.  The `changed` flags are
+    /// the ones returned by the fields evaluated so far.
+    pub fn refresh(&mut self, id: usize, field: usize, changed: &[bool]) -> bool {
+        let schedule = self.nodes[id].schedule.as_mut().unwrap();
+        if !schedule.refresh[field]
+            || !schedule.preds[field]
+                .iter()
+                .any(|x| changed[*x] && !schedule.seen[*x])
+        {
+            return false;
+        }
+        for (seen, changed) in schedule.seen.iter_mut().zip(changed.iter()) {
+            *seen |= *changed;
+        }
+        begin_block(id);
+        true
+    }
+    /// Called by a block at the end of [Block::update_sensitive] with a flag that is
+    /// `true` if anything inside of the block changed.
+    pub fn leave(&mut self, id: usize, changed: bool) {
+        self.nodes[id].active = changed;
+        self.cursor = id + self.nodes[id].span;
+    }
+}
@@ -2,14 +2,14 @@ use std::marker::PhantomData;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::ast::{VerilogLink, VerilogLinkDetails, VerilogLiteral};
-use crate::atom::{Atom, AtomKind};
+use crate::atom::{Atom, AtomKind, AtomMut};
 use crate::bits::Bit;
 use crate::block::Block;
 use crate::clock::Clock;
 use crate::constraint::{Constraint, PinConstraint, SignalType};
 use crate::direction::{Direction, In, InOut, Local, Out};
 use crate::logic::{Logic, LogicJoin, LogicLink};
-use crate::probe::Probe;
+use crate::probe::{Probe, ProbeMut};
 use crate::synth::{Synth, VCDValue};
 use crate::type_descriptor::TypeDescriptor;
 
@@ -29,6 +29,7 @@ pub struct Signal<D: Direction, T: Synth> {
     id: usize,
     tristate_is_output: bool,
     signal_is_undriven: bool,
+    latched_drive: (bool, bool),
     constraints: Vec<PinConstraint>,
     dir: std::marker::PhantomData<D>,
 }
@@ -210,6 +211,21 @@ impl<D: Direction, T: Synth> Atom for Signal<D, T> {
     }
 }
 
+impl<D: Direction, T: Synth> AtomMut for Signal<D, T> {
+    fn set_vcd(&mut self, val: &VCDValue) -> bool {
+        match T::from_vcd(val) {
+            Some(x) => {
+                self.next = x;
+                self.val = x;
+                self.prev = x;
+                self.changed = false;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 impl<D: Direction, T: Synth> Logic for Signal<D, T> {
     fn update(&mut self) {}
     fn connect(&mut self) {
@@ -221,7 +237,11 @@ impl<D: Direction, T: Synth> Block for Signal<D, T> {
     fn connect_all(&mut self) {}
 
     fn update_all(&mut self) {
-        self.changed = self.val != self.next;
+        // Changes to the drive state of a tristate signal are treated as
+        // changes to the signal, so that anything reading it is re-evaluated.
+        let drive = (self.tristate_is_output, self.signal_is_undriven);
+        self.changed = self.val != self.next || self.latched_drive != drive;
+        self.latched_drive = drive;
         if self.changed {
             self.prev = self.val;
             self.val = self.next;
@@ -232,9 +252,19 @@ impl<D: Direction, T: Synth> Block for Signal<D, T> {
         self.changed
     }
 
+    fn latch_inputs(&mut self) {
+        if self.kind() == AtomKind::InputParameter {
+            self.update_all();
+        }
+    }
+
     fn accept(&self, name: &str, probe: &mut dyn Probe) {
         probe.visit_atom(name, self);
     }
+
+    fn accept_mut(&mut self, name: &str, probe: &mut dyn ProbeMut) {
+        probe.visit_atom(name, self);
+    }
 }
 
 impl Signal<In, Clock> {
@@ -259,6 +289,7 @@ impl<T: Synth> Signal<Out, T> {
             id: get_signal_id(),
             tristate_is_output: false,
             signal_is_undriven: false,
+            latched_drive: (false, false),
             constraints: vec![],
             dir: PhantomData,
         }
@@ -277,14 +308,15 @@ impl<D: Direction> Signal<D, Bit> {
 impl<D: Direction, T: Synth> Default for Signal<D, T> {
     fn default() -> Self {
         Self {
-            next: T::default(),
-            val: T::default(),
-            prev: T::default(),
+            next: T::default_value(),
+            val: T::default_value(),
+            prev: T::default_value(),
             changed: false,
             claimed: false,
             id: get_signal_id(),
             tristate_is_output: false,
             signal_is_undriven: false,
+            latched_drive: (false, false),
             constraints: vec![],
             dir: PhantomData,
         }
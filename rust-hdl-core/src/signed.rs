@@ -1,5 +1,6 @@
 use super::bits::Bits;
 use crate::bits::{bit_cast, LiteralType, LITERAL_BITS};
+use crate::fixed::WideCheck;
 use num_bigint::{BigInt, Sign};
 use num_traits::cast::ToPrimitive;
 use std::fmt::{Debug, Formatter, LowerHex, UpperHex};
@@ -92,6 +93,46 @@ impl<const N: usize> Signed<N> {
     pub fn inner(&self) -> Bits<N> {
         self.0
     }
+    pub(crate) fn from_inner(x: Bits<N>) -> Self {
+        Signed(x)
+    }
+    /// Add two signed values without overflow.  The result must be one bit wider than
+    /// the wider of the two arguments (this is checked at compile time).  This can also
+    /// be used in an HDL kernel.
+    /// ```
+    /// # use rust_hdl_core::prelude::*;
+    /// let x: Signed<8> = signed(-100);
+    /// let y: Signed<4> = signed(-8);
+    /// let z: Signed<9> = x.wide_add(y);
+    /// assert_eq!(z, signed(-108));
+    /// ```
+    pub fn wide_add<const M: usize, const O: usize>(self, rhs: Signed<M>) -> Signed<O> {
+        #[allow(clippy::let_unit_value)]
+        let _ = WideCheck::<N, 0, M, 0, O, 0>::SUM;
+        signed_bit_cast::<O, N>(self) + signed_bit_cast::<O, M>(rhs)
+    }
+    /// Subtract two signed values without overflow.  The result has the same width
+    /// as for [Signed::wide_add].
+    pub fn wide_sub<const M: usize, const O: usize>(self, rhs: Signed<M>) -> Signed<O> {
+        #[allow(clippy::let_unit_value)]
+        let _ = WideCheck::<N, 0, M, 0, O, 0>::SUM;
+        signed_bit_cast::<O, N>(self) - signed_bit_cast::<O, M>(rhs)
+    }
+    /// Multiply two signed values without overflow.  The width of the result must be the
+    /// sum of the widths of the arguments (this is checked at compile time).  This can
+    /// also be used in an HDL kernel.
+    /// ```
+    /// # use rust_hdl_core::prelude::*;
+    /// let x: Signed<8> = signed(-128);
+    /// let y: Signed<4> = signed(-8);
+    /// let z: Signed<12> = x.wide_mul(y);
+    /// assert_eq!(z, signed(1024));
+    /// ```
+    pub fn wide_mul<const M: usize, const O: usize>(self, rhs: Signed<M>) -> Signed<O> {
+        #[allow(clippy::let_unit_value)]
+        let _ = WideCheck::<N, 0, M, 0, O, 0>::PRODUCT;
+        (self.bigint() * rhs.bigint()).into()
+    }
 }
 
 impl<const N: usize> From<BigInt> for Signed<N> {
@@ -176,11 +217,15 @@ pub fn signed<const N: usize>(x: SignedLiteralType) -> Signed<N> {
 }
 
 pub fn signed_bit_cast<const M: usize, const N: usize>(x: Signed<N>) -> Signed<M> {
+    // Sign extend by filling the new high bits with copies of the sign bit (negating,
+    // casting and negating again does not work for the most negative value)
+    let mut ret: Bits<M> = bit_cast(x.0);
     if x.sign_bit() {
-        -signed_bit_cast(-x)
-    } else {
-        Signed(bit_cast(x.0))
+        for ndx in N..M {
+            ret = ret.replace_bit(ndx, true);
+        }
     }
+    Signed(ret)
 }
 
 pub fn signed_cast<const N: usize>(x: Bits<N>) -> Signed<N> {
@@ -320,4 +365,18 @@ mod tests {
         let x = -x;
         assert_eq!(x.bigint(), t.clone());
     }
+
+    #[test]
+    fn test_wide_ops() {
+        let x = Signed::<40>::from(Signed::<40>::min());
+        let y = Signed::<30>::from(Signed::<30>::max());
+        let sum: Signed<41> = x.wide_add(y);
+        assert_eq!(sum.bigint(), x.bigint() + y.bigint());
+        let difference: Signed<41> = x.wide_sub(y);
+        assert_eq!(difference.bigint(), x.bigint() - y.bigint());
+        let product: Signed<70> = x.wide_mul(y);
+        assert_eq!(product.bigint(), x.bigint() * y.bigint());
+        let square: Signed<80> = x.wide_mul(x);
+        assert_eq!(square.bigint(), x.bigint() * x.bigint());
+    }
 }
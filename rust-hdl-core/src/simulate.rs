@@ -3,7 +3,15 @@ use crossbeam::channel::{RecvError, SendError};
 
 use crate::block::Block;
 use crate::check_error::{check_all, CheckError};
-use crate::vcd_probe::{write_vcd_change, write_vcd_dump, write_vcd_header};
+use crate::formal::{begin_block, check_properties, reset_properties};
+use crate::fst::FSTWriter;
+use crate::sensitivity::Sensitivity;
+use crate::snapshot::Snapshot;
+use crate::vcd_probe::{
+    write_vcd_change, write_vcd_dump, write_vcd_header_filtered, TraceFilter, VCDProbe,
+};
+use crate::verilator::VerilatorCosim;
+use crate::verilog_testbench::VerilogTestbench;
 use std::io::Write;
 use std::thread::JoinHandle;
 
@@ -41,6 +49,22 @@ pub enum SimError {
     Check(CheckError),
     /// The simulation panicked.  This usually means `.unwrap` was called on a result in the testbench.
     SimPanic,
+    /// The Verilated model of the circuit disagreed with the Rust simulation.  The `signal` is the
+    /// path to the top level output that differs, and the values are given in hex.
+    CosimDivergence {
+        time: u64,
+        signal: String,
+        rust: String,
+        verilog: String,
+    },
+    /// An `hdl_assert!` or `hdl_assume!` property of the circuit did not hold.
+    PropertyFailed { time: u64, property: String },
+    /// The [Snapshot] used to start the simulation does not match the circuit (or the clocks)
+    /// being simulated.
+    SnapshotMismatch { reason: String },
+    /// The trace of the simulation could not be written (or, for an FST trace, the
+    /// `vcd2fst` tool could not be run).
+    TraceFailed { reason: String },
 }
 
 impl From<CheckError> for SimError {
@@ -61,6 +85,14 @@ impl<T> From<SendError<T>> for SimError {
     }
 }
 
+impl From<std::io::Error> for SimError {
+    fn from(x: std::io::Error) -> Self {
+        SimError::TraceFailed {
+            reason: x.to_string(),
+        }
+    }
+}
+
 /// Result type used by the simulation routines.
 pub type Result<T> = std::result::Result<T, SimError>;
 
@@ -102,6 +134,10 @@ pub struct Simulation<T> {
     time: u64,
     testbenches: Vec<JoinHandle<Result<()>>>,
     custom_logic: Vec<CustomLogicFn<T>>,
+    event_driven: bool,
+    sensitivity: Option<Sensitivity>,
+    clocks: Vec<usize>,
+    snapshot: Option<Snapshot>,
 }
 
 /// The `Sim` struct is used to communicate with a simulation.  Every testbench
@@ -137,8 +173,23 @@ impl<T: Send + 'static + Block> Simulation<T> {
             time: 0,
             testbenches: vec![],
             custom_logic: vec![],
+            event_driven: false,
+            sensitivity: None,
+            clocks: vec![],
+            snapshot: None,
         }
     }
+    /// Construct a simulation that starts from a [Snapshot] taken with [Simulation::run_to_snapshot].
+    /// The state of the circuit passed to `run` is replaced by the one in the snapshot,
+    /// and the simulation (and testbench) time starts at the time of the snapshot.
+    /// The clocks must be added in the same order as in the simulation that took the
+    /// snapshot, so that their phases can be restored.  Testbenches start from scratch.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Simulation<T> {
+        let mut sim = Self::new();
+        sim.time = snapshot.time();
+        sim.snapshot = Some(snapshot.clone());
+        sim
+    }
     /// Add a clock function to the simulation
     ///
     /// # Arguments
@@ -170,6 +221,7 @@ impl<T: Send + 'static + Block> Simulation<T> {
     where
         F: Fn(&mut Box<T>) -> () + Send + 'static + std::panic::RefUnwindSafe,
     {
+        self.clocks.push(self.workers.len());
         self.add_testbench(move |mut ep: Sim<T>| {
             let mut x = ep.init()?;
             loop {
@@ -215,9 +267,14 @@ impl<T: Send + 'static + Block> Simulation<T> {
     where
         F: Fn(&mut Box<T>) -> () + Send + 'static + std::panic::RefUnwindSafe,
     {
+        self.clocks.push(self.workers.len());
         self.add_testbench(move |mut ep: Sim<T>| {
             let mut x = ep.init()?;
-            x = ep.wait(phase_delay, x)?;
+            // The phase delay is measured from the start of time, which is not
+            // where a simulation restored from a snapshot starts.
+            if ep.time() <= phase_delay {
+                x = ep.wait(phase_delay - ep.time(), x)?;
+            }
             loop {
                 x = ep.clock(interval, x)?;
                 clock_fn(&mut x);
@@ -257,6 +314,13 @@ impl<T: Send + 'static + Block> Simulation<T> {
     {
         self.custom_logic.push(Box::new(logic));
     }
+    /// Only re-evaluate the parts of the circuit that can be affected by a change,
+    /// instead of updating the whole circuit until it settles.  See [Sensitivity].
+    /// This can pay off for large designs in which little is happening at any one time,
+    /// but the bookkeeping makes it slower for most designs, so it is off by default.
+    pub fn set_event_driven(&mut self, enabled: bool) {
+        self.event_driven = enabled;
+    }
     pub fn endpoint(&mut self) -> Sim<T> {
         let (send_to_worker, recv_from_sim_to_worker) = bounded(0);
         let id = self.workers.len();
@@ -269,7 +333,7 @@ impl<T: Send + 'static + Block> Simulation<T> {
         Sim {
             to_sim: self.channel_to_sim.clone(),
             from_sim: recv_from_sim_to_worker,
-            time: 0,
+            time: self.time,
         }
     }
     fn dispatch(&mut self, idx: usize, x: Box<T>) -> Result<Box<T>> {
@@ -288,21 +352,45 @@ impl<T: Send + 'static + Block> Simulation<T> {
         worker.kind = x.kind;
         // Update the circuit
         let mut converged = false;
-        for _ in 0..100 {
-            for l in &self.custom_logic {
-                l(&mut x.circuit);
+        if let Some(sens) = &mut self.sensitivity {
+            // The worker may have changed anything, so the first pass has
+            // to visit the whole circuit.
+            sens.sweep();
+            for _ in 0..100 {
+                for l in &self.custom_logic {
+                    l(&mut x.circuit);
+                    sens.sweep();
+                }
+                if !sens.update(x.circuit.as_mut()) {
+                    converged = true;
+                    break;
+                }
             }
-            x.circuit.update_all();
-            if !x.circuit.has_changed() {
-                converged = true;
-                break;
+        } else {
+            for _ in 0..100 {
+                for l in &self.custom_logic {
+                    l(&mut x.circuit);
+                }
+                // Every property is re-evaluated on every pass, so only the latest
+                // results count.
+                begin_block(0);
+                x.circuit.update_all();
+                if !x.circuit.has_changed() {
+                    converged = true;
+                    break;
+                }
             }
         }
         if !converged {
-            Err(SimError::FailedToConverge)
-        } else {
-            Ok(x.circuit)
+            return Err(SimError::FailedToConverge);
+        }
+        if let Some(property) = check_properties() {
+            return Err(SimError::PropertyFailed {
+                time: self.time,
+                property,
+            });
         }
+        Ok(x.circuit)
     }
     fn scan_workers(&self, x: &T) -> NextTime {
         let mut min_time = !0_u64;
@@ -355,23 +443,60 @@ impl<T: Send + 'static + Block> Simulation<T> {
             let _ = handle.join().unwrap();
         }
     }
-    pub fn run(&mut self, mut x: Box<T>, max_time: u64) -> Result<()> {
+    fn start(&mut self, mut x: Box<T>) -> Result<Box<T>> {
         x.as_mut().connect_all();
         check_all(x.as_mut())?;
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.restore(x.as_mut())?;
+            if snapshot.clocks().len() != self.clocks.len() {
+                return Err(SimError::SnapshotMismatch {
+                    reason: format!(
+                        "the snapshot has {} clocks, but the simulation has {}",
+                        snapshot.clocks().len(),
+                        self.clocks.len()
+                    ),
+                });
+            }
+        }
+        reset_properties();
+        self.sensitivity = self.event_driven.then(|| Sensitivity::new(x.as_ref()));
         // First initialize the workers.
         for id in 0..self.workers.len() {
             x = self.dispatch(id, x)?;
         }
+        // Then put the clocks back in phase with the snapshot
+        if let Some(snapshot) = self.snapshot.take() {
+            for (id, phase) in self.clocks.iter().zip(snapshot.clocks()) {
+                if let Some(t) = phase {
+                    self.workers[*id].kind = TriggerType::Clock(*t);
+                }
+            }
+        }
+        Ok(x)
+    }
+    fn run_internal<F>(&mut self, x: Box<T>, max_time: u64, mut on_step: F) -> Result<()>
+    where
+        F: FnMut(u64, &T) -> Result<()>,
+    {
+        let mut x = self.start(x)?;
+        if let Err(e) = on_step(self.time, x.as_ref()) {
+            self.terminate();
+            return Err(e);
+        }
         // Next run until we have no one else waiting
         let mut halted = false;
         while self.time < max_time {
-            let next = self.scan_workers(&x);
+            let next = self.scan_workers(x.as_ref());
             if next.time == !0 || next.clocks_only || next.halted {
                 halted = next.halted;
                 break;
             }
             self.time = next.time;
             x = self.dispatch(next.idx, x)?;
+            if let Err(e) = on_step(self.time, x.as_ref()) {
+                self.terminate();
+                return Err(e);
+            }
         }
         self.terminate();
         if self.time >= max_time {
@@ -382,40 +507,111 @@ impl<T: Send + 'static + Block> Simulation<T> {
         }
         Ok(())
     }
-    pub fn run_to_file(&mut self, x: Box<T>, max_time: u64, name: &str) -> Result<()> {
-        let mut vcd = vec![];
-        let result = self.run_traced(x, max_time, &mut vcd);
-        std::fs::write(name, vcd).unwrap();
-        result
+    pub fn run(&mut self, x: Box<T>, max_time: u64) -> Result<()> {
+        self.run_internal(x, max_time, |_, _| Ok(()))
     }
-    pub fn run_traced<W: Write>(&mut self, mut x: Box<T>, max_time: u64, trace: W) -> Result<()> {
-        x.as_mut().connect_all();
-        check_all(x.as_mut())?;
-        let mut vcd = write_vcd_header(trace, x.as_ref());
-        // First initialize the workers.
-        for id in 0..self.workers.len() {
-            x = self.dispatch(id, x)?;
-        }
-        vcd = write_vcd_dump(vcd, x.as_ref());
-        let mut halted = false;
-        // Next run until we have no one else waiting
-        while self.time < max_time {
+    /// Run the simulation up to (but not including) `time`, and return a [Snapshot] of
+    /// the state of the circuit and the clocks.  The testbenches are stopped at that
+    /// point.  Use [Simulation::from_snapshot] to start other simulations from the snapshot.
+    pub fn run_to_snapshot(&mut self, x: Box<T>, time: u64) -> Result<Snapshot> {
+        let mut x = self.start(x)?;
+        loop {
             let next = self.scan_workers(x.as_ref());
-            if next.time == !0 || next.clocks_only || next.halted {
-                halted = next.halted;
+            if next.halted {
+                self.terminate();
+                return Err(SimError::SimHalted);
+            }
+            if next.time == !0 || next.time >= time {
                 break;
             }
             self.time = next.time;
             x = self.dispatch(next.idx, x)?;
-            vcd.timestamp(next.time).unwrap();
-            vcd = write_vcd_change(vcd, x.as_ref());
         }
+        let clocks = self
+            .clocks
+            .iter()
+            .map(|id| match self.workers[*id].kind {
+                TriggerType::Clock(t) => Some(t),
+                _ => None,
+            })
+            .collect();
+        let snapshot = Snapshot::capture(x.as_mut(), self.time, clocks);
         self.terminate();
-        if self.time >= max_time {
-            return Err(SimError::MaxTimeReached);
-        }
-        if halted {
-            return Err(SimError::SimHalted);
+        snapshot
+    }
+    pub fn run_to_file(&mut self, x: Box<T>, max_time: u64, name: &str) -> Result<()> {
+        let mut vcd = vec![];
+        let result = self.run_traced(x, max_time, &mut vcd);
+        std::fs::write(name, vcd)?;
+        result
+    }
+    /// Run the simulation, and write an FST trace (see [FSTWriter]) of the signals selected
+    /// by the filter to the file `name`.  Use `TraceFilter::default()` to trace everything.
+    pub fn run_to_fst(
+        &mut self,
+        x: Box<T>,
+        max_time: u64,
+        name: &str,
+        filter: &TraceFilter,
+    ) -> Result<()> {
+        let mut fst = FSTWriter::new(name)?;
+        let result = self.run_traced_filtered(x, max_time, &mut fst, filter);
+        fst.finish()?;
+        result
+    }
+    pub fn run_traced<W: Write>(&mut self, x: Box<T>, max_time: u64, trace: W) -> Result<()> {
+        self.run_traced_filtered(x, max_time, trace, &TraceFilter::default())
+    }
+    /// Run the simulation, and write a VCD trace of the signals selected by the filter
+    /// (see [TraceFilter]).
+    pub fn run_traced_filtered<W: Write>(
+        &mut self,
+        x: Box<T>,
+        max_time: u64,
+        trace: W,
+        filter: &TraceFilter,
+    ) -> Result<()> {
+        let mut vcd: Option<VCDProbe<W>> = None;
+        let mut trace = Some(trace);
+        self.run_internal(x, max_time, |time, x| {
+            vcd = Some(match (vcd.take(), trace.take()) {
+                (None, Some(trace)) => {
+                    write_vcd_dump(write_vcd_header_filtered(trace, x, filter)?, x)?
+                }
+                (Some(mut vcd), _) => {
+                    vcd.timestamp(time)?;
+                    write_vcd_change(vcd, x)?
+                }
+                (None, None) => unreachable!(),
+            });
+            Ok(())
+        })
+    }
+    /// Run the simulation in lock step with a Verilated model of the circuit (see [VerilatorCosim]).
+    /// The testbenches only see the Rust circuit.  After each step of the simulation, the
+    /// top level outputs of the two are compared, and the first difference is returned as
+    /// a [SimError::CosimDivergence].
+    pub fn run_cosim(&mut self, x: Box<T>, max_time: u64, mut cosim: VerilatorCosim) -> Result<()> {
+        self.run_internal(x, max_time, |time, x| cosim.step(time, x))
+    }
+    /// Run the simulation, and record the values of the top level ports of the circuit at
+    /// each step.  If the simulation passes, a self-checking Verilog testbench (see
+    /// [VerilogTestbench]) that replays the stimulus and checks the outputs is written to `tb`.
+    pub fn run_to_testbench<W: Write>(
+        &mut self,
+        x: Box<T>,
+        max_time: u64,
+        mut tb: W,
+    ) -> Result<()> {
+        let mut recorder: Option<VerilogTestbench> = None;
+        self.run_internal(x, max_time, |time, x| {
+            recorder
+                .get_or_insert_with(|| VerilogTestbench::new(x))
+                .record(time, x);
+            Ok(())
+        })?;
+        if let Some(recorder) = recorder {
+            write!(tb, "{}", recorder.generate())?;
         }
         Ok(())
     }
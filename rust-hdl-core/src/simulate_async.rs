@@ -0,0 +1,639 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::block::Block;
+use crate::check_error::check_all;
+use crate::formal::{begin_block, check_properties, reset_properties};
+use crate::fst::FSTWriter;
+use crate::sensitivity::Sensitivity;
+use crate::simulate::{CustomLogicFn, Result, SimError};
+use crate::snapshot::Snapshot;
+use crate::vcd_probe::{
+    write_vcd_change, write_vcd_dump, write_vcd_header_filtered, TraceFilter, VCDProbe,
+};
+use crate::verilator::VerilatorCosim;
+use crate::verilog_testbench::VerilogTestbench;
+
+enum TriggerType<T> {
+    Never,
+    Time(u64),
+    Function(Box<dyn Fn(&T) -> bool>),
+    Clock(u64),
+    Halt,
+}
+
+type ClockFn<T> = Box<dyn Fn(&mut Box<T>)>;
+type Testbench = Pin<Box<dyn Future<Output = Result<()>>>>;
+
+enum Task<T> {
+    Clock {
+        interval: u64,
+        phase_delay: Option<u64>,
+        clock_fn: ClockFn<T>,
+    },
+    Testbench(Option<Testbench>),
+}
+
+// State shared between the kernel and the testbench handles.  Only one
+// party ever holds the circuit at a time, so the slot doubles as the
+// hand-off point that the channels provide in the threaded [Simulation].
+struct Shared<T> {
+    time: u64,
+    circuit: Option<Box<T>>,
+    triggers: Vec<TriggerType<T>>,
+}
+
+/// A single threaded alternative to [Simulation](crate::simulate::Simulation).
+///
+/// Testbenches are written as `async` blocks instead of closures that run on their
+/// own threads.  Every testbench is polled by the simulation kernel on the calling
+/// thread, and the circuit is handed back and forth without any synchronization
+/// or context switches.  For long running simulations with many clock edges, this
+/// is substantially faster than the threaded version.  Clocks are evaluated directly
+/// by the kernel and do not require a testbench at all.
+///
+/// The [AsyncSim] handle provides the same `init`, `clock`, `wait`, `watch`, `done`,
+/// `halt` and `time` methods as [Sim](crate::simulate::Sim), with the blocking calls
+/// replaced by futures.  Porting a testbench is usually a matter of wrapping the body
+/// in `async move`, adding `.await` to the blocking calls, and replacing the
+/// `wait_clock_*` macros with their `await_clock_*` counterparts.
+///
+/// # Example
+///
+/// ```rust
+/// # use rust_hdl_core::prelude::*;
+///
+/// #[derive(LogicBlock, Default)]
+/// struct Foo {
+///    pub clock: Signal<In, Clock>,
+///    pub strobe: Signal<Out, Bit>,
+/// }
+///
+/// impl Logic for Foo {
+///   #[hdl_gen]
+///   fn update(&mut self) {
+///      self.strobe.next = true;
+///   }
+/// }
+///
+/// let mut sim : AsyncSimulation<Foo> = Default::default();
+/// sim.add_clock(5, |x| x.clock.next = !x.clock.val());
+/// sim.add_testbench(|mut sim: AsyncSim<Foo>| async move {
+///     let mut x = sim.init()?;
+///     await_clock_cycles!(sim, clock, x, 10);
+///     sim_assert!(sim, x.strobe.val(), x);
+///     sim.done(x)
+/// });
+/// sim.run(Box::new(Foo::default()), 1000).unwrap();
+/// ```
+pub struct AsyncSimulation<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+    tasks: Vec<Task<T>>,
+    custom_logic: Vec<CustomLogicFn<T>>,
+    event_driven: bool,
+    sensitivity: Option<Sensitivity>,
+    snapshot: Option<Snapshot>,
+}
+
+/// The `AsyncSim` struct is the handle a testbench uses to communicate with an
+/// [AsyncSimulation].  It mirrors [Sim](crate::simulate::Sim), except that the
+/// calls that yield control to the simulation return futures.
+pub struct AsyncSim<T> {
+    id: usize,
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+/// The future returned by [AsyncSim::clock], [AsyncSim::wait] and [AsyncSim::watch].
+/// It resolves to the circuit once the simulation kernel schedules the testbench again.
+pub struct SimTrigger<T> {
+    id: usize,
+    shared: Rc<RefCell<Shared<T>>>,
+    pending: Option<(TriggerType<T>, Box<T>)>,
+}
+
+impl<T> Future for SimTrigger<T> {
+    type Output = Result<Box<T>>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        let mut shared = me.shared.borrow_mut();
+        if let Some((kind, circuit)) = me.pending.take() {
+            shared.triggers[me.id] = kind;
+            shared.circuit = Some(circuit);
+            return Poll::Pending;
+        }
+        match shared.circuit.take() {
+            Some(circuit) => Poll::Ready(Ok(circuit)),
+            None => Poll::Ready(Err(SimError::SimTerminated)),
+        }
+    }
+}
+
+impl<T> AsyncSim<T> {
+    fn trigger(&self, kind: TriggerType<T>, x: Box<T>) -> SimTrigger<T> {
+        SimTrigger {
+            id: self.id,
+            shared: self.shared.clone(),
+            pending: Some((kind, x)),
+        }
+    }
+    fn release(&self, kind: TriggerType<T>, x: Box<T>) {
+        let mut shared = self.shared.borrow_mut();
+        shared.triggers[self.id] = kind;
+        shared.circuit = Some(x);
+    }
+    pub fn init(&self) -> Result<Box<T>> {
+        self.shared
+            .borrow_mut()
+            .circuit
+            .take()
+            .ok_or(SimError::SimTerminated)
+    }
+    pub fn watch<S>(&mut self, check: S, x: Box<T>) -> SimTrigger<T>
+    where
+        S: Fn(&T) -> bool + 'static,
+    {
+        self.trigger(TriggerType::Function(Box::new(check)), x)
+    }
+    pub fn clock(&mut self, delta: u64, x: Box<T>) -> SimTrigger<T> {
+        self.trigger(TriggerType::Clock(delta + self.time()), x)
+    }
+    pub fn wait(&mut self, delta: u64, x: Box<T>) -> SimTrigger<T> {
+        self.trigger(TriggerType::Time(delta + self.time()), x)
+    }
+    pub fn done(&self, x: Box<T>) -> Result<()> {
+        self.release(TriggerType::Never, x);
+        Ok(())
+    }
+    pub fn halt(&self, x: Box<T>) -> Result<()> {
+        self.release(TriggerType::Halt, x);
+        Err(SimError::SimHalted)
+    }
+    pub fn time(&self) -> u64 {
+        self.shared.borrow().time
+    }
+}
+
+struct NextTime {
+    time: u64,
+    idx: usize,
+    clocks_only: bool,
+    halted: bool,
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    // Safety: the vtable functions ignore the data pointer entirely.
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+impl<T: Block + 'static> Default for AsyncSimulation<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Block + 'static> AsyncSimulation<T> {
+    /// Construct a single threaded simulation struct
+    pub fn new() -> AsyncSimulation<T> {
+        Self {
+            shared: Rc::new(RefCell::new(Shared {
+                time: 0,
+                circuit: None,
+                triggers: vec![],
+            })),
+            tasks: vec![],
+            custom_logic: vec![],
+            event_driven: false,
+            sensitivity: None,
+            snapshot: None,
+        }
+    }
+    /// Construct a simulation that starts from a [Snapshot].  See
+    /// [Simulation::from_snapshot](crate::simulate::Simulation::from_snapshot).
+    pub fn from_snapshot(snapshot: &Snapshot) -> AsyncSimulation<T> {
+        let sim = Self::new();
+        sim.shared.borrow_mut().time = snapshot.time();
+        AsyncSimulation {
+            snapshot: Some(snapshot.clone()),
+            ..sim
+        }
+    }
+    fn add_task(&mut self, task: Task<T>) {
+        self.tasks.push(task);
+        self.shared.borrow_mut().triggers.push(TriggerType::Never);
+    }
+    /// Add a clock function to the simulation.  The clock closure is called every
+    /// `interval` picoseconds directly by the kernel.  See
+    /// [Simulation::add_clock](crate::simulate::Simulation::add_clock).
+    pub fn add_clock<F>(&mut self, interval: u64, clock_fn: F)
+    where
+        F: Fn(&mut Box<T>) + 'static,
+    {
+        self.add_task(Task::Clock {
+            interval,
+            phase_delay: None,
+            clock_fn: Box::new(clock_fn),
+        });
+    }
+    /// Add a phased clock to the simulation.  The first call to the clock closure
+    /// happens `interval` picoseconds after `phase_delay`.  See
+    /// [Simulation::add_phased_clock](crate::simulate::Simulation::add_phased_clock).
+    pub fn add_phased_clock<F>(&mut self, interval: u64, phase_delay: u64, clock_fn: F)
+    where
+        F: Fn(&mut Box<T>) + 'static,
+    {
+        self.add_task(Task::Clock {
+            interval,
+            phase_delay: Some(phase_delay),
+            clock_fn: Box::new(clock_fn),
+        });
+    }
+    /// Add a testbench to the simulation
+    ///
+    /// # Arguments
+    ///
+    /// * `testbench` - a function that takes an [AsyncSim] handle and returns the
+    ///   future that runs the testbench (usually an `async move` block).
+    pub fn add_testbench<F, R>(&mut self, testbench: F)
+    where
+        F: FnOnce(AsyncSim<T>) -> R,
+        R: Future<Output = Result<()>> + 'static,
+    {
+        let ep = AsyncSim {
+            id: self.tasks.len(),
+            shared: self.shared.clone(),
+        };
+        self.add_task(Task::Testbench(Some(Box::pin(testbench(ep)))));
+    }
+    pub fn add_custom_logic<F>(&mut self, logic: F)
+    where
+        F: Fn(&mut T) + 'static,
+    {
+        self.custom_logic.push(Box::new(logic));
+    }
+    /// Only re-evaluate the parts of the circuit that can be affected by a change.  See
+    /// [Simulation::set_event_driven](crate::simulate::Simulation::set_event_driven).
+    pub fn set_event_driven(&mut self, enabled: bool) {
+        self.event_driven = enabled;
+    }
+    fn set_trigger(&self, idx: usize, kind: TriggerType<T>) {
+        self.shared.borrow_mut().triggers[idx] = kind;
+    }
+    fn dispatch(&mut self, idx: usize, mut x: Box<T>) -> Result<Box<T>> {
+        let time = self.shared.borrow().time;
+        match &mut self.tasks[idx] {
+            Task::Clock {
+                interval,
+                phase_delay,
+                clock_fn,
+            } => {
+                let interval = *interval;
+                // The phase delay is measured from the start of time, which is not
+                // where a simulation restored from a snapshot starts.
+                match phase_delay.take().filter(|delay| *delay >= time) {
+                    Some(delay) => self.set_trigger(idx, TriggerType::Time(delay)),
+                    None => {
+                        if matches!(self.shared.borrow().triggers[idx], TriggerType::Clock(_)) {
+                            clock_fn(&mut x);
+                        }
+                        self.set_trigger(idx, TriggerType::Clock(time + interval));
+                    }
+                }
+            }
+            Task::Testbench(slot) => {
+                self.shared.borrow_mut().circuit = Some(x);
+                let finished = match slot {
+                    Some(testbench) => {
+                        let waker = noop_waker();
+                        let mut cx = Context::from_waker(&waker);
+                        let poll = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                            testbench.as_mut().poll(&mut cx)
+                        }))
+                        .map_err(|_| SimError::SimPanic)?;
+                        poll.is_ready()
+                    }
+                    None => true,
+                };
+                if finished {
+                    *slot = None;
+                }
+                x = self
+                    .shared
+                    .borrow_mut()
+                    .circuit
+                    .take()
+                    .ok_or(SimError::SimTerminated)?;
+            }
+        }
+        // Update the circuit
+        if let Some(sens) = &mut self.sensitivity {
+            // The worker may have changed anything, so the first pass has
+            // to visit the whole circuit.
+            sens.sweep();
+        }
+        for _ in 0..100 {
+            let changed = match &mut self.sensitivity {
+                Some(sens) => {
+                    for l in &self.custom_logic {
+                        l(&mut x);
+                        sens.sweep();
+                    }
+                    sens.update(x.as_mut())
+                }
+                None => {
+                    for l in &self.custom_logic {
+                        l(&mut x);
+                    }
+                    // Every property is re-evaluated on every pass, so only the latest
+                    // results count.
+                    begin_block(0);
+                    x.update_all();
+                    x.has_changed()
+                }
+            };
+            if !changed {
+                if let Some(property) = check_properties() {
+                    return Err(SimError::PropertyFailed { time, property });
+                }
+                return Ok(x);
+            }
+        }
+        Err(SimError::FailedToConverge)
+    }
+    fn scan_workers(&self, x: &T) -> NextTime {
+        let shared = self.shared.borrow();
+        let mut min_time = !0_u64;
+        let mut min_idx = 0;
+        let mut only_clock_waiters = true;
+        for (id, kind) in shared.triggers.iter().enumerate() {
+            match kind {
+                TriggerType::Halt => {
+                    return NextTime {
+                        halted: true,
+                        time: !0,
+                        idx: !0,
+                        clocks_only: false,
+                    }
+                }
+                TriggerType::Never => {}
+                TriggerType::Time(t) => {
+                    only_clock_waiters = false;
+                    if *t < min_time {
+                        min_time = *t;
+                        min_idx = id;
+                    }
+                }
+                TriggerType::Function(watch) => {
+                    only_clock_waiters = false;
+                    if watch(x) {
+                        min_idx = id;
+                        min_time = shared.time;
+                        break;
+                    }
+                }
+                TriggerType::Clock(t) => {
+                    if *t < min_time {
+                        min_time = *t;
+                        min_idx = id;
+                    }
+                }
+            }
+        }
+        NextTime {
+            time: min_time,
+            idx: min_idx,
+            clocks_only: only_clock_waiters,
+            halted: false,
+        }
+    }
+    fn terminate(&mut self) {
+        self.tasks.clear();
+        let mut shared = self.shared.borrow_mut();
+        shared.triggers.clear();
+        shared.circuit = None;
+    }
+    fn clock_ids(&self) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| matches!(task, Task::Clock { .. }))
+            .map(|(id, _)| id)
+            .collect()
+    }
+    fn start(&mut self, mut x: Box<T>) -> Result<Box<T>> {
+        x.as_mut().connect_all();
+        check_all(x.as_mut())?;
+        let clocks = self.clock_ids();
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.restore(x.as_mut())?;
+            if snapshot.clocks().len() != clocks.len() {
+                return Err(SimError::SnapshotMismatch {
+                    reason: format!(
+                        "the snapshot has {} clocks, but the simulation has {}",
+                        snapshot.clocks().len(),
+                        clocks.len()
+                    ),
+                });
+            }
+        }
+        reset_properties();
+        self.sensitivity = self.event_driven.then(|| Sensitivity::new(x.as_ref()));
+        // First initialize the workers.
+        for id in 0..self.tasks.len() {
+            x = self.dispatch(id, x)?;
+        }
+        // Then put the clocks back in phase with the snapshot
+        if let Some(snapshot) = self.snapshot.take() {
+            for (id, phase) in clocks.iter().zip(snapshot.clocks()) {
+                if let Some(t) = phase {
+                    self.set_trigger(*id, TriggerType::Clock(*t));
+                }
+            }
+        }
+        Ok(x)
+    }
+    fn run_internal<F>(&mut self, x: Box<T>, max_time: u64, mut on_step: F) -> Result<()>
+    where
+        F: FnMut(u64, &T) -> Result<()>,
+    {
+        let mut x = self.start(x)?;
+        let mut time = self.shared.borrow().time;
+        if let Err(e) = on_step(time, x.as_ref()) {
+            self.terminate();
+            return Err(e);
+        }
+        // Next run until we have no one else waiting
+        let mut halted = false;
+        while time < max_time {
+            let next = self.scan_workers(&x);
+            if next.time == !0 || next.clocks_only || next.halted {
+                halted = next.halted;
+                break;
+            }
+            time = next.time;
+            self.shared.borrow_mut().time = time;
+            x = self.dispatch(next.idx, x)?;
+            if let Err(e) = on_step(time, x.as_ref()) {
+                self.terminate();
+                return Err(e);
+            }
+        }
+        self.terminate();
+        if time >= max_time {
+            return Err(SimError::MaxTimeReached);
+        }
+        if halted {
+            return Err(SimError::SimHalted);
+        }
+        Ok(())
+    }
+    pub fn run(&mut self, x: Box<T>, max_time: u64) -> Result<()> {
+        self.run_internal(x, max_time, |_, _| Ok(()))
+    }
+    /// Run the simulation up to (but not including) `time`, and return a [Snapshot].  See
+    /// [Simulation::run_to_snapshot](crate::simulate::Simulation::run_to_snapshot).
+    pub fn run_to_snapshot(&mut self, x: Box<T>, time: u64) -> Result<Snapshot> {
+        let mut x = self.start(x)?;
+        loop {
+            let next = self.scan_workers(&x);
+            if next.halted {
+                self.terminate();
+                return Err(SimError::SimHalted);
+            }
+            if next.time == !0 || next.time >= time {
+                break;
+            }
+            self.shared.borrow_mut().time = next.time;
+            x = self.dispatch(next.idx, x)?;
+        }
+        let clocks = self
+            .clock_ids()
+            .into_iter()
+            .map(|id| match self.shared.borrow().triggers[id] {
+                TriggerType::Clock(t) => Some(t),
+                _ => None,
+            })
+            .collect();
+        let snapshot = Snapshot::capture(x.as_mut(), self.shared.borrow().time, clocks);
+        self.terminate();
+        snapshot
+    }
+    pub fn run_to_file(&mut self, x: Box<T>, max_time: u64, name: &str) -> Result<()> {
+        let mut vcd = vec![];
+        let result = self.run_traced(x, max_time, &mut vcd);
+        std::fs::write(name, vcd)?;
+        result
+    }
+    /// Run the simulation, and write an FST trace (see [FSTWriter]) of the signals selected
+    /// by the filter to the file `name`.  Use `TraceFilter::default()` to trace everything.
+    pub fn run_to_fst(
+        &mut self,
+        x: Box<T>,
+        max_time: u64,
+        name: &str,
+        filter: &TraceFilter,
+    ) -> Result<()> {
+        let mut fst = FSTWriter::new(name)?;
+        let result = self.run_traced_filtered(x, max_time, &mut fst, filter);
+        fst.finish()?;
+        result
+    }
+    pub fn run_traced<W: Write>(&mut self, x: Box<T>, max_time: u64, trace: W) -> Result<()> {
+        self.run_traced_filtered(x, max_time, trace, &TraceFilter::default())
+    }
+    /// Run the simulation, and write a VCD trace of the signals selected by the filter
+    /// (see [TraceFilter]).
+    pub fn run_traced_filtered<W: Write>(
+        &mut self,
+        x: Box<T>,
+        max_time: u64,
+        trace: W,
+        filter: &TraceFilter,
+    ) -> Result<()> {
+        let mut vcd: Option<VCDProbe<W>> = None;
+        let mut trace = Some(trace);
+        self.run_internal(x, max_time, |time, x| {
+            vcd = Some(match (vcd.take(), trace.take()) {
+                (None, Some(trace)) => {
+                    write_vcd_dump(write_vcd_header_filtered(trace, x, filter)?, x)?
+                }
+                (Some(mut vcd), _) => {
+                    vcd.timestamp(time)?;
+                    write_vcd_change(vcd, x)?
+                }
+                (None, None) => unreachable!(),
+            });
+            Ok(())
+        })
+    }
+    /// Run the simulation in lock step with a Verilated model of the circuit.  See
+    /// [Simulation::run_cosim](crate::simulate::Simulation::run_cosim).
+    pub fn run_cosim(&mut self, x: Box<T>, max_time: u64, mut cosim: VerilatorCosim) -> Result<()> {
+        self.run_internal(x, max_time, |time, x| cosim.step(time, x))
+    }
+    /// Run the simulation, and write a self-checking Verilog testbench if it passes.  See
+    /// [Simulation::run_to_testbench](crate::simulate::Simulation::run_to_testbench).
+    pub fn run_to_testbench<W: Write>(
+        &mut self,
+        x: Box<T>,
+        max_time: u64,
+        mut tb: W,
+    ) -> Result<()> {
+        let mut recorder: Option<VerilogTestbench> = None;
+        self.run_internal(x, max_time, |time, x| {
+            recorder
+                .get_or_insert_with(|| VerilogTestbench::new(x))
+                .record(time, x);
+            Ok(())
+        })?;
+        if let Some(recorder) = recorder {
+            write!(tb, "{}", recorder.generate())?;
+        }
+        Ok(())
+    }
+}
+
+#[macro_export]
+macro_rules! await_clock_true {
+    ($sim: ident, $($clock: ident).+, $me: expr) => {
+        $me = $sim.watch(|x| x.$($clock).+.val().clk, $me).await?
+    };
+}
+
+#[macro_export]
+macro_rules! await_clock_false {
+    ($sim: ident, $($clock: ident).+, $me: expr) => {
+        $me = $sim.watch(|x| !x.$($clock).+.val().clk, $me).await?
+    };
+}
+
+#[macro_export]
+macro_rules! await_clock_cycle {
+    ($sim: ident, $($clock: ident).+, $me: expr) => {
+        if $me.$($clock).+.val().clk {
+            await_clock_false!($sim, $($clock).+, $me);
+            await_clock_true!($sim, $($clock).+, $me);
+        } else {
+            await_clock_true!($sim, $($clock).+, $me);
+            await_clock_false!($sim, $($clock).+, $me);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! await_clock_cycles {
+    ($sim: ident, $($clock: ident).+, $me: expr, $count: expr) => {
+        for _i in 0..$count {
+            await_clock_cycle!($sim, $($clock).+, $me);
+        }
+    };
+}
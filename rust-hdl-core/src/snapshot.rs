@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::iter::Peekable;
+
+use crate::atom::{Atom, AtomKind, AtomMut};
+use crate::block::Block;
+use crate::probe::{Probe, ProbeMut};
+use crate::simulate::SimError;
+use crate::synth::VCDValue;
+
+/// The state of a simulation at a point in time, from which another simulation can
+/// be started.  A snapshot holds the value of every signal in the circuit (which
+/// includes the state of every `DFF`), the simulation time, and the phases of the
+/// clocks.  It is taken with [Simulation::run_to_snapshot](crate::simulate::Simulation::run_to_snapshot),
+/// and used with [Simulation::from_snapshot](crate::simulate::Simulation::from_snapshot).
+/// Snapshots can be saved to (and loaded from) a file, so that several tests can
+/// branch off from the same (expensive to reach) state.
+///
+/// Simulation-only state that is not held in a signal (like the contents of a `RAM`)
+/// is saved for each block that provides it with [Logic::sim_state](crate::logic::Logic::sim_state).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    time: u64,
+    clocks: Vec<Option<u64>>,
+    signals: BTreeMap<String, VCDValue>,
+    states: BTreeMap<String, Vec<VCDValue>>,
+}
+
+#[derive(Default)]
+struct SnapshotCapture {
+    path: Vec<String>,
+    signals: BTreeMap<String, VCDValue>,
+    states: BTreeMap<String, Vec<VCDValue>>,
+}
+
+impl Probe for SnapshotCapture {
+    fn visit_start_scope(&mut self, name: &str, node: &dyn Block) {
+        self.path.push(name.to_string());
+        let state = node.sim_state();
+        if !state.is_empty() {
+            self.states.insert(self.path.join("."), state);
+        }
+    }
+
+    fn visit_start_namespace(&mut self, name: &str, _node: &dyn Block) {
+        self.path.push(name.to_string());
+    }
+
+    fn visit_atom(&mut self, name: &str, signal: &dyn Atom) {
+        if signal.kind() != AtomKind::Constant {
+            self.signals
+                .insert(format!("{}.{}", self.path.join("."), name), signal.vcd());
+        }
+    }
+
+    fn visit_end_namespace(&mut self, _name: &str, _node: &dyn Block) {
+        self.path.pop();
+    }
+
+    fn visit_end_scope(&mut self, _name: &str, _node: &dyn Block) {
+        self.path.pop();
+    }
+}
+
+// Finds the first block that cannot be restored from a snapshot.
+#[derive(Default)]
+struct SnapshotCheck {
+    path: Vec<String>,
+    unsupported: Option<String>,
+}
+
+impl ProbeMut for SnapshotCheck {
+    fn visit_start_scope(&mut self, name: &str, _node: &mut dyn Block) {
+        self.path.push(name.to_string());
+    }
+
+    fn visit_start_namespace(&mut self, name: &str) {
+        self.path.push(name.to_string());
+    }
+
+    fn visit_end_namespace(&mut self, _name: &str) {
+        self.path.pop();
+    }
+
+    fn visit_end_scope(&mut self, _name: &str) {
+        self.path.pop();
+    }
+
+    fn visit_unsupported(&mut self, name: &str) {
+        if self.unsupported.is_none() {
+            self.path.push(name.to_string());
+            self.unsupported = Some(self.path.join("."));
+            self.path.pop();
+        }
+    }
+}
+
+fn unsupported_block(path: &str) -> String {
+    format!(
+        "{} does not implement accept_mut, so its state cannot be restored",
+        path
+    )
+}
+
+struct SnapshotRestore<'a> {
+    path: Vec<String>,
+    signals: &'a BTreeMap<String, VCDValue>,
+    states: &'a BTreeMap<String, Vec<VCDValue>>,
+    restored: HashSet<String>,
+    error: Option<String>,
+}
+
+impl<'a> ProbeMut for SnapshotRestore<'a> {
+    fn visit_start_scope(&mut self, name: &str, node: &mut dyn Block) {
+        self.path.push(name.to_string());
+        if self.error.is_some() {
+            return;
+        }
+        // Blocks without an entry had no state when the snapshot was taken
+        let path = self.path.join(".");
+        let state = self.states.get(&path).map(|x| &x[..]).unwrap_or_default();
+        if node.set_sim_state(state) {
+            self.restored.insert(path);
+        } else {
+            self.error = Some(format!("the state of {} cannot be restored", path));
+        }
+    }
+
+    fn visit_start_namespace(&mut self, name: &str) {
+        self.path.push(name.to_string());
+    }
+
+    fn visit_atom(&mut self, name: &str, signal: &mut dyn AtomMut) {
+        if self.error.is_some() {
+            return;
+        }
+        let path = format!("{}.{}", self.path.join("."), name);
+        match self.signals.get(&path) {
+            Some(val) if signal.set_vcd(val) => {
+                self.restored.insert(path);
+            }
+            Some(_) => {
+                self.error = Some(format!("the value of {} cannot be restored", path));
+            }
+            None => {
+                self.error = Some(format!("{} is missing from the snapshot", path));
+            }
+        }
+    }
+
+    fn visit_end_namespace(&mut self, _name: &str) {
+        self.path.pop();
+    }
+
+    fn visit_end_scope(&mut self, _name: &str) {
+        self.path.pop();
+    }
+
+    fn visit_unsupported(&mut self, name: &str) {
+        if self.error.is_none() {
+            self.path.push(name.to_string());
+            self.error = Some(unsupported_block(&self.path.join(".")));
+            self.path.pop();
+        }
+    }
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn vcd_char(x: &vcd::Value) -> char {
+    match x {
+        vcd::Value::V0 => '0',
+        vcd::Value::V1 => '1',
+        vcd::Value::X => 'x',
+        vcd::Value::Z => 'z',
+    }
+}
+
+fn char_vcd(x: char) -> std::io::Result<vcd::Value> {
+    match x {
+        '0' => Ok(vcd::Value::V0),
+        '1' => Ok(vcd::Value::V1),
+        'x' => Ok(vcd::Value::X),
+        'z' => Ok(vcd::Value::Z),
+        _ => Err(invalid_data(format!("Unexpected value {} in snapshot", x))),
+    }
+}
+
+// Values are written as space separated tokens: `b0` for a single bit, `v0101` for
+// a vector, `sIdle` for an enum, `r0.25` for a fixed point number, and `{ ... }`
+// around the fields of a struct.
+fn write_value(val: &VCDValue, tokens: &mut Vec<String>) {
+    match val {
+        VCDValue::Single(x) => tokens.push(format!("b{}", vcd_char(x))),
+        VCDValue::Vector(x) => {
+            tokens.push(format!("v{}", x.iter().map(vcd_char).collect::<String>()))
+        }
+        VCDValue::String(x) => tokens.push(format!("s{}", x)),
+        VCDValue::Real(x) => tokens.push(format!("r{}", x)),
+        VCDValue::Composite(x) => {
+            tokens.push("{".into());
+            for field in x {
+                write_value(field, tokens);
+            }
+            tokens.push("}".into());
+        }
+    }
+}
+
+fn read_value<'a, I: Iterator<Item = &'a str>>(
+    tokens: &mut Peekable<I>,
+) -> std::io::Result<VCDValue> {
+    let token = tokens
+        .next()
+        .ok_or_else(|| invalid_data("Missing value in snapshot".into()))?;
+    let mut chars = token.chars();
+    match chars.next() {
+        Some('b') => Ok(VCDValue::Single(char_vcd(
+            chars.next().unwrap_or_default(),
+        )?)),
+        Some('v') => Ok(VCDValue::Vector(
+            chars.map(char_vcd).collect::<std::io::Result<_>>()?,
+        )),
+        Some('s') => Ok(VCDValue::String(chars.collect())),
+        Some('r') => Ok(VCDValue::Real(chars.as_str().parse().map_err(|_| {
+            invalid_data(format!("Invalid real value {} in snapshot", token))
+        })?)),
+        Some('{') => {
+            let mut fields = vec![];
+            while tokens.next_if_eq(&"}").is_none() {
+                fields.push(Box::new(read_value(tokens)?));
+            }
+            Ok(VCDValue::Composite(fields))
+        }
+        _ => Err(invalid_data(format!(
+            "Unexpected token {} in snapshot",
+            token
+        ))),
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn capture(
+        uut: &mut dyn Block,
+        time: u64,
+        clocks: Vec<Option<u64>>,
+    ) -> Result<Snapshot, SimError> {
+        // A snapshot that cannot be restored is of no use
+        let mut check = SnapshotCheck::default();
+        uut.accept_mut("uut", &mut check);
+        if let Some(path) = check.unsupported {
+            return Err(SimError::SnapshotMismatch {
+                reason: unsupported_block(&path),
+            });
+        }
+        let mut capture = SnapshotCapture::default();
+        uut.accept("uut", &mut capture);
+        Ok(Snapshot {
+            time,
+            clocks,
+            signals: capture.signals,
+            states: capture.states,
+        })
+    }
+
+    pub(crate) fn restore(&self, uut: &mut dyn Block) -> Result<(), SimError> {
+        let mut restore = SnapshotRestore {
+            path: vec![],
+            signals: &self.signals,
+            states: &self.states,
+            restored: HashSet::new(),
+            error: None,
+        };
+        uut.accept_mut("uut", &mut restore);
+        if let Some(reason) = restore.error {
+            return Err(SimError::SnapshotMismatch { reason });
+        }
+        if let Some(path) = self
+            .signals
+            .keys()
+            .chain(self.states.keys())
+            .find(|x| !restore.restored.contains(*x))
+        {
+            return Err(SimError::SnapshotMismatch {
+                reason: format!("{} is not part of the circuit", path),
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn clocks(&self) -> &[Option<u64>] {
+        &self.clocks
+    }
+
+    /// The simulation time at which the snapshot was taken
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// The value of a signal in the snapshot, using the same path as a trace, e.g., `uut.counter.q`.
+    pub fn signal(&self, path: &str) -> Option<&VCDValue> {
+        self.signals.get(path)
+    }
+
+    /// Write the snapshot in a (line oriented) text format.
+    pub fn save<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        writeln!(w, "time {}", self.time)?;
+        for clock in &self.clocks {
+            match clock {
+                Some(t) => writeln!(w, "clock {}", t)?,
+                None => writeln!(w, "clock -")?,
+            }
+        }
+        for (path, val) in &self.signals {
+            let mut tokens = vec![];
+            write_value(val, &mut tokens);
+            writeln!(w, "signal {} {}", path, tokens.join(" "))?;
+        }
+        for (path, state) in &self.states {
+            let mut tokens = vec![];
+            for val in state {
+                write_value(val, &mut tokens);
+            }
+            writeln!(w, "state {} {}", path, tokens.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Read a snapshot written by [Snapshot::save].
+    pub fn load<R: Read>(r: R) -> std::io::Result<Snapshot> {
+        let mut snapshot = Snapshot::default();
+        for line in BufReader::new(r).lines() {
+            let line = line?;
+            let mut tokens = line.split_whitespace().peekable();
+            match (tokens.next(), tokens.next()) {
+                (Some("time"), Some(t)) => {
+                    snapshot.time = t.parse().map_err(|_| invalid_data(line.clone()))?;
+                }
+                (Some("clock"), Some("-")) => snapshot.clocks.push(None),
+                (Some("clock"), Some(t)) => snapshot
+                    .clocks
+                    .push(Some(t.parse().map_err(|_| invalid_data(line.clone()))?)),
+                (Some("signal"), Some(path)) => {
+                    let val = read_value(&mut tokens)?;
+                    snapshot.signals.insert(path.to_string(), val);
+                }
+                (Some("state"), Some(path)) => {
+                    let mut state = vec![];
+                    while tokens.peek().is_some() {
+                        state.push(read_value(&mut tokens)?);
+                    }
+                    snapshot.states.insert(path.to_string(), state);
+                }
+                (None, _) => {}
+                _ => {
+                    return Err(invalid_data(format!(
+                        "Unexpected line {} in snapshot",
+                        line
+                    )))
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+}
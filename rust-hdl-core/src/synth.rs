@@ -1,8 +1,12 @@
 use std::fmt::Debug;
 
+use num_bigint::BigUint;
+
 use crate::ast::VerilogLiteral;
 use crate::bits::{Bit, Bits};
 use crate::clock::Clock;
+use crate::fixed::{Fixed, UFixed};
+use crate::reset::{Reset, ResetN};
 use crate::signed::Signed;
 use crate::type_descriptor::{TypeDescriptor, TypeKind};
 
@@ -12,6 +16,7 @@ pub enum VCDValue {
     Vector(Vec<vcd::Value>),
     String(String),
     Composite(Vec<Box<VCDValue>>),
+    Real(f64),
 }
 
 impl From<bool> for VCDValue {
@@ -24,14 +29,61 @@ impl From<bool> for VCDValue {
     }
 }
 
-pub trait Synth: Default + Copy + PartialEq + Debug {
+pub trait Synth: Copy + PartialEq + Debug {
     const BITS: usize;
+    /// The number of bits in the tag of a tagged union (i.e., an enum with variants that
+    /// carry data).  The tag is held in the low bits of the value, and the payload of the
+    /// variant above it.  `None` for all other types.
+    const TAG_BITS: Option<usize> = None;
     fn descriptor() -> TypeDescriptor;
+    /// The value a signal of this type holds before it is driven (e.g., zero).  This
+    /// is usually `Default::default()`, but is part of the trait so that arrays (which
+    /// only implement `Default` up to 32 elements) can be carried by a signal.  Hand
+    /// written impls for types that are `Default` can simply return `Self::default()`.
+    fn default_value() -> Self;
     fn vcd(self) -> VCDValue;
     fn verilog(self) -> VerilogLiteral;
     fn bits(self) -> usize {
         Self::BITS
     }
+    /// The inverse of [Synth::vcd].  Used to restore the state of a circuit from a
+    /// [Snapshot](crate::snapshot::Snapshot).  Returns `None` if the value does not
+    /// describe this type (or if the type does not support it).
+    fn from_vcd(_val: &VCDValue) -> Option<Self> {
+        None
+    }
+}
+
+/// Pack any synthesizable value into a (possibly wider) bit vector.
+#[doc(hidden)]
+pub fn synth_to_bits<T: Synth, const N: usize>(x: T) -> Bits<N> {
+    BigUint::from_slice(&x.verilog().to_u32_words()).into()
+}
+
+/// Extract `width` bits starting at `offset` from a (scalar or vector) VCD value.
+#[doc(hidden)]
+pub fn vcd_slice(val: &VCDValue, offset: usize, width: usize) -> Option<VCDValue> {
+    let bits = vcd_to_bits(val)?;
+    if offset + width > bits.len() {
+        return None;
+    }
+    Some(VCDValue::Vector(
+        bits[offset..offset + width]
+            .iter()
+            .rev()
+            .map(|x| if *x { vcd::Value::V1 } else { vcd::Value::V0 })
+            .collect(),
+    ))
+}
+
+// Converts a (scalar or vector) VCD value into bits, LSB first.  Anything that is
+// not a `1` (e.g., an undriven signal) is read as a `0`.
+fn vcd_to_bits(val: &VCDValue) -> Option<Vec<bool>> {
+    match val {
+        VCDValue::Single(x) => Some(vec![*x == vcd::Value::V1]),
+        VCDValue::Vector(x) => Some(x.iter().rev().map(|x| *x == vcd::Value::V1).collect()),
+        _ => None,
+    }
 }
 
 impl<const N: usize> Synth for Bits<N> {
@@ -44,6 +96,10 @@ impl<const N: usize> Synth for Bits<N> {
         }
     }
 
+    fn default_value() -> Self {
+        Self::default()
+    }
+
     fn vcd(self) -> VCDValue {
         self.into()
     }
@@ -51,6 +107,18 @@ impl<const N: usize> Synth for Bits<N> {
     fn verilog(self) -> VerilogLiteral {
         self.into()
     }
+
+    fn from_vcd(val: &VCDValue) -> Option<Self> {
+        let bits = vcd_to_bits(val)?;
+        if bits.len() != N {
+            return None;
+        }
+        Some(
+            bits.into_iter()
+                .enumerate()
+                .fold(Bits::default(), |x, (ndx, bit)| x.replace_bit(ndx, bit)),
+        )
+    }
 }
 
 impl Synth for Bit {
@@ -63,6 +131,10 @@ impl Synth for Bit {
         }
     }
 
+    fn default_value() -> Self {
+        Self::default()
+    }
+
     fn vcd(self) -> VCDValue {
         if self {
             VCDValue::Single(vcd::Value::V1)
@@ -74,6 +146,13 @@ impl Synth for Bit {
     fn verilog(self) -> VerilogLiteral {
         self.into()
     }
+
+    fn from_vcd(val: &VCDValue) -> Option<Self> {
+        match vcd_to_bits(val)?.as_slice() {
+            [x] => Some(*x),
+            _ => None,
+        }
+    }
 }
 
 impl Synth for Clock {
@@ -86,6 +165,10 @@ impl Synth for Clock {
         }
     }
 
+    fn default_value() -> Self {
+        Self::default()
+    }
+
     fn vcd(self) -> VCDValue {
         self.clk.into()
     }
@@ -93,6 +176,100 @@ impl Synth for Clock {
     fn verilog(self) -> VerilogLiteral {
         self.clk.into()
     }
+
+    fn from_vcd(val: &VCDValue) -> Option<Self> {
+        Bit::from_vcd(val).map(|clk| Clock { clk })
+    }
+}
+
+impl Synth for Reset {
+    const BITS: usize = 1;
+
+    fn descriptor() -> TypeDescriptor {
+        TypeDescriptor {
+            name: "reset".to_string(),
+            kind: TypeKind::Bits(1),
+        }
+    }
+
+    fn default_value() -> Self {
+        Self::default()
+    }
+
+    fn vcd(self) -> VCDValue {
+        self.rst.into()
+    }
+
+    fn verilog(self) -> VerilogLiteral {
+        self.rst.into()
+    }
+
+    fn from_vcd(val: &VCDValue) -> Option<Self> {
+        Bit::from_vcd(val).map(|rst| Reset { rst })
+    }
+}
+
+impl Synth for ResetN {
+    const BITS: usize = 1;
+
+    fn descriptor() -> TypeDescriptor {
+        TypeDescriptor {
+            name: "reset_n".to_string(),
+            kind: TypeKind::Bits(1),
+        }
+    }
+
+    fn default_value() -> Self {
+        Self::default()
+    }
+
+    fn vcd(self) -> VCDValue {
+        self.rst_n.into()
+    }
+
+    fn verilog(self) -> VerilogLiteral {
+        self.rst_n.into()
+    }
+
+    fn from_vcd(val: &VCDValue) -> Option<Self> {
+        Bit::from_vcd(val).map(|rst_n| ResetN { rst_n })
+    }
+}
+
+// Arrays are packed with element 0 in the low bits
+impl<T: Synth, const N: usize> Synth for [T; N] {
+    const BITS: usize = N * T::BITS;
+    fn descriptor() -> TypeDescriptor {
+        TypeDescriptor {
+            name: format!("[{}; {}]", T::descriptor().name, N),
+            kind: TypeKind::Array {
+                element: Box::new(T::descriptor()),
+                width: T::BITS,
+                count: N,
+            },
+        }
+    }
+    fn default_value() -> Self {
+        std::array::from_fn(|_| T::default_value())
+    }
+    fn vcd(self) -> VCDValue {
+        VCDValue::Composite(self.iter().map(|x| Box::new(x.vcd())).collect())
+    }
+    fn verilog(self) -> VerilogLiteral {
+        self.into()
+    }
+    fn from_vcd(val: &VCDValue) -> Option<Self> {
+        match val {
+            VCDValue::Composite(vals) if vals.len() == N => {
+                let mut ret = Self::default_value();
+                for (x, val) in ret.iter_mut().zip(vals) {
+                    *x = T::from_vcd(val)?;
+                }
+                Some(ret)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl<const N: usize> Synth for Signed<N> {
@@ -103,10 +280,52 @@ impl<const N: usize> Synth for Signed<N> {
             kind: TypeKind::Signed(Self::BITS),
         }
     }
+    fn default_value() -> Self {
+        Self::default()
+    }
     fn vcd(self) -> VCDValue {
         self.inner().vcd()
     }
     fn verilog(self) -> VerilogLiteral {
         self.inner().into()
     }
+    fn from_vcd(val: &VCDValue) -> Option<Self> {
+        Bits::<N>::from_vcd(val).map(Signed::from_inner)
+    }
 }
+
+macro_rules! define_fixed_synth {
+    ($name: ident, $signed: expr) => {
+        impl<const I: usize, const F: usize> Synth for $name<I, F> {
+            const BITS: usize = I + F;
+            fn descriptor() -> TypeDescriptor {
+                TypeDescriptor {
+                    name: format!("{}::<{}, {}>", stringify!($name), I, F),
+                    kind: TypeKind::Fixed {
+                        signed: $signed,
+                        integer: I,
+                        fraction: F,
+                    },
+                }
+            }
+            fn default_value() -> Self {
+                Self::default()
+            }
+            fn vcd(self) -> VCDValue {
+                VCDValue::Real(self.to_f64())
+            }
+            fn verilog(self) -> VerilogLiteral {
+                self.into()
+            }
+            fn from_vcd(val: &VCDValue) -> Option<Self> {
+                match val {
+                    VCDValue::Real(x) => Some(Self::from_f64(*x)),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+define_fixed_synth!(Fixed, true);
+define_fixed_synth!(UFixed, false);
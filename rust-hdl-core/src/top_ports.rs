@@ -0,0 +1,84 @@
+use crate::ast::VerilogLiteral;
+use crate::atom::{Atom, AtomKind};
+use crate::block::Block;
+use crate::named_path::NamedPath;
+use crate::probe::Probe;
+
+/// Describes one of the ports of the top level module generated for a circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopPort {
+    /// The path to the signal in the Rust circuit, e.g., `uut.bus.data`
+    pub path: String,
+    /// The name of the port in the generated Verilog, e.g., `bus$data`
+    pub verilog_name: String,
+    /// The width of the port in bits
+    pub bits: usize,
+    /// `true` if the port is driven by the testbench, `false` if it is an output
+    pub is_input: bool,
+}
+
+// Collects the top level ports of a circuit, along with their current
+// values.  Tristate (InOut) signals cannot be driven from outside the
+// circuit in a meaningful way, and are skipped.
+#[derive(Default)]
+struct TopPortProbe {
+    depth: usize,
+    namespace: NamedPath,
+    ports: Vec<TopPort>,
+    values: Vec<VerilogLiteral>,
+}
+
+impl Probe for TopPortProbe {
+    fn visit_start_scope(&mut self, _name: &str, _node: &dyn Block) {
+        self.depth += 1;
+    }
+
+    fn visit_start_namespace(&mut self, name: &str, _node: &dyn Block) {
+        self.namespace.push(name);
+    }
+
+    fn visit_atom(&mut self, name: &str, signal: &dyn Atom) {
+        if self.depth != 1 {
+            return;
+        }
+        let is_input = match signal.kind() {
+            AtomKind::InputParameter => true,
+            AtomKind::OutputParameter | AtomKind::OutputPassthrough => false,
+            _ => return,
+        };
+        let mut path = self.namespace.clone();
+        path.push(name);
+        self.ports.push(TopPort {
+            path: format!("uut.{}", path.flat(".")),
+            verilog_name: path.flat("$"),
+            bits: signal.bits(),
+            is_input,
+        });
+        self.values.push(signal.verilog());
+    }
+
+    fn visit_end_namespace(&mut self, _name: &str, _node: &dyn Block) {
+        self.namespace.pop();
+    }
+
+    fn visit_end_scope(&mut self, _name: &str, _node: &dyn Block) {
+        self.depth -= 1;
+    }
+}
+
+fn probe_top_ports(uut: &dyn Block) -> TopPortProbe {
+    let mut probe = TopPortProbe::default();
+    uut.accept("uut", &mut probe);
+    probe
+}
+
+/// Returns the inputs and outputs of the top level module for the circuit.
+pub fn top_ports(uut: &dyn Block) -> Vec<TopPort> {
+    probe_top_ports(uut).ports
+}
+
+/// Returns the current values of the ports of the circuit, in the same order
+/// as [top_ports].
+pub fn top_port_values(uut: &dyn Block) -> Vec<VerilogLiteral> {
+    probe_top_ports(uut).values
+}
@@ -1,4 +1,11 @@
-use crate::{ast::Verilog, block::Block, logic::Logic, probe::Probe, timing::TimingInfo};
+use crate::{
+    ast::Verilog,
+    block::Block,
+    logic::Logic,
+    probe::{Probe, ProbeMut},
+    sensitivity::Sensitivity,
+    timing::TimingInfo,
+};
 
 pub struct TopWrap<U: Block> {
     pub uut: U,
@@ -31,4 +38,29 @@ impl<U: Block> Block for TopWrap<U> {
         self.uut.accept("uut", probe);
         probe.visit_end_scope(name, self);
     }
+    fn accept_mut(&mut self, name: &str, probe: &mut dyn ProbeMut) {
+        probe.visit_start_scope(name, self);
+        self.uut.accept_mut("uut", probe);
+        probe.visit_end_scope(name);
+    }
+    fn update_sensitive(&mut self, sens: &mut Sensitivity, dirty: bool) -> bool {
+        match sens.enter(dirty) {
+            None => false,
+            Some(id) => {
+                let evaluated = sens.evaluate(id, &[self.uut.ports_changed()], &["uut"]);
+                if evaluated {
+                    self.update();
+                }
+                let changed = self.uut.update_sensitive(sens, evaluated);
+                sens.leave(id, changed);
+                changed
+            }
+        }
+    }
+    fn ports_changed(&self) -> bool {
+        self.uut.atoms_changed()
+    }
+    fn atoms_changed(&self) -> bool {
+        false
+    }
 }
@@ -1,19 +1,40 @@
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TypeDescriptor {
     pub name: String,
     pub kind: TypeKind,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TypeField {
     pub fieldname: String,
     pub kind: TypeDescriptor,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum TypeKind {
     Bits(usize),
     Signed(usize),
     Enum(Vec<String>),
     Composite(Vec<Box<TypeField>>),
+    Array {
+        element: Box<TypeDescriptor>,
+        width: usize,
+        count: usize,
+    },
+    Fixed {
+        signed: bool,
+        integer: usize,
+        fraction: usize,
+    },
+}
+
+impl TypeKind {
+    /// True for types that are held in two's complement form (and so are declared
+    /// `signed` in the generated HDL).
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            TypeKind::Signed(_) | TypeKind::Fixed { signed: true, .. }
+        )
+    }
 }
@@ -33,99 +33,219 @@ impl<W: Write> VCDProbe<W> {
     }
 }
 
-struct VCDHeader<W: Write>(VCDProbe<W>);
+/// Restricts the signals that are written to a trace.  By default, every signal
+/// in the circuit is traced.
+///
+/// Paths use the same names as the trace itself, starting with `uut`.  For example,
+/// `TraceFilter::default().path("uut.sdram").max_depth(2)` traces the signals of
+/// the `sdram` block (and its children), but nothing more than 2 levels below the top.
+#[derive(Clone, Debug, Default)]
+pub struct TraceFilter {
+    paths: Vec<String>,
+    max_depth: Option<usize>,
+}
+
+impl TraceFilter {
+    /// Trace the signals in (or below) the given path.  Can be used more than once
+    /// to trace several parts of the circuit.
+    pub fn path(mut self, path: &str) -> TraceFilter {
+        self.paths.push(path.to_string());
+        self
+    }
+    /// Only trace the signals at most `depth` levels below the top level circuit.  A
+    /// depth of 0 traces the top level signals only.
+    pub fn max_depth(mut self, depth: usize) -> TraceFilter {
+        self.max_depth = Some(depth);
+        self
+    }
+    fn depth_ok(&self, depth: usize) -> bool {
+        self.max_depth.map(|x| depth <= x).unwrap_or(true)
+    }
+    // Is the path inside one of the selected paths?
+    fn selects(&self, path: &str) -> bool {
+        self.paths.is_empty()
+            || self
+                .paths
+                .iter()
+                .any(|p| path == p || path.starts_with(&format!("{}.", p)))
+    }
+    // Is the scope inside one of the selected paths, or on the way to one?
+    fn visits(&self, path: &str) -> bool {
+        self.selects(path)
+            || self
+                .paths
+                .iter()
+                .any(|p| p.starts_with(&format!("{}.", path)))
+    }
+}
+
+// The visitors cannot return errors, so they keep the first one (and stop writing)
+fn keep_first_error(result: &mut std::io::Result<()>, write: impl FnOnce() -> std::io::Result<()>) {
+    if result.is_ok() {
+        *result = write();
+    }
+}
+
+struct VCDHeader<'a, W: Write> {
+    probe: VCDProbe<W>,
+    filter: &'a TraceFilter,
+    path: Vec<String>,
+    depth: usize,
+    skipped: usize,
+    result: std::io::Result<()>,
+}
+
+impl<'a, W: Write> VCDHeader<'a, W> {
+    fn start(&mut self, name: &str, depth: usize) {
+        self.path.push(name.to_string());
+        if self.skipped > 0
+            || !self.filter.depth_ok(depth)
+            || !self.filter.visits(&self.path.join("."))
+        {
+            self.skipped += 1;
+        } else {
+            let vcd = &mut self.probe.vcd;
+            keep_first_error(&mut self.result, || vcd.add_module(name));
+        }
+    }
+    fn end(&mut self) {
+        self.path.pop();
+        if self.skipped > 0 {
+            self.skipped -= 1;
+        } else {
+            let vcd = &mut self.probe.vcd;
+            keep_first_error(&mut self.result, || vcd.upscope());
+        }
+    }
+}
 
 fn register_signal<W: Write>(
     name: &str,
     descriptor: &TypeDescriptor,
     vcd: &mut vcd::Writer<W>,
-) -> VCDIDCode {
-    match &descriptor.kind {
+) -> std::io::Result<VCDIDCode> {
+    Ok(match &descriptor.kind {
         TypeKind::Bits(width) | TypeKind::Signed(width) => {
-            VCDIDCode::Singleton(vcd.add_wire(*width as u32, name).unwrap())
+            VCDIDCode::Singleton(vcd.add_wire(*width as u32, name)?)
+        }
+        TypeKind::Enum(_) => VCDIDCode::Singleton(vcd.add_wire(0, name)?),
+        TypeKind::Fixed { .. } => {
+            VCDIDCode::Singleton(vcd.add_var(vcd::VarType::Real, 64, name, None)?)
         }
-        TypeKind::Enum(_) => VCDIDCode::Singleton(vcd.add_wire(0, name).unwrap()),
         TypeKind::Composite(k) => {
             let mut ret = vec![];
             for field in k {
                 let sub_name = format!("{}${}", name, field.fieldname);
-                let code = register_signal(&sub_name, &field.kind, vcd);
+                let code = register_signal(&sub_name, &field.kind, vcd)?;
                 ret.push(Box::new(code));
             }
             VCDIDCode::Composite(ret)
         }
-    }
+        TypeKind::Array { element, count, .. } => VCDIDCode::Composite(
+            (0..*count)
+                .map(|ndx| {
+                    register_signal(&format!("{}${}", name, ndx), element, vcd).map(Box::new)
+                })
+                .collect::<std::io::Result<_>>()?,
+        ),
+    })
 }
 
-impl<W: Write> Probe for VCDHeader<W> {
+// The depth counts blocks (scopes) only, so the signals of an interface are at the
+// same depth as the block that holds it.
+impl<'a, W: Write> Probe for VCDHeader<'a, W> {
     fn visit_start_scope(&mut self, name: &str, _node: &dyn Block) {
-        self.0.vcd.add_module(name).unwrap();
+        let depth = self.depth;
+        self.depth += 1;
+        self.start(name, depth);
     }
 
     fn visit_start_namespace(&mut self, name: &str, _node: &dyn Block) {
-        self.0.vcd.add_module(name).unwrap();
+        self.start(name, self.depth.saturating_sub(1));
     }
 
     fn visit_atom(&mut self, name: &str, signal: &dyn Atom) {
-        self.0.id_map.insert(
-            signal.id(),
-            register_signal(name, &signal.descriptor(), &mut self.0.vcd),
-        );
+        if self.skipped > 0 || self.result.is_err() {
+            return;
+        }
+        let path = format!("{}.{}", self.path.join("."), name);
+        if !self.filter.selects(&path) {
+            return;
+        }
+        match register_signal(name, &signal.descriptor(), &mut self.probe.vcd) {
+            Ok(code) => {
+                self.probe.id_map.insert(signal.id(), code);
+            }
+            Err(err) => self.result = Err(err),
+        }
     }
 
     fn visit_end_namespace(&mut self, _name: &str, _node: &dyn Block) {
-        self.0.vcd.upscope().unwrap();
+        self.end();
     }
 
     fn visit_end_scope(&mut self, _name: &str, _node: &dyn Block) {
-        self.0.vcd.upscope().unwrap();
+        self.depth -= 1;
+        self.end();
     }
 }
 
-pub fn write_vcd_header<W: Write>(writer: W, uut: &dyn Block) -> VCDProbe<W> {
-    let mut visitor = VCDHeader(VCDProbe::new(writer));
-    visitor.0.vcd.timescale(1, vcd::TimescaleUnit::PS).unwrap();
+pub fn write_vcd_header<W: Write>(writer: W, uut: &dyn Block) -> std::io::Result<VCDProbe<W>> {
+    write_vcd_header_filtered(writer, uut, &TraceFilter::default())
+}
+
+/// Write the header of a VCD trace that only includes the signals selected by the filter.
+pub fn write_vcd_header_filtered<W: Write>(
+    writer: W,
+    uut: &dyn Block,
+    filter: &TraceFilter,
+) -> std::io::Result<VCDProbe<W>> {
+    let mut visitor = VCDHeader {
+        probe: VCDProbe::new(writer),
+        filter,
+        path: vec![],
+        depth: 0,
+        skipped: 0,
+        result: Ok(()),
+    };
+    visitor.probe.vcd.timescale(1, vcd::TimescaleUnit::PS)?;
     uut.accept("uut", &mut visitor);
-    visitor.0.vcd.enddefinitions().unwrap();
-    visitor.0
+    visitor.result?;
+    visitor.probe.vcd.enddefinitions()?;
+    Ok(visitor.probe)
 }
 
-struct VCDChange<W: Write>(VCDProbe<W>);
+// Writes the values of the signals, either all of them (for a dump) or just the ones
+// that changed
+struct VCDChange<W: Write> {
+    probe: VCDProbe<W>,
+    dump: bool,
+    result: std::io::Result<()>,
+}
 
 impl<W: Write> Probe for VCDChange<W> {
     fn visit_atom(&mut self, _name: &str, signal: &dyn Atom) {
-        if let Some(idc) = self.0.id_map.get(&signal.id()) {
-            do_vcd_change(
-                &mut self.0.val_map,
-                &mut self.0.vcd,
-                idc,
-                &signal.vcd(),
-                false,
-            );
+        if let Some(idc) = self.probe.id_map.get(&signal.id()) {
+            let (val_map, vcd) = (&mut self.probe.val_map, &mut self.probe.vcd);
+            keep_first_error(&mut self.result, || {
+                do_vcd_change(val_map, vcd, idc, &signal.vcd(), self.dump)
+            });
         }
     }
 }
 
-pub fn write_vcd_change<W: Write>(vcd: VCDProbe<W>, uut: &dyn Block) -> VCDProbe<W> {
-    let mut visitor = VCDChange(vcd);
+pub fn write_vcd_change<W: Write>(
+    vcd: VCDProbe<W>,
+    uut: &dyn Block,
+) -> std::io::Result<VCDProbe<W>> {
+    let mut visitor = VCDChange {
+        probe: vcd,
+        dump: false,
+        result: Ok(()),
+    };
     uut.accept("uut", &mut visitor);
-    visitor.0
-}
-
-struct VCDDump<W: Write>(VCDProbe<W>);
-
-impl<W: Write> Probe for VCDDump<W> {
-    fn visit_atom(&mut self, _name: &str, signal: &dyn Atom) {
-        if let Some(idc) = &self.0.id_map.get(&signal.id()) {
-            do_vcd_change(
-                &mut self.0.val_map,
-                &mut self.0.vcd,
-                idc,
-                &signal.vcd(),
-                true,
-            );
-        }
-    }
+    visitor.result?;
+    Ok(visitor.probe)
 }
 
 fn do_vcd_change<W: Write>(
@@ -134,30 +254,33 @@ fn do_vcd_change<W: Write>(
     idc: &VCDIDCode,
     val: &VCDValue,
     dump: bool,
-) {
+) -> std::io::Result<()> {
     match idc {
         VCDIDCode::Singleton(idc) => {
             if !dump {
                 if let Some(old_val) = val_map.get(idc) {
                     if val.eq(old_val) {
-                        return;
+                        return Ok(());
                     }
                 }
             }
             let _ = val_map.insert(*idc, val.clone());
             match val {
                 VCDValue::Single(s) => {
-                    vcd.change_scalar(*idc, s.clone()).unwrap();
+                    vcd.change_scalar(*idc, s.clone())?;
                 }
                 VCDValue::Vector(v) => {
                     if v.len() == 1 {
-                        vcd.change_scalar(*idc, v[0]).unwrap();
+                        vcd.change_scalar(*idc, v[0])?;
                     } else {
-                        vcd.change_vector(*idc, &v).unwrap();
+                        vcd.change_vector(*idc, &v)?;
                     }
                 }
                 VCDValue::String(t) => {
-                    vcd.change_string(*idc, &t).unwrap();
+                    vcd.change_string(*idc, &t)?;
+                }
+                VCDValue::Real(x) => {
+                    vcd.change_real(*idc, *x)?;
                 }
                 VCDValue::Composite(_) => {
                     panic!("Composite data received for singleton type");
@@ -172,7 +295,7 @@ fn do_vcd_change<W: Write>(
                     "Mismatch in values versus type information"
                 );
                 for n in 0..idcs.len() {
-                    do_vcd_change(val_map, vcd, &idcs[n], &vals[n], dump);
+                    do_vcd_change(val_map, vcd, &idcs[n], &vals[n], dump)?;
                 }
             }
             _ => {
@@ -180,16 +303,18 @@ fn do_vcd_change<W: Write>(
             }
         },
     }
+    Ok(())
 }
 
-pub fn write_vcd_dump<W: Write>(vcd: VCDProbe<W>, uut: &dyn Block) -> VCDProbe<W> {
-    let mut visitor = VCDDump(vcd);
-    visitor
-        .0
-        .vcd
-        .begin(vcd::SimulationCommand::Dumpvars)
-        .unwrap();
+pub fn write_vcd_dump<W: Write>(vcd: VCDProbe<W>, uut: &dyn Block) -> std::io::Result<VCDProbe<W>> {
+    let mut visitor = VCDChange {
+        probe: vcd,
+        dump: true,
+        result: Ok(()),
+    };
+    visitor.probe.vcd.begin(vcd::SimulationCommand::Dumpvars)?;
     uut.accept("uut", &mut visitor);
-    visitor.0.vcd.end().unwrap();
-    visitor.0
+    visitor.result?;
+    visitor.probe.vcd.end()?;
+    Ok(visitor.probe)
 }
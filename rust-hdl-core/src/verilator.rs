@@ -0,0 +1,300 @@
+use std::env::temp_dir;
+use std::ffi::c_void;
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::block::Block;
+use crate::module_defines::generate_verilog;
+use crate::simulate::SimError;
+use crate::top_ports::{top_port_values, top_ports};
+use crate::yosys::SynthError;
+
+/// Describes one of the top level ports of the circuit, as seen by the
+/// co-simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CosimPort {
+    /// The path to the signal in the Rust circuit, e.g., `uut.bus.data`
+    pub path: String,
+    /// The name of the port in the Verilated model
+    pub verilated_name: String,
+    /// The width of the port in bits
+    pub bits: usize,
+    /// `true` if the port is driven by the testbench, `false` if it is an output
+    pub is_input: bool,
+}
+
+/// Returns the top level ports of the circuit that are checked by the co-simulation.
+pub fn cosim_ports(uut: &dyn Block) -> Vec<CosimPort> {
+    top_ports(uut)
+        .into_iter()
+        .map(|port| CosimPort {
+            path: port.path,
+            verilated_name: verilator_encode_name(&port.verilog_name),
+            bits: port.bits,
+            is_input: port.is_input,
+        })
+        .collect()
+}
+
+/// Verilator escapes any characters in an identifier that are not legal in C++.
+/// RustHDL uses `$` to flatten hierarchical names, so we need to apply the same
+/// encoding to find the ports on the Verilated model.
+pub fn verilator_encode_name(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    let mut ndx = 0;
+    while ndx < chars.len() {
+        let c = chars[ndx];
+        let legal = if ndx == 0 {
+            c.is_ascii_alphabetic()
+        } else {
+            c.is_ascii_alphanumeric()
+        };
+        if legal {
+            out.push(c);
+        } else if c == '_' {
+            if chars.get(ndx + 1) == Some(&'_') {
+                out += "___05F";
+                ndx += 1;
+            } else {
+                out.push(c);
+            }
+        } else {
+            out += &format!("__0{:02X}", c as u32);
+        }
+        ndx += 1;
+    }
+    out
+}
+
+/// Generate the C++ shim that exposes a Verilated model of `top` through a
+/// C interface.  Values are passed as arrays of 32 bit words, least
+/// significant word first, and ports are referred to by their index in `ports`.
+pub fn verilator_wrapper(ports: &[CosimPort]) -> String {
+    let mut setters = String::new();
+    let mut getters = String::new();
+    for (ndx, port) in ports.iter().enumerate() {
+        let name = &port.verilated_name;
+        let (set, get) = if port.bits <= 32 {
+            (format!("m->{name} = w[0];"), format!("w[0] = m->{name};"))
+        } else if port.bits <= 64 {
+            (
+                format!("m->{name} = (((QData) w[1]) << 32) | w[0];"),
+                format!("w[0] = (IData) m->{name}; w[1] = (IData) (m->{name} >> 32);"),
+            )
+        } else {
+            let words = port.bits.div_ceil(32);
+            (
+                format!("for (int i=0;i<{words};i++) m->{name}[i] = w[i];"),
+                format!("for (int i=0;i<{words};i++) w[i] = m->{name}[i];"),
+            )
+        };
+        if port.is_input {
+            setters += &format!("    case {ndx}: {set} break;\n");
+        }
+        getters += &format!("    case {ndx}: {get} break;\n");
+    }
+    format!(
+        r#"#include "Vtop.h"
+#include "verilated.h"
+#include <cstdint>
+
+extern "C" {{
+
+void *rust_hdl_cosim_new() {{
+  return new Vtop;
+}}
+
+void rust_hdl_cosim_delete(void *model) {{
+  Vtop *m = (Vtop *) model;
+  m->final();
+  delete m;
+}}
+
+void rust_hdl_cosim_eval(void *model) {{
+  ((Vtop *) model)->eval();
+}}
+
+void rust_hdl_cosim_set(void *model, int port, const uint32_t *w) {{
+  Vtop *m = (Vtop *) model;
+  switch (port) {{
+{setters}  }}
+}}
+
+void rust_hdl_cosim_get(void *model, int port, uint32_t *w) {{
+  Vtop *m = (Vtop *) model;
+  switch (port) {{
+{getters}  }}
+}}
+
+}}
+"#
+    )
+}
+
+/// Compile the given Verilog translation (which must contain a module named `top`)
+/// and the C++ shim into a shared library using Verilator.  Returns the path to the library.
+pub fn verilator_build(
+    prefix: &str,
+    translation: &str,
+    wrapper: &str,
+) -> Result<PathBuf, SynthError> {
+    let dir = temp_dir().as_path().join(prefix);
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir)?;
+    write!(File::create(dir.join("top.v"))?, "{}", translation)?;
+    write!(File::create(dir.join("rust_hdl_cosim.cpp"))?, "{}", wrapper)?;
+    let output = Command::new("verilator")
+        .current_dir(dir.clone())
+        .args([
+            "--cc",
+            "top.v",
+            "rust_hdl_cosim.cpp",
+            "--top-module",
+            "top",
+            "--exe",
+            "--build",
+            "-Wno-fatal",
+            "-CFLAGS",
+            "-fPIC",
+            "-LDFLAGS",
+            "-shared",
+            "-o",
+            "librust_hdl_cosim.so",
+        ])
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    {
+        let mut debug = File::create(dir.join("verilator.stdout"))?;
+        write!(debug, "{}", stdout)?;
+        write!(debug, "{}", stderr)?;
+    }
+    let lib = dir.join("obj_dir").join("librust_hdl_cosim.so");
+    if !output.status.success() || !lib.exists() {
+        return Err(SynthError::SynthesisFailed {
+            stdout,
+            stderr,
+            source: None,
+        });
+    }
+    Ok(lib)
+}
+
+type ModelNew = unsafe extern "C" fn() -> *mut c_void;
+type ModelFn = unsafe extern "C" fn(*mut c_void);
+type ModelSet = unsafe extern "C" fn(*mut c_void, i32, *const u32);
+type ModelGet = unsafe extern "C" fn(*mut c_void, i32, *mut u32);
+
+/// A Verilated model of a circuit, that can be run in lock step with the Rust
+/// simulation of the same circuit.
+///
+/// The model is built from the output of [generate_verilog], so the circuit must
+/// pass the same checks (and be connected) first.  After every step of the simulation,
+/// the values of the top level inputs of the Rust circuit are copied into the Verilated
+/// model, it is evaluated, and each of the top level outputs is compared against
+/// the Rust circuit.  The first mismatch is reported as a [SimError::CosimDivergence].
+/// You do not need to use this directly, instead, use
+/// [Simulation::run_cosim](crate::simulate::Simulation::run_cosim).
+///
+/// Requires `verilator` and a C++ compiler to be installed.  Tristate (`InOut`)
+/// top level signals are not supported, and are ignored.
+pub struct VerilatorCosim {
+    ports: Vec<CosimPort>,
+    model: *mut c_void,
+    set: ModelSet,
+    get: ModelGet,
+    eval: ModelFn,
+    delete: ModelFn,
+    _lib: libloading::Library,
+}
+
+impl VerilatorCosim {
+    /// Build a Verilated model of the given circuit.  The `prefix` names the
+    /// (temporary) directory in which the model is built.
+    pub fn new<U: Block>(prefix: &str, uut: &U) -> Result<VerilatorCosim, SynthError> {
+        let ports = cosim_ports(uut);
+        let lib = verilator_build(prefix, &generate_verilog(uut), &verilator_wrapper(&ports))?;
+        let missing = |e: libloading::Error| SynthError::IOError(std::io::Error::other(e));
+        // Safety: the library and the symbols come from the wrapper generated above.
+        unsafe {
+            let lib = libloading::Library::new(lib).map_err(missing)?;
+            let new: ModelNew = *lib
+                .get::<ModelNew>(b"rust_hdl_cosim_new")
+                .map_err(missing)?;
+            let set: ModelSet = *lib
+                .get::<ModelSet>(b"rust_hdl_cosim_set")
+                .map_err(missing)?;
+            let get: ModelGet = *lib
+                .get::<ModelGet>(b"rust_hdl_cosim_get")
+                .map_err(missing)?;
+            let eval: ModelFn = *lib
+                .get::<ModelFn>(b"rust_hdl_cosim_eval")
+                .map_err(missing)?;
+            let delete: ModelFn = *lib
+                .get::<ModelFn>(b"rust_hdl_cosim_delete")
+                .map_err(missing)?;
+            Ok(VerilatorCosim {
+                ports,
+                model: new(),
+                set,
+                get,
+                eval,
+                delete,
+                _lib: lib,
+            })
+        }
+    }
+    /// The top level ports of the circuit that are driven and checked
+    pub fn ports(&self) -> &[CosimPort] {
+        &self.ports
+    }
+    /// Drive the inputs of the Verilated model from the circuit, evaluate it and
+    /// compare the outputs.  The `time` is only used for reporting.
+    pub fn step(&mut self, time: u64, uut: &dyn Block) -> Result<(), SimError> {
+        let values = top_port_values(uut)
+            .iter()
+            .map(|x| x.to_u32_words())
+            .collect::<Vec<_>>();
+        assert_eq!(values.len(), self.ports.len(), "Circuit ports have changed");
+        for (ndx, (port, value)) in self.ports.iter().zip(values.iter()).enumerate() {
+            if port.is_input {
+                // Safety: value holds enough words for the port width
+                unsafe { (self.set)(self.model, ndx as i32, value.as_ptr()) }
+            }
+        }
+        unsafe { (self.eval)(self.model) }
+        for (ndx, (port, value)) in self.ports.iter().zip(values.iter()).enumerate() {
+            if port.is_input {
+                continue;
+            }
+            let mut verilated = vec![0_u32; value.len()];
+            unsafe { (self.get)(self.model, ndx as i32, verilated.as_mut_ptr()) }
+            if verilated != *value {
+                return Err(SimError::CosimDivergence {
+                    time,
+                    signal: port.path.clone(),
+                    rust: format_words(value),
+                    verilog: format_words(&verilated),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VerilatorCosim {
+    fn drop(&mut self) {
+        unsafe { (self.delete)(self.model) }
+    }
+}
+
+fn format_words(words: &[u32]) -> String {
+    let mut ret = "0x".to_string();
+    for word in words.iter().rev() {
+        ret += &format!("{:08x}", word);
+    }
+    ret
+}
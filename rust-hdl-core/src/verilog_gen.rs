@@ -3,16 +3,77 @@ use num_bigint::BigUint;
 use regex::Regex;
 
 use crate::ast::{
-    VerilogBlock, VerilogBlockOrConditional, VerilogCase, VerilogConditional, VerilogExpression,
+    SourceLocation, VerilogBlock, VerilogBlockOrConditional, VerilogCall, VerilogCase,
+    VerilogConditional, VerilogElement, VerilogExpression, VerilogFunction, VerilogLet,
     VerilogLink, VerilogLinkDetails, VerilogLiteral, VerilogLoop, VerilogMatch, VerilogOp,
-    VerilogOpUnary,
+    VerilogOpUnary, VerilogProperty, VerilogPropertyKind, VerilogStatement,
 };
 use crate::code_writer::CodeWriter;
-use crate::verilog_visitor::{walk_block, VerilogVisitor};
+use crate::verilog_visitor::{walk_block, walk_call, walk_statement, VerilogVisitor};
+use std::collections::{BTreeMap, BTreeSet};
 
-struct LoopVariable {
-    variable: String,
-    value: usize,
+pub(crate) struct LoopVariable {
+    pub(crate) variable: String,
+    pub(crate) value: usize,
+}
+
+/// An array-valued signal that a kernel indexes.  The kernel works on an unpacked array
+/// (e.g., `reg [7:0] x [0:15]`), so that an element is picked by an index into the array,
+/// rather than by a part select of a vector.  Arrays of arrays are flattened into arrays
+/// of their innermost elements.
+#[derive(Clone, Debug)]
+pub(crate) struct UnpackedArray {
+    pub(crate) name: String,
+    pub(crate) width: usize,
+    pub(crate) count: usize,
+    pub(crate) signed: bool,
+    pub(crate) packed: PackedArray,
+}
+
+/// Verilog-2001 has no array ports, so the ports of a module (and the signals that connect
+/// to its sub-modules) stay packed vectors, with element 0 in the low bits.
+#[derive(Clone, Debug)]
+pub(crate) enum PackedArray {
+    /// A local signal only exists as an unpacked array
+    None,
+    /// The unpacked array is copied from the vector at the start of the kernel
+    Source(String),
+    /// The unpacked array is copied to the vector at the end of the kernel
+    Sink(String),
+}
+
+impl UnpackedArray {
+    fn elements(&self) -> String {
+        let elements = (0..self.count)
+            .rev()
+            .map(|ndx| format!("{}[{}]", self.name, ndx))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{{{}}}", elements)
+    }
+    fn copy_in(&self) -> Vec<String> {
+        match &self.packed {
+            PackedArray::Source(vector) => (0..self.count)
+                .map(|ndx| {
+                    format!(
+                        "{}[{}] = {}[{}:{}];",
+                        self.name,
+                        ndx,
+                        vector,
+                        (ndx + 1) * self.width - 1,
+                        ndx * self.width
+                    )
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+    fn copy_out(&self) -> Vec<String> {
+        match &self.packed {
+            PackedArray::Sink(vector) => vec![format!("{} = {};", vector, self.elements())],
+            _ => vec![],
+        }
+    }
 }
 
 #[derive(Default)]
@@ -20,53 +81,152 @@ struct VerilogCodeGenerator {
     io: CodeWriter,
     loops: Vec<LoopVariable>,
     links: Vec<VerilogLink>,
+    lets: Vec<VerilogLet>,
+    source: Option<SourceLocation>,
+    source_comment: bool,
+    arrays: BTreeMap<String, UnpackedArray>,
+    copies: Option<(Vec<String>, Vec<String>)>,
+    indexed: BTreeSet<String>,
 }
 
-impl VerilogCodeGenerator {
-    fn array_index_simplification(&self, a: &str) -> String {
-        let re = Regex::new(r"\[([^\]]*)\]").unwrap();
-        let mut context = evalexpr::HashMapContext::new();
-        for lvar in &self.loops {
-            let _ = context.set_value(lvar.variable.clone(), (lvar.value as i64).into());
+#[derive(Default)]
+struct LetBindings {
+    lets: Vec<VerilogLet>,
+}
+
+impl VerilogVisitor for LetBindings {
+    fn visit_let(&mut self, l: &VerilogLet) {
+        self.lets.push(l.clone());
+    }
+}
+
+#[derive(Default)]
+struct FunctionDefinitions {
+    functions: Vec<VerilogFunction>,
+}
+
+impl VerilogVisitor for FunctionDefinitions {
+    fn visit_call(&mut self, c: &VerilogCall) {
+        if !self.functions.iter().any(|x| x.name == c.function.name) {
+            // Functions may call other functions, which are defined first
+            self.visit_block(&c.function.block);
+            self.functions.push(c.function.as_ref().clone());
         }
-        if let Some(x) = re.captures(a) {
-            if x.len() == 2 {
-                if let Some(txt) = x.get(1) {
-                    let arg = evalexpr::eval_with_context(txt.as_str(), &context).unwrap();
-                    return re.replace(a, format!("$${}", arg)).to_string();
-                }
+        walk_call(self, c);
+    }
+}
+
+fn array_index_simplification(loops: &[LoopVariable], a: &str) -> String {
+    let re = Regex::new(r"\[([^\]]*)\]").unwrap();
+    let mut context = evalexpr::HashMapContext::new();
+    for lvar in loops {
+        let _ = context.set_value(lvar.variable.clone(), (lvar.value as i64).into());
+    }
+    if let Some(x) = re.captures(a) {
+        if x.len() == 2 {
+            if let Some(txt) = x.get(1) {
+                let arg = evalexpr::eval_with_context(txt.as_str(), &context).unwrap();
+                return re.replace(a, format!("$${}", arg)).to_string();
             }
         }
-        a.to_string()
     }
+    a.to_string()
+}
 
-    fn link_fixup(&self, x: &VerilogLinkDetails) -> VerilogLinkDetails {
-        VerilogLinkDetails {
-            my_name: self.ident_fixup(&x.my_name),
-            owner_name: self.ident_fixup(&x.owner_name),
-            other_name: self.ident_fixup(&x.other_name),
+// Maps the name of a signal in the AST to the flattened name used in the generated code,
+// substituting the values of any (unrolled) loop variables.
+pub(crate) fn ident_fixup(loops: &[LoopVariable], a: &str) -> String {
+    let mut x = a.to_owned();
+    for index in loops {
+        if x == index.variable {
+            x = format!("{}", index.value);
         }
     }
+    if x.starts_with(".") {
+        x.remove(0);
+    }
+    x = x
+        .replace(".", "$")
+        .replace("::", "$")
+        .trim_end_matches("$next")
+        .to_owned();
+    if x.contains('[') {
+        x = array_index_simplification(loops, &x);
+    }
+    x
+}
+
+pub(crate) fn link_fixup(loops: &[LoopVariable], x: &VerilogLinkDetails) -> VerilogLinkDetails {
+    VerilogLinkDetails {
+        my_name: ident_fixup(loops, &x.my_name),
+        owner_name: ident_fixup(loops, &x.owner_name),
+        other_name: ident_fixup(loops, &x.other_name),
+    }
+}
+
+impl VerilogCodeGenerator {
+    fn link_fixup(&self, x: &VerilogLinkDetails) -> VerilogLinkDetails {
+        link_fixup(&self.loops, x)
+    }
 
     fn ident_fixup(&self, a: &str) -> String {
-        let mut x = a.to_owned();
-        for index in &self.loops {
-            if x == index.variable {
-                x = format!("{}", index.value);
+        ident_fixup(&self.loops, a)
+    }
+
+    fn unpacked_array(&self, e: &VerilogExpression) -> Option<UnpackedArray> {
+        match e {
+            VerilogExpression::Signal(x) => self.arrays.get(&self.ident_fixup(x)).cloned(),
+            _ => None,
+        }
+    }
+
+    fn note_indexed(&mut self, e: &VerilogElement) {
+        if let VerilogExpression::Signal(x) = &e.array {
+            self.indexed.insert(self.ident_fixup(x));
+        }
+    }
+
+    // An element of an unpacked array.  Each index is scaled by the number of (innermost)
+    // elements it steps over.  An element that is itself an array is the concatenation
+    // of its elements.
+    fn write_element(&mut self, array: &UnpackedArray, e: &VerilogElement) {
+        let count = e.width() / array.width;
+        if count > 1 {
+            self.io.write("{");
+        }
+        for ndx in (0..count).rev() {
+            self.io.write(format!("{}[", array.name));
+            for (position, (index, width)) in e.indices.iter().enumerate() {
+                if position != 0 {
+                    self.io.write(" + ");
+                }
+                if *width == array.width {
+                    self.visit_expression(index);
+                } else {
+                    self.io.write("(");
+                    self.visit_expression(index);
+                    self.io.write(format!(") * {}", width / array.width));
+                }
+            }
+            if count > 1 {
+                self.io.write(format!(" + {}", ndx));
+            }
+            self.io.write("]");
+            if ndx != 0 {
+                self.io.write(", ");
             }
         }
-        if x.starts_with(".") {
-            x.remove(0);
+        if count > 1 {
+            self.io.write("}");
         }
-        x = x
-            .replace(".", "$")
-            .replace("::", "$")
-            .trim_end_matches("$next")
-            .to_owned();
-        if x.contains('[') {
-            x = self.array_index_simplification(&x);
+    }
+
+    // Tags an assignment with the line of the kernel it comes from, so that tools (and the
+    // diagnostics from `yosys_validate`) can refer back to it.
+    fn write_source_attribute(&mut self) {
+        if let Some(source) = &self.source {
+            self.io.write(format!("(* src = \"{}\" *) ", source));
         }
-        x
     }
 }
 
@@ -76,23 +236,122 @@ impl ToString for VerilogCodeGenerator {
     }
 }
 
+// The (flattened) names of the array-valued signals that the code indexes
+pub(crate) fn verilog_array_extraction(code: &VerilogBlock) -> BTreeSet<String> {
+    let mut gen = VerilogCodeGenerator::default();
+    gen.visit_block(code);
+    gen.indexed
+}
+
 pub fn verilog_link_extraction(code: &VerilogBlock) -> Vec<VerilogLink> {
     let mut gen = VerilogCodeGenerator::default();
     gen.visit_block(code);
     gen.links
 }
 
+pub fn verilog_let_extraction(code: &VerilogBlock) -> Vec<VerilogLet> {
+    let mut lets = LetBindings::default();
+    lets.visit_block(code);
+    lets.lets
+}
+
+pub fn verilog_function_extraction(code: &VerilogBlock) -> Vec<VerilogFunction> {
+    let mut functions = FunctionDefinitions::default();
+    functions.visit_block(code);
+    functions.functions
+}
+
+fn verilog_range(width: usize, signed: bool) -> String {
+    let signed = if signed { "signed " } else { "" };
+    if width == 1 {
+        signed.to_string()
+    } else {
+        format!("{}[{}:0] ", signed, width - 1)
+    }
+}
+
+pub fn verilog_function(f: &VerilogFunction) -> String {
+    let mut io = CodeWriter::default();
+    io.add(format!(
+        "function {}{};",
+        verilog_range(f.width, f.signed),
+        f.name
+    ));
+    io.push();
+    for arg in &f.args {
+        io.add(format!(
+            "input {}{};",
+            verilog_range(arg.width, arg.signed),
+            arg.name
+        ));
+    }
+    let lets = verilog_let_extraction(&f.block);
+    for x in &lets {
+        io.add(format!(
+            "reg {}{};",
+            verilog_range(x.width, x.signed),
+            x.name
+        ));
+    }
+    let mut gen = VerilogCodeGenerator {
+        lets,
+        ..Default::default()
+    };
+    gen.visit_block(&f.block);
+    io.add(gen.to_string());
+    io.pop();
+    io.add("endfunction");
+    io.to_string()
+}
+
 pub fn verilog_combinatorial(code: &VerilogBlock) -> String {
-    let mut gen = VerilogCodeGenerator::default();
+    combinatorial("always @(*)", code, &[])
+}
+
+pub fn systemverilog_combinatorial(code: &VerilogBlock) -> String {
+    combinatorial("always_comb", code, &[])
+}
+
+pub(crate) fn combinatorial(always: &str, code: &VerilogBlock, arrays: &[UnpackedArray]) -> String {
+    let copies = (
+        arrays.iter().flat_map(|x| x.copy_in()).collect(),
+        arrays.iter().flat_map(|x| x.copy_out()).collect(),
+    );
+    let mut gen = VerilogCodeGenerator {
+        lets: verilog_let_extraction(code),
+        arrays: arrays
+            .iter()
+            .map(|x| match &x.packed {
+                PackedArray::Source(name) | PackedArray::Sink(name) => (name.clone(), x.clone()),
+                PackedArray::None => (x.name.clone(), x.clone()),
+            })
+            .collect(),
+        copies: Some(copies),
+        ..Default::default()
+    };
     gen.visit_block(code);
-    format!("always @(*) {}\n", gen.to_string())
+    format!("{} {}\n", always, gen.to_string())
 }
 
 impl VerilogVisitor for VerilogCodeGenerator {
     fn visit_block(&mut self, b: &VerilogBlock) {
         self.io.writeln("begin");
         self.io.push();
+        // Let bindings are cleared on entry to the kernel, so that a binding made
+        // in only some of the branches does not infer a latch
+        for x in std::mem::take(&mut self.lets) {
+            self.io.add(format!("{} = {}'h0;", x.name, x.width));
+        }
+        // The unpacked arrays are copied from their vectors on entry to the kernel,
+        // and to their vectors on exit
+        let (copy_in, copy_out) = self.copies.take().unwrap_or_default();
+        for x in copy_in {
+            self.io.add(x);
+        }
         walk_block(self, b);
+        for x in copy_out {
+            self.io.add(x);
+        }
         self.io.pop();
         self.io.add_line("end");
     }
@@ -117,6 +376,7 @@ impl VerilogVisitor for VerilogCodeGenerator {
         offset: &VerilogExpression,
         replacement: &VerilogExpression,
     ) {
+        self.write_source_attribute();
         self.visit_expression(base);
         self.io.write("[(");
         self.visit_expression(offset);
@@ -125,6 +385,20 @@ impl VerilogVisitor for VerilogCodeGenerator {
         self.io.writeln(";");
     }
 
+    fn visit_element_assignment(&mut self, e: &VerilogElement, r: &VerilogExpression) {
+        self.note_indexed(e);
+        match self.unpacked_array(&e.array) {
+            Some(array) => {
+                self.write_source_attribute();
+                self.write_element(&array, e);
+                self.io.write(" = ");
+                self.visit_expression(r);
+                self.io.writeln(";");
+            }
+            None => self.visit_slice_assignment(&e.array, &e.width(), &e.offset(), r),
+        }
+    }
+
     fn visit_conditional(&mut self, c: &VerilogConditional) {
         self.io.write("if (");
         self.visit_expression(&c.test);
@@ -161,8 +435,53 @@ impl VerilogVisitor for VerilogCodeGenerator {
         self.io.add(format!("// {}", x));
     }
 
+    fn visit_source(&mut self, s: &SourceLocation) {
+        self.source = Some(s.clone());
+        self.source_comment = true;
+    }
+
+    // Assignments carry the line of the kernel they come from as an attribute.  Any
+    // other statement (e.g., an `if` or a `case`) is preceded by a comment instead.
+    fn visit_statement(&mut self, s: &VerilogStatement) {
+        if !matches!(s, VerilogStatement::Source(_)) {
+            let assignment = matches!(
+                s,
+                VerilogStatement::Assignment(..)
+                    | VerilogStatement::SliceAssignment { .. }
+                    | VerilogStatement::ElementAssignment(..)
+                    | VerilogStatement::Let(_)
+            );
+            if std::mem::take(&mut self.source_comment) && !assignment {
+                if let Some(source) = &self.source {
+                    self.io.add(format!("// {}", source));
+                }
+            }
+        }
+        walk_statement(self, s);
+    }
+
+    // Properties are only seen by formal tools (e.g., yosys with read -formal)
+    fn visit_property(&mut self, p: &VerilogProperty) {
+        self.io.add("`ifdef FORMAL");
+        self.io.write(match p.kind {
+            VerilogPropertyKind::Assert => "assert property (",
+            VerilogPropertyKind::Assume => "assume property (",
+            VerilogPropertyKind::Cover => "cover property (",
+        });
+        self.visit_expression(&p.test);
+        self.io.writeln(");");
+        self.io.add("`endif");
+    }
+
+    // The vector is stale while the kernel drives the unpacked array
     fn visit_signal(&mut self, sig: &str) {
-        self.io.write(self.ident_fixup(sig));
+        let name = self.ident_fixup(sig);
+        match self.arrays.get(&name) {
+            Some(array) if !matches!(array.packed, PackedArray::Source(_)) => {
+                self.io.write(array.elements())
+            }
+            _ => self.io.write(name),
+        }
     }
 
     fn visit_literal(&mut self, v: &VerilogLiteral) {
@@ -180,7 +499,13 @@ impl VerilogVisitor for VerilogCodeGenerator {
     }
 
     fn visit_case(&mut self, c: &VerilogCase) {
-        self.io.write(self.ident_fixup(&c.condition));
+        let condition = c
+            .condition
+            .split(", ")
+            .map(|x| self.ident_fixup(x))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.io.write(condition);
         self.io.writeln(":");
         self.io.push();
         self.visit_block(&c.block);
@@ -224,12 +549,27 @@ impl VerilogVisitor for VerilogCodeGenerator {
     }
 
     fn visit_assignment(&mut self, l: &VerilogExpression, r: &VerilogExpression) {
-        self.visit_expression(l);
+        self.write_source_attribute();
+        match self.unpacked_array(l) {
+            Some(array) => self.io.write(array.elements()),
+            None => self.visit_expression(l),
+        }
         self.io.write(" = ");
         self.visit_expression(r);
         self.io.writeln(";");
     }
 
+    fn visit_call(&mut self, c: &VerilogCall) {
+        self.io.write(format!("{}(", c.function.name));
+        for (ndx, arg) in c.args.iter().enumerate() {
+            if ndx != 0 {
+                self.io.write(", ");
+            }
+            self.visit_expression(arg);
+        }
+        self.io.write(")");
+    }
+
     fn visit_paren(&mut self, e: &VerilogExpression) {
         self.io.write("(");
         self.visit_expression(e);
@@ -269,6 +609,14 @@ impl VerilogVisitor for VerilogCodeGenerator {
         self.io.write(format!(")+:({})]", width));
     }
 
+    fn visit_element(&mut self, e: &VerilogElement) {
+        self.note_indexed(e);
+        match self.unpacked_array(&e.array) {
+            Some(array) => self.write_element(&array, e),
+            None => self.visit_slice(&e.array, &e.width(), &e.offset()),
+        }
+    }
+
     fn visit_index_replace(
         &mut self,
         sig: &VerilogExpression,
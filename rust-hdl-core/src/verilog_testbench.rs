@@ -0,0 +1,189 @@
+use std::env::temp_dir;
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::io::Write;
+use std::process::Command;
+
+use crate::ast::VerilogLiteral;
+use crate::block::Block;
+use crate::code_writer::CodeWriter;
+use crate::top_ports::{top_port_values, top_ports, TopPort};
+use crate::yosys::SynthError;
+
+/// A record of the values seen on the top level ports of a circuit during
+/// a simulation, that can be turned into a self-checking Verilog testbench.
+///
+/// The testbench instantiates the generated `top` module (see [generate_verilog](crate::module_defines::generate_verilog)),
+/// replays the stimulus applied to the inputs at the times it was applied in the simulation,
+/// and checks that every output matches the value seen in the Rust simulation shortly
+/// after each step (outputs are sampled at `t + 0.1ps`).  Any mismatches are
+/// reported with `$display`, and the testbench prints `TESTBENCH PASSED` or
+/// `TESTBENCH FAILED` at the end.  You will normally get one of these from
+/// [Simulation::run_to_testbench](crate::simulate::Simulation::run_to_testbench).
+pub struct VerilogTestbench {
+    ports: Vec<TopPort>,
+    steps: Vec<(u64, Vec<VerilogLiteral>)>,
+}
+
+impl VerilogTestbench {
+    /// Create an (empty) testbench for the given circuit.
+    pub fn new(uut: &dyn Block) -> VerilogTestbench {
+        VerilogTestbench {
+            ports: top_ports(uut),
+            steps: vec![],
+        }
+    }
+    /// Record the values of the top level ports at the given time.  If more than
+    /// one step happens at the same time, only the last one is kept.
+    pub fn record(&mut self, time: u64, uut: &dyn Block) {
+        let values = top_port_values(uut);
+        assert_eq!(values.len(), self.ports.len(), "Circuit ports have changed");
+        if let Some(last) = self.steps.last_mut() {
+            if last.0 == time {
+                last.1 = values;
+                return;
+            }
+        }
+        self.steps.push((time, values));
+    }
+    /// Generate the Verilog testbench (as a module named `top_tb`).
+    pub fn generate(&self) -> String {
+        let mut io = CodeWriter::default();
+        io.add("`timescale 1ps/100fs");
+        io.add("module top_tb;");
+        io.push();
+        for port in &self.ports {
+            let kind = if port.is_input { "reg" } else { "wire" };
+            if port.bits == 1 {
+                io.add(format!("{} {};", kind, port.verilog_name));
+            } else {
+                io.add(format!(
+                    "{} [{}:0] {};",
+                    kind,
+                    port.bits - 1,
+                    port.verilog_name
+                ));
+            }
+        }
+        io.add("integer errors;");
+        io.add_line("");
+        let connections = self
+            .ports
+            .iter()
+            .map(|x| format!(".{}({})", x.verilog_name, x.verilog_name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        io.add(format!("top uut({});", connections));
+        io.add_line("");
+        io.add("initial begin");
+        io.push();
+        io.add("errors = 0;");
+        let mut previous: Option<&(u64, Vec<VerilogLiteral>)> = None;
+        for step in &self.steps {
+            let (time, values) = step;
+            if let Some((last_time, _)) = previous {
+                // We are currently 0.1ps past the last step
+                let delay = (time - last_time) * 10 - 1;
+                io.add(format!("#{}.{};", delay / 10, delay % 10));
+            }
+            for (ndx, port) in self.ports.iter().enumerate() {
+                if !port.is_input {
+                    continue;
+                }
+                if let Some((_, last_values)) = previous {
+                    if last_values[ndx] == values[ndx] {
+                        continue;
+                    }
+                }
+                io.add(format!(
+                    "{} = {};",
+                    port.verilog_name,
+                    hex_literal(&values[ndx])
+                ));
+            }
+            io.add("#0.1;");
+            for (ndx, port) in self.ports.iter().enumerate() {
+                if port.is_input {
+                    continue;
+                }
+                let expected = hex_literal(&values[ndx]);
+                io.add(format!("if ({} !== {}) begin", port.verilog_name, expected));
+                io.push();
+                io.add(format!(
+                    r#"$display("Mismatch at time %0t on {}: expected {}, got %h", $time, {});"#,
+                    port.path, expected, port.verilog_name
+                ));
+                io.add("errors = errors + 1;");
+                io.pop();
+                io.add("end");
+            }
+            previous = Some(step);
+        }
+        io.add("if (errors == 0)");
+        io.push();
+        io.add(r#"$display("TESTBENCH PASSED");"#);
+        io.pop();
+        io.add("else");
+        io.push();
+        io.add(r#"$display("TESTBENCH FAILED with %0d errors", errors);"#);
+        io.pop();
+        io.add("$finish;");
+        io.pop();
+        io.add("end");
+        io.pop();
+        io.add("endmodule");
+        io.to_string()
+    }
+}
+
+fn hex_literal(x: &VerilogLiteral) -> String {
+    let mut hex = x
+        .to_u32_words()
+        .iter()
+        .rev()
+        .map(|w| format!("{:08x}", w))
+        .collect::<String>()
+        .trim_start_matches('0')
+        .to_string();
+    if hex.is_empty() {
+        hex = "0".into();
+    }
+    format!("{}'h{}", x.bits(), hex)
+}
+
+/// Compile the given translation (which must contain a module named `top`) along with
+/// a testbench from [VerilogTestbench::generate] using Icarus Verilog, and run it.
+/// Requires `iverilog` and `vvp` to be installed.
+pub fn iverilog_run_testbench(prefix: &str, translation: &str, tb: &str) -> Result<(), SynthError> {
+    let dir = temp_dir().as_path().join(prefix);
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir)?;
+    write!(File::create(dir.join("top.v"))?, "{}", translation)?;
+    write!(File::create(dir.join("tb.v"))?, "{}", tb)?;
+    let output = Command::new("iverilog")
+        .current_dir(dir.clone())
+        .args(["-s", "top_tb", "-o", "tb", "top.v", "tb.v"])
+        .output()?;
+    let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if output.status.success() {
+        let output = Command::new("vvp")
+            .current_dir(dir.clone())
+            .arg("tb")
+            .output()?;
+        stdout += &String::from_utf8_lossy(&output.stdout);
+        stderr += &String::from_utf8_lossy(&output.stderr);
+    }
+    {
+        let mut debug = File::create(dir.join("iverilog.stdout"))?;
+        write!(debug, "{}", stdout)?;
+        write!(debug, "{}", stderr)?;
+    }
+    if !stdout.contains("TESTBENCH PASSED") {
+        return Err(SynthError::SynthesisFailed {
+            stdout,
+            stderr,
+            source: None,
+        });
+    }
+    Ok(())
+}
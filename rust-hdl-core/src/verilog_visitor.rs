@@ -1,7 +1,8 @@
 use crate::ast::{
-    VerilogBlock, VerilogBlockOrConditional, VerilogCase, VerilogConditional, VerilogExpression,
-    VerilogIndexAssignment, VerilogLink, VerilogLiteral, VerilogLoop, VerilogMatch, VerilogOp,
-    VerilogOpUnary, VerilogStatement,
+    SourceLocation, VerilogBlock, VerilogBlockOrConditional, VerilogCall, VerilogCase,
+    VerilogConditional, VerilogElement, VerilogExpression, VerilogIndexAssignment, VerilogLet,
+    VerilogLink, VerilogLiteral, VerilogLoop, VerilogMatch, VerilogOp, VerilogOpUnary,
+    VerilogProperty, VerilogStatement,
 };
 
 pub trait VerilogVisitor {
@@ -31,6 +32,10 @@ pub trait VerilogVisitor {
         walk_slice_assignment(self, base, width, offset, replacement);
     }
 
+    fn visit_element_assignment(&mut self, e: &VerilogElement, r: &VerilogExpression) {
+        walk_element_assignment(self, e, r);
+    }
+
     fn visit_conditional(&mut self, c: &VerilogConditional) {
         walk_conditional(self, c);
     }
@@ -43,10 +48,26 @@ pub trait VerilogVisitor {
         walk_match(self, m);
     }
 
+    fn visit_source(&mut self, _s: &SourceLocation) {
+        // Terminal
+    }
+
     fn visit_comment(&mut self, _c: &str) {
         // Terminal
     }
 
+    fn visit_property(&mut self, p: &VerilogProperty) {
+        walk_property(self, p);
+    }
+
+    fn visit_let(&mut self, l: &VerilogLet) {
+        walk_let(self, l);
+    }
+
+    fn visit_call(&mut self, c: &VerilogCall) {
+        walk_call(self, c);
+    }
+
     fn visit_signal(&mut self, _c: &str) {
         // Terminal
     }
@@ -107,6 +128,10 @@ pub trait VerilogVisitor {
         walk_slice(self, a, b, c);
     }
 
+    fn visit_element(&mut self, e: &VerilogElement) {
+        walk_element(self, e);
+    }
+
     fn visit_slice_replace(
         &mut self,
         a: &VerilogExpression,
@@ -148,6 +173,11 @@ pub fn walk_slice<V: VerilogVisitor + ?Sized>(
     visitor.visit_expression(c);
 }
 
+// For analysis, an element of an array is the slice of the packed array that holds it
+pub fn walk_element<V: VerilogVisitor + ?Sized>(visitor: &mut V, e: &VerilogElement) {
+    visitor.visit_slice(&e.array, &e.width(), &e.offset());
+}
+
 pub fn walk_slice_replace<V: VerilogVisitor + ?Sized>(
     visitor: &mut V,
     a: &VerilogExpression,
@@ -209,6 +239,14 @@ pub fn walk_slice_assignment<V: VerilogVisitor + ?Sized>(
     visitor.visit_expression(replacement);
 }
 
+pub fn walk_element_assignment<V: VerilogVisitor + ?Sized>(
+    visitor: &mut V,
+    e: &VerilogElement,
+    r: &VerilogExpression,
+) {
+    visitor.visit_slice_assignment(&e.array, &e.width(), &e.offset(), r);
+}
+
 pub fn walk_assignment<V: VerilogVisitor + ?Sized>(
     visitor: &mut V,
     l: &VerilogExpression,
@@ -231,6 +269,9 @@ pub fn walk_statement<V: VerilogVisitor + ?Sized>(visitor: &mut V, s: &VerilogSt
         } => {
             visitor.visit_slice_assignment(base, width, offset, replacement);
         }
+        VerilogStatement::ElementAssignment(e, r) => {
+            visitor.visit_element_assignment(e, r);
+        }
         VerilogStatement::If(c) => {
             visitor.visit_conditional(c);
         }
@@ -251,9 +292,27 @@ pub fn walk_statement<V: VerilogVisitor + ?Sized>(visitor: &mut V, s: &VerilogSt
                 visitor.visit_statement(statement);
             }
         }
+        VerilogStatement::Property(p) => {
+            visitor.visit_property(p);
+        }
+        VerilogStatement::Source(x) => {
+            visitor.visit_source(x);
+        }
+        VerilogStatement::Let(l) => {
+            visitor.visit_let(l);
+        }
     }
 }
 
+// A let binding is (for the purposes of analysis) an assignment to its local signal
+pub fn walk_let<V: VerilogVisitor + ?Sized>(visitor: &mut V, l: &VerilogLet) {
+    visitor.visit_assignment(&VerilogExpression::Signal(l.name.clone()), &l.value);
+}
+
+pub fn walk_property<V: VerilogVisitor + ?Sized>(visitor: &mut V, p: &VerilogProperty) {
+    visitor.visit_expression(&p.test);
+}
+
 pub fn walk_index_assignment<V: VerilogVisitor + ?Sized>(
     visitor: &mut V,
     a: &VerilogIndexAssignment,
@@ -345,6 +404,9 @@ pub fn walk_expression<V: VerilogVisitor + ?Sized>(visitor: &mut V, e: &VerilogE
         VerilogExpression::Slice(a, b, c) => {
             visitor.visit_slice(a, b, c);
         }
+        VerilogExpression::Element(e) => {
+            visitor.visit_element(e);
+        }
         VerilogExpression::IndexReplace(a, b, c) => {
             visitor.visit_index_replace(a, b, c);
         }
@@ -354,5 +416,15 @@ pub fn walk_expression<V: VerilogVisitor + ?Sized>(visitor: &mut V, e: &VerilogE
         VerilogExpression::Unsigned(a) => {
             visitor.visit_unsigned(a);
         }
+        VerilogExpression::Call(c) => {
+            visitor.visit_call(c);
+        }
+    }
+}
+
+// The body of the function is in its own scope, so only the arguments are walked
+pub fn walk_call<V: VerilogVisitor + ?Sized>(visitor: &mut V, c: &VerilogCall) {
+    for arg in &c.args {
+        visitor.visit_expression(arg);
     }
 }
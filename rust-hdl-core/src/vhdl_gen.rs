@@ -0,0 +1,1164 @@
+use std::collections::HashMap;
+
+use num_bigint::{BigInt, Sign};
+
+use crate::ast::{
+    SourceLocation, Verilog, VerilogBlock, VerilogBlockOrConditional, VerilogConditional,
+    VerilogExpression, VerilogFunction, VerilogLet, VerilogLink, VerilogLiteral, VerilogLoop,
+    VerilogMatch, VerilogOp, VerilogOpUnary, VerilogProperty, VerilogPropertyKind,
+    VerilogStatement,
+};
+use crate::atom::AtomKind;
+use crate::bits::clog2;
+use crate::code_writer::CodeWriter;
+use crate::module_defines::{get_link_equivalence, AtomDetails, ModuleDefines, ModuleDetails};
+use crate::verilog_gen::{
+    ident_fixup, verilog_function_extraction, verilog_let_extraction, LoopVariable,
+};
+use crate::verilog_visitor::{walk_block, VerilogVisitor};
+
+// The VHDL translation models every signal as an `unsigned` (or `signed`) vector, which
+// keeps the Verilog width and signedness rules tractable.  These helpers fill in the
+// operations that have no direct equivalent in numeric_std.
+const VHDL_PACKAGE: &str = r#"library ieee;
+use ieee.std_logic_1164.all;
+use ieee.numeric_std.all;
+
+package rust_hdl_pkg is
+    function to_u(x : boolean) return unsigned;
+    function to_b(x : unsigned) return boolean;
+    function lsb(x : unsigned) return std_ulogic;
+    function bit_of(x : unsigned; n : natural) return unsigned;
+    function slice_of(x : unsigned; n : natural; w : natural) return unsigned;
+    function replace_bit(x : unsigned; n : natural; v : unsigned) return unsigned;
+    function reduce_and(x : unsigned) return unsigned;
+    function reduce_or(x : unsigned) return unsigned;
+    function reduce_xor(x : unsigned) return unsigned;
+end package rust_hdl_pkg;
+
+package body rust_hdl_pkg is
+    function to_u(x : boolean) return unsigned is
+        variable r : unsigned(0 downto 0) := "0";
+    begin
+        if x then
+            r := "1";
+        end if;
+        return r;
+    end function;
+
+    function to_b(x : unsigned) return boolean is
+    begin
+        return x /= 0;
+    end function;
+
+    function lsb(x : unsigned) return std_ulogic is
+    begin
+        return x(x'right);
+    end function;
+
+    function bit_of(x : unsigned; n : natural) return unsigned is
+        variable t : unsigned(x'length - 1 downto 0) := x;
+        variable r : unsigned(0 downto 0);
+    begin
+        r(0) := t(n);
+        return r;
+    end function;
+
+    function slice_of(x : unsigned; n : natural; w : natural) return unsigned is
+        variable t : unsigned(x'length - 1 downto 0) := x;
+        variable r : unsigned(w - 1 downto 0);
+    begin
+        r := t(n + w - 1 downto n);
+        return r;
+    end function;
+
+    function replace_bit(x : unsigned; n : natural; v : unsigned) return unsigned is
+        variable t : unsigned(x'length - 1 downto 0) := x;
+    begin
+        t(n) := lsb(v);
+        return t;
+    end function;
+
+    function reduce_and(x : unsigned) return unsigned is
+    begin
+        return to_u(x = (x'range => '1'));
+    end function;
+
+    function reduce_or(x : unsigned) return unsigned is
+    begin
+        return to_u(x /= 0);
+    end function;
+
+    function reduce_xor(x : unsigned) return unsigned is
+        variable r : unsigned(0 downto 0) := "0";
+    begin
+        for i in x'range loop
+            r(0) := r(0) xor x(i);
+        end loop;
+        return r;
+    end function;
+end package body rust_hdl_pkg;
+"#;
+
+const VHDL_CONTEXT: &str = "library ieee;
+use ieee.std_logic_1164.all;
+use ieee.numeric_std.all;
+use work.rust_hdl_pkg.all;";
+
+const VHDL_RESERVED: &[&str] = &[
+    "abs",
+    "access",
+    "after",
+    "alias",
+    "all",
+    "and",
+    "architecture",
+    "array",
+    "assert",
+    "assume",
+    "attribute",
+    "begin",
+    "block",
+    "body",
+    "buffer",
+    "bus",
+    "case",
+    "component",
+    "configuration",
+    "constant",
+    "context",
+    "cover",
+    "default",
+    "disconnect",
+    "downto",
+    "else",
+    "elsif",
+    "end",
+    "entity",
+    "exit",
+    "fairness",
+    "file",
+    "for",
+    "force",
+    "function",
+    "generate",
+    "generic",
+    "group",
+    "guarded",
+    "if",
+    "impure",
+    "in",
+    "inertial",
+    "inout",
+    "is",
+    "label",
+    "library",
+    "linkage",
+    "literal",
+    "loop",
+    "map",
+    "mod",
+    "nand",
+    "new",
+    "next",
+    "nor",
+    "not",
+    "null",
+    "of",
+    "on",
+    "open",
+    "or",
+    "others",
+    "out",
+    "package",
+    "parameter",
+    "port",
+    "postponed",
+    "procedure",
+    "process",
+    "property",
+    "protected",
+    "pure",
+    "range",
+    "record",
+    "register",
+    "reject",
+    "release",
+    "rem",
+    "report",
+    "restrict",
+    "return",
+    "rol",
+    "ror",
+    "select",
+    "sequence",
+    "severity",
+    "shared",
+    "signal",
+    "sla",
+    "sll",
+    "sra",
+    "srl",
+    "strong",
+    "subtype",
+    "then",
+    "to",
+    "transport",
+    "type",
+    "unaffected",
+    "units",
+    "until",
+    "use",
+    "variable",
+    "vmode",
+    "vprop",
+    "vunit",
+    "wait",
+    "when",
+    "while",
+    "with",
+    "xnor",
+    "xor",
+];
+
+/// Returns the VHDL identifier for a (flattened) RustHDL signal or module name.  Names
+/// that are not legal basic identifiers (for example, because they contain the `$` used to
+/// flatten the hierarchy) are written as extended identifiers.
+pub fn vhdl_ident(name: &str) -> String {
+    let legal = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !name.ends_with('_')
+        && !name.contains("__")
+        && !VHDL_RESERVED.contains(&name.to_ascii_lowercase().as_str());
+    if legal {
+        name.to_string()
+    } else {
+        format!("\\{}\\", name.replace('\\', "\\\\"))
+    }
+}
+
+/// Returns the VHDL type used for a signal of the given width.
+pub fn vhdl_type(width: usize, signed: bool) -> String {
+    let kind = if signed { "signed" } else { "unsigned" };
+    format!("{}({} downto 0)", kind, width.max(1) - 1)
+}
+
+fn literal_bits(val: &BigInt, bits: usize) -> Vec<bool> {
+    let val = if val.sign() == Sign::Minus {
+        val + (BigInt::from(1) << bits)
+    } else {
+        val.clone()
+    };
+    (0..bits).map(|i| val.bit(i as u64)).collect()
+}
+
+fn bit_string(bits: &[bool]) -> String {
+    if !bits.is_empty() && bits.len().is_multiple_of(4) {
+        let digits = bits
+            .chunks(4)
+            .rev()
+            .map(|x| {
+                let nibble = x
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (ndx, b)| acc | ((*b as u32) << ndx));
+                format!("{:X}", nibble)
+            })
+            .collect::<String>();
+        format!("x\"{}\"", digits)
+    } else {
+        let digits = bits
+            .iter()
+            .rev()
+            .map(|x| if *x { '1' } else { '0' })
+            .collect::<String>();
+        format!("\"{}\"", digits)
+    }
+}
+
+/// Returns a VHDL bit string literal (e.g., `x"1B"`) of the given width holding the
+/// value of the literal.  The value is truncated or zero extended as needed.
+pub fn vhdl_bit_string(x: &VerilogLiteral, width: usize) -> String {
+    let words = x.to_u32_words();
+    let bits = (0..width.max(1))
+        .map(|i| {
+            words
+                .get(i / 32)
+                .map(|w| (w >> (i % 32)) & 1 == 1)
+                .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+    bit_string(&bits)
+}
+
+// Parse the literal used as a match pattern (e.g., `3`, `0x1f`, `0b101_u8`).
+fn parse_pattern_literal(x: &str) -> Option<BigInt> {
+    let x = x.replace('_', "");
+    let (radix, digits) = if let Some(d) = x.strip_prefix("0x") {
+        (16, d)
+    } else if let Some(d) = x.strip_prefix("0b") {
+        (2, d)
+    } else if let Some(d) = x.strip_prefix("0o") {
+        (8, d)
+    } else {
+        (10, x.as_str())
+    };
+    let digits = digits.split(['u', 'i']).next().unwrap_or_default();
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+}
+
+// An expression translated to VHDL, along with the width and signedness it
+// would have in Verilog.
+struct Typed {
+    code: String,
+    width: usize,
+    signed: bool,
+}
+
+impl Typed {
+    fn new(code: String, width: usize, signed: bool) -> Typed {
+        Typed {
+            code,
+            width,
+            signed,
+        }
+    }
+    // Change the signedness, without changing the bits
+    fn convert(&self, signed: bool) -> String {
+        match (self.signed, signed) {
+            (false, true) => format!("signed({})", self.code),
+            (true, false) => format!("unsigned({})", self.code),
+            _ => self.code.clone(),
+        }
+    }
+    fn unsigned(&self) -> String {
+        self.convert(false)
+    }
+    // Extend or truncate as Verilog would (based on the signedness of the expression),
+    // and then reinterpret
+    fn fit(&self, width: usize, signed: bool) -> String {
+        if self.width == width {
+            return self.convert(signed);
+        }
+        Typed::new(
+            format!("resize({}, {})", self.code, width),
+            width,
+            self.signed,
+        )
+        .convert(signed)
+    }
+}
+
+#[derive(Default)]
+struct VHDLCodeGenerator {
+    io: CodeWriter,
+    loops: Vec<LoopVariable>,
+    symbols: HashMap<String, (usize, bool)>,
+    // The name of the function being generated (if any).  The result of a function
+    // is held in a variable, and all assignments are to variables.
+    function: Option<String>,
+}
+
+impl VHDLCodeGenerator {
+    fn ident_fixup(&self, a: &str) -> String {
+        ident_fixup(&self.loops, a)
+    }
+
+    fn signal(&self, name: &str) -> Typed {
+        let name = self.ident_fixup(name);
+        if let Some((width, signed)) = self.symbols.get(&name) {
+            if self.function.as_ref() == Some(&name) {
+                return Typed::new(function_result(&name), *width, *signed);
+            }
+            return Typed::new(vhdl_ident(&name), *width, *signed);
+        }
+        if let Ok(value) = name.parse::<u32>() {
+            // An unrolled loop variable
+            return Typed::new(format!("to_unsigned({}, 32)", value), 32, false);
+        }
+        Typed::new(vhdl_ident(&name), 1, false)
+    }
+
+    // Sized literals are unsigned in Verilog, so they are unsigned here too
+    fn literal(&self, x: &VerilogLiteral) -> Typed {
+        Typed::new(
+            format!("unsigned'({})", vhdl_bit_string(x, x.bits())),
+            x.bits().max(1),
+            false,
+        )
+    }
+
+    fn index(&self, e: &VerilogExpression) -> String {
+        match e {
+            VerilogExpression::Literal(x) => x.as_usize().to_string(),
+            _ => {
+                let e = self.expression(e);
+                if e.code.starts_with("to_unsigned(") {
+                    e.code
+                        .trim_start_matches("to_unsigned(")
+                        .split(',')
+                        .next()
+                        .unwrap()
+                        .to_string()
+                } else {
+                    format!("to_integer({})", e.unsigned())
+                }
+            }
+        }
+    }
+
+    fn expression(&self, e: &VerilogExpression) -> Typed {
+        match e {
+            VerilogExpression::Signal(x) => self.signal(x),
+            VerilogExpression::Literal(x) => self.literal(x),
+            VerilogExpression::Cast(a, bits) => {
+                let a = self.expression(a);
+                let a = Typed::new(a.unsigned(), a.width, false);
+                Typed::new(a.fit(*bits, false), *bits, false)
+            }
+            VerilogExpression::Signed(a) => {
+                let a = self.expression(a);
+                Typed::new(a.convert(true), a.width, true)
+            }
+            VerilogExpression::Unsigned(a) => {
+                let a = self.expression(a);
+                Typed::new(a.convert(false), a.width, false)
+            }
+            VerilogExpression::Paren(a) => {
+                let a = self.expression(a);
+                Typed::new(format!("({})", a.code), a.width, a.signed)
+            }
+            VerilogExpression::Binary(l, op, r) => self.binop(l, op, r),
+            VerilogExpression::Unary(op, a) => {
+                let a = self.expression(a);
+                match op {
+                    VerilogOpUnary::Not => {
+                        Typed::new(format!("(not {})", a.code), a.width, a.signed)
+                    }
+                    VerilogOpUnary::Neg => {
+                        if a.signed {
+                            Typed::new(format!("(-{})", a.code), a.width, true)
+                        } else {
+                            Typed::new(format!("(0 - {})", a.code), a.width, false)
+                        }
+                    }
+                    VerilogOpUnary::All => {
+                        Typed::new(format!("reduce_and({})", a.unsigned()), 1, false)
+                    }
+                    VerilogOpUnary::Any => {
+                        Typed::new(format!("reduce_or({})", a.unsigned()), 1, false)
+                    }
+                    VerilogOpUnary::Xor => {
+                        Typed::new(format!("reduce_xor({})", a.unsigned()), 1, false)
+                    }
+                }
+            }
+            VerilogExpression::Index(a, b) => {
+                let a = self.expression(a);
+                Typed::new(
+                    format!("bit_of({}, {})", a.unsigned(), self.index(b)),
+                    1,
+                    false,
+                )
+            }
+            VerilogExpression::Slice(a, width, offset) => {
+                let a = self.expression(a);
+                Typed::new(
+                    format!(
+                        "slice_of({}, {}, {})",
+                        a.unsigned(),
+                        self.index(offset),
+                        width
+                    ),
+                    *width,
+                    false,
+                )
+            }
+            VerilogExpression::Element(e) => self.expression(&e.packed()),
+            VerilogExpression::IndexReplace(a, b, c) => {
+                let a = self.expression(a);
+                let c = self.expression(c);
+                Typed::new(
+                    format!(
+                        "replace_bit({}, {}, {})",
+                        a.unsigned(),
+                        self.index(b),
+                        c.unsigned()
+                    ),
+                    a.width,
+                    false,
+                )
+            }
+            VerilogExpression::Call(c) => {
+                let args = c
+                    .function
+                    .args
+                    .iter()
+                    .zip(&c.args)
+                    .map(|(arg, x)| self.expression(x).fit(arg.width, arg.signed))
+                    .collect::<Vec<_>>();
+                Typed::new(
+                    format!("{}({})", vhdl_ident(&c.function.name), args.join(", ")),
+                    c.function.width,
+                    c.function.signed,
+                )
+            }
+        }
+    }
+
+    fn binop(&self, l: &VerilogExpression, op: &VerilogOp, r: &VerilogExpression) -> Typed {
+        let l = self.expression(l);
+        let r = self.expression(r);
+        let width = l.width.max(r.width);
+        let signed = l.signed && r.signed;
+        let arith = |sym: &str| {
+            Typed::new(
+                format!("{} {} {}", l.fit(width, signed), sym, r.fit(width, signed)),
+                width,
+                signed,
+            )
+        };
+        let compare = |sym: &str| {
+            Typed::new(
+                format!("to_u({} {} {})", l.convert(signed), sym, r.convert(signed)),
+                1,
+                false,
+            )
+        };
+        match op {
+            VerilogOp::Add => arith("+"),
+            VerilogOp::Sub => arith("-"),
+            VerilogOp::Mul => Typed::new(
+                format!(
+                    "resize({} * {}, {})",
+                    l.fit(width, signed),
+                    r.fit(width, signed),
+                    width
+                ),
+                width,
+                signed,
+            ),
+            VerilogOp::BitXor => arith("xor"),
+            VerilogOp::BitAnd => arith("and"),
+            VerilogOp::BitOr => arith("or"),
+            VerilogOp::LogicalAnd => Typed::new(
+                format!("to_u(to_b({}) and to_b({}))", l.unsigned(), r.unsigned()),
+                1,
+                false,
+            ),
+            VerilogOp::LogicalOr => Typed::new(
+                format!("to_u(to_b({}) or to_b({}))", l.unsigned(), r.unsigned()),
+                1,
+                false,
+            ),
+            VerilogOp::Shl => Typed::new(
+                format!("shift_left({}, to_integer({}))", l.code, r.unsigned()),
+                l.width,
+                l.signed,
+            ),
+            // Verilog >> is a logical shift, even for signed values
+            VerilogOp::Shr => {
+                let shifted = Typed::new(
+                    format!(
+                        "shift_right({}, to_integer({}))",
+                        l.unsigned(),
+                        r.unsigned()
+                    ),
+                    l.width,
+                    false,
+                );
+                Typed::new(shifted.convert(l.signed), l.width, l.signed)
+            }
+            VerilogOp::Eq => compare("="),
+            VerilogOp::Lt => compare("<"),
+            VerilogOp::Le => compare("<="),
+            VerilogOp::Ne => compare("/="),
+            VerilogOp::Ge => compare(">="),
+            VerilogOp::Gt => compare(">"),
+        }
+    }
+
+    // The translation of an expression used as a condition
+    fn test(&self, e: &VerilogExpression) -> String {
+        let e = self.expression(e);
+        if let Some(inner) = e
+            .code
+            .strip_prefix("to_u(")
+            .and_then(|x| x.strip_suffix(')'))
+        {
+            if balanced(inner) {
+                return inner.to_string();
+            }
+        }
+        format!("to_b({})", e.unsigned())
+    }
+
+    fn target(&self, e: &VerilogExpression) -> Option<(String, Typed)> {
+        match e {
+            VerilogExpression::Signal(x) => {
+                let t = self.signal(x);
+                Some((t.code.clone(), t))
+            }
+            VerilogExpression::Index(a, b) => {
+                let (name, _) = self.target(a)?;
+                let name = format!("{}({})", name, self.index(b));
+                Some((name.clone(), Typed::new(name, 0, false)))
+            }
+            VerilogExpression::Slice(a, width, offset) => {
+                let (name, t) = self.target(a)?;
+                let name = self.slice_name(&name, *width, offset);
+                Some((name.clone(), Typed::new(name, *width, t.signed)))
+            }
+            VerilogExpression::Element(e) => self.target(&e.packed()),
+            _ => None,
+        }
+    }
+
+    fn slice_name(&self, name: &str, width: usize, offset: &VerilogExpression) -> String {
+        match offset {
+            VerilogExpression::Literal(x) => {
+                let lo = x.as_usize();
+                format!("{}({} downto {})", name, lo + width - 1, lo)
+            }
+            _ => {
+                let lo = self.index(offset);
+                format!("{}({} + {} downto {})", name, lo, width - 1, lo)
+            }
+        }
+    }
+
+    fn assign(&mut self, target: &VerilogExpression, value: Typed) {
+        let op = if self.function.is_some() { ":=" } else { "<=" };
+        match self.target(target) {
+            Some((name, t)) if t.width == 0 => {
+                // Single bit target
+                self.io
+                    .add(format!("{} {} lsb({});", name, op, value.unsigned()));
+            }
+            Some((name, t)) => {
+                self.io
+                    .add(format!("{} {} {};", name, op, value.fit(t.width, t.signed)));
+            }
+            None => {
+                self.io
+                    .add(format!("-- Unsupported assignment target {:?}", target));
+            }
+        }
+    }
+
+    fn conditional(&mut self, c: &VerilogConditional, keyword: &str) {
+        self.io
+            .add(format!("{} {} then", keyword, self.test(&c.test)));
+        self.io.push();
+        self.statements(&c.then);
+        self.io.pop();
+        match &c.otherwise {
+            VerilogBlockOrConditional::Block(b) => {
+                self.io.add("else");
+                self.io.push();
+                self.statements(b);
+                self.io.pop();
+            }
+            VerilogBlockOrConditional::Conditional(s) => {
+                if let VerilogStatement::If(c) = s.as_ref() {
+                    self.conditional(c, "elsif");
+                    return;
+                } else {
+                    self.io.add("else");
+                    self.io.push();
+                    self.visit_statement(s);
+                    self.io.pop();
+                }
+            }
+            VerilogBlockOrConditional::None => {}
+        }
+        self.io.add("end if;");
+    }
+
+    fn statements(&mut self, b: &VerilogBlock) {
+        if b.is_empty() {
+            self.io.add("null;");
+        } else {
+            walk_block(self, b);
+        }
+    }
+
+    // A case may list several values (e.g., for an or-pattern), separated by commas.
+    fn choice(&self, condition: &str, width: usize) -> String {
+        if condition.contains(", ") {
+            return condition
+                .split(", ")
+                .map(|x| self.choice(x, width))
+                .collect::<Vec<_>>()
+                .join(" | ");
+        }
+        if condition == "default" {
+            return "others".into();
+        }
+        if let Some(val) = parse_pattern_literal(condition) {
+            return bit_string(&literal_bits(&val, width));
+        }
+        vhdl_ident(&self.ident_fixup(condition))
+    }
+}
+
+// Check that the parenthesis in an expression are balanced, so that stripping an
+// outer function call is safe.
+fn balanced(x: &str) -> bool {
+    let mut depth = 0;
+    for c in x.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+impl VerilogVisitor for VHDLCodeGenerator {
+    fn visit_loop(&mut self, a: &VerilogLoop) {
+        let start = a.from.as_usize();
+        let end = a.to.as_usize();
+        for i in start..end {
+            self.loops.push(LoopVariable {
+                variable: a.index.clone(),
+                value: i,
+            });
+            walk_block(self, &a.block);
+            self.loops.pop();
+        }
+    }
+
+    fn visit_slice_assignment(
+        &mut self,
+        base: &VerilogExpression,
+        width: &usize,
+        offset: &VerilogExpression,
+        replacement: &VerilogExpression,
+    ) {
+        let value = self.expression(replacement);
+        let target =
+            VerilogExpression::Slice(Box::new(base.clone()), *width, Box::new(offset.clone()));
+        self.assign(&target, value);
+    }
+
+    fn visit_conditional(&mut self, c: &VerilogConditional) {
+        self.conditional(c, "if");
+    }
+
+    fn visit_match(&mut self, m: &VerilogMatch) {
+        let test = self.expression(&m.test);
+        self.io.add(format!("case {} is", test.code));
+        self.io.push();
+        for case in &m.cases {
+            self.io.add(format!(
+                "when {} =>",
+                self.choice(&case.condition, test.width)
+            ));
+            self.io.push();
+            let block = case.block.clone();
+            self.statements(&block);
+            self.io.pop();
+        }
+        if !m.cases.iter().any(|x| x.condition == "default") {
+            self.io.add("when others =>");
+            self.io.push();
+            self.io.add("null;");
+            self.io.pop();
+        }
+        self.io.pop();
+        self.io.add("end case;");
+    }
+
+    fn visit_comment(&mut self, x: &str) {
+        self.io.add(format!("-- {}", x));
+    }
+
+    fn visit_source(&mut self, s: &SourceLocation) {
+        self.io.add(format!("-- {}", s));
+    }
+
+    // Formal properties are only translated to (System)Verilog
+    fn visit_property(&mut self, p: &VerilogProperty) {
+        let kind = match p.kind {
+            VerilogPropertyKind::Assert => "assert",
+            VerilogPropertyKind::Assume => "assume",
+            VerilogPropertyKind::Cover => "cover",
+        };
+        self.io.add(format!("-- {} property ({})", kind, p.text));
+    }
+
+    fn visit_assignment(&mut self, l: &VerilogExpression, r: &VerilogExpression) {
+        let value = self.expression(r);
+        self.assign(l, value);
+    }
+
+    // Let bindings are process variables, so that they update immediately
+    fn visit_let(&mut self, l: &VerilogLet) {
+        let value = self.expression(&l.value);
+        self.io.add(format!(
+            "{} := {};",
+            vhdl_ident(&l.name),
+            value.fit(l.width, l.signed)
+        ));
+    }
+}
+
+pub fn vhdl_combinatorial(code: &VerilogBlock, symbols: HashMap<String, (usize, bool)>) -> String {
+    let mut gen = VHDLCodeGenerator {
+        symbols,
+        ..Default::default()
+    };
+    let lets = verilog_let_extraction(code);
+    gen.io.add("process(all)");
+    gen.io.push();
+    for x in &lets {
+        gen.symbols.insert(x.name.clone(), (x.width, x.signed));
+        gen.io.add(format!(
+            "variable {} : {};",
+            vhdl_ident(&x.name),
+            vhdl_type(x.width, x.signed)
+        ));
+    }
+    gen.io.pop();
+    gen.io.add("begin");
+    gen.io.push();
+    gen.statements(code);
+    gen.io.pop();
+    gen.io.add("end process;");
+    gen.io.to_string()
+}
+
+fn function_result(name: &str) -> String {
+    vhdl_ident(&format!("{}$result", name))
+}
+
+fn vhdl_function(f: &VerilogFunction) -> String {
+    let mut gen = VHDLCodeGenerator {
+        function: Some(f.name.clone()),
+        ..Default::default()
+    };
+    gen.symbols.insert(f.name.clone(), (f.width, f.signed));
+    let args = f
+        .args
+        .iter()
+        .map(|x| {
+            gen.symbols.insert(x.name.clone(), (x.width, x.signed));
+            format!("{} : {}", vhdl_ident(&x.name), vhdl_type(x.width, x.signed))
+        })
+        .collect::<Vec<_>>();
+    let args = if args.is_empty() {
+        "".to_string()
+    } else {
+        format!("({})", args.join("; "))
+    };
+    let kind = if f.signed { "signed" } else { "unsigned" };
+    gen.io.add(format!(
+        "function {}{} return {} is",
+        vhdl_ident(&f.name),
+        args,
+        kind
+    ));
+    gen.io.push();
+    gen.io.add(format!(
+        "variable {} : {};",
+        function_result(&f.name),
+        vhdl_type(f.width, f.signed)
+    ));
+    for x in verilog_let_extraction(&f.block) {
+        gen.symbols.insert(x.name.clone(), (x.width, x.signed));
+        gen.io.add(format!(
+            "variable {} : {};",
+            vhdl_ident(&x.name),
+            vhdl_type(x.width, x.signed)
+        ));
+    }
+    gen.io.pop();
+    gen.io.add("begin");
+    gen.io.push();
+    gen.statements(&f.block);
+    gen.io.add(format!("return {};", function_result(&f.name)));
+    gen.io.pop();
+    gen.io.add("end function;");
+    gen.io.to_string()
+}
+
+fn vhdl_direction(x: &AtomKind) -> &str {
+    match x {
+        AtomKind::InputParameter => "in",
+        AtomKind::InOutParameter => "inout",
+        _ => "out",
+    }
+}
+
+fn vhdl_ports(atoms: &[AtomDetails]) -> Vec<String> {
+    atoms
+        .iter()
+        .filter(|x| x.kind.is_parameter())
+        .map(|x| {
+            format!(
+                "{} : {} {}",
+                vhdl_ident(&x.name),
+                vhdl_direction(&x.kind),
+                vhdl_type(x.width, x.signed)
+            )
+        })
+        .collect()
+}
+
+fn add_port_clause(io: &mut CodeWriter, ports: &[String]) {
+    if ports.is_empty() {
+        return;
+    }
+    io.add("port (");
+    io.push();
+    io.add(ports.join(";\n"));
+    io.pop();
+    io.add(");");
+}
+
+fn signal_decl(x: &AtomDetails) -> String {
+    format!(
+        "signal {} : {};",
+        vhdl_ident(&x.name),
+        vhdl_type(x.width, x.signed)
+    )
+}
+
+// Modules whose implementation is provided outside of the generated code (e.g., vendor
+// primitives).  These are declared as components.
+fn is_foreign(details: &ModuleDetails) -> bool {
+    matches!(&details.code, Verilog::Blackbox(_) | Verilog::Wrapper(_))
+}
+
+fn component_name(defines: &ModuleDefines, kind: &str) -> String {
+    match &defines.details[kind].code {
+        Verilog::Blackbox(b) => b.name.clone(),
+        _ => kind.to_string(),
+    }
+}
+
+fn process_module_vhdl(
+    defines: &ModuleDefines,
+    module_name: &str,
+    module_details: &ModuleDetails,
+    io: &mut CodeWriter,
+) {
+    let atoms = &module_details.atoms;
+    let entity = vhdl_ident(module_name);
+    io.add_line("");
+    io.add(VHDL_CONTEXT);
+    io.add_line("");
+    io.add(format!("entity {} is", entity));
+    io.push();
+    add_port_clause(io, &vhdl_ports(atoms));
+    io.pop();
+    io.add(format!("end entity {};", entity));
+    io.add_line("");
+    io.add(format!("architecture rtl of {} is", entity));
+    io.push();
+    let mut symbols = HashMap::new();
+    for atom in atoms {
+        symbols.insert(atom.name.clone(), (atom.width, atom.signed));
+    }
+    let consts = atoms
+        .iter()
+        .filter(|x| x.kind == AtomKind::Constant)
+        .collect::<Vec<_>>();
+    if !consts.is_empty() {
+        io.add("-- Constant declarations");
+        for x in consts {
+            io.add(format!(
+                "constant {} : {} := {};",
+                vhdl_ident(&x.name),
+                vhdl_type(x.width, x.signed),
+                vhdl_bit_string(&x.const_val, x.width)
+            ));
+        }
+    }
+    if !module_details.enums.is_empty() {
+        io.add("-- Enums");
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for x in &module_details.enums {
+            *counts.entry(&x.type_name).or_default() += 1;
+        }
+        for x in &module_details.enums {
+            let width = clog2(counts[x.type_name.as_str()]).max(1);
+            let name = x.discriminant.replace("::", "$");
+            symbols.insert(name.clone(), (width, false));
+            io.add(format!(
+                "constant {} : {} := {};",
+                vhdl_ident(&name),
+                vhdl_type(width, false),
+                bit_string(&literal_bits(&BigInt::from(x.value), width))
+            ));
+        }
+    }
+    let stubs = atoms
+        .iter()
+        .filter(|x| x.kind.is_stub())
+        .filter(|x| !defines.stub_is_linked_to_module_argument(module_details, &x.name))
+        .collect::<Vec<_>>();
+    if !stubs.is_empty() {
+        io.add("-- Stub signals");
+        stubs.iter().for_each(|x| io.add(signal_decl(x)));
+    }
+    let locals = atoms
+        .iter()
+        .filter(|x| x.kind == AtomKind::LocalSignal)
+        .collect::<Vec<_>>();
+    if !locals.is_empty() {
+        io.add("-- Local signals");
+        locals.iter().for_each(|x| io.add(signal_decl(x)));
+    }
+    if let Verilog::Combinatorial(code) = &module_details.code {
+        let functions = verilog_function_extraction(code);
+        if !functions.is_empty() {
+            io.add("-- Functions");
+            functions.iter().for_each(|x| io.add(vhdl_function(x)));
+        }
+    }
+    let mut components = vec![];
+    for child in &module_details.sub_modules {
+        let entry = &defines.details[&child.kind];
+        let name = component_name(defines, &child.kind);
+        if is_foreign(entry) && !components.contains(&name) {
+            io.add(format!(
+                "-- Implementation of {} is not available in VHDL",
+                name
+            ));
+            io.add(format!("component {}", vhdl_ident(&name)));
+            io.push();
+            add_port_clause(io, &vhdl_ports(&entry.atoms));
+            io.pop();
+            io.add("end component;");
+            components.push(name);
+        }
+    }
+    io.pop();
+    io.add("begin");
+    io.push();
+    if !module_details.sub_modules.is_empty() {
+        io.add("-- Sub module instances");
+    }
+    for child in &module_details.sub_modules {
+        let entry = &defines.details[&child.kind];
+        let unit = if is_foreign(entry) {
+            vhdl_ident(&component_name(defines, &child.kind))
+        } else {
+            format!("entity work.{}", vhdl_ident(&child.kind))
+        };
+        let child_args = entry
+            .atoms
+            .iter()
+            .filter(|x| x.kind.is_parameter())
+            .map(|x| {
+                let arg_name = format!("{}${}", child.name, x.name);
+                let arg_name =
+                    if defines.stub_is_linked_to_module_argument(module_details, &arg_name) {
+                        defines.get_linked_argument_name(module_details, &arg_name)
+                    } else {
+                        arg_name
+                    };
+                format!("{} => {}", vhdl_ident(&x.name), vhdl_ident(&arg_name))
+            })
+            .collect::<Vec<_>>();
+        io.add(format!("{} : {}", vhdl_ident(&child.name), unit));
+        io.push();
+        if child_args.is_empty() {
+            io.add(";");
+        } else {
+            io.add("port map (");
+            io.push();
+            io.add(child_args.join(",\n"));
+            io.pop();
+            io.add(");");
+        }
+        io.pop();
+    }
+    match &module_details.code {
+        Verilog::Combinatorial(code) => {
+            io.add("-- Update code");
+            io.add(vhdl_combinatorial(code, symbols));
+        }
+        Verilog::Custom(_) => {
+            if let Some(code) = &module_details.custom {
+                io.add("-- Update code (custom)");
+                io.add(code);
+            }
+        }
+        _ => {}
+    }
+    for x in &module_details.links {
+        let equiv = get_link_equivalence(x);
+        if defines.signal_name_is_module_argument(module_details, &equiv.0)
+            || defines.signal_name_is_module_argument(module_details, &equiv.1)
+        {
+            continue;
+        }
+        let flat = |x: &str| x.replace('[', "$").replace(']', "");
+        match x {
+            VerilogLink::Forward(x) => io.add(format!(
+                "{} <= {};",
+                vhdl_ident(&format!("{}${}", flat(&x.other_name), x.my_name)),
+                vhdl_ident(&format!("{}${}", flat(&x.owner_name), x.my_name))
+            )),
+            VerilogLink::Backward(x) => io.add(format!(
+                "{} <= {};",
+                vhdl_ident(&format!("{}${}", flat(&x.owner_name), x.my_name)),
+                vhdl_ident(&format!("{}${}", flat(&x.other_name), x.my_name))
+            )),
+            VerilogLink::Bidirectional(_) => io.add(format!(
+                "-- Bidirectional link between {} and {} is not supported in VHDL",
+                equiv.0, equiv.1
+            )),
+        }
+    }
+    io.pop();
+    io.add(format!("end architecture rtl; -- {}", module_name));
+}
+
+// Modules are written out leaves first, since an entity must be analyzed before
+// it can be instantiated.  The path of a child module always extends the path of
+// its parent, so reverse order does the trick.
+pub(crate) fn vhdl_defines(defines: &ModuleDefines) -> String {
+    // Custom Verilog has no VHDL equivalent unless the kernel provides one
+    for (module_name, details) in &defines.details {
+        if matches!(details.code, Verilog::Custom(_)) && details.custom.is_none() {
+            panic!(
+                "Module {} uses custom Verilog with no VHDL translation (implement Logic::hdl_custom for it)",
+                module_name
+            );
+        }
+    }
+    let mut io = CodeWriter::default();
+    let foreign = defines
+        .details
+        .iter()
+        .filter(|x| !x.0.is_empty() && is_foreign(x.1))
+        .map(|x| component_name(defines, x.0))
+        .collect::<Vec<_>>();
+    if !foreign.is_empty() {
+        io.add("-- The following modules have no VHDL implementation, and must be provided");
+        io.add("-- separately (for example, by the Verilog translation in a mixed language flow):");
+        for name in &foreign {
+            io.add(format!("--   {}", name));
+        }
+    }
+    io.add(VHDL_PACKAGE);
+    defines
+        .details
+        .iter()
+        .rev()
+        .filter(|x| !x.0.is_empty() && !is_foreign(x.1))
+        .for_each(|(module_name, module_details)| {
+            process_module_vhdl(defines, module_name, module_details, &mut io)
+        });
+    io.to_string()
+}
@@ -3,14 +3,25 @@ use std::fs::{create_dir_all, remove_dir_all, File};
 use std::io::{Error, Write};
 use std::process::Command;
 
+use crate::ast::SourceLocation;
+use crate::check_error::PathedName;
+
+/// The errors reported by the tools (e.g., `yosys`) run on the generated HDL.  Where the
+/// fault can be traced to a signal or a line of the generated Verilog, the line of the
+/// HDL kernel it came from is included (see [SourceLocation]).  The path of a
+/// [PathedName] is the name of the Verilog module that holds the signal.
 #[derive(Debug)]
 pub enum SynthError {
-    SynthesisFailed { stdout: String, stderr: String },
-    LatchingWriteToSignal(Vec<String>),
-    ImplicitlyDeclared(Vec<String>),
+    SynthesisFailed {
+        stdout: String,
+        stderr: String,
+        source: Option<SourceLocation>,
+    },
+    LatchingWriteToSignal(Vec<PathedName>),
+    ImplicitlyDeclared(Vec<PathedName>),
     DuplicateModule(Vec<String>),
     IOError(std::io::Error),
-    WireHasNoDriver(Vec<String>),
+    WireHasNoDriver(Vec<PathedName>),
     MissingModule(Vec<String>),
 }
 
@@ -59,21 +70,24 @@ pub fn yosys_validate(prefix: &str, translation: &str) -> Result<(), SynthError>
         )));
     }
     if stdout.contains("implicitly declared.") {
-        return Err(SynthError::ImplicitlyDeclared(capture(
+        return Err(SynthError::ImplicitlyDeclared(capture_signals(
             &stdout,
             r#"Identifier (\S*) is implicitly declared"#,
+            translation,
         )));
     }
     if stdout.contains("Latch inferred for") {
-        return Err(SynthError::LatchingWriteToSignal(capture(
+        return Err(SynthError::LatchingWriteToSignal(capture_signals(
             &stdout,
             r#"Latch inferred for signal (\S*)"#,
+            translation,
         )));
     }
     if stdout.contains("is used but has no driver") {
-        return Err(SynthError::WireHasNoDriver(capture(
+        return Err(SynthError::WireHasNoDriver(capture_signals(
             &stdout,
             r#"Wire (\S*) .*? is used but has no driver."#,
+            translation,
         )));
     }
     if stderr.contains("is not part of the design") {
@@ -83,7 +97,108 @@ pub fn yosys_validate(prefix: &str, translation: &str) -> Result<(), SynthError>
         )));
     }
     if !stdout.contains("End of script.") {
-        return Err(SynthError::SynthesisFailed { stdout, stderr });
+        let source = error_source(&format!("{}{}", stdout, stderr), translation);
+        return Err(SynthError::SynthesisFailed {
+            stdout,
+            stderr,
+            source,
+        });
     }
     Ok(())
 }
+
+// Yosys names signals as `\module.\signal` (quoted in various ways).  Each signal is
+// located by the `(* src *)` attribute on the first assignment to it in its module.
+fn capture_signals(stdout: &str, reg_exp: &str, translation: &str) -> Vec<PathedName> {
+    let regex = regex::Regex::new(reg_exp).unwrap();
+    regex
+        .captures_iter(stdout)
+        .map(|capture| {
+            let name = capture[1].replace(['`', '\'', '\\'], "");
+            let (module, name) = name.rsplit_once('.').unwrap_or(("top", &name));
+            PathedName {
+                path: module.to_string(),
+                name: name.to_string(),
+                source: signal_source(translation, module, name),
+            }
+        })
+        .collect()
+}
+
+fn signal_source(translation: &str, module: &str, signal: &str) -> Option<SourceLocation> {
+    let src = regex::Regex::new(r#"^\s*\(\* src = "(.*):(\d+)" \*\) (\S+?)(\[|\s)"#).unwrap();
+    let header = format!("module {}(", module);
+    translation
+        .lines()
+        .skip_while(|line| !line.starts_with(&header))
+        .take_while(|line| !line.starts_with("endmodule"))
+        .filter_map(|line| src.captures(line))
+        .find(|capture| &capture[3] == signal)
+        .map(|capture| SourceLocation::new(&capture[1], capture[2].parse().unwrap()))
+}
+
+// Errors refer to lines of `top.v` (i.e., the translation).  The line is mapped back to
+// the source attribute (or comment) that precedes it in the same module.
+fn error_source(output: &str, translation: &str) -> Option<SourceLocation> {
+    let line_ref = regex::Regex::new(r"top\.v:(\d+)").unwrap();
+    let line: usize = line_ref.captures(output)?[1].parse().ok()?;
+    let src = regex::Regex::new(r#"\(\* src = "(.*):(\d+)" \*\)|^\s*// (.*\.rs):(\d+)$"#).unwrap();
+    translation
+        .lines()
+        .take(line)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .take_while(|line| !line.starts_with("module "))
+        .find_map(|line| {
+            let capture = src.captures(line)?;
+            let file = capture.get(1).or_else(|| capture.get(3))?.as_str();
+            let line = capture.get(2).or_else(|| capture.get(4))?.as_str();
+            Some(SourceLocation::new(file, line.parse().ok()?))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSLATION: &str = r#"
+
+module top$inner(a,z);
+    input wire  a;
+    output reg  z;
+    always @(*) begin
+        // src/lib.rs:11
+        if (a) begin
+            (* src = "src/lib.rs:12" *) z = 1'b1;
+        end
+    end
+endmodule // top$inner
+"#;
+
+    #[test]
+    fn test_signals_are_located_by_their_assignments() {
+        let stdout =
+            r"Latch inferred for signal `\top$inner.\z' from process `\top$inner.$proc$top.v:7$1'";
+        let signals = capture_signals(stdout, r#"Latch inferred for signal (\S*)"#, TRANSLATION);
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].path, "top$inner");
+        assert_eq!(signals[0].name, "z");
+        assert_eq!(
+            signals[0].source,
+            Some(SourceLocation::new("src/lib.rs", 12))
+        );
+    }
+
+    #[test]
+    fn test_errors_are_located_by_the_preceding_source() {
+        let source = error_source("top.v:8: ERROR: syntax error", TRANSLATION);
+        assert_eq!(source, Some(SourceLocation::new("src/lib.rs", 11)));
+        let source = error_source("top.v:9: ERROR: syntax error", TRANSLATION);
+        assert_eq!(source, Some(SourceLocation::new("src/lib.rs", 12)));
+        assert_eq!(
+            error_source("top.v:4: ERROR: syntax error", TRANSLATION),
+            None
+        );
+    }
+}
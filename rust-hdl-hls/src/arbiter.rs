@@ -0,0 +1,180 @@
+use crate::bus::{SoCBusController, SoCBusResponder};
+use rust_hdl_core::prelude::*;
+use rust_hdl_widgets::prelude::*;
+
+// An arbiter lets several controllers (e.g., a host bridge and a DMA
+// engine or soft CPU) share a single tree of routers and bridges.
+//
+//                  +---------+
+//  -- bus (0) -->  |         |
+//  -- bus (1) -->  | arbiter | ---- bus --> router/bridge
+//  -- bus (N) -->  |         |
+//                  +---------+
+//
+// The bus is granted to one controller at a time.  Each controller's
+// address strobe is latched by the arbiter, and replayed on the
+// downstream bus once that controller holds the grant.  Until then,
+// the controller simply sees the bus as not ready, so no changes are
+// needed to the controllers.  This adds a clock of latency to each
+// address strobe.
+//
+// The SoC bus has no signal to mark the end of a transaction, so a
+// controller keeps the grant until it has been quiet (no strobes) for
+// 16 clocks, and another controller is waiting.  The bus then moves
+// to the next controller.  The controller that lost it sees the bus as
+// not ready until it issues a new address strobe (and is granted the bus
+// again), so a transaction that pauses for longer than that cannot be
+// finished.  A controller whose transactions may pause (e.g., a sequence
+// of strobes paced by something else) must assert its `lock` input before
+// the address strobe, and hold it until the last strobe.  The bus is not
+// taken away from a controller that holds `lock`.
+//
+// When several controllers are waiting for the bus, the next one is
+// picked by the policy set when the arbiter is built.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SoCBusArbiterPolicy {
+    // Controllers are granted the bus in turn
+    RoundRobin,
+    // The lowest numbered controller that is waiting is granted the bus
+    FixedPriority,
+}
+
+#[derive(LogicBlock)]
+pub struct SoCBusArbiter<const D: usize, const A: usize, const N: usize> {
+    pub clock: Signal<In, Clock>,
+    pub controllers: [SoCBusResponder<D, A>; N],
+    pub lock: [Signal<In, Bit>; N],
+    pub downstream: SoCBusController<D, A>,
+    round_robin: Constant<Bit>,
+    grant: DFF<Bits<8>>,
+    quiet: DFF<Bits<5>>,
+    pending: [DFF<Bit>; N],
+    address: [DFF<Bits<A>>; N],
+    request: Signal<Local, Bit>,
+    release: Signal<Local, Bit>,
+    next_grant: Signal<Local, Bits<8>>,
+    blocked: [Signal<Local, Bit>; N],
+}
+
+impl<const D: usize, const A: usize, const N: usize> SoCBusArbiter<D, A, N> {
+    pub fn new(policy: SoCBusArbiterPolicy) -> Self {
+        assert!(N <= 256);
+        Self {
+            clock: Default::default(),
+            controllers: array_init::array_init(|_| Default::default()),
+            lock: array_init::array_init(|_| Default::default()),
+            downstream: Default::default(),
+            round_robin: Constant::new(policy == SoCBusArbiterPolicy::RoundRobin),
+            grant: Default::default(),
+            quiet: Default::default(),
+            pending: array_init::array_init(|_| Default::default()),
+            address: array_init::array_init(|_| Default::default()),
+            request: Default::default(),
+            release: Default::default(),
+            next_grant: Default::default(),
+            blocked: array_init::array_init(|_| Default::default()),
+        }
+    }
+}
+
+impl<const D: usize, const A: usize, const N: usize> Logic for SoCBusArbiter<D, A, N> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, grant, quiet);
+        self.downstream.clock.next = self.clock.val();
+        // Default values for output signals.
+        self.downstream.address.next = 0.into();
+        self.downstream.address_strobe.next = false;
+        self.downstream.from_controller.next = 0.into();
+        self.downstream.strobe.next = false;
+        self.release.next = false;
+        // Count the clocks since the controller that holds the grant last used the bus
+        if !self.quiet.q.val().get_bit(4) {
+            self.quiet.d.next = self.quiet.q.val() + 1;
+        }
+        // Latch the address strobes from each controller
+        for i in 0..N {
+            self.pending[i].clock.next = self.clock.val();
+            self.address[i].clock.next = self.clock.val();
+            self.pending[i].d.next = self.pending[i].q.val();
+            self.address[i].d.next = self.address[i].q.val();
+            self.controllers[i].ready.next = false;
+            self.controllers[i].to_controller.next = 0.into();
+            if self.controllers[i].address_strobe.val() {
+                self.pending[i].d.next = true;
+                self.address[i].d.next = self.controllers[i].address.val();
+            }
+        }
+        // Route the controller that holds the grant to the bus
+        for i in 0..N {
+            if self.grant.q.val().index() == i {
+                self.downstream.address.next = self.address[i].q.val();
+                self.downstream.from_controller.next = self.controllers[i].from_controller.val();
+                self.downstream.strobe.next = self.controllers[i].strobe.val();
+                self.controllers[i].to_controller.next = self.downstream.to_controller.val();
+                self.controllers[i].ready.next = self.downstream.ready.val()
+                    & !self.pending[i].q.val()
+                    & !self.controllers[i].address_strobe.val();
+                if self.pending[i].q.val() {
+                    self.downstream.address_strobe.next = true;
+                    self.pending[i].d.next = false;
+                }
+                if self.pending[i].q.val() | self.controllers[i].strobe.val() {
+                    self.quiet.d.next = 0.into();
+                }
+                // The grant can only move once the controller has been quiet for a
+                // while, and never while locked
+                self.release.next = !self.lock[i].val()
+                    & !self.pending[i].q.val()
+                    & !self.controllers[i].address_strobe.val()
+                    & !self.controllers[i].strobe.val()
+                    & self.quiet.q.val().get_bit(4);
+            }
+        }
+        // Pick the next controller to be granted the bus.  For round robin, this
+        // is the highest numbered controller below the current one that is waiting,
+        // or (if there are none) the highest numbered one that is.
+        self.request.next = false;
+        self.next_grant.next = self.grant.q.val();
+        for i in 0..N {
+            if self.pending[i].q.val() {
+                self.request.next = true;
+                self.next_grant.next = i.to_bits();
+            }
+        }
+        for i in 0..N {
+            if self.pending[i].q.val() & (self.grant.q.val().index() > i) {
+                self.next_grant.next = i.to_bits();
+            }
+        }
+        // For fixed priority, it is the lowest numbered controller that is waiting.
+        for i in 0..N {
+            self.blocked[i].next = false;
+            for j in 0..N {
+                if (j < i) & self.pending[j].q.val() {
+                    self.blocked[i].next = true;
+                }
+            }
+        }
+        if !self.round_robin.val() {
+            for i in 0..N {
+                if self.pending[i].q.val() & !self.blocked[i].val() {
+                    self.next_grant.next = i.to_bits();
+                }
+            }
+        }
+        if self.request.val() & self.release.val() {
+            self.grant.d.next = self.next_grant.val();
+            self.quiet.d.next = 0.into();
+        }
+    }
+}
+
+#[test]
+fn test_arbiter_is_synthesizable() {
+    let mut uut = SoCBusArbiter::<16, 8, 3>::new(SoCBusArbiterPolicy::RoundRobin);
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("soc_bus_arbiter", &vlog).unwrap();
+}
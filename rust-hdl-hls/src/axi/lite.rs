@@ -0,0 +1,190 @@
+use crate::bus::SoCBusController;
+use rust_hdl_core::prelude::*;
+use rust_hdl_widgets::prelude::*;
+
+// The AXI4-Lite interface, with a 32 bit data path and an address
+// of A bits.  The clock (ACLK) is not part of the interface, since
+// it is normally shared by all of the AXI devices.
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "AXI4LiteResponder"]
+pub struct AXI4LiteController<const A: usize> {
+    // Write address channel
+    pub awaddr: Signal<Out, Bits<A>>,
+    pub awprot: Signal<Out, Bits<3>>,
+    pub awvalid: Signal<Out, Bit>,
+    pub awready: Signal<In, Bit>,
+    // Write data channel
+    pub wdata: Signal<Out, Bits<32>>,
+    pub wstrb: Signal<Out, Bits<4>>,
+    pub wvalid: Signal<Out, Bit>,
+    pub wready: Signal<In, Bit>,
+    // Write response channel
+    pub bresp: Signal<In, Bits<2>>,
+    pub bvalid: Signal<In, Bit>,
+    pub bready: Signal<Out, Bit>,
+    // Read address channel
+    pub araddr: Signal<Out, Bits<A>>,
+    pub arprot: Signal<Out, Bits<3>>,
+    pub arvalid: Signal<Out, Bit>,
+    pub arready: Signal<In, Bit>,
+    // Read data channel
+    pub rdata: Signal<In, Bits<32>>,
+    pub rresp: Signal<In, Bits<2>>,
+    pub rvalid: Signal<In, Bit>,
+    pub rready: Signal<Out, Bit>,
+}
+
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "AXI4LiteController"]
+pub struct AXI4LiteResponder<const A: usize> {
+    // Write address channel
+    pub awaddr: Signal<In, Bits<A>>,
+    pub awprot: Signal<In, Bits<3>>,
+    pub awvalid: Signal<In, Bit>,
+    pub awready: Signal<Out, Bit>,
+    // Write data channel
+    pub wdata: Signal<In, Bits<32>>,
+    pub wstrb: Signal<In, Bits<4>>,
+    pub wvalid: Signal<In, Bit>,
+    pub wready: Signal<Out, Bit>,
+    // Write response channel
+    pub bresp: Signal<Out, Bits<2>>,
+    pub bvalid: Signal<Out, Bit>,
+    pub bready: Signal<In, Bit>,
+    // Read address channel
+    pub araddr: Signal<In, Bits<A>>,
+    pub arprot: Signal<In, Bits<3>>,
+    pub arvalid: Signal<In, Bit>,
+    pub arready: Signal<Out, Bit>,
+    // Read data channel
+    pub rdata: Signal<Out, Bits<32>>,
+    pub rresp: Signal<Out, Bits<2>>,
+    pub rvalid: Signal<Out, Bit>,
+    pub rready: Signal<In, Bit>,
+}
+
+#[derive(LogicState, Debug, Copy, Clone, PartialEq)]
+enum AXI4LiteBridgeState {
+    Idle,
+    WriteSettle,
+    Write,
+    WriteResponse,
+    ReadSettle,
+    Read,
+    ReadResponse,
+}
+
+// An AXI4-Lite responder that drives a SoC bus, so that a tree of
+// bridges and ports can be mapped into the address space of an AXI
+// master (like a soft CPU or a PCIe bridge).  Each 32 bit AXI word maps
+// onto one SoC bus address, so the SoC bus address is taken from bits
+// [2..A+2] of the AXI address.  Only the lower D bits of the data are
+// used, and reads are zero extended to 32 bits.  The SoC bus has no byte
+// enables, so wstrb is ignored, and the response is always OKAY.
+//
+// One transaction is handled at a time, with writes taking priority
+// over reads.  A transaction waits for the addressed port to be ready,
+// so a port that never becomes ready will stall the AXI bus.
+#[derive(LogicBlock)]
+pub struct AXI4LiteBridge<const D: usize, const A: usize, const AXI_A: usize> {
+    pub clock: Signal<In, Clock>,
+    pub axi: AXI4LiteResponder<AXI_A>,
+    pub bus: SoCBusController<D, A>,
+    state: DFF<AXI4LiteBridgeState>,
+    data: DFF<Bits<D>>,
+}
+
+impl<const D: usize, const A: usize, const AXI_A: usize> Default for AXI4LiteBridge<D, A, AXI_A> {
+    fn default() -> Self {
+        assert!(D <= 32);
+        assert!(A + 2 <= AXI_A);
+        Self {
+            clock: Default::default(),
+            axi: Default::default(),
+            bus: Default::default(),
+            state: Default::default(),
+            data: Default::default(),
+        }
+    }
+}
+
+impl<const D: usize, const A: usize, const AXI_A: usize> Logic for AXI4LiteBridge<D, A, AXI_A> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, state, data);
+        self.bus.clock.next = self.clock.val();
+        // Default values for output signals.
+        self.bus.address.next = self.axi.awaddr.val().get_bits::<A>(2);
+        self.bus.address_strobe.next = false;
+        self.bus.from_controller.next = self.data.q.val();
+        self.bus.strobe.next = false;
+        self.axi.awready.next = false;
+        self.axi.wready.next = false;
+        self.axi.bresp.next = 0.into();
+        self.axi.bvalid.next = false;
+        self.axi.arready.next = false;
+        self.axi.rdata.next = bit_cast::<32, D>(self.data.q.val());
+        self.axi.rresp.next = 0.into();
+        self.axi.rvalid.next = false;
+        match self.state.q.val() {
+            AXI4LiteBridgeState::Idle => {
+                if self.axi.awvalid.val() & self.axi.wvalid.val() {
+                    // Accept the address and data together
+                    self.axi.awready.next = true;
+                    self.axi.wready.next = true;
+                    self.bus.address_strobe.next = true;
+                    self.data.d.next = self.axi.wdata.val().get_bits::<D>(0);
+                    self.state.d.next = AXI4LiteBridgeState::WriteSettle;
+                } else if self.axi.arvalid.val() {
+                    self.axi.arready.next = true;
+                    self.bus.address.next = self.axi.araddr.val().get_bits::<A>(2);
+                    self.bus.address_strobe.next = true;
+                    self.state.d.next = AXI4LiteBridgeState::ReadSettle;
+                }
+            }
+            AXI4LiteBridgeState::WriteSettle => {
+                // Give the bus a cycle to select the port
+                self.state.d.next = AXI4LiteBridgeState::Write;
+            }
+            AXI4LiteBridgeState::Write => {
+                if self.bus.ready.val() {
+                    self.bus.strobe.next = true;
+                    self.state.d.next = AXI4LiteBridgeState::WriteResponse;
+                }
+            }
+            AXI4LiteBridgeState::WriteResponse => {
+                self.axi.bvalid.next = true;
+                if self.axi.bready.val() {
+                    self.state.d.next = AXI4LiteBridgeState::Idle;
+                }
+            }
+            AXI4LiteBridgeState::ReadSettle => {
+                self.state.d.next = AXI4LiteBridgeState::Read;
+            }
+            AXI4LiteBridgeState::Read => {
+                if self.bus.ready.val() {
+                    self.data.d.next = self.bus.to_controller.val();
+                    self.bus.strobe.next = true;
+                    self.state.d.next = AXI4LiteBridgeState::ReadResponse;
+                }
+            }
+            AXI4LiteBridgeState::ReadResponse => {
+                self.axi.rvalid.next = true;
+                if self.axi.rready.val() {
+                    self.state.d.next = AXI4LiteBridgeState::Idle;
+                }
+            }
+            _ => {
+                self.state.d.next = AXI4LiteBridgeState::Idle;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_axi4lite_bridge_is_synthesizable() {
+    let mut uut = AXI4LiteBridge::<16, 8, 12>::default();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("axi4lite_bridge", &vlog).unwrap();
+}
@@ -0,0 +1,9 @@
+// AXI4 interfaces, so that RustHDL blocks can be dropped into vendor
+// block designs (and vendor IP can be used from RustHDL).  The AXI
+// signal names are used (in lower case) so the generated ports are
+// easy to map onto the vendor interface definitions.  The bridges
+// convert between these interfaces and the (simpler) SoC bus and
+// FIFO interfaces in `crate::bus`.
+pub mod lite;
+pub mod sim;
+pub mod stream;
@@ -0,0 +1,132 @@
+// Bus functional models for the AXI interfaces.  These macros are used in
+// testbenches to play the part of the AXI device at the other end of an
+// interface, by driving (and sampling) the interface signals directly.
+// The handshake signals are sampled half way through the clock cycle, so
+// the clock must be the one that the interface is synchronous to.
+
+/// Write `$data` to `$addr` through the AXI4-Lite interface `$axi` (a responder in the
+/// circuit).  The address and data are presented together, and the simulation halts
+/// if the write response is not OKAY.
+#[macro_export]
+macro_rules! axi4lite_write {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($axi: ident).+, $addr: expr, $data: expr) => {{
+        wait_clock_true!($sim, $($clock).+, $uut);
+        $uut.$($axi).+.awaddr.next = ($addr as u64).to_bits();
+        $uut.$($axi).+.awprot.next = 0.into();
+        $uut.$($axi).+.awvalid.next = true;
+        $uut.$($axi).+.wdata.next = ($data as u64).to_bits();
+        $uut.$($axi).+.wstrb.next = 0xF.into();
+        $uut.$($axi).+.wvalid.next = true;
+        let mut aw_pending = true;
+        let mut w_pending = true;
+        while aw_pending || w_pending {
+            wait_clock_false!($sim, $($clock).+, $uut);
+            let aw_done = $uut.$($axi).+.awready.val();
+            let w_done = $uut.$($axi).+.wready.val();
+            wait_clock_true!($sim, $($clock).+, $uut);
+            if aw_done {
+                aw_pending = false;
+                $uut.$($axi).+.awvalid.next = false;
+            }
+            if w_done {
+                w_pending = false;
+                $uut.$($axi).+.wvalid.next = false;
+            }
+        }
+        $uut.$($axi).+.bready.next = true;
+        loop {
+            wait_clock_false!($sim, $($clock).+, $uut);
+            let done = $uut.$($axi).+.bvalid.val();
+            let resp = $uut.$($axi).+.bresp.val();
+            wait_clock_true!($sim, $($clock).+, $uut);
+            if done {
+                sim_assert!($sim, !resp.any(), $uut);
+                break;
+            }
+        }
+        $uut.$($axi).+.bready.next = false;
+    }};
+}
+
+/// Read from `$addr` through the AXI4-Lite interface `$axi` (a responder in the
+/// circuit), and return the data as a `Bits<32>`.  The simulation halts if the
+/// read response is not OKAY.
+#[macro_export]
+macro_rules! axi4lite_read {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($axi: ident).+, $addr: expr) => {{
+        wait_clock_true!($sim, $($clock).+, $uut);
+        $uut.$($axi).+.araddr.next = ($addr as u64).to_bits();
+        $uut.$($axi).+.arprot.next = 0.into();
+        $uut.$($axi).+.arvalid.next = true;
+        loop {
+            wait_clock_false!($sim, $($clock).+, $uut);
+            let done = $uut.$($axi).+.arready.val();
+            wait_clock_true!($sim, $($clock).+, $uut);
+            if done {
+                break;
+            }
+        }
+        $uut.$($axi).+.arvalid.next = false;
+        $uut.$($axi).+.rready.next = true;
+        let data = loop {
+            wait_clock_false!($sim, $($clock).+, $uut);
+            let done = $uut.$($axi).+.rvalid.val();
+            let data = $uut.$($axi).+.rdata.val();
+            let resp = $uut.$($axi).+.rresp.val();
+            wait_clock_true!($sim, $($clock).+, $uut);
+            if done {
+                sim_assert!($sim, !resp.any(), $uut);
+                break data;
+            }
+        };
+        $uut.$($axi).+.rready.next = false;
+        data
+    }};
+}
+
+/// Send the words in `$data` on the AXI4-Stream `$axis` (a responder in the circuit).
+/// `tlast` is asserted with the last word.
+#[macro_export]
+macro_rules! axi4stream_send {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($axis: ident).+, $data: expr) => {
+        wait_clock_true!($sim, $($clock).+, $uut);
+        let count = $data.len();
+        for (ndx, val) in $data.iter().enumerate() {
+            $uut.$($axis).+.tdata.next = (*val).to_bits();
+            $uut.$($axis).+.tlast.next = ndx + 1 == count;
+            $uut.$($axis).+.tvalid.next = true;
+            loop {
+                wait_clock_false!($sim, $($clock).+, $uut);
+                let done = $uut.$($axis).+.tready.val();
+                wait_clock_true!($sim, $($clock).+, $uut);
+                if done {
+                    break;
+                }
+            }
+        }
+        $uut.$($axis).+.tvalid.next = false;
+        $uut.$($axis).+.tlast.next = false;
+    };
+}
+
+/// Receive `$count` words from the AXI4-Stream `$axis` (a controller in the circuit),
+/// and return them as a `Vec`.
+#[macro_export]
+macro_rules! axi4stream_receive {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($axis: ident).+, $count: expr) => {{
+        wait_clock_true!($sim, $($clock).+, $uut);
+        let mut received = vec![];
+        $uut.$($axis).+.tready.next = true;
+        while received.len() < $count {
+            wait_clock_false!($sim, $($clock).+, $uut);
+            let done = $uut.$($axis).+.tvalid.val();
+            let data = $uut.$($axis).+.tdata.val();
+            wait_clock_true!($sim, $($clock).+, $uut);
+            if done {
+                received.push(data);
+            }
+        }
+        $uut.$($axis).+.tready.next = false;
+        received
+    }};
+}
@@ -0,0 +1,69 @@
+use crate::bus::{FIFOReadController, FIFOWriteController};
+use rust_hdl_core::prelude::*;
+
+// The AXI4-Stream interface.  Only the data, handshake and last
+// signals are provided (no keep, strobe, id, dest or user).  A word is
+// transferred on each clock where both tvalid and tready are high.
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "AXI4StreamResponder"]
+pub struct AXI4StreamController<T: Synth> {
+    pub tdata: Signal<Out, T>,
+    pub tvalid: Signal<Out, Bit>,
+    pub tready: Signal<In, Bit>,
+    pub tlast: Signal<Out, Bit>,
+}
+
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "AXI4StreamController"]
+pub struct AXI4StreamResponder<T: Synth> {
+    pub tdata: Signal<In, T>,
+    pub tvalid: Signal<In, Bit>,
+    pub tready: Signal<Out, Bit>,
+    pub tlast: Signal<In, Bit>,
+}
+
+// Accepts words from an AXI4-Stream and writes them to a FIFO.  The
+// stream is held off while the FIFO is full.  Packet boundaries (tlast)
+// are not kept.
+#[derive(LogicBlock, Default)]
+pub struct AXI4StreamToFIFO<T: Synth> {
+    pub axis: AXI4StreamResponder<T>,
+    pub fifo: FIFOWriteController<T>,
+}
+
+impl<T: Synth> Logic for AXI4StreamToFIFO<T> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.fifo.data.next = self.axis.tdata.val();
+        self.fifo.write.next = self.axis.tvalid.val() & !self.fifo.full.val();
+        self.axis.tready.next = !self.fifo.full.val();
+    }
+}
+
+// Reads words from a FIFO and presents them on an AXI4-Stream.  Since
+// the FIFO carries no packet boundaries, tlast is never asserted.
+#[derive(LogicBlock, Default)]
+pub struct FIFOToAXI4Stream<T: Synth> {
+    pub fifo: FIFOReadController<T>,
+    pub axis: AXI4StreamController<T>,
+}
+
+impl<T: Synth> Logic for FIFOToAXI4Stream<T> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.axis.tdata.next = self.fifo.data.val();
+        self.axis.tvalid.next = !self.fifo.empty.val();
+        self.axis.tlast.next = false;
+        self.fifo.read.next = !self.fifo.empty.val() & self.axis.tready.val();
+    }
+}
+
+#[test]
+fn test_axi4stream_bridges_are_synthesizable() {
+    let mut uut = AXI4StreamToFIFO::<Bits<8>>::default();
+    uut.connect_all();
+    yosys_validate("axi4stream_to_fifo", &generate_verilog(&uut)).unwrap();
+    let mut uut = FIFOToAXI4Stream::<Bits<8>>::default();
+    uut.connect_all();
+    yosys_validate("fifo_to_axi4stream", &generate_verilog(&uut)).unwrap();
+}
@@ -1,3 +1,5 @@
+pub mod arbiter;
+pub mod axi;
 pub mod bidi;
 pub mod bridge;
 pub mod bus;
@@ -23,6 +25,9 @@ pub mod sdram_fifo;
 pub mod sim;
 pub mod spi;
 pub mod test_helpers;
+pub mod uart;
+pub mod uart_host;
+pub mod wishbone;
 
 pub trait HLSNamedPorts {
     fn ports(&self) -> Vec<String>;
@@ -1,3 +1,12 @@
+pub use crate::arbiter::{SoCBusArbiter, SoCBusArbiterPolicy};
+pub use crate::axi::lite::{AXI4LiteBridge, AXI4LiteController, AXI4LiteResponder};
+pub use crate::axi::stream::{
+    AXI4StreamController, AXI4StreamResponder, AXI4StreamToFIFO, FIFOToAXI4Stream,
+};
+pub use crate::axi4lite_read;
+pub use crate::axi4lite_write;
+pub use crate::axi4stream_receive;
+pub use crate::axi4stream_send;
 pub use crate::bidi::{BidiBusD, BidiBusM, BidiMaster, BidiSimulatedDevice};
 pub use crate::bridge::Bridge;
 pub use crate::bus::{
@@ -40,4 +49,16 @@ pub use crate::spi::HLSSPIMaster;
 pub use crate::spi::HLSSPIMasterDynamicMode;
 pub use crate::spi::{HLSSPIMuxMasters, HLSSPIMuxSlaves};
 pub use crate::test_helpers::*;
+pub use crate::uart::HLSUART;
+pub use crate::uart_host::{UARTHost, UARTHostClient};
+pub use crate::wishbone::bus::{
+    WishboneClassicToPipelined, WishboneController, WishbonePipelinedController,
+    WishbonePipelinedResponder, WishbonePipelinedToClassic, WishboneResponder,
+};
+pub use crate::wishbone::interconnect::WishboneInterconnect;
+pub use crate::wishbone::soc_bridge::{SoCBusToWishbone, WishboneToSoCBus};
+pub use crate::wishbone_pipelined_read;
+pub use crate::wishbone_pipelined_write;
+pub use crate::wishbone_read;
+pub use crate::wishbone_write;
 pub use crate::HLSNamedPorts;
@@ -83,7 +83,7 @@ pub struct LazyFIFOFeeder<T: Synth, const N: usize> {
     data_len: Constant<Bits<N>>,
 }
 
-impl<T: Synth, const N: usize> LazyFIFOFeeder<T, N> {
+impl<T: Synth + Default, const N: usize> LazyFIFOFeeder<T, N> {
     pub fn new(data: &[T], sleeps: &[Bits<32>]) -> LazyFIFOFeeder<T, N> {
         assert!(clog2(data.len()) <= N);
         assert_eq!(data.len(), sleeps.len());
@@ -166,7 +166,7 @@ pub struct LazyFIFOReader<T: Synth, const N: usize> {
     data_len: Constant<Bits<N>>,
 }
 
-impl<T: Synth, const N: usize> LazyFIFOReader<T, N> {
+impl<T: Synth + Default, const N: usize> LazyFIFOReader<T, N> {
     pub fn new(data: &[T], sleeps: &[Bits<32>]) -> LazyFIFOReader<T, N> {
         assert!(clog2(data.len()) <= N);
         assert_eq!(data.len(), sleeps.len());
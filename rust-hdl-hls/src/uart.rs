@@ -0,0 +1,85 @@
+use crate::bus::{FIFOReadResponder, FIFOWriteResponder};
+use rust_hdl_core::prelude::*;
+use rust_hdl_widgets::prelude::*;
+
+/// A UART with FIFOs on both sides.  Bytes written to [`bus_write`](Self::bus_write) are
+/// sent on [`tx`](Self::tx), and bytes received on [`rx`](Self::rx) can be read from
+/// [`bus_read`](Self::bus_read).  Received bytes with framing or parity errors are
+/// dropped (as are bytes received while the read FIFO is full), and the error outputs
+/// are pulsed instead.
+#[derive(LogicBlock)]
+pub struct HLSUART<const N: usize, const NP1: usize> {
+    pub clock: Signal<In, Clock>,
+    pub tx: Signal<Out, Bit>,
+    pub rx: Signal<In, Bit>,
+    pub bus_write: FIFOWriteResponder<Bits<8>>,
+    pub bus_read: FIFOReadResponder<Bits<8>>,
+    pub framing_error: Signal<Out, Bit>,
+    pub parity_error: Signal<Out, Bit>,
+    tx_fifo: SynchronousFIFO<Bits<8>, N, NP1, 1>,
+    rx_fifo: SynchronousFIFO<Bits<8>, N, NP1, 1>,
+    transmitter: UARTTransmitter,
+    receiver: UARTReceiver,
+}
+
+impl<const N: usize, const NP1: usize> HLSUART<N, NP1> {
+    pub fn new(config: UARTConfig) -> Self {
+        Self {
+            clock: Default::default(),
+            tx: Default::default(),
+            rx: Default::default(),
+            bus_write: Default::default(),
+            bus_read: Default::default(),
+            framing_error: Default::default(),
+            parity_error: Default::default(),
+            tx_fifo: Default::default(),
+            rx_fifo: Default::default(),
+            transmitter: UARTTransmitter::new(config),
+            receiver: UARTReceiver::new(config),
+        }
+    }
+}
+
+impl<const N: usize, const NP1: usize> Logic for HLSUART<N, NP1> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, tx_fifo, rx_fifo, transmitter, receiver);
+        // Connect up the write side of the transmit FIFO
+        self.tx_fifo.data_in.next = self.bus_write.data.val();
+        self.tx_fifo.write.next = self.bus_write.write.val();
+        self.bus_write.full.next = self.tx_fifo.full.val();
+        self.bus_write.almost_full.next = self.tx_fifo.almost_full.val();
+        // Feed the transmitter from the FIFO whenever it is idle
+        self.transmitter.data.next = self.tx_fifo.data_out.val();
+        self.transmitter.start_send.next = !self.tx_fifo.empty.val() & !self.transmitter.busy.val();
+        self.tx_fifo.read.next = !self.tx_fifo.empty.val() & !self.transmitter.busy.val();
+        self.tx.next = self.transmitter.tx.val();
+        // Store the good bytes from the receiver
+        self.receiver.rx.next = self.rx.val();
+        self.rx_fifo.data_in.next = self.receiver.data.val();
+        self.rx_fifo.write.next = self.receiver.data_valid.val()
+            & !self.receiver.framing_error.val()
+            & !self.receiver.parity_error.val()
+            & !self.rx_fifo.full.val();
+        self.framing_error.next = self.receiver.framing_error.val();
+        self.parity_error.next = self.receiver.parity_error.val();
+        // Connect up the read side of the receive FIFO
+        self.bus_read.data.next = self.rx_fifo.data_out.val();
+        self.bus_read.empty.next = self.rx_fifo.empty.val();
+        self.bus_read.almost_empty.next = self.rx_fifo.almost_empty.val();
+        self.rx_fifo.read.next = self.bus_read.read.val();
+    }
+}
+
+#[test]
+fn test_hls_uart_is_synthesizable() {
+    let mut uut = HLSUART::<4, 5>::new(UARTConfig {
+        clock_speed: 48_000_000,
+        baud_rate: 115_200,
+        parity: UARTParity::None,
+        stop_bits: 1,
+    });
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("hls_uart", &vlog).unwrap();
+}
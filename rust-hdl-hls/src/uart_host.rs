@@ -0,0 +1,212 @@
+use crate::bus::{FIFOReadController, FIFOWriteController, SoCBusController};
+use crate::controller::BaseController;
+use crate::expander::Expander;
+use crate::fifo::SyncFIFO;
+use crate::reducer::Reducer;
+use crate::uart::HLSUART;
+use rust_hdl_core::prelude::*;
+use rust_hdl_widgets::prelude::*;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+// Creates a Host object that connects a UART to a Controller.  The
+// bytes received on the UART are assembled into 16 bit words (using
+// the given word order) for the controller, and the replies are split
+// back into bytes.  Everything runs in a single clock domain.
+//
+// There is no flow control on the UART, so the host should not send
+// more than a few FIFOs worth of data to a target that is not ready.
+#[derive(LogicBlock)]
+pub struct UARTHost<const A: usize> {
+    pub clock: Signal<In, Clock>,
+    pub tx: Signal<Out, Bit>,
+    pub rx: Signal<In, Bit>,
+    pub bus: SoCBusController<16, A>,
+    uart: HLSUART<4, 5>,
+    bytes_to_words: Expander<8, 16>,
+    from_cpu: SyncFIFO<Bits<16>, 3, 4, 1>,
+    words_to_bytes: Reducer<16, 8>,
+    to_cpu: SyncFIFO<Bits<16>, 3, 4, 1>,
+    controller: BaseController<A>,
+}
+
+impl<const A: usize> UARTHost<A> {
+    pub fn new(config: UARTConfig, order: WordOrder) -> Self {
+        Self {
+            clock: Default::default(),
+            tx: Default::default(),
+            rx: Default::default(),
+            bus: Default::default(),
+            uart: HLSUART::new(config),
+            bytes_to_words: Expander::new(order),
+            from_cpu: Default::default(),
+            words_to_bytes: Reducer::new(order),
+            to_cpu: Default::default(),
+            controller: Default::default(),
+        }
+    }
+}
+
+impl<const A: usize> Logic for UARTHost<A> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(
+            self,
+            clock,
+            uart,
+            bytes_to_words,
+            from_cpu,
+            words_to_bytes,
+            to_cpu,
+            controller
+        );
+        self.tx.next = self.uart.tx.val();
+        self.uart.rx.next = self.rx.val();
+        // Bytes from the UART are widened into words for the controller
+        FIFOReadController::<Bits<8>>::join(
+            &mut self.bytes_to_words.bus_read,
+            &mut self.uart.bus_read,
+        );
+        FIFOWriteController::<Bits<16>>::join(
+            &mut self.bytes_to_words.bus_write,
+            &mut self.from_cpu.bus_write,
+        );
+        FIFOReadController::<Bits<16>>::join(
+            &mut self.controller.from_cpu,
+            &mut self.from_cpu.bus_read,
+        );
+        // Words from the controller are narrowed into bytes for the UART
+        FIFOWriteController::<Bits<16>>::join(
+            &mut self.controller.to_cpu,
+            &mut self.to_cpu.bus_write,
+        );
+        FIFOReadController::<Bits<16>>::join(
+            &mut self.words_to_bytes.bus_read,
+            &mut self.to_cpu.bus_read,
+        );
+        FIFOWriteController::<Bits<8>>::join(
+            &mut self.words_to_bytes.bus_write,
+            &mut self.uart.bus_write,
+        );
+        SoCBusController::<16, A>::link(&mut self.bus, &mut self.controller.bus);
+    }
+}
+
+#[test]
+fn test_uart_host_synthesizes() {
+    let mut uut = UARTHost::<8>::new(
+        UARTConfig {
+            clock_speed: 48_000_000,
+            baud_rate: 115_200,
+            parity: UARTParity::None,
+            stop_bits: 1,
+        },
+        WordOrder::MostSignificantFirst,
+    );
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("uart_host", &vlog).unwrap();
+}
+
+/// The PC side of a [UARTHost].  The `port` can be anything that implements
+/// [Read] and [Write] (a serial port, a TCP connection to a terminal server,
+/// or a simulated link), and the `order` must match the one used to build
+/// the [UARTHost].
+pub struct UARTHostClient<T: Read + Write> {
+    port: T,
+    order: WordOrder,
+}
+
+impl<T: Read + Write> UARTHostClient<T> {
+    pub fn new(port: T, order: WordOrder) -> Self {
+        Self { port, order }
+    }
+    /// Release the underlying port.
+    pub fn into_inner(self) -> T {
+        self.port
+    }
+    fn send_words(&mut self, words: &[u16]) -> Result<()> {
+        let mut bytes = Vec::with_capacity(words.len() * 2);
+        for word in words {
+            match self.order {
+                WordOrder::MostSignificantFirst => bytes.extend(word.to_be_bytes()),
+                WordOrder::LeastSignificantFirst => bytes.extend(word.to_le_bytes()),
+            }
+        }
+        self.port.write_all(&bytes)?;
+        self.port.flush()
+    }
+    fn receive_words(&mut self, count: usize) -> Result<Vec<u16>> {
+        let mut bytes = vec![0_u8; count * 2];
+        self.port.read_exact(&mut bytes)?;
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|x| match self.order {
+                WordOrder::MostSignificantFirst => u16::from_be_bytes([x[0], x[1]]),
+                WordOrder::LeastSignificantFirst => u16::from_le_bytes([x[0], x[1]]),
+            })
+            .collect())
+    }
+    /// Send a ping with the given `id`, and check that it is echoed back.
+    pub fn ping(&mut self, id: u8) -> Result<()> {
+        self.send_words(&[0x0100 | (id as u16)])?;
+        let reply = self.receive_words(1)?[0];
+        if reply != 0x0100 | (id as u16) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Ping reply was {:x}, expected {:x}",
+                    reply,
+                    0x0100 | (id as u16)
+                ),
+            ));
+        }
+        Ok(())
+    }
+    pub fn write_data_to_address(&mut self, address: u8, data: &[u16]) -> Result<()> {
+        check_transfer_length(data.len())?;
+        let mut msg = vec![0x0300 | (address as u16), data.len() as u16];
+        msg.extend_from_slice(data);
+        self.send_words(&msg)
+    }
+    pub fn read_data_from_address(&mut self, address: u8, len: usize) -> Result<Vec<u16>> {
+        check_transfer_length(len)?;
+        self.send_words(&[0x0200 | (address as u16), len as u16])?;
+        self.receive_words(len)
+    }
+    /// Returns `true` if the port at `address` is ready.
+    pub fn poll(&mut self, address: u8) -> Result<bool> {
+        self.send_words(&[0x0400 | (address as u16)])?;
+        let reply = self.receive_words(1)?[0];
+        if reply & 0xFF00 != 0xFF00 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Poll reply was {:x}", reply),
+            ));
+        }
+        Ok(reply & 1 != 0)
+    }
+    /// Start streaming data from the port at `address`.  Use [stream_read](Self::stream_read)
+    /// to collect the words, and [disable_streaming](Self::disable_streaming) to stop.
+    pub fn enable_streaming(&mut self, address: u8) -> Result<()> {
+        self.send_words(&[0x0500 | (address as u16)])
+    }
+    pub fn stream_read(&mut self, num_words: usize) -> Result<Vec<u16>> {
+        self.receive_words(num_words)
+    }
+    /// Stop streaming.  Words that were already in flight are still delivered, and must be
+    /// read before issuing further commands.
+    pub fn disable_streaming(&mut self) -> Result<()> {
+        self.send_words(&[0xFFFF])
+    }
+}
+
+// The length of a transfer is sent as a single word, and must not be zero
+fn check_transfer_length(len: usize) -> Result<()> {
+    if len == 0 || len >= (1 << 16) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Transfer length {} must be between 1 and 65535 words", len),
+        ));
+    }
+    Ok(())
+}
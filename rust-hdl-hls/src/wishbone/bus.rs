@@ -0,0 +1,138 @@
+use rust_hdl_core::prelude::*;
+use rust_hdl_widgets::prelude::*;
+
+// The classic Wishbone interface.  A transfer starts when cyc and
+// stb are asserted, and ends when the responder asserts ack (or err).
+// The controller holds the request steady until then.  Keeping cyc
+// asserted across several transfers makes a block (burst) cycle.
+// The clock and reset are not part of the interface, since they are
+// normally shared by everything on the bus.
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "WishboneResponder"]
+pub struct WishboneController<const A: usize> {
+    pub adr: Signal<Out, Bits<A>>,
+    pub dat_w: Signal<Out, Bits<32>>,
+    pub dat_r: Signal<In, Bits<32>>,
+    pub sel: Signal<Out, Bits<4>>,
+    pub we: Signal<Out, Bit>,
+    pub cyc: Signal<Out, Bit>,
+    pub stb: Signal<Out, Bit>,
+    pub ack: Signal<In, Bit>,
+    pub err: Signal<In, Bit>,
+}
+
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "WishboneController"]
+pub struct WishboneResponder<const A: usize> {
+    pub adr: Signal<In, Bits<A>>,
+    pub dat_w: Signal<In, Bits<32>>,
+    pub dat_r: Signal<Out, Bits<32>>,
+    pub sel: Signal<In, Bits<4>>,
+    pub we: Signal<In, Bit>,
+    pub cyc: Signal<In, Bit>,
+    pub stb: Signal<In, Bit>,
+    pub ack: Signal<Out, Bit>,
+    pub err: Signal<Out, Bit>,
+}
+
+// The pipelined Wishbone interface.  A request is accepted on any
+// clock where stb is asserted and stall is not, so the controller
+// can issue a new request every clock.  The acks come back later (in
+// the same order as the requests), while cyc is held asserted.
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "WishbonePipelinedResponder"]
+pub struct WishbonePipelinedController<const A: usize> {
+    pub adr: Signal<Out, Bits<A>>,
+    pub dat_w: Signal<Out, Bits<32>>,
+    pub dat_r: Signal<In, Bits<32>>,
+    pub sel: Signal<Out, Bits<4>>,
+    pub we: Signal<Out, Bit>,
+    pub cyc: Signal<Out, Bit>,
+    pub stb: Signal<Out, Bit>,
+    pub stall: Signal<In, Bit>,
+    pub ack: Signal<In, Bit>,
+    pub err: Signal<In, Bit>,
+}
+
+#[derive(Clone, Debug, Default, LogicInterface)]
+#[join = "WishbonePipelinedController"]
+pub struct WishbonePipelinedResponder<const A: usize> {
+    pub adr: Signal<In, Bits<A>>,
+    pub dat_w: Signal<In, Bits<32>>,
+    pub dat_r: Signal<Out, Bits<32>>,
+    pub sel: Signal<In, Bits<4>>,
+    pub we: Signal<In, Bit>,
+    pub cyc: Signal<In, Bit>,
+    pub stb: Signal<In, Bit>,
+    pub stall: Signal<Out, Bit>,
+    pub ack: Signal<Out, Bit>,
+    pub err: Signal<Out, Bit>,
+}
+
+// Connects a pipelined controller to a classic responder.  The
+// controller is stalled until the responder acknowledges the current
+// request, so only one request is outstanding at a time.
+#[derive(LogicBlock, Default)]
+pub struct WishbonePipelinedToClassic<const A: usize> {
+    pub upstream: WishbonePipelinedResponder<A>,
+    pub downstream: WishboneController<A>,
+}
+
+impl<const A: usize> Logic for WishbonePipelinedToClassic<A> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.downstream.adr.next = self.upstream.adr.val();
+        self.downstream.dat_w.next = self.upstream.dat_w.val();
+        self.downstream.sel.next = self.upstream.sel.val();
+        self.downstream.we.next = self.upstream.we.val();
+        self.downstream.cyc.next = self.upstream.cyc.val();
+        self.downstream.stb.next = self.upstream.stb.val();
+        self.upstream.dat_r.next = self.downstream.dat_r.val();
+        self.upstream.ack.next = self.downstream.ack.val();
+        self.upstream.err.next = self.downstream.err.val();
+        self.upstream.stall.next = !self.downstream.ack.val() & !self.downstream.err.val();
+    }
+}
+
+// Connects a classic controller to a pipelined responder.  Each
+// classic request is issued once to the responder, and then stb is
+// withdrawn until the responder acknowledges it.
+#[derive(LogicBlock, Default)]
+pub struct WishboneClassicToPipelined<const A: usize> {
+    pub clock: Signal<In, Clock>,
+    pub upstream: WishboneResponder<A>,
+    pub downstream: WishbonePipelinedController<A>,
+    issued: DFF<Bit>,
+}
+
+impl<const A: usize> Logic for WishboneClassicToPipelined<A> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, issued);
+        self.downstream.adr.next = self.upstream.adr.val();
+        self.downstream.dat_w.next = self.upstream.dat_w.val();
+        self.downstream.sel.next = self.upstream.sel.val();
+        self.downstream.we.next = self.upstream.we.val();
+        self.downstream.cyc.next = self.upstream.cyc.val();
+        self.downstream.stb.next = self.upstream.stb.val() & !self.issued.q.val();
+        self.upstream.dat_r.next = self.downstream.dat_r.val();
+        self.upstream.ack.next = self.downstream.ack.val();
+        self.upstream.err.next = self.downstream.err.val();
+        if self.upstream.stb.val() & !self.issued.q.val() & !self.downstream.stall.val() {
+            self.issued.d.next = true;
+        }
+        if self.downstream.ack.val() | self.downstream.err.val() | !self.upstream.cyc.val() {
+            self.issued.d.next = false;
+        }
+    }
+}
+
+#[test]
+fn test_wishbone_adapters_are_synthesizable() {
+    let mut uut = WishbonePipelinedToClassic::<8>::default();
+    uut.connect_all();
+    yosys_validate("wishbone_pipelined_to_classic", &generate_verilog(&uut)).unwrap();
+    let mut uut = WishboneClassicToPipelined::<8>::default();
+    uut.connect_all();
+    yosys_validate("wishbone_classic_to_pipelined", &generate_verilog(&uut)).unwrap();
+}
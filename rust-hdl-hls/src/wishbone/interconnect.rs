@@ -0,0 +1,147 @@
+use crate::wishbone::bus::{WishboneController, WishboneResponder};
+use rust_hdl_core::prelude::*;
+use rust_hdl_widgets::prelude::*;
+
+// A shared bus interconnect for classic Wishbone.  Up to M controllers
+// are attached to the `controllers` ports, and N responders to the
+// `responders` ports.  Each responder is given a range of addresses
+// (base and size, which must not overlap) when the interconnect is
+// built, and sees addresses relative to its base.  A request to an
+// address that does not belong to any responder is terminated with err.
+//
+// The controllers are granted the bus in round robin order.  A
+// controller keeps the bus for as long as it holds cyc, so a block
+// cycle (or a read-modify-write) is never interrupted.
+#[derive(LogicBlock)]
+pub struct WishboneInterconnect<const M: usize, const N: usize, const A: usize> {
+    pub clock: Signal<In, Clock>,
+    pub controllers: [WishboneResponder<A>; M],
+    pub responders: [WishboneController<A>; N],
+    base_address: [Constant<Bits<A>>; N],
+    end_address: [Constant<Bits<A>>; N],
+    active: DFF<Bit>,
+    grant: DFF<Bits<8>>,
+    request: Signal<Local, Bit>,
+    next_grant: Signal<Local, Bits<8>>,
+    adr: Signal<Local, Bits<A>>,
+    cyc: Signal<Local, Bit>,
+    stb: Signal<Local, Bit>,
+    mapped: Signal<Local, Bit>,
+}
+
+impl<const M: usize, const N: usize, const A: usize> WishboneInterconnect<M, N, A> {
+    pub fn new(address_map: [(LiteralType, LiteralType); N]) -> Self {
+        assert!(M <= 256);
+        for (ndx, (base, size)) in address_map.iter().enumerate() {
+            assert_ne!(*size, 0);
+            assert!(base + size <= (1 << A));
+            for (other_base, other_size) in &address_map[ndx + 1..] {
+                assert!(base + size <= *other_base || other_base + other_size <= *base);
+            }
+        }
+        Self {
+            clock: Default::default(),
+            controllers: array_init::array_init(|_| Default::default()),
+            responders: array_init::array_init(|_| Default::default()),
+            base_address: array_init::array_init(|i| Constant::new(address_map[i].0.into())),
+            // The end address is inclusive, so that a responder can reach the top of the
+            // address space
+            end_address: array_init::array_init(|i| {
+                Constant::new((address_map[i].0 + address_map[i].1 - 1).into())
+            }),
+            active: Default::default(),
+            grant: Default::default(),
+            request: Default::default(),
+            next_grant: Default::default(),
+            adr: Default::default(),
+            cyc: Default::default(),
+            stb: Default::default(),
+            mapped: Default::default(),
+        }
+    }
+}
+
+impl<const M: usize, const N: usize, const A: usize> Logic for WishboneInterconnect<M, N, A> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, active, grant);
+        // Round robin arbitration.  The next grant goes to the highest numbered
+        // controller below the current one that wants the bus, or (if there are
+        // none) to the highest numbered one that does.
+        self.request.next = false;
+        self.next_grant.next = self.grant.q.val();
+        for i in 0..M {
+            if self.controllers[i].cyc.val() {
+                self.request.next = true;
+                self.next_grant.next = i.to_bits();
+            }
+        }
+        for i in 0..M {
+            if self.controllers[i].cyc.val() & (self.grant.q.val().index() > i) {
+                self.next_grant.next = i.to_bits();
+            }
+        }
+        if !self.active.q.val() & self.request.val() {
+            self.grant.d.next = self.next_grant.val();
+            self.active.d.next = true;
+        }
+        // Route the granted controller to the bus
+        self.adr.next = 0.into();
+        self.cyc.next = false;
+        self.stb.next = false;
+        for i in 0..M {
+            self.controllers[i].dat_r.next = 0.into();
+            self.controllers[i].ack.next = false;
+            self.controllers[i].err.next = false;
+            if self.active.q.val() & (self.grant.q.val().index() == i) {
+                self.adr.next = self.controllers[i].adr.val();
+                self.cyc.next = self.controllers[i].cyc.val();
+                self.stb.next = self.controllers[i].stb.val();
+                if !self.controllers[i].cyc.val() {
+                    self.active.d.next = false;
+                }
+            }
+        }
+        // Decode the address and route the bus to the selected responder
+        self.mapped.next = false;
+        for j in 0..N {
+            self.responders[j].adr.next = self.adr.val() - self.base_address[j].val();
+            self.responders[j].dat_w.next = 0.into();
+            self.responders[j].sel.next = 0.into();
+            self.responders[j].we.next = false;
+            self.responders[j].cyc.next = false;
+            self.responders[j].stb.next = false;
+            if (self.adr.val() >= self.base_address[j].val())
+                & (self.adr.val() <= self.end_address[j].val())
+            {
+                self.mapped.next = true;
+                self.responders[j].cyc.next = self.cyc.val();
+                self.responders[j].stb.next = self.stb.val();
+                for i in 0..M {
+                    if self.active.q.val() & (self.grant.q.val().index() == i) {
+                        self.responders[j].dat_w.next = self.controllers[i].dat_w.val();
+                        self.responders[j].sel.next = self.controllers[i].sel.val();
+                        self.responders[j].we.next = self.controllers[i].we.val();
+                        self.controllers[i].dat_r.next = self.responders[j].dat_r.val();
+                        self.controllers[i].ack.next = self.responders[j].ack.val();
+                        self.controllers[i].err.next = self.responders[j].err.val();
+                    }
+                }
+            }
+        }
+        // Requests to unmapped addresses are terminated with an error
+        for i in 0..M {
+            if self.active.q.val() & (self.grant.q.val().index() == i) & !self.mapped.val() {
+                self.controllers[i].err.next = self.stb.val();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_wishbone_interconnect_is_synthesizable() {
+    let mut uut = WishboneInterconnect::<2, 3, 8>::new([(0x00, 0x10), (0x10, 0x04), (0x80, 0x80)]);
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("wishbone_interconnect", &vlog).unwrap();
+}
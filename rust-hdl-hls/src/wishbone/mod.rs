@@ -0,0 +1,11 @@
+// Wishbone B4 support, for using open cores (and their
+// peripherals) with RustHDL designs.  Both the classic and the
+// pipelined variants of the bus are provided, along with adapters
+// between them, a shared bus interconnect, and bridges to and from
+// the SoC bus in `crate::bus`.  The data path is 32 bits wide, with
+// word addressing and 4 byte selects (as used by LiteX and most of
+// the OpenCores peripherals).
+pub mod bus;
+pub mod interconnect;
+pub mod sim;
+pub mod soc_bridge;
@@ -0,0 +1,144 @@
+// Bus functional models for Wishbone.  These macros are used in testbenches
+// to play the part of a Wishbone controller, by driving (and sampling) the
+// interface signals of a responder in the circuit directly.  The responses
+// are sampled half way through the clock cycle, so the clock must be the
+// one that the bus is synchronous to.  Transfers of more than one word are
+// sent as a burst (a block cycle for classic Wishbone, and back to back
+// requests for pipelined Wishbone) to incrementing addresses.  The
+// simulation halts if a transfer is terminated with err.
+
+/// Write the words in `$data` to the classic Wishbone responder `$bus`, starting
+/// at address `$addr`.
+#[macro_export]
+macro_rules! wishbone_write {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($bus: ident).+, $addr: expr, $data: expr) => {
+        wait_clock_true!($sim, $($clock).+, $uut);
+        $uut.$($bus).+.cyc.next = true;
+        for (ndx, val) in $data.iter().enumerate() {
+            $uut.$($bus).+.adr.next = (($addr as u64) + (ndx as u64)).to_bits();
+            $uut.$($bus).+.dat_w.next = (*val as u64).to_bits();
+            $uut.$($bus).+.sel.next = 0xF.into();
+            $uut.$($bus).+.we.next = true;
+            $uut.$($bus).+.stb.next = true;
+            loop {
+                wait_clock_false!($sim, $($clock).+, $uut);
+                let ack = $uut.$($bus).+.ack.val();
+                let err = $uut.$($bus).+.err.val();
+                wait_clock_true!($sim, $($clock).+, $uut);
+                sim_assert!($sim, !err, $uut);
+                if ack {
+                    break;
+                }
+            }
+        }
+        $uut.$($bus).+.stb.next = false;
+        $uut.$($bus).+.we.next = false;
+        $uut.$($bus).+.cyc.next = false;
+    };
+}
+
+/// Read `$count` words from the classic Wishbone responder `$bus`, starting at
+/// address `$addr`, and return them as a `Vec<Bits<32>>`.
+#[macro_export]
+macro_rules! wishbone_read {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($bus: ident).+, $addr: expr, $count: expr) => {{
+        wait_clock_true!($sim, $($clock).+, $uut);
+        let mut received = vec![];
+        $uut.$($bus).+.cyc.next = true;
+        for ndx in 0..$count {
+            $uut.$($bus).+.adr.next = (($addr as u64) + (ndx as u64)).to_bits();
+            $uut.$($bus).+.sel.next = 0xF.into();
+            $uut.$($bus).+.we.next = false;
+            $uut.$($bus).+.stb.next = true;
+            loop {
+                wait_clock_false!($sim, $($clock).+, $uut);
+                let ack = $uut.$($bus).+.ack.val();
+                let err = $uut.$($bus).+.err.val();
+                let data = $uut.$($bus).+.dat_r.val();
+                wait_clock_true!($sim, $($clock).+, $uut);
+                sim_assert!($sim, !err, $uut);
+                if ack {
+                    received.push(data);
+                    break;
+                }
+            }
+        }
+        $uut.$($bus).+.stb.next = false;
+        $uut.$($bus).+.cyc.next = false;
+        received
+    }};
+}
+
+/// Write the words in `$data` to the pipelined Wishbone responder `$bus`, starting
+/// at address `$addr`.
+#[macro_export]
+macro_rules! wishbone_pipelined_write {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($bus: ident).+, $addr: expr, $data: expr) => {
+        wait_clock_true!($sim, $($clock).+, $uut);
+        let count = $data.len();
+        let mut issued = 0;
+        let mut acked = 0;
+        $uut.$($bus).+.cyc.next = true;
+        $uut.$($bus).+.we.next = true;
+        $uut.$($bus).+.sel.next = 0xF.into();
+        while acked < count {
+            $uut.$($bus).+.stb.next = issued < count;
+            if issued < count {
+                $uut.$($bus).+.adr.next = (($addr as u64) + (issued as u64)).to_bits();
+                $uut.$($bus).+.dat_w.next = ($data[issued] as u64).to_bits();
+            }
+            wait_clock_false!($sim, $($clock).+, $uut);
+            let accepted = (issued < count) & !$uut.$($bus).+.stall.val();
+            let ack = $uut.$($bus).+.ack.val();
+            let err = $uut.$($bus).+.err.val();
+            wait_clock_true!($sim, $($clock).+, $uut);
+            sim_assert!($sim, !err, $uut);
+            if accepted {
+                issued += 1;
+            }
+            if ack {
+                acked += 1;
+            }
+        }
+        $uut.$($bus).+.stb.next = false;
+        $uut.$($bus).+.we.next = false;
+        $uut.$($bus).+.cyc.next = false;
+    };
+}
+
+/// Read `$count` words from the pipelined Wishbone responder `$bus`, starting at
+/// address `$addr`, and return them as a `Vec<Bits<32>>`.
+#[macro_export]
+macro_rules! wishbone_pipelined_read {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($bus: ident).+, $addr: expr, $count: expr) => {{
+        wait_clock_true!($sim, $($clock).+, $uut);
+        let count = $count;
+        let mut issued = 0;
+        let mut received = vec![];
+        $uut.$($bus).+.cyc.next = true;
+        $uut.$($bus).+.we.next = false;
+        $uut.$($bus).+.sel.next = 0xF.into();
+        while received.len() < count {
+            $uut.$($bus).+.stb.next = issued < count;
+            if issued < count {
+                $uut.$($bus).+.adr.next = (($addr as u64) + (issued as u64)).to_bits();
+            }
+            wait_clock_false!($sim, $($clock).+, $uut);
+            let accepted = (issued < count) & !$uut.$($bus).+.stall.val();
+            let ack = $uut.$($bus).+.ack.val();
+            let err = $uut.$($bus).+.err.val();
+            let data = $uut.$($bus).+.dat_r.val();
+            wait_clock_true!($sim, $($clock).+, $uut);
+            sim_assert!($sim, !err, $uut);
+            if accepted {
+                issued += 1;
+            }
+            if ack {
+                received.push(data);
+            }
+        }
+        $uut.$($bus).+.stb.next = false;
+        $uut.$($bus).+.cyc.next = false;
+        received
+    }};
+}
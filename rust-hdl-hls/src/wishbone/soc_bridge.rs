@@ -0,0 +1,245 @@
+use crate::bus::{SoCBusController, SoCBusResponder};
+use crate::wishbone::bus::{WishboneController, WishboneResponder};
+use crate::HLSNamedPorts;
+use rust_hdl_core::prelude::*;
+use rust_hdl_widgets::prelude::*;
+
+#[derive(LogicState, Debug, Copy, Clone, PartialEq)]
+enum WishboneToSoCBusState {
+    Idle,
+    Settle,
+    Transfer,
+    Ack,
+}
+
+// A classic Wishbone responder that drives a SoC bus, so that a
+// Wishbone controller (like a soft CPU) can reach the ports of a tree
+// of routers and bridges.  The Wishbone (word) address selects the SoC
+// bus address directly.  Only the lower D bits of the data are used,
+// reads are zero extended to 32 bits, and sel is ignored (the SoC bus
+// has no byte enables).  Each transfer waits for the addressed port to
+// be ready, so a port that never becomes ready will stall the bus.
+#[derive(LogicBlock)]
+pub struct WishboneToSoCBus<const D: usize, const A: usize, const WA: usize> {
+    pub clock: Signal<In, Clock>,
+    pub wishbone: WishboneResponder<WA>,
+    pub bus: SoCBusController<D, A>,
+    state: DFF<WishboneToSoCBusState>,
+    data: DFF<Bits<D>>,
+}
+
+impl<const D: usize, const A: usize, const WA: usize> Default for WishboneToSoCBus<D, A, WA> {
+    fn default() -> Self {
+        assert!(D <= 32);
+        assert!(A <= WA);
+        Self {
+            clock: Default::default(),
+            wishbone: Default::default(),
+            bus: Default::default(),
+            state: Default::default(),
+            data: Default::default(),
+        }
+    }
+}
+
+impl<const D: usize, const A: usize, const WA: usize> Logic for WishboneToSoCBus<D, A, WA> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, state, data);
+        self.bus.clock.next = self.clock.val();
+        // Default values for output signals.
+        self.bus.address.next = self.wishbone.adr.val().get_bits::<A>(0);
+        self.bus.address_strobe.next = false;
+        self.bus.from_controller.next = self.wishbone.dat_w.val().get_bits::<D>(0);
+        self.bus.strobe.next = false;
+        self.wishbone.dat_r.next = bit_cast::<32, D>(self.data.q.val());
+        self.wishbone.ack.next = false;
+        self.wishbone.err.next = false;
+        match self.state.q.val() {
+            WishboneToSoCBusState::Idle => {
+                if self.wishbone.cyc.val() & self.wishbone.stb.val() {
+                    self.bus.address_strobe.next = true;
+                    self.state.d.next = WishboneToSoCBusState::Settle;
+                }
+            }
+            WishboneToSoCBusState::Settle => {
+                // Give the bus a cycle to select the port
+                self.state.d.next = WishboneToSoCBusState::Transfer;
+            }
+            WishboneToSoCBusState::Transfer => {
+                if !self.wishbone.cyc.val() {
+                    // The controller gave up on the transfer
+                    self.state.d.next = WishboneToSoCBusState::Idle;
+                } else if self.bus.ready.val() {
+                    self.bus.strobe.next = true;
+                    if !self.wishbone.we.val() {
+                        self.data.d.next = self.bus.to_controller.val();
+                    }
+                    self.state.d.next = WishboneToSoCBusState::Ack;
+                }
+            }
+            WishboneToSoCBusState::Ack => {
+                self.wishbone.ack.next = true;
+                self.state.d.next = WishboneToSoCBusState::Idle;
+            }
+            _ => {
+                self.state.d.next = WishboneToSoCBusState::Idle;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_wishbone_to_soc_bus_is_synthesizable() {
+    let mut uut = WishboneToSoCBus::<16, 8, 8>::default();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("wishbone_to_soc_bus", &vlog).unwrap();
+}
+
+#[derive(LogicState, Debug, Copy, Clone, PartialEq)]
+enum SoCBusToWishboneState {
+    Idle,
+    WriteReady,
+    WriteCycle,
+    ReadCycle,
+    ReadReady,
+}
+
+// A SoC bus responder that hosts a classic Wishbone responder, so that
+// Wishbone peripherals can be placed in a tree of routers and bridges.
+// The SoC bus has no read/write signal (the direction is a property of
+// the port), so each Wishbone address appears as a pair of SoC bus
+// ports: writes go to SoC address 2*n, and reads come from SoC address
+// 2*n + 1.  Data is zero extended to 32 bits (with all of sel asserted)
+// for writes, and truncated to D bits for reads.
+//
+// As with the other ports, the data for a read must be ready before
+// the controller asks for it.  So the bridge reads the Wishbone address
+// when the read port is selected, and again after each word is taken.
+// This means one word more than requested is read from the peripheral,
+// which matters for registers where a read has side effects.  A
+// Wishbone cycle that is under way when a new port is selected is
+// allowed to finish first.
+#[derive(LogicBlock)]
+pub struct SoCBusToWishbone<const D: usize, const A: usize, const WA: usize> {
+    pub upstream: SoCBusResponder<D, A>,
+    pub wishbone: WishboneController<WA>,
+    pub clock_out: Signal<Out, Clock>,
+    state: DFF<SoCBusToWishboneState>,
+    address: DFF<Bits<WA>>,
+    data: DFF<Bits<32>>,
+    pending: DFF<Bit>,
+    pending_address: DFF<Bits<A>>,
+}
+
+impl<const D: usize, const A: usize, const WA: usize> Default for SoCBusToWishbone<D, A, WA> {
+    fn default() -> Self {
+        assert!(D <= 32);
+        assert!(WA < A);
+        Self {
+            upstream: Default::default(),
+            wishbone: Default::default(),
+            clock_out: Default::default(),
+            state: Default::default(),
+            address: Default::default(),
+            data: Default::default(),
+            pending: Default::default(),
+            pending_address: Default::default(),
+        }
+    }
+}
+
+impl<const D: usize, const A: usize, const WA: usize> HLSNamedPorts for SoCBusToWishbone<D, A, WA> {
+    fn ports(&self) -> Vec<String> {
+        (0..(1_usize << WA))
+            .flat_map(|n| [format!("write_{}", n), format!("read_{}", n)])
+            .collect()
+    }
+}
+
+impl<const D: usize, const A: usize, const WA: usize> Logic for SoCBusToWishbone<D, A, WA> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.clock_out.next = self.upstream.clock.val();
+        dff_setup!(
+            self,
+            clock_out,
+            state,
+            address,
+            data,
+            pending,
+            pending_address
+        );
+        // Default values for output signals.
+        self.upstream.ready.next = false;
+        self.upstream.to_controller.next = self.data.q.val().get_bits::<D>(0);
+        self.wishbone.adr.next = self.address.q.val();
+        self.wishbone.dat_w.next = self.data.q.val();
+        self.wishbone.sel.next = 0xF.into();
+        self.wishbone.we.next = false;
+        self.wishbone.cyc.next = false;
+        self.wishbone.stb.next = false;
+        match self.state.q.val() {
+            SoCBusToWishboneState::Idle => {}
+            SoCBusToWishboneState::WriteReady => {
+                self.upstream.ready.next = !self.pending.q.val();
+                if self.upstream.strobe.val() & !self.pending.q.val() {
+                    self.data.d.next = bit_cast::<32, D>(self.upstream.from_controller.val());
+                    self.state.d.next = SoCBusToWishboneState::WriteCycle;
+                }
+            }
+            SoCBusToWishboneState::WriteCycle => {
+                self.wishbone.we.next = true;
+                self.wishbone.cyc.next = true;
+                self.wishbone.stb.next = true;
+                if self.wishbone.ack.val() | self.wishbone.err.val() {
+                    self.state.d.next = SoCBusToWishboneState::WriteReady;
+                }
+            }
+            SoCBusToWishboneState::ReadCycle => {
+                self.wishbone.cyc.next = true;
+                self.wishbone.stb.next = true;
+                if self.wishbone.ack.val() | self.wishbone.err.val() {
+                    self.data.d.next = self.wishbone.dat_r.val();
+                    self.state.d.next = SoCBusToWishboneState::ReadReady;
+                }
+            }
+            SoCBusToWishboneState::ReadReady => {
+                self.upstream.ready.next = !self.pending.q.val();
+                if self.upstream.strobe.val() & !self.pending.q.val() {
+                    self.state.d.next = SoCBusToWishboneState::ReadCycle;
+                }
+            }
+            _ => {
+                self.state.d.next = SoCBusToWishboneState::Idle;
+            }
+        }
+        // Switch to a newly selected port once the bus is free
+        if self.pending.q.val()
+            & (self.state.q.val() != SoCBusToWishboneState::WriteCycle)
+            & (self.state.q.val() != SoCBusToWishboneState::ReadCycle)
+        {
+            self.pending.d.next = false;
+            self.address.d.next = self.pending_address.q.val().get_bits::<WA>(1);
+            if self.pending_address.q.val().get_bit(0) {
+                self.state.d.next = SoCBusToWishboneState::ReadCycle;
+            } else {
+                self.state.d.next = SoCBusToWishboneState::WriteReady;
+            }
+        }
+        if self.upstream.address_strobe.val() {
+            self.upstream.ready.next = false;
+            self.pending.d.next = true;
+            self.pending_address.d.next = self.upstream.address.val();
+        }
+    }
+}
+
+#[test]
+fn test_soc_bus_to_wishbone_is_synthesizable() {
+    let mut uut = SoCBusToWishbone::<16, 8, 4>::default();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("soc_bus_to_wishbone", &vlog).unwrap();
+}
@@ -0,0 +1,701 @@
+use std::collections::HashMap;
+
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Lit, Meta, NestedMeta, Result};
+
+use crate::common::TS;
+
+// The options given to the `black_box` attribute
+#[derive(Default)]
+struct BlackBoxArgs {
+    file: Option<String>,
+    code: Option<String>,
+    module: Option<String>,
+    model: Option<syn::Path>,
+    clocks: Vec<String>,
+    params: Vec<(String, String)>,
+}
+
+fn string_arg(lit: &Lit) -> Result<String> {
+    match lit {
+        Lit::Str(s) => Ok(s.value()),
+        _ => Err(syn::Error::new(lit.span(), "Expected a string")),
+    }
+}
+
+fn parse_args(args: &[NestedMeta]) -> Result<BlackBoxArgs> {
+    let mut ret = BlackBoxArgs::default();
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(nv)) => {
+                let key = nv
+                    .path
+                    .get_ident()
+                    .map(|x| x.to_string())
+                    .unwrap_or_default();
+                match key.as_str() {
+                    "file" => ret.file = Some(string_arg(&nv.lit)?),
+                    "code" => ret.code = Some(string_arg(&nv.lit)?),
+                    "module" => ret.module = Some(string_arg(&nv.lit)?),
+                    "model" => ret.model = Some(syn::parse_str(&string_arg(&nv.lit)?)?),
+                    _ => return Err(syn::Error::new(nv.span(), "Unknown black box option")),
+                }
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("clocks") => {
+                for clock in &list.nested {
+                    match clock {
+                        NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => {
+                            ret.clocks.push(path.get_ident().unwrap().to_string())
+                        }
+                        _ => return Err(syn::Error::new(clock.span(), "Expected a port name")),
+                    }
+                }
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("params") => {
+                for param in &list.nested {
+                    match param {
+                        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.get_ident().is_some() => {
+                            let value = match &nv.lit {
+                                Lit::Int(x) => x.base10_digits().to_string(),
+                                Lit::Str(x) => x.value(),
+                                _ => {
+                                    return Err(syn::Error::new(
+                                        nv.lit.span(),
+                                        "Expected an integer or a string",
+                                    ))
+                                }
+                            };
+                            ret.params
+                                .push((nv.path.get_ident().unwrap().to_string(), value))
+                        }
+                        _ => {
+                            return Err(syn::Error::new(
+                                param.span(),
+                                "Expected a parameter assignment",
+                            ))
+                        }
+                    }
+                }
+            }
+            _ => return Err(syn::Error::new(arg.span(), "Unknown black box option")),
+        }
+    }
+    if ret.file.is_some() == ret.code.is_some() {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "A black box needs either a file or code (but not both)",
+        ));
+    }
+    Ok(ret)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum PortDirection {
+    Input,
+    Output,
+    InOut,
+}
+
+#[derive(Clone, Debug)]
+struct Port {
+    name: String,
+    direction: PortDirection,
+    width: usize,
+    signed: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct ModuleHeader {
+    name: String,
+    // Parameters that can be set by the instance
+    parameters: Vec<String>,
+    ports: Vec<Port>,
+}
+
+// Split Verilog into tokens.  Comments, attributes and compiler directives are dropped.
+fn tokenize(code: &str) -> Vec<String> {
+    let regex = regex::Regex::new(
+        r#"(?s)//[^\n]*|/\*.*?\*/|\(\*\s*[A-Za-z_][^;]*?\*\)|`[^\n]*|"(?:[^"\\]|\\.)*"|(?:\d[\d_]*)?'[sS]?[bBoOdDhH][0-9a-fA-FxXzZ_?]+|\d[\d_]*|[A-Za-z_$][A-Za-z0-9_$]*|<<|>>|\*\*|\S"#,
+    )
+    .unwrap();
+    regex
+        .find_iter(code)
+        .map(|x| x.as_str())
+        .filter(|x| !x.starts_with("//") && !x.starts_with("/*") && !x.starts_with("(*"))
+        .filter(|x| !x.starts_with('`'))
+        .map(|x| x.to_string())
+        .collect()
+}
+
+fn is_identifier(x: &str) -> bool {
+    x.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+fn number(x: &str) -> std::result::Result<i64, String> {
+    let x = x.replace('_', "");
+    let (radix, digits) = match x.find('\'') {
+        Some(ndx) => {
+            let spec = x[ndx + 1..].trim_start_matches(['s', 'S']);
+            let radix = match spec.chars().next().map(|c| c.to_ascii_lowercase()) {
+                Some('b') => 2,
+                Some('o') => 8,
+                Some('d') => 10,
+                _ => 16,
+            };
+            (radix, spec[1..].to_string())
+        }
+        None => (10, x.clone()),
+    };
+    i64::from_str_radix(&digits, radix).map_err(|_| format!("Cannot evaluate the literal {}", x))
+}
+
+// Evaluates the constant expressions used for the widths of ports (and the values of
+// parameters).  Only integer arithmetic and `$clog2` are supported.
+struct Evaluator<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    values: &'a HashMap<String, i64>,
+}
+
+type Eval = std::result::Result<i64, String>;
+
+impl<'a> Evaluator<'a> {
+    fn eval(tokens: &'a [String], values: &'a HashMap<String, i64>) -> Eval {
+        let mut x = Self {
+            tokens,
+            pos: 0,
+            values,
+        };
+        let value = x.shift()?;
+        if x.pos != tokens.len() {
+            return Err(format!("Cannot evaluate {}", tokens.join(" ")));
+        }
+        Ok(value)
+    }
+    fn peek(&self) -> &str {
+        self.tokens.get(self.pos).map(|x| x.as_str()).unwrap_or("")
+    }
+    fn next(&mut self) -> String {
+        let ret = self.peek().to_string();
+        self.pos += 1;
+        ret
+    }
+    fn expect(&mut self, x: &str) -> std::result::Result<(), String> {
+        if self.next() != x {
+            return Err(format!("Expected {} in {}", x, self.tokens.join(" ")));
+        }
+        Ok(())
+    }
+    fn shift(&mut self) -> Eval {
+        let mut value = self.sum()?;
+        loop {
+            match self.peek() {
+                "<<" => {
+                    self.next();
+                    value <<= self.sum()?
+                }
+                ">>" => {
+                    self.next();
+                    value >>= self.sum()?
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+    fn sum(&mut self) -> Eval {
+        let mut value = self.product()?;
+        loop {
+            match self.peek() {
+                "+" => {
+                    self.next();
+                    value += self.product()?
+                }
+                "-" => {
+                    self.next();
+                    value -= self.product()?
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+    fn product(&mut self) -> Eval {
+        let mut value = self.unary()?;
+        loop {
+            match self.peek() {
+                "*" => {
+                    self.next();
+                    value *= self.unary()?
+                }
+                "/" | "%" => {
+                    let op = self.next();
+                    let divisor = self.unary()?;
+                    if divisor == 0 {
+                        return Err("Division by zero".into());
+                    }
+                    value = if op == "/" {
+                        value / divisor
+                    } else {
+                        value % divisor
+                    };
+                }
+                "**" => {
+                    self.next();
+                    value = value.pow(self.unary()? as u32)
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+    fn unary(&mut self) -> Eval {
+        match self.peek() {
+            "-" => {
+                self.next();
+                Ok(-self.unary()?)
+            }
+            "+" => {
+                self.next();
+                self.unary()
+            }
+            _ => self.primary(),
+        }
+    }
+    fn primary(&mut self) -> Eval {
+        let token = self.next();
+        if token == "(" {
+            let value = self.shift()?;
+            self.expect(")")?;
+            Ok(value)
+        } else if token == "$clog2" {
+            self.expect("(")?;
+            let value = self.shift()?;
+            self.expect(")")?;
+            Ok(64 - (value.max(1) - 1).leading_zeros() as i64)
+        } else if token.starts_with(|c: char| c.is_ascii_digit() || c == '\'') {
+            number(&token)
+        } else if let Some(value) = self.values.get(&token) {
+            Ok(*value)
+        } else {
+            Err(format!("Cannot evaluate {}", token))
+        }
+    }
+}
+
+// Parses the header of a Verilog module (in either the ANSI or the older style), and
+// works out the widths of its ports, given the values of its parameters.
+struct HeaderParser {
+    tokens: Vec<String>,
+    pos: usize,
+    values: HashMap<String, i64>,
+    overrides: HashMap<String, String>,
+    header: ModuleHeader,
+}
+
+type Parse<T> = std::result::Result<T, String>;
+
+impl HeaderParser {
+    fn peek(&self) -> &str {
+        self.tokens.get(self.pos).map(|x| x.as_str()).unwrap_or("")
+    }
+    fn next(&mut self) -> Parse<String> {
+        match self.tokens.get(self.pos) {
+            Some(x) => {
+                self.pos += 1;
+                Ok(x.clone())
+            }
+            None => Err(format!("Unexpected end of module {}", self.header.name)),
+        }
+    }
+    fn expect(&mut self, x: &str) -> Parse<()> {
+        let token = self.next()?;
+        if token != x {
+            return Err(format!("Expected {} but found {}", x, token));
+        }
+        Ok(())
+    }
+    fn eval(&self, tokens: &[String]) -> Parse<i64> {
+        Evaluator::eval(tokens, &self.values)
+    }
+    // Collect the tokens of an expression, up to (but not including) one of the terminators
+    fn expression(&mut self, terminators: &[&str]) -> Parse<Vec<String>> {
+        let mut depth = 0;
+        let mut ret = vec![];
+        loop {
+            let token = self.peek();
+            if depth == 0 && terminators.contains(&token) {
+                return Ok(ret);
+            }
+            match token {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth -= 1,
+                _ => {}
+            }
+            ret.push(self.next()?);
+        }
+    }
+    // `parameter [type] NAME = value` (the keyword may be missing in a parameter port list)
+    fn parameter(&mut self, local: bool, terminators: &[&str]) -> Parse<()> {
+        loop {
+            while [
+                "integer", "int", "signed", "unsigned", "logic", "bit", "reg",
+            ]
+            .contains(&self.peek())
+            {
+                self.next()?;
+            }
+            if self.peek() == "[" {
+                self.expression(&["]"])?;
+                self.expect("]")?;
+            }
+            let name = self.next()?;
+            self.expect("=")?;
+            let value = self.expression(terminators)?;
+            let value = match self.overrides.get(&name) {
+                Some(x) if !local => tokenize(x),
+                _ => value,
+            };
+            let value = self.eval(&value)?;
+            self.values.insert(name.clone(), value);
+            if !local {
+                self.header.parameters.push(name);
+            }
+            // A list of parameters may follow the keyword
+            if self.peek() == ","
+                && is_identifier(
+                    self.tokens
+                        .get(self.pos + 1)
+                        .map(|x| x.as_str())
+                        .unwrap_or(""),
+                )
+                && !["parameter", "localparam"].contains(&self.tokens[self.pos + 1].as_str())
+                && self.tokens.get(self.pos + 2).map(|x| x.as_str()) == Some("=")
+            {
+                self.next()?;
+                continue;
+            }
+            return Ok(());
+        }
+    }
+    // `[direction] [net type] [signed] [range] name`, where anything that is missing is
+    // taken from the previous declaration
+    fn port(&mut self, previous: &Option<Port>, terminators: &[&str]) -> Parse<Port> {
+        let mut port = previous.clone();
+        let mut fresh = false;
+        if let Some(direction) = match self.peek() {
+            "input" => Some(PortDirection::Input),
+            "output" => Some(PortDirection::Output),
+            "inout" => Some(PortDirection::InOut),
+            _ => None,
+        } {
+            self.next()?;
+            fresh = true;
+            port = Some(Port {
+                name: String::new(),
+                direction,
+                width: 1,
+                signed: false,
+            });
+        }
+        let Some(mut port) = port else {
+            return Err(format!("Port {} has no direction", self.peek()));
+        };
+        while [
+            "wire", "reg", "logic", "var", "tri", "bit", "signed", "unsigned",
+        ]
+        .contains(&self.peek())
+        {
+            let kind = self.next()?;
+            if !fresh {
+                // A new type starts a new declaration (without a direction)
+                return Err(format!("Port declaration {} has no direction", kind));
+            }
+            if kind == "signed" {
+                port.signed = true;
+            }
+        }
+        if self.peek() == "[" {
+            self.next()?;
+            let msb = self.expression(&[":"])?;
+            self.expect(":")?;
+            let lsb = self.expression(&["]"])?;
+            self.expect("]")?;
+            port.width = ((self.eval(&msb)? - self.eval(&lsb)?).abs() + 1) as usize;
+        }
+        port.name = self.next()?;
+        if !is_identifier(&port.name) {
+            return Err(format!("Expected a port name, but found {}", port.name));
+        }
+        if !terminators.contains(&self.peek()) {
+            return Err(format!(
+                "Unsupported declaration of port {} (arrays are not supported)",
+                port.name
+            ));
+        }
+        Ok(port)
+    }
+    fn parse(mut self, module: Option<&str>) -> Parse<ModuleHeader> {
+        // Find the module
+        loop {
+            if self.pos >= self.tokens.len() {
+                return Err(match module {
+                    Some(name) => format!("No module named {} was found", name),
+                    None => "No module was found".into(),
+                });
+            }
+            if self.tokens[self.pos] == "module"
+                && module
+                    .map(|x| self.tokens.get(self.pos + 1) == Some(&x.to_string()))
+                    .unwrap_or(true)
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.next()?;
+        self.header.name = self.next()?;
+        // Parameter port list
+        if self.peek() == "#" {
+            self.next()?;
+            self.expect("(")?;
+            while self.peek() != ")" {
+                if self.peek() == "parameter" {
+                    self.next()?;
+                }
+                self.parameter(false, &[",", ")"])?;
+                if self.peek() == "," {
+                    self.next()?;
+                }
+            }
+            self.expect(")")?;
+        }
+        // Port list
+        let mut names = vec![];
+        if self.peek() == "(" {
+            self.next()?;
+            let mut previous = None;
+            while self.peek() != ")" {
+                if is_identifier(self.peek())
+                    && previous.is_none()
+                    && !["input", "output", "inout"].contains(&self.peek())
+                {
+                    // Ports that are declared in the body of the module
+                    names.push(self.next()?);
+                } else {
+                    let port = self.port(&previous, &[",", ")"])?;
+                    self.header.ports.push(port.clone());
+                    previous = Some(port);
+                }
+                if self.peek() == "," {
+                    self.next()?;
+                }
+            }
+            self.expect(")")?;
+        }
+        self.expect(";")?;
+        // The body of the module (for the older style of port declarations)
+        let mut body_ports = vec![];
+        while self.peek() != "endmodule" {
+            match self.peek() {
+                "parameter" | "localparam" => {
+                    let local = self.next()? == "localparam";
+                    self.parameter(local, &[";", ","])?;
+                    self.expect(";")?;
+                }
+                "function" | "task" => {
+                    // The arguments of functions and tasks are not ports
+                    let end = format!("end{}", self.next()?);
+                    while self.next()? != end {}
+                }
+                "input" | "output" | "inout" => {
+                    let mut previous = None;
+                    loop {
+                        let port = self.port(&previous, &[",", ";"])?;
+                        body_ports.push(port.clone());
+                        previous = Some(port);
+                        if self.next()? == ";" {
+                            break;
+                        }
+                    }
+                }
+                _ => {
+                    self.expression(&[";", "endmodule"])?;
+                    if self.peek() == ";" {
+                        self.next()?;
+                    }
+                }
+            }
+        }
+        for name in names {
+            match body_ports.iter().find(|x| x.name == name) {
+                Some(port) => self.header.ports.push(port.clone()),
+                None => return Err(format!("Port {} is not declared", name)),
+            }
+        }
+        for name in self.overrides.keys() {
+            if !self.header.parameters.contains(name) {
+                return Err(format!(
+                    "Module {} has no parameter {}",
+                    self.header.name, name
+                ));
+            }
+        }
+        Ok(self.header)
+    }
+}
+
+fn parse_header(
+    code: &str,
+    module: Option<&str>,
+    overrides: &[(String, String)],
+) -> Parse<ModuleHeader> {
+    HeaderParser {
+        tokens: tokenize(code),
+        pos: 0,
+        values: Default::default(),
+        overrides: overrides.iter().cloned().collect(),
+        header: Default::default(),
+    }
+    .parse(module)
+}
+
+pub(crate) fn black_box_process(args: Vec<NestedMeta>, item: syn::ItemStruct) -> Result<TS> {
+    let args = parse_args(&args)?;
+    if !item.fields.is_empty() || !item.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            item.span(),
+            "The fields of a black box come from the Verilog module, so the struct must be empty",
+        ));
+    }
+    let (code, code_ts) = match (&args.file, &args.code) {
+        (Some(file), _) => {
+            let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+            let path = std::path::Path::new(&root).join(file);
+            let code = std::fs::read_to_string(&path).map_err(|e| {
+                syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("Cannot read {}: {}", path.display(), e),
+                )
+            })?;
+            // Using include_str! means the crate is rebuilt when the file changes
+            (
+                code,
+                quote!(include_str!(
+                    concat!(env!("CARGO_MANIFEST_DIR"), "/", #file)
+                )),
+            )
+        }
+        (_, Some(code)) => (code.clone(), quote!(#code)),
+        _ => unreachable!(),
+    };
+    let header = parse_header(&code, args.module.as_deref(), &args.params)
+        .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), e))?;
+    for clock in &args.clocks {
+        match header.ports.iter().find(|x| &x.name == clock) {
+            Some(port) if port.direction != PortDirection::InOut && port.width == 1 => {}
+            _ => {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("Clock {} must be a single bit port", clock),
+                ))
+            }
+        }
+    }
+    let mut fields = vec![];
+    let mut driven = vec![];
+    for port in &header.ports {
+        if syn::parse_str::<syn::Ident>(&port.name).is_err() {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!(
+                    "The port {} cannot be used as the name of a field",
+                    port.name
+                ),
+            ));
+        }
+        let name = format_ident!("{}", port.name);
+        let direction = match port.direction {
+            PortDirection::Input => quote!(In),
+            PortDirection::Output => {
+                driven.push(name.clone());
+                quote!(Out)
+            }
+            PortDirection::InOut => {
+                driven.push(name.clone());
+                quote!(InOut)
+            }
+        };
+        let width = port.width;
+        let kind = if args.clocks.contains(&port.name) {
+            quote!(Clock)
+        } else if port.signed {
+            quote!(Signed<#width>)
+        } else if width == 1 {
+            quote!(Bit)
+        } else {
+            quote!(Bits<#width>)
+        };
+        let doc = format!("The `{}` port of the `{}` module", port.name, header.name);
+        fields.push(quote! {
+            #[doc = #doc]
+            pub #name: Signal<#direction, #kind>
+        });
+    }
+    let module = &header.name;
+    // Parameters are set by a wrapper around the module, which keeps different
+    // settings of the same module apart
+    let hdl = if args.params.is_empty() {
+        quote! {
+            Verilog::Blackbox(BlackBox {
+                code: #code_ts.into(),
+                name: #module.into(),
+            })
+        }
+    } else {
+        let params = args
+            .params
+            .iter()
+            .map(|(name, value)| format!(".{}({})", name, value))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let ports = header
+            .ports
+            .iter()
+            .map(|x| format!(".{0}({0})", x.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let wrapper = format!("{} #({}) {}_inst({});", module, params, module, ports);
+        quote! {
+            Verilog::Wrapper(Wrapper {
+                code: #wrapper.into(),
+                cores: #code_ts.into(),
+            })
+        }
+    };
+    let update = match &args.model {
+        Some(model) => quote!(#model(self);),
+        None => quote!(),
+    };
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let name = &item.ident;
+    Ok(quote! {
+        #(#attrs)*
+        #[derive(LogicBlock, Default)]
+        #[allow(non_snake_case)]
+        #vis struct #name {
+            #(#fields,)*
+        }
+
+        impl Logic for #name {
+            fn update(&mut self) {
+                #update
+            }
+            fn connect(&mut self) {
+                #(self.#driven.connect();)*
+            }
+            fn hdl(&self) -> Verilog {
+                #hdl
+            }
+        }
+    })
+}
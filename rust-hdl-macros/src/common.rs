@@ -98,6 +98,62 @@ pub fn get_update_all(fields: Vec<TS>) -> syn::Result<TS> {
     })
 }
 
+pub fn get_update_sensitive(fields: Vec<TS>) -> syn::Result<TS> {
+    let field_names = fields.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    let count = fields.len();
+    let indices = 0..count;
+    // The fields are evaluated in the order given by the sensitivity map
+    let schedule = if fields.is_empty() {
+        quote! {
+            let changed = false;
+        }
+    } else {
+        quote! {
+            let mut dirty = evaluated;
+            let mut changed = [false; #count];
+            for step in 0..#count {
+                let field = sens.field(id, step);
+                if sens.refresh(id, field, &changed) {
+                    self.update();
+                    dirty = true;
+                }
+                changed[field] = match field {
+                    #(#indices => self.#fields.update_sensitive(sens, dirty),)*
+                    _ => unreachable!(),
+                };
+            }
+            let changed = changed.iter().any(|x| *x);
+        }
+    };
+    Ok(quote! {
+        fn update_sensitive(&mut self, sens: &mut sensitivity::Sensitivity, dirty: bool) -> bool {
+            match sens.enter(dirty) {
+                None => false,
+                Some(id) => {
+                    if sens.is_combinational(id) {
+                        #(self.#fields.latch_inputs();)*
+                    }
+                    let evaluated = sens.evaluate(id, &[#(self.#fields.ports_changed()),*], &[#(#field_names),*]);
+                    if evaluated {
+                        self.update();
+                    }
+                    #schedule
+                    sens.leave(id, changed);
+                    changed
+                }
+            }
+        }
+
+        fn ports_changed(&self) -> bool {
+            false #(|| self.#fields.atoms_changed())*
+        }
+
+        fn atoms_changed(&self) -> bool {
+            false
+        }
+    })
+}
+
 pub fn get_has_changed(fields: Vec<TS>) -> syn::Result<TS> {
     if fields.is_empty() {
         Ok(quote! {
@@ -158,3 +214,37 @@ impl Parse for DFFSetupArgs {
         Ok(DFFSetupArgs { me, clock, dffs })
     }
 }
+
+// The dff_setup_with_reset macro uses clock, reset, dfflist arguments
+#[derive(Debug)]
+pub struct DFFResetSetupArgs {
+    pub me: Expr,
+    pub clock: Expr,
+    pub reset: Expr,
+    pub dffs: Vec<Expr>,
+}
+
+impl Parse for DFFResetSetupArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let me: Expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let clock: Expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let reset: Expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let mut dffs = Vec::new();
+        while !input.is_empty() {
+            let dff_name: Expr = input.parse()?;
+            dffs.push(dff_name);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(DFFResetSetupArgs {
+            me,
+            clock,
+            reset,
+            dffs,
+        })
+    }
+}
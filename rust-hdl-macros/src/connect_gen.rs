@@ -1,4 +1,4 @@
-use crate::common::{DFFSetupArgs, TS};
+use crate::common::{DFFResetSetupArgs, DFFSetupArgs, TS};
 use quote::quote;
 use std::ops::Index;
 use syn::spanned::Spanned;
@@ -25,9 +25,11 @@ fn connect_statement(statement: &syn::Stmt) -> Result<TS> {
     match statement {
         syn::Stmt::Expr(e) => connect_inner_statement(e),
         syn::Stmt::Semi(e, _) => connect_inner_statement(e),
+        // Let bindings do not drive any signals
+        syn::Stmt::Local(_) => Ok(TS::new()),
         _ => Err(syn::Error::new(
             statement.span(),
-            "Local definitions and items are not allowed in HDL kernels",
+            "Items are not allowed in HDL kernels",
         )),
     }
 }
@@ -77,6 +79,10 @@ fn connect_call(node: &syn::ExprCall) -> Result<TS> {
 }
 
 fn get_base_of_next(expr: &Expr) -> Result<TS> {
+    // An indexed write (x.next[n] = ...) drives all of x
+    if let Expr::Index(ndx) = expr {
+        return get_base_of_next(&ndx.expr);
+    }
     if let Expr::Field(field) = expr {
         if let Member::Named(nxt) = &field.member {
             if nxt.eq("next") {
@@ -147,6 +153,17 @@ fn connect_macro(m: &syn::ExprMacro) -> Result<TS> {
                     logic::logic_connect_fn(&mut #me.#dff.d);
                 )*
             })
+        } else if macro_name == "dff_setup_with_reset" {
+            let args: DFFResetSetupArgs = m.mac.parse_body()?;
+            let me = &args.me;
+            let dff = &args.dffs;
+            Ok(quote! {
+                #(
+                    logic::logic_connect_fn(&mut #me.#dff.clock);
+                    logic::logic_connect_fn(&mut #me.#dff.reset);
+                    logic::logic_connect_fn(&mut #me.#dff.d);
+                )*
+            })
         } else if macro_name == "clock" {
             let args: DFFSetupArgs = m.mac.parse_body()?;
             let me = &args.me;
@@ -1,14 +1,69 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ops::Index;
 
 use quote::format_ident;
 use quote::quote;
+use quote::quote_spanned;
 use syn::spanned::Spanned;
 use syn::{BinOp, Expr, Pat, PathSegment, Result, Stmt, UnOp};
 
 use crate::common;
-use crate::common::{squash, DFFSetupArgs, TS};
+use crate::common::{squash, DFFResetSetupArgs, DFFSetupArgs, TS};
+
+// The let bindings that are visible at the current point in the kernel.  Each binding
+// is given a unique (per kernel) name in the generated code.  These start with an
+// underscore, so they cannot clash with the name of a signal.
+#[derive(Default)]
+struct LetBindings {
+    scopes: Vec<Vec<(String, String)>>,
+    counts: HashMap<String, usize>,
+}
+
+thread_local! {
+    static LET_BINDINGS: RefCell<LetBindings> = RefCell::new(LetBindings::default());
+}
+
+fn let_binding(ident: &str) -> Option<String> {
+    LET_BINDINGS.with(|x| {
+        x.borrow()
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(rust, _)| rust == ident)
+            .map(|(_, name)| name.clone())
+    })
+}
+
+fn bind_let(ident: &str) -> String {
+    LET_BINDINGS.with(|x| {
+        let mut x = x.borrow_mut();
+        let count = x.counts.entry(ident.to_string()).or_default();
+        let name = if *count == 0 {
+            format!("_{}", ident)
+        } else {
+            format!("_{}${}", ident, count)
+        };
+        *count += 1;
+        if let Some(scope) = x.scopes.last_mut() {
+            scope.push((ident.to_string(), name.clone()));
+        }
+        name
+    })
+}
+
+fn let_bound_path(expr: &syn::Expr) -> Option<String> {
+    if let Expr::Path(p) = expr {
+        if let Some(ident) = p.path.get_ident() {
+            return let_binding(&ident.to_string());
+        }
+    }
+    None
+}
 
 pub(crate) fn hdl_gen_process(item: syn::ItemFn) -> Result<TS> {
+    LET_BINDINGS.with(|x| *x.borrow_mut() = LetBindings::default());
     let signature = &item.sig;
     if signature.inputs.len() != 1 {
         return Err(syn::Error::new(
@@ -25,28 +80,349 @@ pub(crate) fn hdl_gen_process(item: syn::ItemFn) -> Result<TS> {
 }
 
 fn hdl_block(block: &syn::Block) -> Result<TS> {
-    let mut stmt = vec![];
-    for statement in &block.stmts {
-        stmt.push(hdl_statement(statement)?);
+    hdl_block_with_result(block, None)
+}
+
+// If a result is given, the block is the body of an HDL function (or one of its branches),
+// and the value of the block is assigned to the result.
+fn hdl_block_with_result(block: &syn::Block, result: Option<&TS>) -> Result<TS> {
+    if result.is_some() && !matches!(block.stmts.last(), Some(Stmt::Expr(_))) {
+        return Err(syn::Error::new(
+            block.span(),
+            "The body of an HDL function must end with an expression",
+        ));
     }
+    LET_BINDINGS.with(|x| x.borrow_mut().scopes.push(vec![]));
+    let stmt = block
+        .stmts
+        .iter()
+        .enumerate()
+        .map(|(ndx, statement)| {
+            let source = hdl_source(statement.span());
+            let stmt = match (statement, result) {
+                (Stmt::Local(local), _) => hdl_let(local)?,
+                (Stmt::Expr(e), Some(result)) if ndx + 1 == block.stmts.len() => {
+                    let stmt = hdl_result(e, result)?;
+                    quote!(ret.push(#stmt);)
+                }
+                _ => {
+                    let stmt = hdl_statement(statement)?;
+                    quote!(ret.push(#stmt);)
+                }
+            };
+            Ok(quote!(#source #stmt))
+        })
+        .collect::<Result<Vec<_>>>();
+    LET_BINDINGS.with(|x| x.borrow_mut().scopes.pop());
+    let stmt = stmt?;
     Ok(quote! {
     {
         let mut ret = vec![];
-        #(ret.push(#stmt));*;
+        #(#stmt)*
         ret
     }
     })
 }
 
+// Records the line of the kernel that the following statements come from.  The
+// `line!()` and `file!()` macros are given the span of the statement, so they expand
+// to its location (and not that of the `#[hdl_gen]` attribute).
+fn hdl_source(span: proc_macro2::Span) -> TS {
+    let file = quote_spanned!(span=> ::std::file!());
+    let line = quote_spanned!(span=> ::std::line!());
+    quote!(ret.push(ast::VerilogStatement::Source(ast::SourceLocation::new(#file, #line)));)
+}
+
 fn hdl_statement(statement: &syn::Stmt) -> Result<TS> {
     match statement {
         Stmt::Expr(e) => hdl_inner_statement(e),
         Stmt::Semi(e, _) => hdl_inner_statement(e),
         _ => Err(syn::Error::new(
             statement.span(),
-            "Local definitions and items are not allowed in HDL kernels",
+            "Items are not allowed in HDL kernels",
+        )),
+    }
+}
+
+// A let binding is mapped to a local signal in the generated code.  The binding is also
+// made in the generated Rust code, so that the type (and hence the width) of the local
+// signal is known.  The value bound in the Rust code is a default, so that the
+// initializer is never evaluated when the HDL is generated.
+fn hdl_let(local: &syn::Local) -> Result<TS> {
+    let (pat, ty) = match &local.pat {
+        Pat::Type(x) => (x.pat.as_ref(), Some(&x.ty)),
+        x => (x, None),
+    };
+    let ident = match pat {
+        Pat::Ident(x) if x.by_ref.is_none() && x.subpat.is_none() => x,
+        _ => {
+            return Err(syn::Error::new(
+                local.pat.span(),
+                "Only simple let bindings (e.g., let x = <expr>;) are supported in HDL kernels",
+            ))
+        }
+    };
+    if ident.mutability.is_some() {
+        return Err(syn::Error::new(
+            local.pat.span(),
+            "Mutable let bindings are not supported in HDL kernels (shadow the binding instead)",
+        ));
+    }
+    let init = match &local.init {
+        Some((_, init)) => init,
+        None => {
+            return Err(syn::Error::new(
+                local.span(),
+                "Let bindings in HDL kernels must be initialized where they are declared, or they would infer a latch",
+            ))
+        }
+    };
+    let binding = match ty {
+        Some(ty) => quote!(let #ident: #ty = synth::Synth::default_value();),
+        None => quote!(let #ident = if true { synth::Synth::default_value() } else { #init };),
+    };
+    // The value is computed before the binding is made, since it may refer to a
+    // binding that it shadows
+    let value = hdl_compute(init)?;
+    let name = bind_let(&ident.ident.to_string());
+    Ok(quote! {
+        let hdl_let_value = #value;
+        #[allow(unused_variables)]
+        #binding
+        ret.push(ast::VerilogStatement::Let(ast::VerilogLet::new(&#ident, #name, hdl_let_value)));
+    })
+}
+
+pub(crate) fn hdl_function_process(item: &syn::ItemFn) -> Result<TS> {
+    LET_BINDINGS.with(|x| *x.borrow_mut() = LetBindings::default());
+    let signature = &item.sig;
+    if signature.receiver().is_some() {
+        return Err(syn::Error::new(
+            signature.span(),
+            "HDL functions cannot take self as an argument",
+        ));
+    }
+    let ret = match &signature.output {
+        syn::ReturnType::Type(_, ty) => ty,
+        syn::ReturnType::Default => {
+            return Err(syn::Error::new(
+                signature.span(),
+                "HDL functions must return a value",
+            ))
+        }
+    };
+    let mut consts = vec![];
+    for param in &signature.generics.params {
+        match param {
+            syn::GenericParam::Const(x) => consts.push(x.ident.clone()),
+            _ => {
+                return Err(syn::Error::new(
+                    param.span(),
+                    "HDL functions can only be generic over constants (e.g., const N: usize)",
+                ))
+            }
+        }
+    }
+    let mut args = vec![];
+    let mut types = vec![];
+    for arg in &signature.inputs {
+        match arg {
+            syn::FnArg::Typed(x) => match x.pat.as_ref() {
+                Pat::Ident(ident) if ident.by_ref.is_none() && ident.subpat.is_none() => {
+                    args.push(ident.ident.clone());
+                    types.push(&x.ty);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        x.span(),
+                        "HDL function arguments must be simple (e.g., x: Bits<8>)",
+                    ))
+                }
+            },
+            _ => {
+                return Err(syn::Error::new(
+                    arg.span(),
+                    "HDL functions cannot take self as an argument",
+                ))
+            }
+        }
+    }
+    let arg_names = args.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    // The arguments are bound (like a let) to the inputs of the function, so that
+    // the fields of struct-valued arguments can be accessed
+    LET_BINDINGS.with(|x| {
+        x.borrow_mut()
+            .scopes
+            .push(arg_names.iter().map(|x| (x.clone(), x.clone())).collect())
+    });
+    let name = signature.ident.to_string();
+    // Each instance of a generic function is a separate function in the generated code
+    let name = if consts.is_empty() {
+        quote!(#name.to_string())
+    } else {
+        quote!(format!("{}${}", #name, [#(#consts.to_string()),*].join("$")))
+    };
+    let result = quote!(ast::VerilogExpression::Signal(hdl_function_name.clone()));
+    let body = hdl_block_with_result(&item.block, Some(&result))?;
+    let vis = &item.vis;
+    let hdl_name = format_ident!("{}_hdl", signature.ident);
+    let (impl_generics, _, where_clause) = signature.generics.split_for_impl();
+    Ok(quote! {
+        #vis fn #hdl_name #impl_generics (#(#args: #types),*) -> ast::VerilogFunction #where_clause {
+            let hdl_function_name: String = #name;
+            let block = #body;
+            ast::VerilogFunction::new::<#ret>(
+                &hdl_function_name,
+                vec![#(ast::VerilogFunctionArg::new(&#args, #arg_names)),*],
+                block,
+            )
+        }
+    })
+}
+
+// The value of an HDL function (or one of its branches) is assigned to the result
+fn hdl_result(expr: &syn::Expr, result: &TS) -> Result<TS> {
+    match expr {
+        Expr::If(x) => {
+            let test = hdl_compute(&x.cond)?;
+            let then = hdl_block_with_result(&x.then_branch, Some(result))?;
+            let otherwise = match &x.else_branch {
+                Some((_, e)) => match e.as_ref() {
+                    Expr::Block(b) => {
+                        let block = hdl_block_with_result(&b.block, Some(result))?;
+                        quote!(ast::VerilogBlockOrConditional::Block(#block))
+                    }
+                    Expr::If(_) => {
+                        let cond = hdl_result(e, result)?;
+                        quote!(ast::VerilogBlockOrConditional::Conditional(Box::new(#cond)))
+                    }
+                    _ => {
+                        return Err(syn::Error::new(x.span(), "Unsupported if/else structure"));
+                    }
+                },
+                None => {
+                    return Err(syn::Error::new(
+                        x.span(),
+                        "An if expression in an HDL function needs an else branch",
+                    ))
+                }
+            };
+            Ok(quote!({
+                ast::VerilogStatement::If(ast::VerilogConditional{test: #test, then: #then, otherwise: #otherwise})
+            }))
+        }
+        Expr::Match(m) => {
+            let test = hdl_compute(m.expr.as_ref())?;
+            let mut condition = vec![];
+            let mut blocks = vec![];
+            for arm in &m.arms {
+                condition.push(hdl_pattern(&arm.pat)?);
+                blocks.push(match arm.body.as_ref() {
+                    Expr::Block(b) => hdl_block_with_result(&b.block, Some(result))?,
+                    body => {
+                        let statement = hdl_result(body, result)?;
+                        quote!({ vec![#statement] })
+                    }
+                });
+            }
+            Ok(quote!({
+               {
+                  let mut cases = vec![];
+                  #(cases.push(ast::VerilogCase{condition: #condition.to_string(), block: #blocks}));*;
+                  ast::VerilogStatement::Match(ast::VerilogMatch{test: #test, cases: cases})
+               }
+            }))
+        }
+        Expr::Return(_) => Err(syn::Error::new(
+            expr.span(),
+            "HDL functions must return their value as the final expression (not with return)",
         )),
+        _ => {
+            let value = hdl_compute(expr)?;
+            Ok(quote!(ast::VerilogStatement::Assignment(#result, #value)))
+        }
+    }
+}
+
+// The calls that can have a companion: a free function (which is snake_case, as is
+// the path to it), or the constructor of a variant of a tagged union (Type::Variant).
+// Anything else (like an associated function of a type, or a trait method) cannot be
+// an #[hdl_function], and is not supported.
+fn hdl_function_path(call: &syn::ExprCall) -> Option<&syn::ExprPath> {
+    let path = match call.func.as_ref() {
+        Expr::Path(path) if path.qself.is_none() => path,
+        _ => return None,
+    };
+    let is_type = |x: &syn::PathSegment| x.ident.to_string().starts_with(char::is_uppercase);
+    let segments = &path.path.segments;
+    if !segments.iter().any(is_type) {
+        return Some(path);
+    }
+    let n = segments.len();
+    if n >= 2 && is_type(&segments[n - 2]) && is_type(&segments[n - 1]) {
+        return Some(path);
+    }
+    None
+}
+
+// A call to an #[hdl_function].  The definition of the function comes from the companion
+// <function>_hdl, which is called with placeholder arguments of the right types.
+fn hdl_function_call(call: &syn::ExprCall, path: &syn::ExprPath) -> Result<TS> {
+    let mut hdl_path = path.clone();
+    if let Some(last) = hdl_path.path.segments.last_mut() {
+        // If the companion is missing, the error should point at the function called
+        last.ident = format_ident!("{}_hdl", last.ident, span = last.ident.span());
+    }
+    let placeholders = call
+        .args
+        .iter()
+        .map(|x| quote!(if true { synth::Synth::default_value() } else { #x }))
+        .collect::<Vec<_>>();
+    let args = call
+        .args
+        .iter()
+        .map(hdl_compute)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(quote!({
+        ast::VerilogExpression::Call(ast::VerilogCall {
+            function: Box::new(#hdl_path(#(#placeholders),*)),
+            args: vec![#(#args),*],
+        })
+    }))
+}
+
+// A variant of a tagged union with named fields is built by a constructor function
+// (like a tuple variant), with the fields given by name.
+fn hdl_struct_literal(x: &syn::ExprStruct) -> Result<TS> {
+    if let Some(rest) = &x.rest {
+        return Err(syn::Error::new(
+            rest.span(),
+            "Struct update syntax is not supported in HDL kernels",
+        ));
+    }
+    let mut hdl_path = x.path.clone();
+    if let Some(last) = hdl_path.segments.last_mut() {
+        last.ident = format_ident!("{}_hdl", last.ident);
     }
+    let names = x
+        .fields
+        .iter()
+        .map(|field| match &field.member {
+            syn::Member::Named(x) => x.to_string(),
+            syn::Member::Unnamed(x) => format!("_{}", x.index),
+        })
+        .collect::<Vec<_>>();
+    let values = x
+        .fields
+        .iter()
+        .map(|field| hdl_compute(&field.expr))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(quote!({
+        ast::VerilogExpression::Call(ast::VerilogCall::named(
+            #hdl_path(),
+            vec![#((#names, #values)),*],
+        ))
+    }))
 }
 
 fn hdl_for_loop(expr: &syn::ExprForLoop) -> Result<TS> {
@@ -55,14 +431,20 @@ fn hdl_for_loop(expr: &syn::ExprForLoop) -> Result<TS> {
             if let Some(from) = range.from.as_ref() {
                 if let Some(to) = range.to.as_ref() {
                     let block = hdl_block(&expr.body)?;
-                    let loop_index = quote!(#loop_index).to_string();
+                    let index = quote!(#loop_index).to_string();
+                    // The loop index is bound (to the first value) so that the types of
+                    // any let bindings in the loop body can be inferred.
                     return Ok(quote!(
                         ast::VerilogStatement::Loop(
                             ast::VerilogLoop {
-                            index: #loop_index.into(),
+                            index: #index.into(),
                             from: #from.into(),
                             to: #to.into(),
-                            block: #block,
+                            block: {
+                                #[allow(unused_variables)]
+                                let #loop_index = #from;
+                                #block
+                            },
                         }
                     )));
                 }
@@ -92,16 +474,66 @@ fn hdl_inner_statement(expr: &syn::Expr) -> Result<TS> {
 }
 
 fn hdl_assignment(expr: &syn::ExprAssign) -> Result<TS> {
-    if let syn::Expr::Index(_) = *expr.left {
-        Err(syn::Error::new(
-            expr.span(),
-            "Indexed assignments do not translate",
-        ))
+    if let syn::Expr::Index(ndx) = expr.left.as_ref() {
+        if !is_array_value(&ndx.expr) {
+            return Err(syn::Error::new(
+                expr.span(),
+                "Indexed assignments do not translate",
+            ));
+        }
+        let element = hdl_array_element(ndx, true)?;
+        let value = hdl_compute(expr.right.as_ref())?;
+        Ok(quote!({
+            ast::VerilogStatement::ElementAssignment(#element, #value)
+        }))
     } else {
         hdl_non_indexed_assignment(expr)
     }
 }
 
+// Indexing a value (e.g., `x.val()[n]` or `x.next[n]`) picks an element of an array.
+// Anything else (e.g., `self.foo[n]`) refers to a block in a Vec of blocks.
+fn is_array_value(expr: &Expr) -> bool {
+    match expr {
+        Expr::MethodCall(_) => true,
+        Expr::Field(field) => matches!(&field.member, syn::Member::Named(x) if x == "next"),
+        Expr::Index(ndx) => is_array_value(&ndx.expr),
+        Expr::Paren(paren) => is_array_value(&paren.expr),
+        _ => false,
+    }
+}
+
+// Nested indexing (e.g., `x.val()[i][j]`) collects the indices of a single element,
+// outermost first, each with the width of the element it picks.
+fn hdl_array_indices(ndx: &syn::ExprIndex, assign: bool) -> Result<(TS, Vec<TS>)> {
+    let array = &ndx.expr;
+    let width = quote!(ast::array_element_bits(|| #array));
+    let index = hdl_compute(&ndx.index)?;
+    let index = quote!((#index, #width));
+    match array.as_ref() {
+        Expr::Index(inner) if is_array_value(&inner.expr) => {
+            let (base, mut indices) = hdl_array_indices(inner, assign)?;
+            indices.push(index);
+            Ok((base, indices))
+        }
+        // As with struct fields, the write goes to the signal that holds `.next`
+        Expr::Field(field) if assign => Ok((hdl_compute(&field.base)?, vec![index])),
+        _ if assign => Err(syn::Error::new(
+            ndx.span(),
+            "Indexed assignments should be of the form <signal>.next[index] = <value>",
+        )),
+        _ => Ok((hdl_compute(array)?, vec![index])),
+    }
+}
+
+fn hdl_array_element(ndx: &syn::ExprIndex, assign: bool) -> Result<TS> {
+    let (array, indices) = hdl_array_indices(ndx, assign)?;
+    Ok(quote!(ast::VerilogElement {
+        array: #array,
+        indices: vec![#(#indices),*],
+    }))
+}
+
 fn hdl_non_indexed_assignment(expr: &syn::ExprAssign) -> Result<TS> {
     let target;
     if let Expr::Field(p) = &*expr.left {
@@ -169,7 +601,9 @@ fn hdl_map_field_assign(expr: &syn::ExprField) -> Result<TS> {
 fn hdl_map_field(expr: &syn::ExprField) -> Result<TS> {
     // Check for .val().field - as this indicates a struct membership
     let base = &expr.base;
-    if common::fixup_ident(quote!(#base).to_string()).ends_with("val()") {
+    if common::fixup_ident(quote!(#base).to_string()).ends_with("val()")
+        || let_bound_path(base).is_some()
+    {
         return if let syn::Member::Named(x) = &expr.member {
             let field = x.to_string();
             let get_width_name = format_ident!("get_my_width_{}", field);
@@ -195,6 +629,11 @@ fn hdl_map_field(expr: &syn::ExprField) -> Result<TS> {
 }
 
 fn hdl_map_path(expr: &syn::ExprPath) -> Result<TS> {
+    if let Some(ident) = expr.path.get_ident() {
+        if let Some(name) = let_binding(&ident.to_string()) {
+            return Ok(quote!(ast::VerilogExpression::Signal(#name.to_string())));
+        }
+    }
     let expr_expanded = common::fixup_ident(quote!(#expr).to_string());
     if expr_expanded.ends_with("$next") {
         return Err(syn::Error::new(
@@ -236,12 +675,34 @@ fn hdl_conditional(conditions: &syn::ExprIf) -> Result<TS> {
 }
 
 fn hdl_match(m: &syn::ExprMatch) -> Result<TS> {
-    let test = hdl_compute(m.expr.as_ref())?;
+    let expr = m.expr.as_ref();
+    let test_value = hdl_compute(expr)?;
+    // A match on the variants of an enum needs the type of the value being matched, since
+    // a tagged union is matched on its tag, and the fields of the variant are sliced out of
+    // the rest of it.  As with a let binding, the value is never evaluated.
+    let tagged = m.arms.iter().any(|arm| is_variant_pattern(&arm.pat));
+    let (placeholder, test) = if tagged {
+        (
+            quote!(let hdl_match_test = if true { synth::Synth::default_value() } else { #expr };),
+            quote!(ast::VerilogExpression::match_tag(&hdl_match_test, #test_value)),
+        )
+    } else {
+        (quote!(), test_value.clone())
+    };
     let mut condition = vec![];
     let mut blocks = vec![];
     for arm in &m.arms {
+        if let Some((_, guard)) = &arm.guard {
+            return Err(syn::Error::new(
+                guard.span(),
+                "Match guards are not supported in HDL kernels",
+            ));
+        }
         condition.push(hdl_pattern(&arm.pat)?);
-        blocks.push(hdl_body(&arm.body)?);
+        LET_BINDINGS.with(|x| x.borrow_mut().scopes.push(vec![]));
+        let block = hdl_arm(&arm.pat, &arm.body, &test_value);
+        LET_BINDINGS.with(|x| x.borrow_mut().scopes.pop());
+        blocks.push(block?);
     }
     /*    if condition.len() == 0 || !condition.last().unwrap().eq("default") {
         return Err(syn::Error::new(
@@ -251,6 +712,7 @@ fn hdl_match(m: &syn::ExprMatch) -> Result<TS> {
     }*/
     Ok(quote!({
        {
+          #placeholder
           let mut cases = vec![];
           #(cases.push(ast::VerilogCase{condition: #condition.to_string(), block: #blocks}));*;
           ast::VerilogStatement::Match(ast::VerilogMatch{test: #test, cases: cases})
@@ -258,6 +720,61 @@ fn hdl_match(m: &syn::ExprMatch) -> Result<TS> {
     }))
 }
 
+// The fields bound by a pattern are sliced out of the value being matched, and bound
+// (like a let binding) for the body of the arm.
+fn hdl_arm(pat: &Pat, body: &syn::Expr, test: &TS) -> Result<TS> {
+    let bindings = hdl_pattern_bindings(pat)?;
+    if bindings.is_empty() {
+        return hdl_body(body);
+    }
+    let variant = match pat {
+        Pat::TupleStruct(x) => &x.path,
+        Pat::Struct(x) => &x.path,
+        _ => unreachable!(),
+    };
+    let variant = variant.segments.last().unwrap().ident.to_string();
+    let idents = bindings.iter().map(|(x, _)| x).collect::<Vec<_>>();
+    let fields = bindings.iter().map(|(_, x)| x).collect::<Vec<_>>();
+    let names = idents
+        .iter()
+        .map(|x| bind_let(&x.to_string()))
+        .collect::<Vec<_>>();
+    let defaults = idents
+        .iter()
+        .map(|_| quote!(synth::Synth::default_value()))
+        .collect::<Vec<_>>();
+    let body = hdl_body(body)?;
+    Ok(quote!({
+        #[allow(unused_variables, unreachable_patterns)]
+        let (#(#idents,)*) = match hdl_match_test {
+            #pat => (#(#idents,)*),
+            _ => (#(#defaults,)*),
+        };
+        let mut ret = vec![];
+        #(ret.push(ast::VerilogStatement::Let(ast::VerilogLet::new(&#idents, #names,
+            ast::VerilogExpression::Slice(
+                Box::new(#test),
+                synth::Synth::bits(#idents),
+                Box::new(ast::VerilogExpression::Literal(
+                    (hdl_match_test.hdl_field_offset(#variant, #fields) as u32).into()
+                )),
+            )
+        )));)*
+        ret.extend(#body);
+        ret
+    }))
+}
+
+// Does the pattern name a variant of an enum (e.g., `State::Idle` or `Packet::Data(x)`)?
+fn is_variant_pattern(pat: &Pat) -> bool {
+    match pat {
+        Pat::Path(x) => x.path.segments.len() > 1,
+        Pat::TupleStruct(_) | Pat::Struct(_) => true,
+        Pat::Or(x) => x.cases.iter().any(is_variant_pattern),
+        _ => false,
+    }
+}
+
 fn hdl_compute(m: &syn::Expr) -> Result<TS> {
     //println!("Compute : {} {:?}", quote!(#m).to_string(), m);
     match m {
@@ -270,8 +787,13 @@ fn hdl_compute(m: &syn::Expr) -> Result<TS> {
         Expr::Binary(binop) => hdl_binop(binop),
         Expr::Unary(unop) => hdl_unop(unop),
         Expr::Call(call) => hdl_call(call),
+        Expr::Struct(x) => hdl_struct_literal(x),
         Expr::MethodCall(method) => hdl_method(method),
         Expr::Lit(lit) => hdl_literal(lit),
+        Expr::Index(ndx) if is_array_value(&ndx.expr) => {
+            let element = hdl_array_element(ndx, false)?;
+            Ok(quote!(ast::VerilogExpression::Element(Box::new(#element))))
+        }
         Expr::Index(_ndx) => {
             let ndx_expanded = common::fixup_ident(quote!(#m).to_string());
             Ok(quote!(ast::VerilogExpression::Signal(#ndx_expanded.to_string())))
@@ -387,10 +909,17 @@ fn hdl_call(call: &syn::ExprCall) -> Result<TS> {
     } else if funcname.starts_with("signed_bit_cast") || funcname.starts_with("signed_cast") {
         let target = hdl_compute(&call.args[0])?;
         Ok(quote!({ast::VerilogExpression::Signed(Box::new(#target))}))
+    } else if squash(&funcname).contains("::from_f64(") {
+        // Fixed point constants are evaluated when the HDL is generated
+        Ok(quote!({
+            ast::VerilogExpression::Literal((#call).verilog())
+        }))
     } else if squash(&funcname).contains("::join") {
         hdl_join_or_link(call, "join")
     } else if squash(&funcname).contains("::link") {
         hdl_join_or_link(call, "link")
+    } else if let Some(path) = hdl_function_path(call) {
+        hdl_function_call(call, path)
     } else {
         Err(syn::Error::new(
             call.span(),
@@ -547,6 +1076,52 @@ fn hdl_method(method: &syn::ExprMethodCall) -> Result<TS> {
                 ast::VerilogExpression::Signed(Box::new(#target))
             }))
         }
+        "truncate" | "round" | "saturate" | "round_saturate" => {
+            let receiver = method.receiver.as_ref();
+            let target = hdl_compute(receiver)?;
+            if method.turbofish.as_ref().map(|x| x.args.len()) != Some(2) {
+                return Err(syn::Error::new(method.span(), format!("{} needs type arguments to indicate the format of the result (e.g., x.{}::<4, 12>())", method_name, method_name)));
+            }
+            let rounding = if method_name.starts_with("round") {
+                quote!(fixed::Rounding::Nearest)
+            } else {
+                quote!(fixed::Rounding::Truncate)
+            };
+            let overflow = if method_name.ends_with("saturate") {
+                quote!(fixed::Overflow::Saturate)
+            } else {
+                quote!(fixed::Overflow::Wrap)
+            };
+            Ok(quote!({
+                ast::VerilogExpression::Call(ast::VerilogCall {
+                    function: Box::new(ast::VerilogFunction::fixed_convert(|| #receiver, || #method, #rounding, #overflow)),
+                    args: vec![#target],
+                })
+            }))
+        }
+        "wide_add" | "wide_sub" | "wide_mul" => {
+            let receiver = method.receiver.as_ref();
+            if method.args.len() != 1 {
+                return Err(syn::Error::new(
+                    method.span(),
+                    format!("{} needs one argument", method_name),
+                ));
+            }
+            let arg = &method.args[0];
+            let lhs = hdl_compute(receiver)?;
+            let rhs = hdl_compute(arg)?;
+            let op = match method_name.as_ref() {
+                "wide_add" => quote!(ast::VerilogOp::Add),
+                "wide_sub" => quote!(ast::VerilogOp::Sub),
+                _ => quote!(ast::VerilogOp::Mul),
+            };
+            Ok(quote!({
+                ast::VerilogExpression::Call(ast::VerilogCall {
+                    function: Box::new(ast::VerilogFunction::fixed_wide(|| #receiver, || #arg, #op)),
+                    args: vec![#lhs, #rhs],
+                })
+            }))
+        }
         "val" | "into" | "index" | "to_bits" => {
             let receiver = method.receiver.as_ref();
             hdl_compute(receiver)
@@ -562,17 +1137,79 @@ fn hdl_body(body: &syn::Expr) -> Result<TS> {
     if let Expr::Block(b) = body {
         hdl_block(&b.block)
     } else {
+        let source = hdl_source(body.span());
         let statement = hdl_inner_statement(body)?;
-        Ok(quote!({ vec![#statement] }))
+        Ok(quote!({
+            let mut ret = vec![];
+            #source
+            ret.push(#statement);
+            ret
+        }))
     }
 }
 
+// The largest number of values a range pattern may cover
+const MAX_RANGE_PATTERN: u128 = 256;
+
 fn hdl_pattern(pat: &Pat) -> Result<String> {
     match pat {
-        Pat::Ident(ident) => Ok(ident.ident.to_string()),
+        Pat::Ident(ident) if ident.subpat.is_none() => Ok(ident.ident.to_string()),
         Pat::Lit(lit) => Ok(quote!(#lit).to_string()),
         Pat::Path(pat) => Ok(common::fixup_ident(quote!(#pat).to_string())),
         Pat::Wild(_pat) => Ok("default".to_string()),
+        Pat::TupleStruct(pat) => {
+            let path = &pat.path;
+            Ok(common::fixup_ident(quote!(#path).to_string()))
+        }
+        Pat::Struct(pat) => {
+            let path = &pat.path;
+            Ok(common::fixup_ident(quote!(#path).to_string()))
+        }
+        // The alternatives are listed in a single case
+        Pat::Or(or) => {
+            let mut cases = vec![];
+            for case in &or.cases {
+                if !hdl_pattern_bindings(case)?.is_empty() {
+                    return Err(syn::Error::new(
+                        case.span(),
+                        "Or-patterns cannot bind fields in HDL kernels",
+                    ));
+                }
+                let case = match hdl_pattern(case)? {
+                    x if x == "default" => {
+                        return Err(syn::Error::new(
+                            case.span(),
+                            "A wildcard cannot be part of an or-pattern in HDL kernels",
+                        ))
+                    }
+                    x => x,
+                };
+                cases.push(case);
+            }
+            Ok(cases.join(", "))
+        }
+        // A range is expanded into the list of values it covers
+        Pat::Range(range) => {
+            let lo = hdl_pattern_int(&range.lo)?;
+            let hi = hdl_pattern_int(&range.hi)?;
+            let hi = match range.limits {
+                syn::RangeLimits::HalfOpen(_) => hi.checked_sub(1),
+                syn::RangeLimits::Closed(_) => Some(hi),
+            };
+            match hi {
+                Some(hi) if hi >= lo && hi - lo < MAX_RANGE_PATTERN => Ok((lo..=hi)
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")),
+                _ => Err(syn::Error::new(
+                    range.span(),
+                    format!(
+                        "Range patterns in HDL kernels must cover between 1 and {} values",
+                        MAX_RANGE_PATTERN
+                    ),
+                )),
+            }
+        }
         _ => Err(syn::Error::new(
             pat.span(),
             format!(
@@ -583,6 +1220,64 @@ fn hdl_pattern(pat: &Pat) -> Result<String> {
     }
 }
 
+fn hdl_pattern_int(expr: &Expr) -> Result<u128> {
+    if let Expr::Lit(syn::ExprLit {
+        lit: syn::Lit::Int(x),
+        ..
+    }) = expr
+    {
+        return x.base10_parse();
+    }
+    Err(syn::Error::new(
+        expr.span(),
+        "The ends of a range pattern must be unsigned integer literals in HDL kernels",
+    ))
+}
+
+// The bindings made by a pattern, and the field each one is bound to.  Fields of a
+// variant can be bound to a name, ignored with `_`, or skipped with a trailing `..`.
+fn hdl_pattern_bindings(pat: &Pat) -> Result<Vec<(syn::Ident, String)>> {
+    let field_pattern = |pat: &Pat, field: String| match pat {
+        Pat::Ident(x) if x.by_ref.is_none() && x.mutability.is_none() && x.subpat.is_none() => {
+            Ok(Some((x.ident.clone(), field)))
+        }
+        Pat::Wild(_) => Ok(None),
+        _ => Err(syn::Error::new(
+            pat.span(),
+            "The fields of a variant can only be bound to a name (or ignored) in HDL kernels",
+        )),
+    };
+    let mut ret = vec![];
+    match pat {
+        Pat::TupleStruct(x) => {
+            let elems = &x.pat.elems;
+            for (ndx, elem) in elems.iter().enumerate() {
+                if let Pat::Rest(rest) = elem {
+                    if ndx + 1 != elems.len() {
+                        return Err(syn::Error::new(
+                            rest.span(),
+                            "Only trailing fields can be skipped with `..` in HDL kernels",
+                        ));
+                    }
+                    continue;
+                }
+                ret.extend(field_pattern(elem, ndx.to_string())?);
+            }
+        }
+        Pat::Struct(x) => {
+            for field in &x.fields {
+                let member = match &field.member {
+                    syn::Member::Named(x) => x.to_string(),
+                    syn::Member::Unnamed(x) => x.index.to_string(),
+                };
+                ret.extend(field_pattern(&field.pat, member)?);
+            }
+        }
+        _ => {}
+    }
+    Ok(ret)
+}
+
 fn hdl_macro(x: &syn::ExprMacro) -> Result<TS> {
     let ident = &x.mac.path;
     let macro_name = quote!(#ident).to_string();
@@ -606,6 +1301,23 @@ fn hdl_macro(x: &syn::ExprMacro) -> Result<TS> {
                 .replace("\")", "");
             Ok(quote!(ast::VerilogStatement::Comment(#invocation_as_string.to_string())))
         }
+        "hdl_assert" | "hdl_assume" | "hdl_cover" => {
+            let test: Expr = x.mac.parse_body()?;
+            let test_code = hdl_compute(&test)?;
+            let text = quote!(#test).to_string();
+            let kind = match macro_name.as_ref() {
+                "hdl_assert" => quote!(ast::VerilogPropertyKind::Assert),
+                "hdl_assume" => quote!(ast::VerilogPropertyKind::Assume),
+                _ => quote!(ast::VerilogPropertyKind::Cover),
+            };
+            Ok(
+                quote!(ast::VerilogStatement::Property(ast::VerilogProperty {
+                    kind: #kind,
+                    test: #test_code,
+                    text: #text.to_string(),
+                })),
+            )
+        }
         "dff_setup" => {
             let args: DFFSetupArgs = x.mac.parse_body()?;
             let args_clock = &args.clock;
@@ -634,6 +1346,42 @@ fn hdl_macro(x: &syn::ExprMacro) -> Result<TS> {
                 }
             ))
         }
+        "dff_setup_with_reset" => {
+            let args: DFFResetSetupArgs = x.mac.parse_body()?;
+            let args_clock = &args.clock;
+            let clk = common::fixup_ident(quote!(#args_clock).to_string());
+            let args_reset = &args.reset;
+            let rst = common::fixup_ident(quote!(#args_reset).to_string());
+            let dffs_clk = &args
+                .dffs
+                .iter()
+                .map(|x| common::fixup_ident(quote!(#x.clock.next).to_string()))
+                .collect::<Vec<_>>();
+            let dffs_rst = &args
+                .dffs
+                .iter()
+                .map(|x| common::fixup_ident(quote!(#x.reset.next).to_string()))
+                .collect::<Vec<_>>();
+            let dffs_d = &args
+                .dffs
+                .iter()
+                .map(|x| common::fixup_ident(quote!(#x.d.next).to_string()))
+                .collect::<Vec<_>>();
+            let dffs_q = &args
+                .dffs
+                .iter()
+                .map(|x| common::fixup_ident(quote!(#x.q).to_string()))
+                .collect::<Vec<_>>();
+            Ok(quote!(
+                {
+                    let mut ret = vec![];
+                    #(ret.push(ast::VerilogStatement::Assignment(ast::VerilogExpression::Signal(#dffs_clk.to_string()), ast::VerilogExpression::Signal(#clk.to_string()))));*;
+                    #(ret.push(ast::VerilogStatement::Assignment(ast::VerilogExpression::Signal(#dffs_rst.to_string()), ast::VerilogExpression::Signal(#rst.to_string()))));*;
+                    #(ret.push(ast::VerilogStatement::Assignment(ast::VerilogExpression::Signal(#dffs_d.to_string()), ast::VerilogExpression::Signal(#dffs_q.to_string()))));*;
+                    ast::VerilogStatement::Macro(ret)
+                }
+            ))
+        }
         "clock" => {
             let args: DFFSetupArgs = x.mac.parse_body()?;
             let args_clock = &args.clock;
@@ -1,3 +1,4 @@
+mod black_box;
 mod common;
 mod connect_gen;
 mod hdl_gen;
@@ -9,9 +10,10 @@ mod logic_struct;
 use syn::parse_macro_input;
 use syn::DeriveInput;
 
+use crate::black_box::black_box_process;
 use crate::common::TS;
 use crate::connect_gen::connect_gen;
-use crate::hdl_gen::hdl_gen_process;
+use crate::hdl_gen::{hdl_function_process, hdl_gen_process};
 use crate::logic_block::get_impl_for_logic_block;
 use crate::logic_interface::get_impl_for_logic_interface;
 use crate::logic_state::get_logic_state_impls;
@@ -85,3 +87,31 @@ pub fn hdl_gen(_attr: TokenStream, item: TokenStream) -> TokenStream {
         }),
     }
 }
+
+#[proc_macro_attribute]
+pub fn hdl_function(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let orig = TS::from(item.clone());
+    let parse = parse_macro_input!(item as syn::ItemFn);
+    match hdl_function_process(&parse) {
+        Err(e) => e.to_compile_error().into(),
+        Ok(hdl_code) => TokenStream::from(quote! {
+            #orig
+
+        #[doc(hidden)]
+        #[allow(dead_code)]
+        #[allow(unused_variables)]
+        #[automatically_derived]
+            #hdl_code
+        }),
+    }
+}
+
+#[proc_macro_attribute]
+pub fn black_box(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as syn::AttributeArgs);
+    let parse = parse_macro_input!(item as syn::ItemStruct);
+    match black_box_process(args, parse) {
+        Err(e) => e.to_compile_error().into(),
+        Ok(x) => x.into(),
+    }
+}
@@ -12,6 +12,7 @@ pub(crate) fn get_impl_for_logic_interface(input: &syn::DeriveInput) -> Result<T
     let link_hdl = get_link_hdl(fields.clone(), field_types.clone())?;
     let update_all = get_update_all(fields.clone())?;
     let has_changed = get_has_changed(fields.clone())?;
+    let update_sensitive = get_interface_update_sensitive(fields.clone())?;
     let connect_all = get_connect_all(fields.clone())?;
     let join_connect = get_join_connect(fields.clone())?;
     let join_hdl = get_join_hdl(fields.clone(), field_types)?;
@@ -39,6 +40,7 @@ pub(crate) fn get_impl_for_logic_interface(input: &syn::DeriveInput) -> Result<T
             #connect_all
             #update_all
             #has_changed
+            #update_sensitive
             #accept
         }
 
@@ -57,6 +59,26 @@ pub(crate) fn get_impl_for_logic_interface(input: &syn::DeriveInput) -> Result<T
     })
 }
 
+fn get_interface_update_sensitive(fields: Vec<TS>) -> Result<TS> {
+    Ok(quote! {
+        fn update_sensitive(&mut self, sens: &mut sensitivity::Sensitivity, dirty: bool) -> bool {
+            false #(| self.#fields.update_sensitive(sens, dirty))*
+        }
+
+        fn latch_inputs(&mut self) {
+            #(self.#fields.latch_inputs();)*
+        }
+
+        fn ports_changed(&self) -> bool {
+            self.atoms_changed()
+        }
+
+        fn atoms_changed(&self) -> bool {
+            false #(|| self.#fields.atoms_changed())*
+        }
+    })
+}
+
 fn get_join_connect(fields: Vec<TS>) -> Result<TS> {
     Ok(quote! {
         fn join_connect(&mut self) {
@@ -118,6 +140,12 @@ fn get_accept(fields: Vec<TS>) -> Result<TS> {
             #(self.#fields.accept(#fields_as_strings, probe);)*
             probe.visit_end_namespace(name, self);
         }
+
+        fn accept_mut(&mut self, name: &str, probe: &mut dyn probe::ProbeMut) {
+            probe.visit_start_namespace(name);
+            #(self.#fields.accept_mut(#fields_as_strings, probe);)*
+            probe.visit_end_namespace(name);
+        }
     })
 }
 
@@ -1,19 +1,13 @@
 use crate::common::*;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::spanned::Spanned;
-use syn::{Data, Result};
+use syn::{Data, Fields, Result};
 
 fn get_variant_names(input: &syn::DeriveInput) -> Result<Vec<TS>> {
     let mut variants = vec![];
     match &input.data {
         Data::Enum(ed) => {
             for variant in &ed.variants {
-                if !variant.fields.is_empty() {
-                    return Err(syn::Error::new(
-                        variant.span(),
-                        "enum variants cannot have fields",
-                    ));
-                }
                 if variant.discriminant.is_some() {
                     return Err(syn::Error::new(
                         variant.span(),
@@ -34,7 +28,54 @@ fn get_variant_names(input: &syn::DeriveInput) -> Result<Vec<TS>> {
     Ok(variants)
 }
 
+// The fields of a variant that carries data.  Tuple fields are named by their
+// index (so `Variant { 0: x }` works for both kinds of variant).
+struct VariantField {
+    member: TS,
+    arg_name: String,
+    ty: syn::Type,
+}
+
+fn get_variant_fields(variant: &syn::Variant) -> Result<Vec<VariantField>> {
+    variant
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(ndx, field)| match &field.ident {
+            Some(ident) => {
+                let arg_name = ident.to_string();
+                if ["config", "wire", "reg", "module", "edge", "disable"]
+                    .contains(&arg_name.as_str())
+                {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        "Cannot use an HDL keyword here",
+                    ));
+                }
+                Ok(VariantField {
+                    member: quote!(#ident),
+                    arg_name,
+                    ty: field.ty.clone(),
+                })
+            }
+            None => {
+                let index = syn::Index::from(ndx);
+                Ok(VariantField {
+                    member: quote!(#index),
+                    arg_name: format!("_{}", ndx),
+                    ty: field.ty.clone(),
+                })
+            }
+        })
+        .collect()
+}
+
 pub fn get_logic_state_impls(input: &syn::DeriveInput) -> Result<TS> {
+    if let Data::Enum(ed) = &input.data {
+        if ed.variants.iter().any(|x| !x.fields.is_empty()) {
+            return get_tagged_union_impls(input, ed);
+        }
+    }
     let variants = get_variant_names(input)?;
     let first_variant = variants[0].clone();
     let num_variants = variants.len();
@@ -58,6 +99,9 @@ pub fn get_logic_state_impls(input: &syn::DeriveInput) -> Result<TS> {
                     kind: TypeKind::Enum(vec![#(#variants_as_strings.to_string(),)*])
                 }
             }
+            fn default_value() -> Self {
+                Self::default()
+            }
             fn vcd(self) -> VCDValue {
                 match self {
                     #(#name::#variants => VCDValue::String(#variants_only_as_strings.into()),)*
@@ -68,6 +112,12 @@ pub fn get_logic_state_impls(input: &syn::DeriveInput) -> Result<TS> {
                     #(#name::#variants => #discriminants.into(),)*
                 }
             }
+            fn from_vcd(val: &VCDValue) -> Option<Self> {
+                match val {
+                    #(VCDValue::String(x) if x == #variants_only_as_strings => Some(#name::#variants),)*
+                    _ => None,
+                }
+            }
         }
 
         impl Into<Bits<{#name::BITS}>> for #name {
@@ -85,3 +135,181 @@ pub fn get_logic_state_impls(input: &syn::DeriveInput) -> Result<TS> {
         }
     ))
 }
+
+// An enum with variants that carry data is a tagged union.  The tag (the index of the
+// variant) is held in the low bits, and the fields of the variant are packed above it
+// (the first field lowest).  The payload is as wide as the widest variant.
+fn get_tagged_union_impls(input: &syn::DeriveInput, ed: &syn::DataEnum) -> Result<TS> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "LogicState does not support generic enums",
+        ));
+    }
+    let name = &input.ident;
+    let name_as_string = name.to_string();
+    let tag_bits = (usize::BITS - (ed.variants.len().max(2) - 1).leading_zeros()) as usize;
+    let mut variants = vec![];
+    let mut variants_as_strings = vec![];
+    let mut variants_only_as_strings = vec![];
+    let mut members = vec![];
+    let mut locals = vec![];
+    let mut types = vec![];
+    let mut payload_offsets = vec![];
+    let mut payload_widths = vec![];
+    let mut constructors = vec![];
+    for (ndx, variant) in ed.variants.iter().enumerate() {
+        if variant.discriminant.is_some() {
+            return Err(syn::Error::new(
+                variant.span(),
+                "enum variants cannot have discriminants",
+            ));
+        }
+        let fields = get_variant_fields(variant)?;
+        let ident = &variant.ident;
+        let v_members = fields.iter().map(|x| x.member.clone()).collect::<Vec<_>>();
+        let v_locals = (0..fields.len())
+            .map(|x| format_ident!("field_{}", x))
+            .collect::<Vec<_>>();
+        let v_types = fields.iter().map(|x| x.ty.clone()).collect::<Vec<_>>();
+        let v_offsets = (0..fields.len())
+            .map(|x| {
+                let previous = &v_types[0..x];
+                quote!((0_usize #(+ <#previous as synth::Synth>::BITS)*))
+            })
+            .collect::<Vec<_>>();
+        let v_arg_names = fields.iter().map(|x| &x.arg_name).collect::<Vec<_>>();
+        if !fields.is_empty() {
+            let hdl_name = format_ident!("{}_hdl", ident);
+            let function_name = format!("{}${}$new", name_as_string, ident);
+            let args = quote!(vec![#((
+                ast::VerilogFunctionArg::new(&<#v_types as synth::Synth>::default_value(), #v_arg_names),
+                #tag_bits + #v_offsets
+            )),*]);
+            // Tuple variants are constructed like a function call (which passes a placeholder
+            // for each argument).  Struct variants are constructed with named arguments.
+            let params = match &variant.fields {
+                Fields::Unnamed(_) => quote!(#(_: #v_types),*),
+                _ => quote!(),
+            };
+            constructors.push(quote!(
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub fn #hdl_name(#params) -> ast::VerilogFunction {
+                    ast::VerilogFunction::variant::<#name>(#function_name, #ndx, #args)
+                }
+            ));
+        }
+        payload_widths.push(quote!(0_usize #(+ <#v_types as synth::Synth>::BITS)*));
+        variants.push(ident.clone());
+        variants_as_strings.push(format!("{name_as_string}::{ident}"));
+        variants_only_as_strings.push(ident.to_string());
+        members.push(v_members);
+        locals.push(v_locals);
+        types.push(v_types);
+        payload_offsets.push(v_offsets);
+    }
+    let first_variant = &variants[0];
+    let first_members = &members[0];
+    let discriminants = (0..variants.len()).collect::<Vec<_>>();
+    Ok(quote!(
+        impl synth::Synth for #name {
+            const BITS: usize = #tag_bits + {
+                let mut bits = 0;
+                #(if #payload_widths > bits { bits = #payload_widths; })*
+                bits
+            };
+            const TAG_BITS: Option<usize> = Some(#tag_bits);
+            fn descriptor() -> type_descriptor::TypeDescriptor {
+                let payload_bits = Self::BITS - #tag_bits;
+                TypeDescriptor {
+                    name: #name_as_string.to_string(),
+                    kind: TypeKind::Composite(vec![
+                        Box::new(TypeField {
+                            fieldname: "tag".to_string(),
+                            kind: TypeDescriptor {
+                                name: format!("{}$tag", #name_as_string),
+                                kind: TypeKind::Enum(vec![#(#variants_as_strings.to_string(),)*]),
+                            },
+                        }),
+                        Box::new(TypeField {
+                            fieldname: "payload".to_string(),
+                            kind: TypeDescriptor {
+                                name: format!("Bits::<{}>", payload_bits),
+                                kind: TypeKind::Bits(payload_bits),
+                            },
+                        }),
+                    ]),
+                }
+            }
+            fn default_value() -> Self {
+                Self::default()
+            }
+            fn vcd(self) -> VCDValue {
+                let bits: Bits<{#name::BITS}> = self.into();
+                let variant = match self {
+                    #(#name::#variants { .. } => #variants_only_as_strings,)*
+                };
+                VCDValue::Composite(vec![
+                    Box::new(VCDValue::String(variant.into())),
+                    Box::new(synth::vcd_slice(&bits.vcd(), #tag_bits, Self::BITS - #tag_bits).unwrap()),
+                ])
+            }
+            fn verilog(self) -> VerilogLiteral {
+                let t: Bits<{Self::BITS}> = self.into();
+                t.into()
+            }
+            fn from_vcd(val: &VCDValue) -> Option<Self> {
+                let (tag, payload) = match val {
+                    VCDValue::Composite(x) if x.len() == 2 => (x[0].as_ref(), x[1].as_ref()),
+                    _ => return None,
+                };
+                match tag {
+                    #(VCDValue::String(x) if x == #variants_only_as_strings => Some(#name::#variants {
+                        #(#members: <#types as synth::Synth>::from_vcd(
+                            &synth::vcd_slice(payload, #payload_offsets, <#types as synth::Synth>::BITS)?
+                        )?,)*
+                    }),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl From<#name> for Bits<{#name::BITS}> {
+            fn from(x: #name) -> Self {
+                match x {
+                    #(#name::#variants { #(#members: #locals,)* } => {
+                        let tag: Bits<{#name::BITS}> = #discriminants.to_bits();
+                        tag #(| (synth::synth_to_bits::<_, {#name::BITS}>(#locals)
+                            << ((#tag_bits + #payload_offsets) as LiteralType)))*
+                    })*
+                }
+            }
+        }
+
+        impl Default for #name {
+            fn default() -> #name {
+                #name::#first_variant {
+                    #(#first_members: synth::Synth::default_value(),)*
+                }
+            }
+        }
+
+        impl #name {
+            #(#constructors)*
+
+            #[doc(hidden)]
+            pub fn hdl_field_offset(&self, variant: &str, field: &str) -> usize {
+                match variant {
+                    #(#variants_only_as_strings => {
+                        #(if field == stringify!(#members) {
+                            return #tag_bits + #payload_offsets;
+                        })*
+                    })*
+                    _ => {}
+                }
+                panic!("No field {} in variant {}::{}", field, #name_as_string, variant)
+            }
+        }
+    ))
+}
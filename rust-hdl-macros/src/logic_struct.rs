@@ -41,7 +41,7 @@ pub(crate) fn get_impl_for_logic_struct(input: &syn::DeriveInput) -> Result<TS>
             fn from(x: #name) -> Self {
                 Bits::<{<#name>::BITS}>::default()  #(|
                     (bit_cast::<{<#name>::BITS}, {<#field_types>::BITS}>(x.#fields.into())
-                    << (<#name>::default().#get_offset_names() as LiteralType))
+                    << (<#name as Synth>::default_value().#get_offset_names() as LiteralType))
                 )*
             }
         }
@@ -63,6 +63,12 @@ pub(crate) fn get_impl_for_logic_struct(input: &syn::DeriveInput) -> Result<TS>
                 }
             }
 
+            fn default_value() -> Self {
+                Self {
+                    #(#fields: <#field_types as Synth>::default_value(),)*
+                }
+            }
+
             fn vcd(self) -> VCDValue {
                 let mut ret = vec![];
                 #(ret.push(Box::new(self.#fields.vcd()));)*
@@ -73,6 +79,18 @@ pub(crate) fn get_impl_for_logic_struct(input: &syn::DeriveInput) -> Result<TS>
                 let t: Bits<{Self::BITS}> = self.into();
                 t.into()
             }
+
+            fn from_vcd(val: &VCDValue) -> Option<Self> {
+                match val {
+                    VCDValue::Composite(vals) => {
+                        let mut vals = vals.iter();
+                        let mut ret = Self::default_value();
+                        #(ret.#fields = <#field_types as Synth>::from_vcd(vals.next()?)?;)*
+                        Some(ret)
+                    }
+                    _ => None,
+                }
+            }
         }
     })
 }
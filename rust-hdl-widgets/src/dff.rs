@@ -50,6 +50,7 @@ use rust_hdl_core::prelude::*;
 /// It is a good idea to connect [`q`](Self::q) to [`d`](Self::d) to ensure that [`d`](Self::d) is never undriven. The [`dff_setup`] macro can generate that code for you.
 ///
 /// If you need to set an initial value for the flip-flop use [`DFFWithInit`](crate::dff_with_init::DFFWithInit) instead.
+/// For designs that must not rely on initial values (e.g., ASICs), use [`DFFWithReset`](crate::dff_with_reset::DFFWithReset).
 #[derive(Clone, Debug, LogicBlock)]
 pub struct DFF<T: Synth> {
     /// Input for data that will be stored on the next rising edge of [`clock`](Self::clock).
@@ -90,9 +91,68 @@ always @(posedge clock) begin
    q <= d;
 end
       ",
-            T::default().verilog()
+            T::default_value().verilog()
         ))
     }
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        let init = T::default_value().verilog();
+        match lang {
+            HDLLanguage::Verilog => None,
+            HDLLanguage::SystemVerilog => {
+                // A variable written by an always_ff cannot also be written by an
+                // initial block, so (as for VHDL) the state is held in a variable that
+                // is initialized where it is declared.  Enums and packed structs need a
+                // cast from the raw bits.
+                let descriptor = T::descriptor();
+                let (kind, init) = match descriptor.kind {
+                    TypeKind::Enum(_) | TypeKind::Composite(_) => (
+                        descriptor.name.clone(),
+                        format!("{}'({:x})", descriptor.name, init),
+                    ),
+                    _ => {
+                        let signed = if descriptor.kind.is_signed() {
+                            " signed"
+                        } else {
+                            ""
+                        };
+                        (
+                            format!("logic{} [{}:0]", signed, T::BITS - 1),
+                            format!("{:x}", init),
+                        )
+                    }
+                };
+                Some(format!(
+                    "\
+{} state = {};
+
+always_ff @(posedge clock) begin
+   state <= d;
+end
+
+assign q = state;
+",
+                    kind, init
+                ))
+            }
+            HDLLanguage::VHDL => Some(format!(
+                "\
+dff: block
+    signal state : {} := {};
+begin
+    process(clock)
+    begin
+        if rising_edge(clock(0)) then
+            state <= d;
+        end if;
+    end process;
+    q <= state;
+end block dff;
+",
+                vhdl_type(T::BITS, T::descriptor().kind.is_signed()),
+                vhdl_bit_string(&init, T::BITS)
+            )),
+        }
+    }
     fn timing(&self) -> Vec<TimingInfo> {
         vec![TimingInfo {
             name: "dff".into(),
@@ -0,0 +1,290 @@
+use rust_hdl_core::prelude::*;
+
+/// D Flip-Flop with a reset
+///
+/// Like a [`DFF`](crate::dff::DFF), but instead of relying on an initial value (which
+/// does not exist on ASICs, and on many FPGA flows), the flip-flop is loaded with
+/// [`reset_value`](Self::reset_value) while [`reset`](Self::reset) is asserted.
+///
+/// The polarity of the reset comes from its type: [`Reset`] is active high, and [`ResetN`]
+/// is active low.  By default the reset is synchronous (i.e., it is only sampled on the
+/// rising edge of [`clock`](Self::clock)).  Set `ASYNC` to `true` for an asynchronous reset,
+/// which takes effect as soon as it is asserted.
+///
+/// ### Example
+///
+/// A counter that is cleared by an (active low, asynchronous) reset.
+///
+/// ```
+/// # use rust_hdl_core::prelude::*;
+/// # use rust_hdl_widgets::prelude::*;
+/// #
+/// #[derive(LogicBlock, Default)]
+/// struct Counter {
+///     pub clock: Signal<In, Clock>,
+///     pub reset_n: Signal<In, ResetN>,
+///     pub count: Signal<Out, Bits<8>>,
+///     counter: DFFWithReset<Bits<8>, ResetN, true>,
+/// }
+///
+/// impl Logic for Counter {
+///     #[hdl_gen]
+///     fn update(&mut self) {
+///         dff_setup_with_reset!(self, clock, reset_n, counter);
+///         self.counter.d.next = self.counter.q.val() + 1;
+///         self.count.next = self.counter.q.val();
+///     }
+/// }
+/// ```
+///
+/// ### Inputs
+///
+/// * [`clock`](Self::clock) On every rising edge the data from [`d`](Self::d) is stored into the flip-flop.
+/// * [`reset`](Self::reset) While asserted, the flip-flop holds [`reset_value`](Self::reset_value).
+/// * [`d`](Self::d) Input for data that will be stored on the next rising edge of [`clock`](Self::clock).
+///
+/// ### Outputs
+///
+/// * [`q`](Self::q) Outputs the currently stored data.
+#[derive(Clone, Debug, LogicBlock)]
+pub struct DFFWithReset<T: Synth, R: ResetType = Reset, const ASYNC: bool = false> {
+    /// Input for data that will be stored on the next rising edge of [`clock`](Self::clock).
+    pub d: Signal<In, T>,
+    /// Outputs the currently stored data.
+    pub q: Signal<Out, T>,
+    /// On every rising edge the data from [`d`](Self::d) is stored into the flip-flop.
+    pub clock: Signal<In, Clock>,
+    /// While asserted, [`q`](Self::q) is loaded with [`reset_value`](Self::reset_value).
+    pub reset: Signal<In, R>,
+    /// The value held by the flip-flop while it is in reset.
+    pub reset_value: Constant<T>,
+}
+
+impl<T: Synth, R: ResetType, const ASYNC: bool> DFFWithReset<T, R, ASYNC> {
+    pub fn new(reset_value: T) -> Self {
+        Self {
+            d: Default::default(),
+            q: Default::default(),
+            clock: Default::default(),
+            reset: Default::default(),
+            reset_value: Constant::new(reset_value),
+        }
+    }
+
+    // The update code (for both Verilog and SystemVerilog) using the given `always` keyword
+    fn verilog_update(always: &str) -> String {
+        let (edge, test) = if R::ACTIVE_LOW {
+            ("negedge", "!reset")
+        } else {
+            ("posedge", "reset")
+        };
+        let sensitivity = if ASYNC {
+            format!("posedge clock or {} reset", edge)
+        } else {
+            "posedge clock".to_string()
+        };
+        format!(
+            "\
+{} @({}) begin
+   if ({})
+      q <= reset_value;
+   else
+      q <= d;
+end
+",
+            always, sensitivity, test
+        )
+    }
+}
+
+impl<T: Synth, R: ResetType, const ASYNC: bool> Default for DFFWithReset<T, R, ASYNC> {
+    fn default() -> Self {
+        Self::new(T::default_value())
+    }
+}
+
+impl<T: Synth, R: ResetType, const ASYNC: bool> Logic for DFFWithReset<T, R, ASYNC> {
+    fn update(&mut self) {
+        if ASYNC && self.reset.val().asserted() {
+            self.q.next = self.reset_value.val();
+        } else if self.clock.pos_edge() {
+            if self.reset.val().asserted() {
+                self.q.next = self.reset_value.val();
+            } else {
+                self.q.next = self.d.val();
+            }
+        }
+    }
+    fn connect(&mut self) {
+        self.q.connect();
+    }
+    fn hdl(&self) -> Verilog {
+        Verilog::Custom(Self::verilog_update("always"))
+    }
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        let level = if R::ACTIVE_LOW { '0' } else { '1' };
+        match lang {
+            HDLLanguage::Verilog => None,
+            HDLLanguage::SystemVerilog => Some(Self::verilog_update("always_ff")),
+            HDLLanguage::VHDL => {
+                let body = if ASYNC {
+                    format!(
+                        "\
+    process(clock, reset)
+    begin
+        if reset(0) = '{}' then
+            state <= reset_value;
+        elsif rising_edge(clock(0)) then
+            state <= d;
+        end if;
+    end process;",
+                        level
+                    )
+                } else {
+                    format!(
+                        "\
+    process(clock)
+    begin
+        if rising_edge(clock(0)) then
+            if reset(0) = '{}' then
+                state <= reset_value;
+            else
+                state <= d;
+            end if;
+        end if;
+    end process;",
+                        level
+                    )
+                };
+                Some(format!(
+                    "\
+dff: block
+    signal state : {};
+begin
+{}
+    q <= state;
+end block dff;
+",
+                    vhdl_type(T::BITS, T::descriptor().kind.is_signed()),
+                    body
+                ))
+            }
+        }
+    }
+    fn timing(&self) -> Vec<TimingInfo> {
+        vec![TimingInfo {
+            name: "dff_with_reset".into(),
+            clock: "clock".into(),
+            inputs: vec!["d".into(), "reset".into()],
+            outputs: vec!["q".into()],
+        }]
+    }
+}
+
+/// Generate boilerplate connections for one or more [`DFFWithReset`]s
+///
+/// This is the same as [`dff_setup`](crate::dff_setup), but also connects a reset to each of
+/// the flip-flops.  The first argument is `self`, the second is the clock, and the third is
+/// the reset.  Every additional argument is a [`DFFWithReset`].
+///
+/// ```ignore
+/// dff_setup_with_reset!(self, clock, reset, counter);
+///
+/// // Expands to:
+/// // self.counter.clock.next = self.clock.val();
+/// // self.counter.reset.next = self.reset.val();
+/// // self.counter.d.next = self.counter.q.val();
+/// ```
+#[macro_export]
+macro_rules! dff_setup_with_reset {
+    ($self: ident, $clock: ident, $reset: ident, $($dff: ident),+) => {
+        $($self.$dff.clock.next = $self.$clock.val());+;
+        $($self.$dff.reset.next = $self.$reset.val());+;
+        $($self.$dff.d.next = $self.$dff.q.val());+;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(LogicBlock, Default)]
+    struct Counters {
+        pub clock: Signal<In, Clock>,
+        pub reset: Signal<In, Reset>,
+        pub reset_n: Signal<In, ResetN>,
+        sync: DFFWithReset<Bits<4>>,
+        asynch: DFFWithReset<Bits<4>, ResetN, true>,
+    }
+
+    impl Logic for Counters {
+        #[hdl_gen]
+        fn update(&mut self) {
+            dff_setup_with_reset!(self, clock, reset, sync);
+            dff_setup_with_reset!(self, clock, reset_n, asynch);
+            self.sync.d.next = self.sync.q.val() + 1;
+            self.asynch.d.next = self.asynch.q.val() + 1;
+        }
+    }
+
+    fn counters() -> Counters {
+        let mut uut = Counters {
+            sync: DFFWithReset::new(3.into()),
+            ..Default::default()
+        };
+        uut.clock.connect();
+        uut.reset.connect();
+        uut.reset_n.connect();
+        uut.connect_all();
+        uut
+    }
+
+    #[test]
+    fn test_dff_with_reset_verilog() {
+        let vlog = generate_verilog(&counters());
+        assert!(vlog.contains("always @(posedge clock) begin"));
+        assert!(vlog.contains("always @(posedge clock or negedge reset) begin"));
+        assert!(vlog.contains("if (!reset)"));
+        assert!(vlog.contains("localparam  reset_value = 4'h3;"));
+        assert!(vlog.contains("sync$reset = reset;"));
+        assert!(vlog.contains("asynch$reset = reset_n;"));
+        assert!(!vlog.contains("initial begin"));
+        yosys_validate("dff_with_reset", &vlog).unwrap();
+    }
+
+    #[test]
+    fn test_dff_with_reset_vhdl() {
+        let vhdl = generate_hdl(HDLLanguage::VHDL, &counters());
+        assert!(vhdl.contains("if reset(0) = '1' then"));
+        assert!(vhdl.contains("process(clock, reset)"));
+        assert!(vhdl.contains("elsif rising_edge(clock(0)) then"));
+    }
+
+    #[test]
+    fn test_dff_with_reset_simulation() {
+        let mut sim = Simulation::new();
+        sim.add_clock(5, |x: &mut Box<Counters>| x.clock.next = !x.clock.val());
+        sim.add_testbench(move |mut sim: Sim<Counters>| {
+            let mut x = sim.init()?;
+            x.reset.next = Reset::assert();
+            x.reset_n.next = ResetN::assert();
+            wait_clock_cycles!(sim, clock, x, 2);
+            sim_assert_eq!(sim, x.sync.q.val(), 3, x);
+            sim_assert_eq!(sim, x.asynch.q.val(), 0, x);
+            x.reset.next = Reset::release();
+            x.reset_n.next = ResetN::release();
+            wait_clock_cycles!(sim, clock, x, 4);
+            sim_assert_eq!(sim, x.sync.q.val(), 7, x);
+            sim_assert_eq!(sim, x.asynch.q.val(), 4, x);
+            // The asynchronous reset takes effect without waiting for the clock
+            x.reset_n.next = ResetN::assert();
+            x = sim.wait(1, x)?;
+            sim_assert_eq!(sim, x.asynch.q.val(), 0, x);
+            sim_assert_eq!(sim, x.sync.q.val(), 7, x);
+            wait_clock_cycle!(sim, clock, x);
+            sim_assert_eq!(sim, x.asynch.q.val(), 0, x);
+            sim.done(x)
+        });
+        sim.run(Box::new(counters()), 1000).unwrap();
+    }
+}
@@ -39,6 +39,28 @@ always @(posedge clk) q <= d;",
             self.q.verilog()
         ))
     }
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        match lang {
+            HDLLanguage::Verilog | HDLLanguage::SystemVerilog => None,
+            HDLLanguage::VHDL => Some(format!(
+                "\
+edge_ff: block
+    signal state : {} := {};
+begin
+    process(clk)
+    begin
+        if rising_edge(clk(0)) then
+            state <= d;
+        end if;
+    end process;
+    q <= state;
+end block edge_ff;
+",
+                vhdl_type(T::BITS, T::descriptor().kind.is_signed()),
+                vhdl_bit_string(&self.q.verilog(), T::BITS)
+            )),
+        }
+    }
     fn timing(&self) -> Vec<TimingInfo> {
         vec![TimingInfo {
             name: "edge_ff".to_string(),
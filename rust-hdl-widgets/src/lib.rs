@@ -3,6 +3,7 @@ pub mod auto_reset;
 pub mod delay_line;
 pub mod dff;
 pub mod dff_with_init;
+pub mod dff_with_reset;
 pub mod edge_detector;
 pub mod edge_ff;
 pub mod fifo;
@@ -22,3 +23,4 @@ pub mod strobe;
 pub mod synchronizer;
 //pub mod test_helpers;
 pub mod tristate;
+pub mod uart;
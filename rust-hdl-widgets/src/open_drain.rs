@@ -28,6 +28,21 @@ impl Logic for OpenDrainBuffer {
     always @(*) control$line_state = bus;"
         ))
     }
+
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        match lang {
+            HDLLanguage::Verilog | HDLLanguage::SystemVerilog => None,
+            HDLLanguage::VHDL => Some(format!(
+                "\
+{bus} <= \"0\" when {drive_low}(0) = '1' else \"Z\";
+{line_state} <= {bus};
+",
+                bus = vhdl_ident("bus"),
+                drive_low = vhdl_ident("control$drive_low"),
+                line_state = vhdl_ident("control$line_state")
+            )),
+        }
+    }
 }
 
 #[test]
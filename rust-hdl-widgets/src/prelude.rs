@@ -6,7 +6,9 @@ pub use crate::declare_sync_fifo;
 pub use crate::delay_line::DelayLine;
 pub use crate::dff::DFF;
 pub use crate::dff_setup;
+pub use crate::dff_setup_with_reset;
 pub use crate::dff_with_init::DFFWithInit;
+pub use crate::dff_with_reset::DFFWithReset;
 pub use crate::edge_detector::EdgeDetector;
 pub use crate::fifo::async_fifo::AsynchronousFIFO;
 pub use crate::fifo::cross_fifo::CrossNarrowFIFO;
@@ -46,6 +48,10 @@ pub use crate::spi::slave::SPISlave;
 pub use crate::strobe::Strobe;
 pub use crate::synchronizer::{BitSynchronizer, SyncReceiver, SyncSender, VectorSynchronizer};
 pub use crate::tristate::TristateBuffer;
+pub use crate::uart::receiver::UARTReceiver;
+pub use crate::uart::transmitter::UARTTransmitter;
+pub use crate::uart::{UARTConfig, UARTParity};
 pub use crate::{
     i2c_begin_read, i2c_begin_write, i2c_end_transmission, i2c_read, i2c_read_last, i2c_write,
 };
+pub use crate::{uart_receive, uart_send};
@@ -1,4 +1,4 @@
-use crate::ramrom::rom::make_btree_from_iterable;
+use crate::ramrom::rom::{make_btree_from_iterable, vhdl_contents};
 use rust_hdl_core::prelude::*;
 use rust_hdl_core::timing::TimingInfo;
 use std::collections::BTreeMap;
@@ -11,7 +11,7 @@ pub struct RAMWrite<D: Synth, const N: usize> {
     pub enable: Signal<In, bool>,
 }
 
-#[derive(LogicBlock, Default)]
+#[derive(LogicBlock)]
 pub struct RAM<D: Synth, const N: usize> {
     pub read_address: Signal<In, Bits<N>>,
     pub read_clock: Signal<In, Clock>,
@@ -26,12 +26,24 @@ pub struct RAM<D: Synth, const N: usize> {
 impl<D: Synth, const N: usize> RAM<D, N> {
     pub fn new(values: BTreeMap<Bits<N>, D>) -> Self {
         Self {
+            read_address: Default::default(),
+            read_clock: Default::default(),
+            read_data: Default::default(),
+            write_address: Default::default(),
+            write_clock: Default::default(),
+            write_data: Default::default(),
+            write_enable: Default::default(),
             _sim: Box::new(values),
-            ..Default::default()
         }
     }
 }
 
+impl<D: Synth, const N: usize> Default for RAM<D, N> {
+    fn default() -> Self {
+        Self::new(BTreeMap::new())
+    }
+}
+
 impl<I: Iterator<Item = D>, D: Synth, const N: usize> From<I> for RAM<D, N> {
     fn from(v: I) -> Self {
         Self::new(make_btree_from_iterable(v))
@@ -44,7 +56,7 @@ impl<D: Synth, const N: usize> Logic for RAM<D, N> {
             self.read_data.next = *self
                 ._sim
                 .get(&self.read_address.val())
-                .unwrap_or(&D::default());
+                .unwrap_or(&D::default_value());
         }
         if self.write_clock.pos_edge() && self.write_enable.val() {
             self._sim
@@ -97,6 +109,38 @@ end
         ))
     }
 
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        match lang {
+            HDLLanguage::Verilog | HDLLanguage::SystemVerilog => None,
+            HDLLanguage::VHDL => Some(format!(
+                "\
+memory: block
+    type mem_t is array (0 to {Acount}) of {D};
+    signal mem : mem_t := {init};
+begin
+    process(read_clock)
+    begin
+        if rising_edge(read_clock(0)) then
+            read_data <= mem(to_integer(read_address));
+        end if;
+    end process;
+    process(write_clock)
+    begin
+        if rising_edge(write_clock(0)) then
+            if write_enable(0) = '1' then
+                mem(to_integer(write_address)) <= write_data;
+            end if;
+        end if;
+    end process;
+end block memory;
+",
+                Acount = (1_usize << N) - 1,
+                D = vhdl_type(D::BITS, D::descriptor().kind.is_signed()),
+                init = vhdl_contents(&self._sim)
+            )),
+        }
+    }
+
     fn timing(&self) -> Vec<TimingInfo> {
         vec![
             TimingInfo {
@@ -117,4 +161,29 @@ end
             },
         ]
     }
+
+    // The contents of the memory, as (address, value) pairs
+    fn sim_state(&self) -> Vec<VCDValue> {
+        self._sim
+            .iter()
+            .flat_map(|(address, value)| [address.vcd(), value.vcd()])
+            .collect()
+    }
+
+    fn set_sim_state(&mut self, state: &[VCDValue]) -> bool {
+        let mut contents = BTreeMap::new();
+        for pair in state.chunks(2) {
+            match pair {
+                [address, value] => match (Bits::<N>::from_vcd(address), D::from_vcd(value)) {
+                    (Some(address), Some(value)) => {
+                        contents.insert(address, value);
+                    }
+                    _ => return false,
+                },
+                _ => return false,
+            }
+        }
+        *self._sim = contents;
+        true
+    }
 }
@@ -12,7 +12,7 @@ impl<D: Synth, const N: usize> ROM<D, N> {
     pub fn new(values: BTreeMap<Bits<N>, D>) -> Self {
         Self {
             address: Signal::default(),
-            data: Signal::new_with_default(D::default()),
+            data: Signal::new_with_default(D::default_value()),
             _sim: Box::new(values),
         }
     }
@@ -29,6 +29,26 @@ pub fn make_btree_from_iterable<I: Iterator<Item = D>, D: Synth, const N: usize>
     values
 }
 
+// The VHDL aggregate holding the contents of a memory.  Locations that are
+// not listed hold the default value.
+pub(crate) fn vhdl_contents<D: Synth, const N: usize>(values: &BTreeMap<Bits<N>, D>) -> String {
+    let entries = values
+        .iter()
+        .map(|x| {
+            format!(
+                "        {} => {},\n",
+                x.0.index(),
+                vhdl_bit_string(&x.1.verilog(), D::BITS)
+            )
+        })
+        .collect::<String>();
+    format!(
+        "(\n{}        others => {})",
+        entries,
+        vhdl_bit_string(&D::default_value().verilog(), D::BITS)
+    )
+}
+
 impl<I: Iterator<Item = D>, D: Synth, const N: usize> From<I> for ROM<D, N> {
     fn from(v: I) -> Self {
         Self::new(make_btree_from_iterable(v))
@@ -37,7 +57,10 @@ impl<I: Iterator<Item = D>, D: Synth, const N: usize> From<I> for ROM<D, N> {
 
 impl<D: Synth, const N: usize> Logic for ROM<D, N> {
     fn update(&mut self) {
-        self.data.next = *self._sim.get(&self.address.val()).unwrap_or(&D::default());
+        self.data.next = *self
+            ._sim
+            .get(&self.address.val())
+            .unwrap_or(&D::default_value());
     }
 
     fn connect(&mut self) {
@@ -66,7 +89,38 @@ case (address)
 endcase
         ",
             cases = cases,
-            default = D::default().verilog().to_string()
+            default = D::default_value().verilog().to_string()
         ))
     }
+
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        match lang {
+            HDLLanguage::Verilog | HDLLanguage::SystemVerilog => None,
+            HDLLanguage::VHDL => {
+                let cases = self
+                    ._sim
+                    .iter()
+                    .map(|x| {
+                        format!(
+                            "        when {} => data <= {};\n",
+                            vhdl_bit_string(&x.0.verilog(), N),
+                            vhdl_bit_string(&x.1.verilog(), D::BITS)
+                        )
+                    })
+                    .collect::<String>();
+                Some(format!(
+                    "\
+process(all)
+begin
+    case address is
+{cases}        when others => data <= {default};
+    end case;
+end process;
+",
+                    cases = cases,
+                    default = vhdl_bit_string(&D::default_value().verilog(), D::BITS)
+                ))
+            }
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use crate::ramrom::rom::make_btree_from_iterable;
+use crate::ramrom::rom::{make_btree_from_iterable, vhdl_contents};
 use rust_hdl_core::prelude::*;
 use rust_hdl_core::timing::TimingInfo;
 use std::collections::BTreeMap;
@@ -15,7 +15,7 @@ impl<D: Synth, const N: usize> SyncROM<D, N> {
     pub fn new(values: BTreeMap<Bits<N>, D>) -> Self {
         Self {
             address: Signal::default(),
-            data: Signal::new_with_default(D::default()),
+            data: Signal::new_with_default(D::default_value()),
             clock: Signal::default(),
             _sim: Box::new(values),
         }
@@ -31,7 +31,10 @@ impl<I: Iterator<Item = D>, D: Synth, const N: usize> From<I> for SyncROM<D, N>
 impl<D: Synth, const N: usize> Logic for SyncROM<D, N> {
     fn update(&mut self) {
         if self.clock.pos_edge() {
-            self.data.next = *self._sim.get(&self.address.val()).unwrap_or(&D::default());
+            self.data.next = *self
+                ._sim
+                .get(&self.address.val())
+                .unwrap_or(&D::default_value());
         }
     }
 
@@ -68,6 +71,29 @@ end",
             init = init
         ))
     }
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        match lang {
+            HDLLanguage::Verilog | HDLLanguage::SystemVerilog => None,
+            HDLLanguage::VHDL => Some(format!(
+                "\
+memory: block
+    type mem_t is array (0 to {Acount}) of {D};
+    constant mem : mem_t := {init};
+begin
+    process(clock)
+    begin
+        if rising_edge(clock(0)) then
+            data <= mem(to_integer(address));
+        end if;
+    end process;
+end block memory;
+",
+                Acount = (1_usize << N) - 1,
+                D = vhdl_type(D::BITS, D::descriptor().kind.is_signed()),
+                init = vhdl_contents(&self._sim)
+            )),
+        }
+    }
     fn timing(&self) -> Vec<TimingInfo> {
         vec![TimingInfo {
             name: "sync_rom".to_string(),
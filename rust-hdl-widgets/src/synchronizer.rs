@@ -25,6 +25,9 @@ impl Logic for BitSynchronizer {
         self.dff1.d.next = self.dff0.q.val();
         self.sig_out.next = self.dff1.q.val();
     }
+    fn clock_crossings(&self) -> Vec<String> {
+        vec!["sig_in".into()]
+    }
 }
 
 #[test]
@@ -181,6 +184,11 @@ impl<T: Synth> Logic for SyncReceiver<T> {
             }
         }
     }
+    // The crossing data is held steady by the sender, and is only sampled
+    // once the (synchronized) flag says it is valid.
+    fn clock_crossings(&self) -> Vec<String> {
+        vec!["sig_cross".into()]
+    }
 }
 
 #[test]
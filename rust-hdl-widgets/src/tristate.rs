@@ -22,7 +22,7 @@ use rust_hdl_core::prelude::*;
 ///    bus: Signal<InOut, Bits<8>>,
 /// }
 /// ```
-#[derive(LogicBlock, Default)]
+#[derive(LogicBlock)]
 pub struct TristateBuffer<D: Synth> {
     /// The tristated signals come out of this pin.  This should be a top level signal in your design.
     pub bus: Signal<InOut, D>,
@@ -36,6 +36,17 @@ pub struct TristateBuffer<D: Synth> {
     pub read_data: Signal<Out, D>,
 }
 
+impl<D: Synth> Default for TristateBuffer<D> {
+    fn default() -> Self {
+        Self {
+            bus: Default::default(),
+            write_enable: Default::default(),
+            write_data: Default::default(),
+            read_data: Default::default(),
+        }
+    }
+}
+
 impl<D: Synth> Logic for TristateBuffer<D> {
     fn update(&mut self) {
         if self.write_enable.val() {
@@ -58,6 +69,19 @@ always @(*) read_data = bus;",
             WIDTH = D::BITS
         ))
     }
+
+    fn hdl_custom(&self, lang: HDLLanguage) -> Option<String> {
+        match lang {
+            HDLLanguage::Verilog | HDLLanguage::SystemVerilog => None,
+            HDLLanguage::VHDL => Some(format!(
+                "\
+{bus} <= write_data when write_enable(0) = '1' else (others => 'Z');
+read_data <= {bus};
+",
+                bus = vhdl_ident("bus")
+            )),
+        }
+    }
 }
 
 #[test]
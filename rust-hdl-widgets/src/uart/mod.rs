@@ -0,0 +1,102 @@
+pub mod receiver;
+pub mod sim;
+pub mod transmitter;
+
+/// The parity bit (if any) that follows the data bits of a UART frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UARTParity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Configuration of a UART link.  Frames are always 8 data bits, sent LSB first,
+/// preceded by a start bit and followed by an optional parity bit and 1 or 2 stop bits.
+#[derive(Copy, Clone, Debug)]
+pub struct UARTConfig {
+    /// The frequency (in Hz) of the clock driving the UART.
+    pub clock_speed: u64,
+    /// The baud rate (bits per second) of the link.
+    pub baud_rate: u64,
+    pub parity: UARTParity,
+    /// The number of stop bits (1 or 2).
+    pub stop_bits: usize,
+}
+
+impl UARTConfig {
+    /// The number of clock cycles per bit (rounded to the nearest integer).
+    pub fn clocks_per_bit(&self) -> u64 {
+        (self.clock_speed as f64 / self.baud_rate as f64).round() as u64
+    }
+    /// The total number of bits in a frame (including the start bit).
+    pub fn frame_bits(&self) -> usize {
+        assert!(self.stop_bits == 1 || self.stop_bits == 2);
+        1 + 8 + self.parity_bits() + self.stop_bits
+    }
+    fn parity_bits(&self) -> usize {
+        if self.parity == UARTParity::None {
+            0
+        } else {
+            1
+        }
+    }
+    fn parity_bit(&self, byte: u8) -> bool {
+        (byte.count_ones() % 2 == 1) ^ (self.parity == UARTParity::Odd)
+    }
+    /// The line levels (in order) used to send `byte`.
+    pub fn frame(&self, byte: u8) -> Vec<bool> {
+        let mut frame = vec![false];
+        frame.extend((0..8).map(|i| byte & (1 << i) != 0));
+        if self.parity != UARTParity::None {
+            frame.push(self.parity_bit(byte));
+        }
+        frame.extend(std::iter::repeat_n(true, self.stop_bits));
+        frame
+    }
+    /// Decode the line levels of a frame (as produced by [UARTConfig::frame]).  Returns
+    /// `None` if the start, parity or stop bits are wrong.
+    pub fn decode(&self, frame: &[bool]) -> Option<u8> {
+        if frame.len() != self.frame_bits() || frame[0] {
+            return None;
+        }
+        let byte = (0..8).fold(0_u8, |acc, i| acc | ((frame[i + 1] as u8) << i));
+        if self.parity != UARTParity::None && frame[9] != self.parity_bit(byte) {
+            return None;
+        }
+        if !frame[9 + self.parity_bits()..].iter().all(|x| *x) {
+            return None;
+        }
+        Some(byte)
+    }
+}
+
+#[test]
+fn test_uart_frames_round_trip() {
+    for parity in [UARTParity::None, UARTParity::Even, UARTParity::Odd] {
+        for stop_bits in [1, 2] {
+            let config = UARTConfig {
+                clock_speed: 48_000_000,
+                baud_rate: 115_200,
+                parity,
+                stop_bits,
+            };
+            for byte in [0x00, 0x01, 0x5A, 0xA5, 0xFF] {
+                let frame = config.frame(byte);
+                assert_eq!(frame.len(), config.frame_bits());
+                assert_eq!(config.decode(&frame), Some(byte));
+            }
+        }
+    }
+    let config = UARTConfig {
+        clock_speed: 48_000_000,
+        baud_rate: 115_200,
+        parity: UARTParity::Even,
+        stop_bits: 1,
+    };
+    assert_eq!(config.clocks_per_bit(), 417);
+    // 0x07 has an odd number of ones, so the even parity bit is set
+    assert!(config.frame(0x07)[9]);
+    let mut frame = config.frame(0x07);
+    frame[9] = false;
+    assert_eq!(config.decode(&frame), None);
+}
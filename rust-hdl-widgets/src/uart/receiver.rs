@@ -0,0 +1,205 @@
+use crate::uart::{UARTConfig, UARTParity};
+use crate::{dff::DFF, dff_setup, synchronizer::BitSynchronizer};
+use rust_hdl_core::prelude::*;
+
+#[derive(Copy, Clone, PartialEq, Debug, LogicState)]
+enum UARTReceiverState {
+    Idle,
+    Start,
+    Data,
+    Parity,
+    Stop,
+    Break,
+}
+
+/// A UART receiver.  The [`rx`](Self::rx) line is synchronized to the clock and sampled
+/// at 16 times the baud rate.  The sample period is a whole number of clocks, so
+/// [`new`](Self::new) panics unless there are at least 16 clocks per bit, and the bit
+/// period it gives is within 2% of the baud rate.  A falling edge starts a frame, and each bit is sampled
+/// in the middle of its bit period.  When the (first) stop bit has been sampled,
+/// [`data_valid`](Self::data_valid) is asserted for one clock cycle.  If the stop bit was
+/// low, or the parity bit was wrong, then [`framing_error`](Self::framing_error) or
+/// [`parity_error`](Self::parity_error) are asserted along with [`data_valid`](Self::data_valid).
+#[derive(LogicBlock)]
+pub struct UARTReceiver {
+    pub clock: Signal<In, Clock>,
+    /// The serial input.  This is asynchronous to the clock.
+    pub rx: Signal<In, Bit>,
+    /// The last byte received.  Valid when [`data_valid`](Self::data_valid) is asserted.
+    pub data: Signal<Out, Bits<8>>,
+    pub data_valid: Signal<Out, Bit>,
+    pub framing_error: Signal<Out, Bit>,
+    pub parity_error: Signal<Out, Bit>,
+    sync: BitSynchronizer,
+    sample_counter: DFF<Bits<16>>,
+    sample_end: Constant<Bits<16>>,
+    sample: Signal<Local, Bit>,
+    state: DFF<UARTReceiverState>,
+    ticks: DFF<Bits<4>>,
+    bit_count: DFF<Bits<4>>,
+    shift: DFF<Bits<8>>,
+    parity_bad: DFF<Bit>,
+    valid_flop: DFF<Bit>,
+    framing_flop: DFF<Bit>,
+    parity_flop: DFF<Bit>,
+    parity_enable: Constant<Bit>,
+    parity_odd: Constant<Bit>,
+}
+
+impl UARTReceiver {
+    pub fn new(config: UARTConfig) -> Self {
+        let clocks_per_bit = config.clock_speed as f64 / config.baud_rate as f64;
+        assert!(
+            clocks_per_bit >= 16.0,
+            "UART receiver needs at least 16 clocks per bit (got {:.2})",
+            clocks_per_bit
+        );
+        let clocks_per_sample = (clocks_per_bit / 16.0).round() as u64;
+        assert!(clocks_per_sample < (1 << 16));
+        // The sample clock drifts by this much on each bit.  By the last stop bit of an
+        // 8O2 frame (12 bits), 2% adds up to about a quarter of a bit period.
+        let error = ((16 * clocks_per_sample) as f64 - clocks_per_bit).abs() / clocks_per_bit;
+        assert!(
+            error <= 0.02,
+            "UART receiver timing error of {:.1}% per bit is too large (clocks per bit {:.2} sampled every {} clocks)",
+            error * 100.0,
+            clocks_per_bit,
+            clocks_per_sample
+        );
+        Self {
+            clock: Default::default(),
+            rx: Default::default(),
+            data: Default::default(),
+            data_valid: Default::default(),
+            framing_error: Default::default(),
+            parity_error: Default::default(),
+            sync: Default::default(),
+            sample_counter: Default::default(),
+            sample_end: Constant::new((clocks_per_sample - 1).to_bits()),
+            sample: Default::default(),
+            state: Default::default(),
+            ticks: Default::default(),
+            bit_count: Default::default(),
+            shift: Default::default(),
+            parity_bad: Default::default(),
+            valid_flop: Default::default(),
+            framing_flop: Default::default(),
+            parity_flop: Default::default(),
+            parity_enable: Constant::new(config.parity != UARTParity::None),
+            parity_odd: Constant::new(config.parity == UARTParity::Odd),
+        }
+    }
+}
+
+impl Logic for UARTReceiver {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(
+            self,
+            clock,
+            state,
+            ticks,
+            sample_counter,
+            bit_count,
+            shift,
+            parity_bad,
+            valid_flop,
+            framing_flop,
+            parity_flop
+        );
+        clock!(self, clock, sync);
+        self.sync.sig_in.next = self.rx.val();
+        // Sample the line at 16 times the baud rate
+        self.sample_counter.d.next = self.sample_counter.q.val() + 1;
+        self.sample.next = self.sample_counter.q.val() == self.sample_end.val();
+        if self.sample.val() {
+            self.sample_counter.d.next = 0.into();
+        }
+        self.data.next = self.shift.q.val();
+        self.data_valid.next = self.valid_flop.q.val();
+        self.framing_error.next = self.framing_flop.q.val();
+        self.parity_error.next = self.parity_flop.q.val();
+        self.valid_flop.d.next = false;
+        self.framing_flop.d.next = false;
+        self.parity_flop.d.next = false;
+        // The tick counter wraps every 16 samples (i.e., once per bit)
+        if self.sample.val() {
+            self.ticks.d.next = self.ticks.q.val() + 1;
+        }
+        match self.state.q.val() {
+            UARTReceiverState::Idle => {
+                self.ticks.d.next = 0.into();
+                self.sample_counter.d.next = 0.into();
+                if !self.sync.sig_out.val() {
+                    self.state.d.next = UARTReceiverState::Start;
+                }
+            }
+            UARTReceiverState::Start => {
+                // Check the start bit in the middle of the bit period
+                if self.sample.val() & (self.ticks.q.val() == 7) {
+                    self.ticks.d.next = 0.into();
+                    self.bit_count.d.next = 0.into();
+                    self.parity_bad.d.next = false;
+                    if self.sync.sig_out.val() {
+                        // Glitch - go back to waiting for a start bit
+                        self.state.d.next = UARTReceiverState::Idle;
+                    } else {
+                        self.state.d.next = UARTReceiverState::Data;
+                    }
+                }
+            }
+            UARTReceiverState::Data => {
+                if self.sample.val() & (self.ticks.q.val() == 15) {
+                    self.shift.d.next =
+                        (self.shift.q.val() >> 1).replace_bit(7, self.sync.sig_out.val());
+                    self.bit_count.d.next = self.bit_count.q.val() + 1;
+                    if self.bit_count.q.val() == 7 {
+                        if self.parity_enable.val() {
+                            self.state.d.next = UARTReceiverState::Parity;
+                        } else {
+                            self.state.d.next = UARTReceiverState::Stop;
+                        }
+                    }
+                }
+            }
+            UARTReceiverState::Parity => {
+                if self.sample.val() & (self.ticks.q.val() == 15) {
+                    self.parity_bad.d.next =
+                        self.shift.q.val().xor() ^ self.sync.sig_out.val() ^ self.parity_odd.val();
+                    self.state.d.next = UARTReceiverState::Stop;
+                }
+            }
+            UARTReceiverState::Stop => {
+                if self.sample.val() & (self.ticks.q.val() == 15) {
+                    self.valid_flop.d.next = true;
+                    self.framing_flop.d.next = !self.sync.sig_out.val();
+                    self.parity_flop.d.next = self.parity_bad.q.val();
+                    if self.sync.sig_out.val() {
+                        self.state.d.next = UARTReceiverState::Idle;
+                    } else {
+                        self.state.d.next = UARTReceiverState::Break;
+                    }
+                }
+            }
+            UARTReceiverState::Break => {
+                // Wait for the line to return to idle before looking for a start bit
+                if self.sync.sig_out.val() {
+                    self.state.d.next = UARTReceiverState::Idle;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_uart_receiver_is_synthesizable() {
+    let mut uut = UARTReceiver::new(UARTConfig {
+        clock_speed: 48_000_000,
+        baud_rate: 115_200,
+        parity: UARTParity::Even,
+        stop_bits: 1,
+    });
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("uart_rx", &vlog).unwrap();
+}
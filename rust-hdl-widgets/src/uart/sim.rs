@@ -0,0 +1,46 @@
+// A simulated UART terminal.  These macros are used in testbenches to play the part of the
+// device at the other end of a UART link, by driving (or sampling) the serial line directly.
+// Timing is derived from the clock of the testbench, so `clock` must run at the
+// `clock_speed` of the [UARTConfig](crate::uart::UARTConfig).
+
+/// Send the bytes in `$data` on the serial line `$line` (an input of the circuit) using
+/// the given [UARTConfig](crate::uart::UARTConfig).
+#[macro_export]
+macro_rules! uart_send {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($line: ident).+, $config: expr, $data: expr) => {
+        let config: UARTConfig = $config;
+        wait_clock_true!($sim, $($clock).+, $uut);
+        for byte in $data {
+            for bit in config.frame(*byte) {
+                $uut.$($line).+.next = bit;
+                wait_clock_cycles!($sim, $($clock).+, $uut, config.clocks_per_bit());
+            }
+        }
+    };
+}
+
+/// Receive `$count` bytes from the serial line `$line` (an output of the circuit) using
+/// the given [UARTConfig](crate::uart::UARTConfig), and return them as a `Vec<u8>`.  The
+/// simulation halts if a frame is not valid.
+#[macro_export]
+macro_rules! uart_receive {
+    ($sim: ident, $($clock: ident).+, $uut: ident, $($line: ident).+, $config: expr, $count: expr) => {{
+        let config: UARTConfig = $config;
+        let clocks_per_bit = config.clocks_per_bit();
+        let mut received: Vec<u8> = vec![];
+        for _ in 0..$count {
+            // Wait for the start bit, and then sample each bit in the middle
+            $uut = $sim.watch(|x| !x.$($line).+.val(), $uut)?;
+            wait_clock_cycles!($sim, $($clock).+, $uut, clocks_per_bit / 2);
+            let mut frame = vec![$uut.$($line).+.val()];
+            for _ in 1..config.frame_bits() {
+                wait_clock_cycles!($sim, $($clock).+, $uut, clocks_per_bit);
+                frame.push($uut.$($line).+.val());
+            }
+            let byte = config.decode(&frame);
+            sim_assert!($sim, byte.is_some(), $uut);
+            received.push(byte.unwrap());
+        }
+        received
+    }};
+}
@@ -0,0 +1,123 @@
+use crate::uart::{UARTConfig, UARTParity};
+use crate::{dff::DFF, dff_setup, dff_with_init::DFFWithInit};
+use rust_hdl_core::prelude::*;
+
+#[derive(Copy, Clone, PartialEq, Debug, LogicState)]
+enum UARTTransmitterState {
+    Idle,
+    Sending,
+}
+
+/// A UART transmitter.  When [`start_send`](Self::start_send) is asserted while the
+/// transmitter is not [`busy`](Self::busy), the byte on [`data`](Self::data) is framed
+/// (according to the [UARTConfig]) and shifted out on [`tx`](Self::tx).  The line idles high.
+#[derive(LogicBlock)]
+pub struct UARTTransmitter {
+    pub clock: Signal<In, Clock>,
+    /// The byte to send.  Sampled when [`start_send`](Self::start_send) is asserted.
+    pub data: Signal<In, Bits<8>>,
+    /// Assert for one clock cycle to send [`data`](Self::data).  Ignored while busy.
+    pub start_send: Signal<In, Bit>,
+    /// Asserted while a frame is being sent.
+    pub busy: Signal<Out, Bit>,
+    /// The serial output.
+    pub tx: Signal<Out, Bit>,
+    shift: DFF<Bits<12>>,
+    count: DFF<Bits<4>>,
+    state: DFF<UARTTransmitterState>,
+    tx_flop: DFFWithInit<Bit>,
+    baud_counter: DFF<Bits<16>>,
+    bit_end: Constant<Bits<16>>,
+    // The stop bits (and everything above them) as ones, less the start bit
+    stop_mask: Constant<Bits<12>>,
+    // The parity bit (less the start bit), or zero if there is no parity
+    parity_mask: Constant<Bits<12>>,
+    parity_odd: Constant<Bit>,
+    frame_bits: Constant<Bits<4>>,
+}
+
+impl UARTTransmitter {
+    pub fn new(config: UARTConfig) -> Self {
+        let clocks_per_bit = config.clocks_per_bit();
+        assert!((2..(1 << 16)).contains(&clocks_per_bit));
+        let frame_bits = config.frame_bits();
+        let stop_start = frame_bits - config.stop_bits - 1;
+        let stop_mask: u64 = (stop_start..12).map(|i| 1_u64 << i).sum();
+        let parity_mask: u64 = if config.parity == UARTParity::None {
+            0
+        } else {
+            1 << 8
+        };
+        Self {
+            clock: Default::default(),
+            data: Default::default(),
+            start_send: Default::default(),
+            busy: Default::default(),
+            tx: Default::default(),
+            shift: Default::default(),
+            count: Default::default(),
+            state: Default::default(),
+            tx_flop: DFFWithInit::new(true),
+            baud_counter: Default::default(),
+            bit_end: Constant::new((clocks_per_bit - 1).to_bits()),
+            stop_mask: Constant::new(stop_mask.to_bits()),
+            parity_mask: Constant::new(parity_mask.to_bits()),
+            parity_odd: Constant::new(config.parity == UARTParity::Odd),
+            frame_bits: Constant::new((frame_bits - 1).to_bits()),
+        }
+    }
+}
+
+impl Logic for UARTTransmitter {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, shift, count, state, tx_flop, baud_counter);
+        self.tx.next = self.tx_flop.q.val();
+        self.busy.next = true;
+        match self.state.q.val() {
+            UARTTransmitterState::Idle => {
+                self.busy.next = false;
+                if self.start_send.val() {
+                    // Send the start bit now, and queue up the data and stop bits
+                    self.tx_flop.d.next = false;
+                    self.shift.d.next = bit_cast::<12, 8>(self.data.val()) | self.stop_mask.val();
+                    if self.data.val().xor() ^ self.parity_odd.val() {
+                        self.shift.d.next = bit_cast::<12, 8>(self.data.val())
+                            | self.stop_mask.val()
+                            | self.parity_mask.val();
+                    }
+                    self.count.d.next = self.frame_bits.val();
+                    self.baud_counter.d.next = 0.into();
+                    self.state.d.next = UARTTransmitterState::Sending;
+                }
+            }
+            UARTTransmitterState::Sending => {
+                self.baud_counter.d.next = self.baud_counter.q.val() + 1;
+                if self.baud_counter.q.val() == self.bit_end.val() {
+                    self.baud_counter.d.next = 0.into();
+                    if self.count.q.val() == 0 {
+                        // The last stop bit has been on the line for a full bit period
+                        self.state.d.next = UARTTransmitterState::Idle;
+                    } else {
+                        self.tx_flop.d.next = self.shift.q.val().get_bit(0);
+                        self.shift.d.next = self.shift.q.val() >> 1;
+                        self.count.d.next = self.count.q.val() - 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_uart_transmitter_is_synthesizable() {
+    let mut uut = UARTTransmitter::new(UARTConfig {
+        clock_speed: 48_000_000,
+        baud_rate: 115_200,
+        parity: UARTParity::Odd,
+        stop_bits: 2,
+    });
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    yosys_validate("uart_tx", &vlog).unwrap();
+}
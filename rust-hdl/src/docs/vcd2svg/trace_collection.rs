@@ -132,7 +132,9 @@ impl TraceCollection {
             let sig = header
                 .find_var(&path)
                 .ok_or_else(|| anyhow::Error::msg(format!("cannot resolve signal {}", signal)))?;
-            if sig.size == 0 {
+            // Real valued (i.e., fixed point) signals are drawn like enums, with the
+            // value written out as a string
+            if sig.size == 0 || sig.var_type == vcd::VarType::Real {
                 string_valued.insert(sig.code, StringTrace::new());
             } else if sig.size == 1 {
                 scalar_valued.insert(sig.code, BinaryTrace::new());
@@ -164,6 +166,14 @@ impl TraceCollection {
                         })
                     }
                 }
+                vcd::Command::ChangeReal(i, v) => {
+                    if let Some(s) = string_valued.get_mut(&i) {
+                        s.push(TimedValue {
+                            time: timestamp,
+                            value: v.to_string(),
+                        })
+                    }
+                }
                 vcd::Command::ChangeString(i, v) => {
                     if let Some(s) = string_valued.get_mut(&i) {
                         s.push(TimedValue {
@@ -205,9 +205,55 @@
 //! assert_eq!(z, 0xDEAD_BEEF_u32.to_bits());
 //! ```
 //!
+//! Addition and subtraction wrap around (modulo `2^N`), just like they do in hardware.  If you
+//! want to keep the carry (or the full product of a multiplication), use `wide_add` or
+//! `wide_mul` (and `wide_sub` for [Signed](core::signed::Signed) values).  These return a wider
+//! result, whose width is checked at compile time - one bit more than the wider argument for a sum,
+//! and the sum of the widths for a product.
+//! ```rust
+//! # use rust_hdl::prelude::*;
+//! let x: Bits<8> = 0xFF.into();
+//! let sum: Bits<9> = x.wide_add(x);
+//! let product: Bits<16> = x.wide_mul(x);
+//! assert_eq!(sum, 0x1FE);
+//! assert_eq!(product, 0xFE01);
+//! ```
+//!
 //! You can, of course, construct expressions of arbitrary complexity using parenthesis, etc.
 //! The only real surprise may be at synthesis time, when you try to fit the expression onto hardware.
 //!
+//! ### Fixed point numbers
+//!
+//! For DSP work, the [Fixed](core::fixed::Fixed) and [UFixed](core::fixed::UFixed) types hold
+//! signed and unsigned numbers with `I` integer bits and `F` fraction bits.  Coefficients can be
+//! converted from `f64`, and the `wide_add`, `wide_sub` and `wide_mul` methods return a type that
+//! holds the exact result.  To get back to a narrower type, use `truncate`, `round`, `saturate`
+//! or `round_saturate`.  These all work in an HDL kernel (where the format of the result of a
+//! conversion must be given explicitly), and fixed point signals show up as real numbers in
+//! a VCD trace.
+//! ```
+//! # use rust_hdl::prelude::*;
+//! #[derive(LogicBlock, Default)]
+//! struct Mixer {
+//!     pub x: Signal<In, Fixed<2, 10>>,
+//!     pub y: Signal<In, Fixed<2, 10>>,
+//!     pub out: Signal<Out, Fixed<2, 6>>,
+//! }
+//!
+//! impl Logic for Mixer {
+//!     #[hdl_gen]
+//!     fn update(&mut self) {
+//!         let product: Fixed<4, 20> = self.x.val().wide_mul(self.y.val());
+//!         self.out.next = product.round_saturate::<2, 6>();
+//!     }
+//! }
+//! # let mut uut = Mixer::default();
+//! # uut.x.connect();
+//! # uut.y.connect();
+//! # uut.connect_all();
+//! # assert!(generate_verilog(&uut).contains("fixed_s4_20_to_2_6_round_sat(_product)"));
+//! ```
+//!
 //! ### Signal Type
 //!
 //! *Signals are software abstractions to represent physical wires*.  The [Signal](core::signal::Signal)
@@ -397,6 +443,12 @@
 //! check_all(&BadActor::default()).unwrap()
 //! ```
 //!
+//! Each [PathedName](core::check_error::PathedName) in a [CheckError](core::check_error::CheckError)
+//! also carries the [SourceLocation] (file and line) of the kernel statement that refers to the signal,
+//! so in the example above, the error points at the line `self.in1.next = true;`.  The generated
+//! Verilog carries the same information - each assignment is tagged with a `(* src = "file.rs:line" *)`
+//! attribute, and other statements are preceded by a `// file.rs:line` comment.
+//!
 //! ## Traits
 //!
 //! There is only one trait that you typically need to implement to get things to work in RustHDL
@@ -524,7 +576,7 @@
 //! ```
 //!
 //! - The body of the `update` function must be a single block, consisting of statements.
-//! Local definitions and items are not allowed in HDL kernels.  The following, for example, will
+//! Items are not allowed in HDL kernels.  The following, for example, will
 //!fail.  This is an example of valid Rust that is not allowed in an HDL kernel.
 //!
 //!```compile_fail
@@ -535,12 +587,35 @@
 //! impl Logic for Foo {
 //!    #[hdl_gen]
 //!    fn update (&mut self) {
-//!      // Fails because local items are not allowed in HDL kernels.
-//!      let x = 32;
+//!      // Fails because items are not allowed in HDL kernels.
+//!      fn helper() {}
 //!    }
 //! }
 //!```
 //!
+//! - Immutable `let` bindings (including shadowed ones) are allowed, and become local signals
+//!   in the generated code, with a width that comes from the Rust type.  Bindings must be
+//!   initialized where they are declared (so they cannot infer a latch).
+//!
+//! ```rust
+//! # use rust_hdl::prelude::*;
+//!
+//! #[derive(LogicBlock)]
+//! struct Foo {
+//!    pub sig1: Signal<In, Bits<8>>,
+//!    pub sig2: Signal<Out, Bits<8>>,
+//! }
+//!
+//! impl Logic for Foo {
+//!    #[hdl_gen]
+//!    fn update(&mut self) {
+//!       let x = self.sig1.val() + 1;
+//!       let x = x << 1;
+//!       self.sig2.next = x;
+//!    }
+//! }
+//! ```
+//!
 //! - Assignments are allowed as long as you follow the rules about signals.  Types are
 //! still enforced by Rust.
 //!     - Indexed assignments are currently not supported
@@ -623,6 +698,36 @@
 //!     - `bits`
 //!     - `Bits`
 //!     - `Type::join` and `Type::link` used to link and join logical interfaces...
+//!     - functions marked with `#[hdl_function]`.  These are free functions of `Synth` values
+//!       (and may be generic over constants) whose body is a set of `let` bindings followed by
+//!       an expression (which may be an `if` or a `match`).  They run as ordinary Rust functions
+//!       in simulation, and become a Verilog `function` in each module that calls them.
+//!       The attribute defines a companion `<name>_hdl` function next to the original, so
+//!       calling a free function without the attribute fails with "cannot find function
+//!       `<name>_hdl`", and calling a function defined in another module needs the companion
+//!       to be imported as well (or the call to use the path to the function).  The arguments
+//!       can be `LogicStruct`s, whose fields are accessed as usual.
+//! ```rust
+//! # use rust_hdl::prelude::*;
+//!
+//! #[hdl_function]
+//! fn gray_encode<const N: usize>(x: Bits<N>) -> Bits<N> {
+//!     x ^ (x >> 1)
+//! }
+//!
+//! #[derive(LogicBlock)]
+//! struct Foo {
+//!     pub sig1: Signal<In, Bits<8>>,
+//!     pub sig2: Signal<Out, Bits<8>>,
+//! }
+//!
+//! impl Logic for Foo {
+//!     #[hdl_gen]
+//!     fn update(&mut self) {
+//!         self.sig2.next = gray_encode(self.sig1.val());
+//!     }
+//! }
+//! ```
 //! - Method calls - Kernels support the following limited set of method calls
 //!     - `get_bits` - extract a (fixed width) set of bits from a bit vector
 //!     - `get_bit` - extract a single bit from a bit vector
@@ -653,7 +758,9 @@
 //!     }
 //! }
 //! ```
-//! - Matches - Kernels support matching with literals or identifiers
+//! - Matches - Kernels support matching with literals or identifiers, or-patterns (`0 | 1`),
+//!   ranges of literals (`2..=9`) and the variants of enums (including the fields of variants
+//!   that carry data - see [Enums](#enums)).  Match guards are not supported.
 //! Matches are used for state machines and implementing ROMs.  
 //! For now, `match` is a statement, not an expression!  Maybe that will be fixed in a future
 //! version of RustHDL, but for now, the value of the `match` is ignored.
@@ -707,6 +814,7 @@
 //!     - `comment` - also a comment
 //!     - `assert` - converted to a comment
 //!     - `dff_setup` - setup a DFF - this macro is converted into the appropriate HDL
+//!     - `dff_setup_with_reset` - setup a `DFFWithReset`, which also connects a `Reset` or `ResetN` to it
 //!     - `clock` - clock a set of components - this macro is also converted into the appropriate HDL
 //! - Loops - `for` loops are supported for code generation
 //!     - In software parlance, all `for` loops are unrolled at compile time, so they must be of the form `for <ident> in <const>..<const>`.
@@ -915,7 +1023,7 @@
 //!
 //! ## Enums
 //!
-//! In keeping with Rust's strongly typed model, you can use enums (not sum types) in your HDL,
+//! In keeping with Rust's strongly typed model, you can use enums in your HDL,
 //! provided you derive the `LogicState` trait for them.  This makes your code much easier to
 //! read and debug, and `rustc` will make sure you don't do anything illegal with your
 //! enums.
@@ -1044,6 +1152,49 @@
 //! }
 //! ```
 //!
+//! Enums can also have variants that carry data (i.e., sum types).  These are stored as a
+//! tagged union - the tag (which variant it is) is held in the low bits, and the fields of
+//! the variant are packed above it, with the first field lowest.  The payload is as wide
+//! as the widest variant.  In a kernel, variants are built just as they are in Rust, and a
+//! `match` can bind the fields of a variant to names (or ignore them with `_` or `..`).
+//! The bindings behave just like `let` bindings.
+//!
+//! ```rust
+//! # use rust_hdl::prelude::*;
+//!
+//! #[derive(Copy, Clone, PartialEq, Debug, LogicState)]
+//! enum Command {
+//!     Nop,
+//!     Write(Bits<8>),                     // <-- The tag is 2 bits, and the payload
+//!     Seek { addr: Bits<10>, wrap: Bit }, //     is 11 bits, so `Command` is 13 bits
+//! }
+//!
+//! #[derive(LogicBlock, Default)]
+//! struct Decode {
+//!     pub cmd: Signal<In, Command>,
+//!     pub addr: Signal<Out, Bits<10>>,
+//!     pub data: Signal<Out, Bits<8>>,
+//!     pub reply: Signal<Out, Command>,
+//! }
+//!
+//! impl Logic for Decode {
+//!    #[hdl_gen]
+//!    fn update(&mut self) {
+//!       self.addr.next = 0.into();
+//!       self.data.next = 0.into();
+//!       self.reply.next = Command::Nop;
+//!       match self.cmd.val() {
+//!           Command::Write(data) => {
+//!               self.data.next = data;
+//!               self.reply.next = Command::Seek { addr: 1.into(), wrap: false };
+//!           }
+//!           Command::Seek { addr, .. } => self.addr.next = addr,
+//!           _ => {}
+//!       }
+//!    }
+//! }
+//! ```
+//!
 //! ## Interfaces
 //!
 //! One area you will encouter as your circuits become more complex is that the interfaces
@@ -1710,12 +1861,19 @@
 //!   - RustHDL (at least for this trivial example) is a pretty thin wrapper around Verilog.  That's
 //! good for compatibility with tooling.
 //!
+//! Each module is named after the path to its block (e.g., `top$my_reg` above).  Sub-blocks with the
+//! same structure share a single module, which is named after their type instead (e.g., `Strobe`).
+//! If they differ only in the values of constants or the widths of their signals (e.g., two `Strobe<32>`
+//! with different frequencies), the differences become Verilog `parameter`s of the shared module, and
+//! each instance sets them with a `#(...)` override.
+//!
 //! While most FPGAs will require you to use a proprietary and closed source toolchain to synthesize
 //! your design, you can use the open source [Yosys] compiler (if you have it installed) to
 //! check your designs.  For that, you can use the [yosys_validate] function, which runs the Verilog
 //! through some checks and reports on potential errors.  At the moment, [Yosys] is far more
 //! thorough in it's checking than RustHDL, so I highly recommend you install it and use the
-//! [yosys_validate] function on your generated Verilog.
+//! [yosys_validate] function on your generated Verilog.  Where it can, the error returned by
+//! [yosys_validate] (e.g., a latch inferred for a signal) points back to the line of the HDL kernel at fault.
 //!
 //! ## Struct valued signals
 //!
@@ -1840,6 +1998,40 @@
 //! don't get carried away.  Those expressions are evaluated by the HDL kernel generator and
 //! it has a limited vocab.
 //!
+//! Arrays of values (like `[Bits<8>; 16]`) can also be carried by a signal.  Unlike the arrays
+//! of circuits above, these can be indexed with a signal, so they are useful for register files
+//! and small memories.  Elements are read with `.val()[index]`, and written with
+//! `.next[index] = value`.  In the generated Verilog, an array that the kernel indexes is an
+//! unpacked array (e.g., `reg [7:0] regs [0:15]`), and each access is an index into it.  Module
+//! ports cannot be arrays in Verilog-2001, so ports (and the signals that connect to sub-modules)
+//! are packed into a vector (with element 0 in the low bits), which is copied to or from an
+//! unpacked array by the kernel.  Simulation traces show each element of the array as a separate
+//! signal.
+//! ```
+//! # use rust_hdl::prelude::*;
+//! #[derive(LogicBlock, Default)]
+//! struct RegisterFile {
+//!     pub clock: Signal<In, Clock>,
+//!     pub write_enable: Signal<In, Bit>,
+//!     pub write_address: Signal<In, Bits<4>>,
+//!     pub write_data: Signal<In, Bits<8>>,
+//!     pub read_address: Signal<In, Bits<4>>,
+//!     pub read_data: Signal<Out, Bits<8>>,
+//!     regs: DFF<[Bits<8>; 16]>,
+//! }
+//!
+//! impl Logic for RegisterFile {
+//!     #[hdl_gen]
+//!     fn update(&mut self) {
+//!         dff_setup!(self, clock, regs);
+//!         if self.write_enable.val() {
+//!             self.regs.d.next[self.write_address.val().index()] = self.write_data.val();
+//!         }
+//!         self.read_data.next = self.regs.q.val()[self.read_address.val().index()];
+//!     }
+//! }
+//! ```
+//!
 //! ## High Level Synthesis
 //!
 //! RustHDL supports it's own version of High Level Synthesis (HLS).  Normally, this is some kind
@@ -1970,6 +2162,45 @@
 //! }
 //! ```
 //!
+//! If all you need is the Verilog module itself, the `#[black_box]` attribute will write the
+//! struct for you.  It reads the module header (from a file relative to your crate, or from
+//! an inline string), and fills in a `Signal` for each port, with the direction and width
+//! given in the header.  The options are:
+//! - `file = "path/to/module.v"` or `code = "..."` - where to find the Verilog.
+//! - `module = "name"` - which module to import, if there is more than one.
+//! - `clocks(a, b)` - ports that should be `Clock` signals instead of `Bit`s.
+//! - `params(WIDTH = 16)` - values for the parameters of the module.  The port widths are
+//!   worked out using these values, and a [Wrapper](core::ast::Wrapper) passes them to the module.
+//! - `model = "my_model"` - a function `fn(&mut Self)` that is called from `update`, so that
+//!   the block can be simulated in Rust.
+//!
+//!```rust
+//! # use rust_hdl::prelude::*;
+//!
+//! fn clock_model(x: &mut ClockDriver) {
+//!     x.O.next = x.I.val();
+//! }
+//!
+//! #[black_box(
+//!     code = r#"
+//! (* blackbox *)
+//! module IBUFDS(I, B, O);
+//!   input I;
+//!   input B;
+//!   output O;
+//! endmodule"#,
+//!     clocks(I, B, O),
+//!     model = "clock_model"
+//! )]
+//! pub struct ClockDriver;
+//!
+//! let mut x = TopWrap::new(ClockDriver::default());
+//! x.uut.I.connect();
+//! x.uut.B.connect();
+//! x.connect_all();
+//! yosys_validate("clock_driver_black_box", &generate_verilog(&x)).unwrap();
+//! ```
+//!
 
 #![warn(missing_docs)]
 
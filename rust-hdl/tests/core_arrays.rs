@@ -0,0 +1,187 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct RegisterFile {
+    pub clock: Signal<In, Clock>,
+    pub write_enable: Signal<In, Bit>,
+    pub write_address: Signal<In, Bits<2>>,
+    pub write_data: Signal<In, Bits<8>>,
+    pub read_address: Signal<In, Bits<2>>,
+    pub read_data: Signal<Out, Bits<8>>,
+    regs: DFF<[Bits<8>; 4]>,
+}
+
+impl Logic for RegisterFile {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, regs);
+        if self.write_enable.val() {
+            self.regs.d.next[self.write_address.val().index()] = self.write_data.val();
+        }
+        self.read_data.next = self.regs.q.val()[self.read_address.val().index()];
+    }
+}
+
+#[test]
+fn test_array_synth_type() {
+    assert_eq!(<[Bits<8>; 4]>::BITS, 32);
+    let x: [Bits<8>; 4] = [0x12.into(), 0x34.into(), 0x56.into(), 0x78.into()];
+    assert_eq!(format!("{}", x.verilog()), "32'h78563412");
+    assert_eq!(<[Bits<8>; 4]>::from_vcd(&x.vcd()), Some(x));
+}
+
+#[test]
+fn test_array_index_is_lowered_to_unpacked_arrays() {
+    let mut uut = RegisterFile::default();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    // Ports stay packed, and the kernel works on unpacked copies of them
+    assert!(vlog.contains("input wire  [31:0] d;"));
+    assert!(vlog.contains("reg  [7:0] regs$d$array [0:3];"));
+    assert!(vlog.contains("regs$q$array[3] = regs$q[31:24];"));
+    assert!(vlog.contains("regs$d$array[write_address] = write_data;"));
+    assert!(vlog.contains("read_data = regs$q$array[read_address];"));
+    assert!(vlog.contains(
+        "regs$d = {regs$d$array[3], regs$d$array[2], regs$d$array[1], regs$d$array[0]};"
+    ));
+    yosys_validate("arrays", &vlog).unwrap();
+}
+
+#[test]
+fn test_register_file_works() {
+    let mut uut = RegisterFile::default();
+    uut.connect_all();
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<RegisterFile>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<RegisterFile>| {
+        let mut x = sim.init()?;
+        for address in 0..4 {
+            x.write_enable.next = true;
+            x.write_address.next = address.into();
+            x.write_data.next = (0x10 + address).into();
+            wait_clock_cycle!(sim, clock, x);
+        }
+        x.write_enable.next = false;
+        for address in 0..4 {
+            x.read_address.next = (3 - address).into();
+            wait_clock_cycle!(sim, clock, x);
+            sim_assert_eq!(sim, x.read_data.val(), 0x13 - address, x);
+        }
+        sim.done(x)
+    });
+    let mut vcd = vec![];
+    sim.run_traced(Box::new(uut), 1000, &mut vcd).unwrap();
+    let vcd = String::from_utf8(vcd).unwrap();
+    for ndx in 0..4 {
+        assert!(vcd.contains(&format!(" q${} ", ndx)));
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Table {
+    pub lut: Signal<In, [[Bits<4>; 2]; 3]>,
+    pub row: Signal<In, Bits<2>>,
+    pub col: Signal<In, Bits<1>>,
+    pub entry: Signal<Out, Bits<4>>,
+    pub patched: Signal<Out, [[Bits<4>; 2]; 3]>,
+    pub firsts: Signal<Out, [Bits<4>; 3]>,
+}
+
+impl Logic for Table {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.entry.next = self.lut.val()[self.row.val().index()][self.col.val().index()];
+        self.patched.next = self.lut.val();
+        self.patched.next[self.row.val().index()][1] = 0xF.into();
+        for i in 0..3 {
+            self.firsts.next[i] = self.lut.val()[i][0];
+        }
+    }
+}
+
+#[test]
+fn test_nested_arrays() {
+    let mut uut = Table::default();
+    uut.lut.connect();
+    uut.row.connect();
+    uut.col.connect();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    assert!(vlog.contains("input wire  [23:0] lut;"));
+    assert!(vlog.contains("reg  [3:0] lut$array [0:5];"));
+    assert!(vlog.contains("entry = lut$array[(row) * 2 + col];"));
+    assert!(vlog.contains("patched$array[(row) * 2 + 32'h1] = 32'hf;"));
+    assert!(vlog.contains("firsts$array[2] = lut$array[(2) * 2 + 32'h0];"));
+    yosys_validate("nested_arrays", &vlog).unwrap();
+    let lut: [[Bits<4>; 2]; 3] =
+        std::array::from_fn(|row| std::array::from_fn(|col| bits((row * 2 + col) as LiteralType)));
+    uut.lut.next = lut;
+    uut.row.next = 2.into();
+    uut.col.next = 1.into();
+    simulate(&mut uut, 10);
+    assert_eq!(uut.entry.val(), 5);
+    assert_eq!(uut.patched.val()[2][1], 0xF);
+    assert_eq!(uut.patched.val()[1][1], 3);
+    assert_eq!(uut.firsts.val()[2], 4);
+}
+
+#[derive(LogicBlock, Default)]
+struct LargeRegisterFile {
+    pub clock: Signal<In, Clock>,
+    pub write_enable: Signal<In, Bit>,
+    pub write_address: Signal<In, Bits<6>>,
+    pub write_data: Signal<In, Bits<4>>,
+    pub read_address: Signal<In, Bits<6>>,
+    pub read_data: Signal<Out, Bits<4>>,
+    regs: DFF<[Bits<4>; 64]>,
+    masked: Signal<Local, [Bits<4>; 64]>,
+}
+
+impl Logic for LargeRegisterFile {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, regs);
+        if self.write_enable.val() {
+            self.regs.d.next[self.write_address.val().index()] = self.write_data.val();
+        }
+        self.masked.next = self.regs.q.val();
+        self.masked.next[0] = 0.into();
+        self.read_data.next = self.masked.val()[self.read_address.val().index()];
+    }
+}
+
+#[test]
+fn test_arrays_larger_than_32_elements() {
+    let mut uut = LargeRegisterFile::default();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    // A local signal only exists as an unpacked array
+    assert!(vlog.contains("reg  [3:0] masked [0:63];"));
+    assert!(!vlog.contains("reg  [255:0] masked;"));
+    assert!(vlog.contains("read_data = masked[read_address];"));
+    yosys_validate("large_arrays", &vlog).unwrap();
+    let svlog = generate_hdl(HDLLanguage::SystemVerilog, &uut);
+    assert!(svlog.contains("logic [3:0] masked [0:63];"));
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<LargeRegisterFile>| {
+        x.clock.next = !x.clock.val()
+    });
+    sim.add_testbench(move |mut sim: Sim<LargeRegisterFile>| {
+        let mut x = sim.init()?;
+        for address in 0..64 {
+            x.write_enable.next = true;
+            x.write_address.next = address.into();
+            x.write_data.next = (address % 16).into();
+            wait_clock_cycle!(sim, clock, x);
+        }
+        x.write_enable.next = false;
+        for address in 0..64 {
+            x.read_address.next = address.into();
+            wait_clock_cycle!(sim, clock, x);
+            let expected = if address == 0 { 0 } else { address % 16 };
+            sim_assert_eq!(sim, x.read_data.val(), expected, x);
+        }
+        sim.done(x)
+    });
+    sim.run(Box::new(uut), 2000).unwrap();
+}
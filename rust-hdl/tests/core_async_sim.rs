@@ -0,0 +1,139 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct SyncVecTest {
+    pub clock1: Signal<In, Clock>,
+    pub clock2: Signal<In, Clock>,
+    pub sender: SyncSender<Bits<8>>,
+    pub recv: SyncReceiver<Bits<8>>,
+}
+
+impl Logic for SyncVecTest {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock1, sender);
+        clock!(self, clock2, recv);
+        self.sender.ack_in.next = self.recv.ack_out.val();
+        self.recv.flag_in.next = self.sender.flag_out.val();
+        self.recv.sig_cross.next = self.sender.sig_cross.val();
+    }
+}
+
+#[cfg(test)]
+fn make_sync_vec_test() -> SyncVecTest {
+    let mut uut = SyncVecTest::default();
+    uut.sender.sig_in.connect();
+    uut.sender.send.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_async_sync_vec() {
+    let uut = make_sync_vec_test();
+    let mut sim = AsyncSimulation::new();
+    sim.add_clock(5, |x: &mut Box<SyncVecTest>| {
+        x.clock2.next = !x.clock2.val()
+    });
+    sim.add_clock(9, |x: &mut Box<SyncVecTest>| {
+        x.clock1.next = !x.clock1.val()
+    });
+    sim.add_testbench(|mut sim: AsyncSim<SyncVecTest>| async move {
+        let mut x = sim.init()?;
+        await_clock_true!(sim, clock1, x);
+        for i in 0..150 {
+            x.sender.sig_in.next = i.into();
+            x.sender.send.next = true;
+            await_clock_cycle!(sim, clock1, x);
+            x.sender.send.next = false;
+            x = sim.watch(|x| !x.sender.busy.val(), x).await?;
+        }
+        sim.done(x)
+    });
+    sim.add_testbench(|mut sim: AsyncSim<SyncVecTest>| async move {
+        let mut x = sim.init()?;
+        await_clock_true!(sim, clock2, x);
+        for i in 0..150 {
+            x = sim.watch(|x| x.recv.update.val(), x).await?;
+            sim_assert!(sim, x.recv.sig_out.val().eq(&i), x);
+            await_clock_cycle!(sim, clock2, x);
+        }
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(uut),
+        100_000,
+        std::fs::File::create(vcd_path!("async_vsync.vcd")).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_async_halt_is_reported() {
+    let uut = make_sync_vec_test();
+    let mut sim = AsyncSimulation::new();
+    sim.add_clock(5, |x: &mut Box<SyncVecTest>| {
+        x.clock2.next = !x.clock2.val()
+    });
+    sim.add_phased_clock(9, 3, |x: &mut Box<SyncVecTest>| {
+        x.clock1.next = !x.clock1.val()
+    });
+    sim.add_testbench(|mut sim: AsyncSim<SyncVecTest>| async move {
+        let mut x = sim.init()?;
+        x = sim.wait(100, x).await?;
+        sim_assert!(sim, sim.time() == 50, x);
+        sim.done(x)
+    });
+    assert_eq!(sim.run(Box::new(uut), 1000), Err(SimError::SimHalted));
+}
+
+#[test]
+fn test_async_trace_matches_threaded_trace() {
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<SyncVecTest>| {
+        x.clock2.next = !x.clock2.val()
+    });
+    sim.add_phased_clock(9, 2, |x: &mut Box<SyncVecTest>| {
+        x.clock1.next = !x.clock1.val()
+    });
+    sim.add_testbench(move |mut sim: Sim<SyncVecTest>| {
+        let mut x = sim.init()?;
+        wait_clock_true!(sim, clock1, x);
+        for i in 0..20 {
+            x.sender.sig_in.next = i.into();
+            x.sender.send.next = true;
+            wait_clock_cycle!(sim, clock1, x);
+            x.sender.send.next = false;
+            x = sim.watch(|x| !x.sender.busy.val(), x)?;
+        }
+        x = sim.wait(50, x)?;
+        sim.done(x)
+    });
+    let mut threaded = vec![];
+    sim.run_traced(Box::new(make_sync_vec_test()), 100_000, &mut threaded)
+        .unwrap();
+    let mut sim = AsyncSimulation::new();
+    sim.add_clock(5, |x: &mut Box<SyncVecTest>| {
+        x.clock2.next = !x.clock2.val()
+    });
+    sim.add_phased_clock(9, 2, |x: &mut Box<SyncVecTest>| {
+        x.clock1.next = !x.clock1.val()
+    });
+    sim.add_testbench(|mut sim: AsyncSim<SyncVecTest>| async move {
+        let mut x = sim.init()?;
+        await_clock_true!(sim, clock1, x);
+        for i in 0..20 {
+            x.sender.sig_in.next = i.into();
+            x.sender.send.next = true;
+            await_clock_cycle!(sim, clock1, x);
+            x.sender.send.next = false;
+            x = sim.watch(|x| !x.sender.busy.val(), x).await?;
+        }
+        x = sim.wait(50, x).await?;
+        sim.done(x)
+    });
+    let mut single = vec![];
+    sim.run_traced(Box::new(make_sync_vec_test()), 100_000, &mut single)
+        .unwrap();
+    assert_eq!(threaded, single);
+}
@@ -419,7 +419,8 @@ fn test_local_logic_loop_detection() {
     if let CheckError::LogicLoops(m) = e {
         assert!(m.contains(&PathedName {
             path: "uut".to_string(),
-            name: "foo".to_string()
+            name: "foo".to_string(),
+            source: None,
         }))
     } else {
         panic!("Error mismatch on loop detector")
@@ -0,0 +1,120 @@
+use rust_hdl::prelude::*;
+
+#[black_box(
+    code = r#"
+(* blackbox *)
+module IBUFDS(I, B, O);
+  input I;
+  input B;
+  output O;
+endmodule
+"#,
+    clocks(I, B, O)
+)]
+pub struct ClockDriver;
+
+#[test]
+fn test_black_box_from_inline_verilog() {
+    let mut x = TopWrap::new(ClockDriver::default());
+    x.uut.I.connect();
+    x.uut.B.connect();
+    x.connect_all();
+    let vlog = generate_verilog(&x);
+    assert!(vlog.contains("IBUFDS uut("));
+    assert_eq!(vlog.matches("module IBUFDS(I, B, O);").count(), 1);
+    yosys_validate("black_box_inline", &vlog).unwrap();
+}
+
+fn counter_model(x: &mut Counter16) {
+    if x.clk.pos_edge() {
+        x.count.next = if x.reset.val() {
+            0.into()
+        } else if x.load.val() {
+            x.value.val()
+        } else {
+            x.count.val() + 3
+        };
+    }
+    x.low.next = x.count.val().get_bits::<4>(0);
+}
+
+#[black_box(
+    file = "tests/verilog/counter.v",
+    clocks(clk),
+    params(WIDTH = 16, STEP = "2 + 1"),
+    model = "counter_model"
+)]
+pub struct Counter16;
+
+#[derive(LogicBlock, Default)]
+struct Counters {
+    pub clock: Signal<In, Clock>,
+    pub value: Signal<In, Bits<16>>,
+    pub load: Signal<In, Bit>,
+    pub count: Signal<Out, Bits<16>>,
+    pub low: Signal<Out, Bits<4>>,
+    counter_1: Counter16,
+    counter_2: Counter16,
+}
+
+impl Logic for Counters {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.counter_1.clk.next = self.clock.val();
+        self.counter_1.reset.next = false;
+        self.counter_1.load.next = self.load.val();
+        self.counter_1.value.next = self.value.val();
+        self.counter_2.clk.next = self.clock.val();
+        self.counter_2.reset.next = true;
+        self.counter_2.load.next = false;
+        self.counter_2.value.next = 0.into();
+        self.count.next = self.counter_1.count.val() | self.counter_2.count.val();
+        self.low.next = self.counter_1.low.val();
+    }
+}
+
+#[test]
+fn test_black_box_ports_come_from_the_verilog_header() {
+    let x = Counter16::default();
+    let _: &Signal<In, Clock> = &x.clk;
+    let _: &Signal<In, Bit> = &x.load;
+    let _: &Signal<In, Bits<16>> = &x.value;
+    let _: &Signal<Out, Bits<16>> = &x.count;
+    let _: &Signal<Out, Bits<4>> = &x.low;
+}
+
+#[test]
+fn test_black_box_from_verilog_file_with_parameters() {
+    let mut uut = Counters::default();
+    uut.clock.connect();
+    uut.value.connect();
+    uut.load.connect();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    assert!(vlog.contains("counter #(.WIDTH(16), .STEP(2 + 1)) counter_inst("));
+    assert_eq!(vlog.matches("module counter #(").count(), 1);
+    yosys_validate("black_box_file", &vlog).unwrap();
+}
+
+#[test]
+fn test_black_box_simulates_with_model() {
+    let mut uut = Counters::default();
+    uut.clock.connect();
+    uut.value.connect();
+    uut.load.connect();
+    uut.connect_all();
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<Counters>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<Counters>| {
+        let mut x = sim.init()?;
+        x.value.next = 0x100.into();
+        x.load.next = true;
+        wait_clock_cycle!(sim, clock, x);
+        x.load.next = false;
+        wait_clock_cycles!(sim, clock, x, 5);
+        sim_assert_eq!(sim, x.count.val(), 0x100 + 5 * 3, x);
+        sim_assert_eq!(sim, x.low.val(), 15, x);
+        sim.done(x)
+    });
+    sim.run(Box::new(uut), 1000).unwrap();
+}
@@ -0,0 +1,180 @@
+use rust_hdl::core::check_error::{CheckError, ClockCrossing, PathedName};
+use rust_hdl::prelude::*;
+
+fn pathed(path: &str, name: &str) -> PathedName {
+    PathedName {
+        path: path.into(),
+        name: name.into(),
+        source: None,
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Unsynchronized {
+    pub clock_a: Signal<In, Clock>,
+    pub clock_b: Signal<In, Clock>,
+    pub out: Signal<Out, Bit>,
+    a: DFF<Bit>,
+    c: DFF<Bit>,
+    b: DFF<Bit>,
+}
+
+impl Logic for Unsynchronized {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock_a, a);
+        dff_setup!(self, clock_b, b, c);
+        self.a.d.next = !self.a.q.val();
+        self.c.d.next = !self.c.q.val();
+        self.b.d.next = self.a.q.val() & self.c.q.val();
+        self.out.next = self.b.q.val();
+    }
+}
+
+#[test]
+fn test_unsynchronized_crossing_is_flagged() {
+    let mut uut = Unsynchronized::default();
+    uut.connect_all();
+    assert_eq!(
+        check_clock_domains(&uut),
+        Err(CheckError::ClockDomainCrossings(vec![ClockCrossing {
+            from: pathed("top$a", "q"),
+            to: pathed("top$b", "d"),
+        }]))
+    );
+}
+
+#[test]
+fn test_check_all_flags_unsynchronized_crossings() {
+    let mut uut = Unsynchronized::default();
+    uut.connect_all();
+    assert!(matches!(
+        check_all(&uut),
+        Err(CheckError::ClockDomainCrossings(_))
+    ));
+}
+
+#[derive(LogicBlock, Default)]
+struct Synchronized {
+    pub clock_a: Signal<In, Clock>,
+    pub clock_b: Signal<In, Clock>,
+    pub out: Signal<Out, Bit>,
+    a: DFF<Bit>,
+    sync: BitSynchronizer,
+    b: DFF<Bit>,
+}
+
+impl Logic for Synchronized {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock_a, a);
+        dff_setup!(self, clock_b, b);
+        clock!(self, clock_b, sync);
+        self.a.d.next = !self.a.q.val();
+        self.sync.sig_in.next = self.a.q.val();
+        self.b.d.next = self.sync.sig_out.val();
+        self.out.next = self.b.q.val();
+    }
+}
+
+#[test]
+fn test_bit_synchronizer_is_a_legal_crossing() {
+    let mut uut = Synchronized::default();
+    uut.connect_all();
+    assert!(check_clock_domains(&uut).is_ok());
+}
+
+#[derive(LogicBlock, Default)]
+struct VectorCrossing {
+    pub clock_a: Signal<In, Clock>,
+    pub clock_b: Signal<In, Clock>,
+    pub out: Signal<Out, Bits<8>>,
+    count: DFF<Bits<8>>,
+    sync: VectorSynchronizer<Bits<8>>,
+    fifo: AsynchronousFIFO<Bits<8>, 4, 5, 1>,
+    held: DFF<Bits<8>>,
+}
+
+impl Logic for VectorCrossing {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock_a, count);
+        dff_setup!(self, clock_b, held);
+        self.count.d.next = self.count.q.val() + 1;
+        self.sync.clock_in.next = self.clock_a.val();
+        self.sync.clock_out.next = self.clock_b.val();
+        self.sync.sig_in.next = self.count.q.val();
+        self.sync.send.next = !self.sync.busy.val();
+        self.fifo.write_clock.next = self.clock_a.val();
+        self.fifo.read_clock.next = self.clock_b.val();
+        self.fifo.data_in.next = self.count.q.val();
+        self.fifo.write.next = !self.fifo.full.val();
+        self.fifo.read.next = !self.fifo.empty.val();
+        if self.sync.update.val() {
+            self.held.d.next = self.sync.sig_out.val();
+        } else {
+            self.held.d.next = self.fifo.data_out.val();
+        }
+        self.out.next = self.held.q.val();
+    }
+}
+
+#[test]
+fn test_vector_synchronizer_and_async_fifo_are_legal_crossings() {
+    let mut uut = VectorCrossing::default();
+    uut.connect_all();
+    assert!(check_clock_domains(&uut).is_ok());
+}
+
+#[derive(LogicBlock, Default)]
+struct Stage {
+    pub clock: Signal<In, Clock>,
+    pub data_in: Signal<In, Bit>,
+    pub data_out: Signal<Out, Bit>,
+    hold: DFF<Bit>,
+}
+
+impl Logic for Stage {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, hold);
+        self.hold.d.next = self.data_in.val();
+        self.data_out.next = self.hold.q.val();
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Pipeline {
+    pub clock: Signal<In, Clock>,
+    pub other_clock: Signal<In, Clock>,
+    pub data_in: Signal<In, Bit>,
+    pub data_out: Signal<Out, Bit>,
+    first: Stage,
+    second: Stage,
+    third: Stage,
+}
+
+impl Logic for Pipeline {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, first, second);
+        self.third.clock.next = self.other_clock.val();
+        self.first.data_in.next = self.data_in.val();
+        self.second.data_in.next = self.first.data_out.val();
+        self.third.data_in.next = self.second.data_out.val();
+        self.data_out.next = self.third.data_out.val();
+    }
+}
+
+#[test]
+fn test_crossings_are_reported_with_hierarchical_paths() {
+    let mut uut = Pipeline::default();
+    uut.connect_all();
+    assert_eq!(
+        check_clock_domains(&uut),
+        Err(CheckError::ClockDomainCrossings(vec![ClockCrossing {
+            from: pathed("top$second$hold", "q"),
+            to: pathed("top$third$hold", "d"),
+        }]))
+    );
+}
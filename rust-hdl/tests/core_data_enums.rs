@@ -0,0 +1,189 @@
+use rust_hdl::prelude::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, LogicState)]
+enum Packet {
+    Idle,
+    Data(Bits<8>),
+    Pair(Bits<4>, Bits<4>),
+    Addr { addr: Bits<12>, write: Bit },
+}
+
+#[derive(LogicBlock, Default)]
+struct Encoder {
+    pub kind: Signal<In, Bits<2>>,
+    pub data: Signal<In, Bits<8>>,
+    pub packet: Signal<Out, Packet>,
+}
+
+impl Logic for Encoder {
+    #[hdl_gen]
+    fn update(&mut self) {
+        match self.kind.val().index() {
+            0 => self.packet.next = Packet::Idle,
+            1 => self.packet.next = Packet::Data(self.data.val()),
+            2 => {
+                self.packet.next = Packet::Pair(
+                    self.data.val().get_bits::<4>(4),
+                    self.data.val().get_bits::<4>(0),
+                )
+            }
+            _ => {
+                self.packet.next = Packet::Addr {
+                    write: true,
+                    addr: bit_cast::<12, 8>(self.data.val()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Decoder {
+    pub packet: Signal<In, Packet>,
+    pub value: Signal<Out, Bits<12>>,
+    pub write: Signal<Out, Bit>,
+    pub short: Signal<Out, Bit>,
+    pub class: Signal<Out, Bits<2>>,
+}
+
+impl Logic for Decoder {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.write.next = false;
+        match self.packet.val() {
+            Packet::Idle => self.value.next = 0.into(),
+            Packet::Data(d) => self.value.next = bit_cast::<12, 8>(d),
+            Packet::Pair(_, lo) => self.value.next = bit_cast::<12, 4>(lo),
+            Packet::Addr { addr, write } => {
+                self.value.next = addr;
+                self.write.next = write;
+            }
+        }
+        match self.packet.val() {
+            Packet::Idle | Packet::Pair(..) => self.short.next = true,
+            _ => self.short.next = false,
+        }
+        match self.value.val().index() {
+            0 | 1 => self.class.next = 1.into(),
+            2..=9 => self.class.next = 2.into(),
+            _ => self.class.next = 3.into(),
+        }
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Loopback {
+    pub kind: Signal<In, Bits<2>>,
+    pub data: Signal<In, Bits<8>>,
+    pub value: Signal<Out, Bits<12>>,
+    pub write: Signal<Out, Bit>,
+    pub short: Signal<Out, Bit>,
+    pub class: Signal<Out, Bits<2>>,
+    encoder: Encoder,
+    decoder: Decoder,
+}
+
+impl Logic for Loopback {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.encoder.kind.next = self.kind.val();
+        self.encoder.data.next = self.data.val();
+        self.decoder.packet.next = self.encoder.packet.val();
+        self.value.next = self.decoder.value.val();
+        self.write.next = self.decoder.write.val();
+        self.short.next = self.decoder.short.val();
+        self.class.next = self.decoder.class.val();
+    }
+}
+
+fn make_uut() -> Loopback {
+    let mut uut = Loopback::default();
+    uut.kind.connect();
+    uut.data.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_data_enum_packing() {
+    assert_eq!(Packet::BITS, 15);
+    assert_eq!(Packet::TAG_BITS, Some(2));
+    let x: Bits<15> = Packet::Data(0xA5.into()).into();
+    assert_eq!(x, (0xA5 << 2) | 1);
+    let x: Bits<15> = Packet::Pair(0x3.into(), 0xC.into()).into();
+    assert_eq!(x, (0xC3 << 2) | 2);
+    let x: Bits<15> = Packet::Addr {
+        addr: 0x123.into(),
+        write: true,
+    }
+    .into();
+    assert_eq!(x, (1 << 14) | (0x123 << 2) | 3);
+    assert_eq!(Packet::default(), Packet::Idle);
+    for x in [
+        Packet::Idle,
+        Packet::Data(0x5A.into()),
+        Packet::Pair(0x1.into(), 0xF.into()),
+        Packet::Addr {
+            addr: 0xFED.into(),
+            write: false,
+        },
+    ] {
+        assert_eq!(Packet::from_vcd(&x.vcd()), Some(x));
+    }
+}
+
+#[test]
+fn test_data_enums_are_matched_on_their_tag() {
+    let vlog = generate_verilog(&make_uut());
+    assert!(vlog.contains("case (packet[(32'h0)+:(2)])"));
+    assert!(vlog.contains("_d = packet[(32'h2)+:(8)];"));
+    assert!(vlog.contains("_lo = packet[(32'h6)+:(4)];"));
+    assert!(vlog.contains("_write = packet[(32'he)+:(1)];"));
+    assert!(vlog.contains("Packet$Idle, Packet$Pair:"));
+    assert!(vlog.contains("2, 3, 4, 5, 6, 7, 8, 9:"));
+    assert!(vlog.contains("function [14:0] Packet$Addr$new;"));
+    assert!(vlog.contains("packet = Packet$Addr$new(((data) & 12'hfff), 1'b1);"));
+    yosys_validate("data_enums", &vlog).unwrap();
+}
+
+#[test]
+fn test_data_enums_in_other_languages() {
+    let sv = generate_hdl(HDLLanguage::SystemVerilog, &make_uut());
+    assert!(sv.contains("typedef enum logic [1:0] {Packet$Idle = 2'd0"));
+    assert!(sv.contains("Packet$tag tag;"));
+    assert!(sv.contains("logic [12:0] payload;"));
+    let vhdl = generate_hdl(HDLLanguage::VHDL, &make_uut());
+    assert!(vhdl.contains("when \\Packet$Idle\\ | \\Packet$Pair\\ =>"));
+    assert!(vhdl.contains("function \\Packet$Data$new\\"));
+}
+
+#[test]
+fn test_data_enums_simulate() {
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |mut sim: Sim<Loopback>| {
+        let mut x = sim.init()?;
+        x.data.next = 0x5A.into();
+        x.kind.next = 0.into();
+        x = sim.wait(1, x)?;
+        sim_assert_eq!(sim, x.value.val(), 0, x);
+        sim_assert!(sim, x.short.val(), x);
+        sim_assert_eq!(sim, x.class.val(), 1, x);
+        x.kind.next = 1.into();
+        x = sim.wait(1, x)?;
+        sim_assert_eq!(sim, x.value.val(), 0x5A, x);
+        sim_assert!(sim, !x.short.val(), x);
+        sim_assert_eq!(sim, x.class.val(), 3, x);
+        x.kind.next = 2.into();
+        x.data.next = 0x58.into();
+        x = sim.wait(1, x)?;
+        sim_assert_eq!(sim, x.value.val(), 0x8, x);
+        sim_assert!(sim, x.short.val(), x);
+        sim_assert_eq!(sim, x.class.val(), 2, x);
+        x.kind.next = 3.into();
+        x = sim.wait(1, x)?;
+        sim_assert_eq!(sim, x.value.val(), 0x58, x);
+        sim_assert!(sim, x.write.val(), x);
+        sim.done(x)
+    });
+    sim.run(Box::new(make_uut()), 10).unwrap();
+}
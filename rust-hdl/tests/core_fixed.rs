@@ -0,0 +1,128 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock)]
+struct Scaler {
+    pub x: Signal<In, Fixed<4, 8>>,
+    pub gain: Constant<Fixed<2, 6>>,
+    pub product: Signal<Out, Fixed<6, 14>>,
+    pub rounded: Signal<Out, Fixed<4, 4>>,
+    pub truncated: Signal<Out, Fixed<4, 4>>,
+    pub saturated: Signal<Out, Fixed<2, 4>>,
+    pub clamped: Signal<Out, Fixed<3, 2>>,
+    pub sum: Signal<Out, Fixed<5, 8>>,
+    pub offset: Signal<Out, Fixed<4, 8>>,
+}
+
+impl Scaler {
+    fn new(gain: f64) -> Self {
+        Self {
+            x: Default::default(),
+            gain: Constant::new(Fixed::from_f64(gain)),
+            product: Default::default(),
+            rounded: Default::default(),
+            truncated: Default::default(),
+            saturated: Default::default(),
+            clamped: Default::default(),
+            sum: Default::default(),
+            offset: Default::default(),
+        }
+    }
+}
+
+impl Logic for Scaler {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.product.next = self.x.val().wide_mul(self.gain.val());
+        self.rounded.next = self.product.val().round::<4, 4>();
+        self.truncated.next = self.product.val().truncate::<4, 4>();
+        self.saturated.next = self.x.val().saturate::<2, 4>();
+        self.clamped.next = self.product.val().round_saturate::<3, 2>();
+        self.sum.next = self.x.val().wide_add(self.gain.val());
+        self.offset.next = self.x.val() + Fixed::<4, 8>::from_f64(0.5);
+    }
+}
+
+fn scaler() -> Scaler {
+    let mut uut = Scaler::new(-0.7);
+    uut.x.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_fixed_point_verilog() {
+    let uut = scaler();
+    let vlog = generate_verilog(&uut);
+    assert!(vlog.contains("input wire signed [11:0] x;"));
+    assert!(vlog.contains("localparam signed gain = 8'hd3;"));
+    assert!(vlog.contains("function signed [19:0] fixed_s4_8_mul_2_6;"));
+    assert!(vlog.contains("function signed [7:0] fixed_s6_14_to_4_4_round;"));
+    assert!(vlog.contains("function signed [4:0] fixed_s6_14_to_3_2_round_sat;"));
+    assert!(vlog.contains("product = fixed_s4_8_mul_2_6(x, gain);"));
+    assert!(vlog.contains("rounded = wide + 21'h200;"));
+    assert!(vlog.contains("aligned = rounded[(32'ha)+:(11)];"));
+    assert!(vlog.contains("y_aligned = y_ext << 32'h2;"));
+    assert!(vlog.contains("offset = x + 12'h80;"));
+    yosys_validate("fixed_point", &vlog).unwrap();
+}
+
+fn scaler_testbench() -> String {
+    let inputs = [-8.0, -3.3, -0.01, 0.0, 0.4, 1.99, 5.5, 7.99];
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |mut sim: Sim<Scaler>| {
+        let mut x = sim.init()?;
+        for input in inputs {
+            x.x.next = Fixed::from_f64(input);
+            x = sim.wait(1, x)?;
+            let product: Fixed<6, 14> = x.x.val().wide_mul(x.gain.val());
+            sim_assert_eq!(sim, x.product.val(), product, x);
+            sim_assert_eq!(sim, x.rounded.val(), product.round::<4, 4>(), x);
+            sim_assert_eq!(sim, x.truncated.val(), product.truncate::<4, 4>(), x);
+            sim_assert_eq!(sim, x.saturated.val(), x.x.val().saturate::<2, 4>(), x);
+            sim_assert_eq!(sim, x.clamped.val(), product.round_saturate::<3, 2>(), x);
+            sim_assert_eq!(
+                sim,
+                x.offset.val().to_f64(),
+                x.x.val().to_f64() + 0.5 - if input > 7.0 { 16.0 } else { 0.0 },
+                x
+            );
+        }
+        sim.done(x)
+    });
+    let mut tb = vec![];
+    sim.run_to_testbench(Box::new(scaler()), 100, &mut tb)
+        .unwrap();
+    String::from_utf8(tb).unwrap()
+}
+
+#[test]
+fn test_fixed_point_simulation() {
+    let tb = scaler_testbench();
+    assert!(tb.contains("reg [11:0] x;"));
+}
+
+#[test]
+#[ignore = "requires iverilog"]
+fn test_fixed_point_hardware_matches_simulation() {
+    let tb = scaler_testbench();
+    let vlog = generate_verilog(&scaler());
+    iverilog_run_testbench("fixed_point_tb", &vlog, &tb).unwrap();
+}
+
+#[test]
+fn test_fixed_point_is_traced_as_real() {
+    let uut = scaler();
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |mut sim: Sim<Scaler>| {
+        let mut x = sim.init()?;
+        x.x.next = Fixed::from_f64(1.25);
+        x = sim.wait(1, x)?;
+        sim.done(x)
+    });
+    let mut vcd = vec![];
+    sim.run_traced(Box::new(uut), 10, &mut vcd).unwrap();
+    let vcd = String::from_utf8(vcd).unwrap();
+    assert!(vcd.contains("$var real 64"));
+    assert!(vcd.contains("r1.25 "));
+    assert!(vcd.contains("r-0.87890625 "));
+}
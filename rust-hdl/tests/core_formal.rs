@@ -0,0 +1,139 @@
+use rust_hdl::core::formal::{counterexample_to_vcd, sby_file};
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock)]
+struct WrappingCounter {
+    pub clock: Signal<In, Clock>,
+    pub enable: Signal<In, Bit>,
+    pub count: Signal<Out, Bits<4>>,
+    pub limit: Constant<Bits<4>>,
+    counter: DFF<Bits<4>>,
+}
+
+impl Logic for WrappingCounter {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, counter);
+        if self.enable.val() {
+            self.counter.d.next = self.counter.q.val() + 1;
+            if self.counter.q.val() == self.limit.val() {
+                self.counter.d.next = 0.into();
+            }
+        }
+        self.count.next = self.counter.q.val();
+        hdl_assume!(self.enable.val() | !self.enable.val());
+        hdl_assert!(self.counter.q.val() <= 9);
+        hdl_cover!(self.counter.q.val() == 9);
+    }
+}
+
+#[cfg(test)]
+fn make_counter(limit: u64) -> WrappingCounter {
+    let mut uut = WrappingCounter {
+        clock: Default::default(),
+        enable: Default::default(),
+        count: Default::default(),
+        limit: Constant::new(limit.to_bits()),
+        counter: Default::default(),
+    };
+    uut.clock.connect();
+    uut.enable.connect();
+    uut.connect_all();
+    uut
+}
+
+#[cfg(test)]
+fn run_counter(limit: u64, event_driven: bool) -> Result<(), SimError> {
+    let mut sim = Simulation::new();
+    sim.set_event_driven(event_driven);
+    sim.add_clock(5, |x: &mut Box<WrappingCounter>| {
+        x.clock.next = !x.clock.val()
+    });
+    sim.add_testbench(move |mut sim: Sim<WrappingCounter>| {
+        let mut x = sim.init()?;
+        x.enable.next = true;
+        wait_clock_cycles!(sim, clock, x, 40);
+        sim.done(x)
+    });
+    sim.run(Box::new(make_counter(limit)), 10_000)
+}
+
+#[test]
+fn test_properties_are_lowered_to_verilog() {
+    let vlog = generate_verilog(&make_counter(9));
+    assert!(vlog.contains("`ifdef FORMAL"));
+    assert!(vlog.contains("assume property (enable | ~enable);"));
+    assert!(vlog.contains("assert property (counter$q <= 32'h9);"));
+    assert!(vlog.contains("cover property (counter$q == 32'h9);"));
+    assert!(vlog.contains("`endif"));
+}
+
+#[test]
+fn test_assertions_are_checked_in_simulation() {
+    for event_driven in [false, true] {
+        assert!(run_counter(9, event_driven).is_ok());
+        match run_counter(12, event_driven) {
+            Err(SimError::PropertyFailed { time, property }) => {
+                assert!(time > 0);
+                assert!(property.starts_with("assertion `self.counter.q.val() <= 9`"));
+            }
+            x => panic!("Expected the assertion to fail, got {:?}", x),
+        }
+    }
+}
+
+#[test]
+fn test_sby_file() {
+    let sby = sby_file(FormalMode::BMC, 20);
+    assert!(sby.contains("mode bmc\ndepth 20\n"));
+    assert!(sby.contains("read -formal top.v"));
+    assert!(sby_file(FormalMode::Prove, 5).contains("mode prove\n"));
+}
+
+#[test]
+fn test_counterexample_uses_circuit_names() {
+    let trace = br#"$timescale 1ns $end
+$scope module top $end
+$var wire 1 ! clock $end
+$var wire 4 " counter$q [3:0] $end
+$var wire 1 # $auto$formal$1 $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+b1001 "
+1#
+#10
+1!
+b1010 "
+0#
+"#;
+    let mut vcd = vec![];
+    counterexample_to_vcd(&trace[..], &mut vcd).unwrap();
+    let mut parser = vcd::Parser::new(&vcd[..]);
+    let header = parser.parse_header().unwrap();
+    let q = header.find_var(&["uut", "counter", "q"]).unwrap();
+    assert_eq!(q.size, 4);
+    assert!(header.find_var(&["uut", "clock"]).is_some());
+    let changes = parser.map(|x| x.unwrap()).collect::<Vec<_>>();
+    assert_eq!(changes.len(), 6);
+    assert!(changes.contains(&vcd::Command::ChangeVector(
+        q.code,
+        "1010".chars().map(|c| vcd::Value::from(c == '1')).collect()
+    )));
+}
+
+#[test]
+#[ignore = "requires sby"]
+fn test_formal_check() {
+    let good = formal_check("formal_good", &make_counter(9), FormalMode::Prove, 20).unwrap();
+    assert_eq!(good, FormalResult::Pass);
+    match formal_check("formal_bad", &make_counter(12), FormalMode::BMC, 20).unwrap() {
+        FormalResult::Fail { counterexample } => {
+            let mut parser = vcd::Parser::new(&counterexample[..]);
+            let header = parser.parse_header().unwrap();
+            assert!(header.find_var(&["uut", "counter", "q"]).is_some());
+        }
+        x => panic!("Expected a counterexample, got {:?}", x),
+    }
+}
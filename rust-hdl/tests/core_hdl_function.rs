@@ -0,0 +1,190 @@
+use rust_hdl::prelude::*;
+
+#[hdl_function]
+fn gray_encode<const N: usize>(x: Bits<N>) -> Bits<N> {
+    x ^ (x >> 1)
+}
+
+#[hdl_function]
+fn saturating_add(a: Bits<8>, b: Bits<8>) -> Bits<8> {
+    let sum = bit_cast::<9, 8>(a) + bit_cast::<9, 8>(b);
+    if sum.get_bit(8) {
+        255.into()
+    } else {
+        bit_cast::<8, 9>(sum)
+    }
+}
+
+#[hdl_function]
+fn negate(x: Signed<8>) -> Signed<8> {
+    -x
+}
+
+#[hdl_function]
+fn gray_step(x: Bits<8>) -> Bits<8> {
+    gray_encode(x + 1)
+}
+
+#[derive(LogicBlock, Default)]
+struct Functions {
+    pub a: Signal<In, Bits<8>>,
+    pub b: Signal<In, Bits<8>>,
+    pub s: Signal<In, Signed<8>>,
+    pub gray: Signal<Out, Bits<8>>,
+    pub gray4: Signal<Out, Bits<4>>,
+    pub sat: Signal<Out, Bits<8>>,
+    pub neg: Signal<Out, Signed<8>>,
+    pub step: Signal<Out, Bits<8>>,
+}
+
+impl Logic for Functions {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.gray.next = gray_encode(self.a.val());
+        self.gray4.next = gray_encode(self.a.val().get_bits::<4>(0));
+        self.sat.next = saturating_add(self.a.val(), self.b.val());
+        self.neg.next = negate(self.s.val());
+        self.step.next = gray_step(self.b.val());
+    }
+}
+
+fn make_uut() -> Functions {
+    let mut uut = Functions::default();
+    uut.a.connect();
+    uut.b.connect();
+    uut.s.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_functions_are_emitted_into_the_module() {
+    let vlog = generate_verilog(&make_uut());
+    assert_eq!(vlog.matches("function [7:0] gray_encode$8;").count(), 1);
+    assert_eq!(vlog.matches("function [3:0] gray_encode$4;").count(), 1);
+    assert!(vlog.contains("function [7:0] saturating_add;"));
+    assert!(vlog.contains("function signed [7:0] negate;"));
+    assert!(vlog.contains("reg [8:0] _sum;"));
+    assert!(vlog.contains("gray = gray_encode$8(a);"));
+    assert!(vlog.contains("sat = saturating_add(a, b);"));
+    // Functions called by other functions are defined before them
+    assert!(vlog.find("gray_encode$8;").unwrap() < vlog.find("gray_step;").unwrap());
+    yosys_validate("hdl_function", &vlog).unwrap();
+}
+
+#[test]
+fn test_functions_in_other_languages() {
+    let sv = generate_hdl(HDLLanguage::SystemVerilog, &make_uut());
+    assert!(sv.contains("function [7:0] gray_encode$8;"));
+    let vhdl = generate_hdl(HDLLanguage::VHDL, &make_uut());
+    assert!(
+        vhdl.contains("function \\gray_encode$8\\(x : unsigned(7 downto 0)) return unsigned is")
+    );
+    assert!(vhdl.contains("function negate(x : signed(7 downto 0)) return signed is"));
+    assert!(vhdl.contains("return \\negate$result\\;"));
+    assert!(vhdl.contains("gray <= \\gray_encode$8\\(a);"));
+}
+
+#[test]
+fn test_functions_simulate() {
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |mut sim: Sim<Functions>| {
+        let mut x = sim.init()?;
+        for (a, b) in [(0x12, 0x34), (0xF0, 0x20), (0x7F, 0x80), (0xFF, 0xFF)] {
+            x.a.next = a.into();
+            x.b.next = b.into();
+            x.s.next = (a as i64 - 128).into();
+            x = sim.wait(1, x)?;
+            sim_assert_eq!(sim, x.gray.val(), a ^ (a >> 1), x);
+            sim_assert_eq!(sim, x.gray4.val(), (a & 0xF) ^ ((a & 0xF) >> 1), x);
+            sim_assert_eq!(sim, x.sat.val(), (a + b).min(255), x);
+            sim_assert_eq!(sim, x.neg.val(), Signed::<8>::from(128 - a as i64), x);
+            let step = (b + 1) & 0xFF;
+            sim_assert_eq!(sim, x.step.val(), step ^ (step >> 1), x);
+        }
+        sim.done(x)
+    });
+    sim.run(Box::new(make_uut()), 10).unwrap();
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, LogicStruct)]
+struct Interval {
+    pub start: Bits<8>,
+    pub end: Bits<8>,
+}
+
+#[hdl_function]
+fn interval_length(x: Interval) -> Bits<8> {
+    x.end - x.start
+}
+
+#[hdl_function]
+fn latest_start(a: Interval, b: Interval) -> Interval {
+    if a.start > b.start {
+        a
+    } else {
+        b
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Intervals {
+    pub a: Signal<In, Interval>,
+    pub b: Signal<In, Interval>,
+    pub latest: Signal<Out, Interval>,
+    pub length: Signal<Out, Bits<8>>,
+}
+
+impl Logic for Intervals {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.latest.next = latest_start(self.a.val(), self.b.val());
+        self.length.next = interval_length(self.a.val());
+    }
+}
+
+fn make_intervals() -> Intervals {
+    let mut uut = Intervals::default();
+    uut.a.connect();
+    uut.b.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_functions_over_structs() {
+    let vlog = generate_verilog(&make_intervals());
+    assert!(vlog.contains("function [15:0] latest_start;"));
+    assert!(vlog.contains("function [7:0] interval_length;"));
+    assert!(vlog.contains("latest_start = b;"));
+    assert!(vlog.contains("if (a[(64'h0)+:(8)] > b[(64'h0)+:(8)]) begin"));
+    assert!(vlog.contains("interval_length = x[(64'h8)+:(8)] - x[(64'h0)+:(8)];"));
+    assert!(vlog.contains("latest = latest_start(a, b);"));
+    yosys_validate("hdl_function_struct", &vlog).unwrap();
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |mut sim: Sim<Intervals>| {
+        let mut x = sim.init()?;
+        for (a, b) in [((3, 10), (5, 7)), ((9, 12), (2, 200)), ((4, 4), (4, 8))] {
+            let a = Interval {
+                start: a.0.into(),
+                end: a.1.into(),
+            };
+            let b = Interval {
+                start: b.0.into(),
+                end: b.1.into(),
+            };
+            x.a.next = a;
+            x.b.next = b;
+            x = sim.wait(1, x)?;
+            sim_assert_eq!(
+                sim,
+                x.latest.val(),
+                if a.start > b.start { a } else { b },
+                x
+            );
+            sim_assert_eq!(sim, x.length.val(), a.end - a.start, x);
+        }
+        sim.done(x)
+    });
+    sim.run(Box::new(make_intervals()), 10).unwrap();
+}
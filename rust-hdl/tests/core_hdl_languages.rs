@@ -0,0 +1,267 @@
+use rust_hdl::prelude::*;
+use rust_hdl::widgets::edge_ff::EdgeDFF;
+
+#[derive(Copy, Clone, PartialEq, Debug, LogicState)]
+enum State {
+    Idle,
+    Run,
+    Done,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, LogicStruct)]
+struct Pair {
+    pub a: Bits<4>,
+    pub b: Bits<3>,
+}
+
+#[derive(LogicBlock, Default)]
+struct Sequencer {
+    pub clock: Signal<In, Clock>,
+    pub start: Signal<In, Bit>,
+    pub data: Signal<In, Bits<8>>,
+    pub pair: Signal<Out, Pair>,
+    pub count: Signal<Out, Bits<8>>,
+    pub busy: Signal<Out, Bit>,
+    state: DFF<State>,
+    counter: DFF<Bits<8>>,
+}
+
+impl Logic for Sequencer {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, state, counter);
+        self.busy.next = false;
+        self.count.next = self.counter.q.val();
+        self.pair.next.a = self.data.val().get_bits::<4>(2);
+        self.pair.next.b = 3.into();
+        match self.state.q.val() {
+            State::Idle => {
+                if self.start.val() & (self.data.val() > 3) {
+                    self.state.d.next = State::Run;
+                }
+            }
+            State::Run => {
+                self.busy.next = self.data.val().get_bit(3);
+                self.counter.d.next = self.counter.q.val() + 1;
+                if self.counter.q.val().all() {
+                    self.state.d.next = State::Done;
+                }
+            }
+            _ => {
+                self.state.d.next = State::Idle;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn make_sequencer() -> Sequencer {
+    let mut uut = Sequencer::default();
+    uut.clock.connect();
+    uut.start.connect();
+    uut.data.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_verilog_is_the_default_language() {
+    let uut = make_sequencer();
+    assert_eq!(
+        generate_hdl(HDLLanguage::default(), &uut),
+        generate_verilog(&uut)
+    );
+}
+
+#[test]
+fn test_systemverilog_generation() {
+    let uut = make_sequencer();
+    let sv = generate_hdl(HDLLanguage::SystemVerilog, &uut);
+    assert!(sv.contains(
+        "typedef enum logic [1:0] {State$Idle = 2'd0, State$Run = 2'd1, State$Done = 2'd2} State;"
+    ));
+    assert!(sv.contains("typedef struct packed {"));
+    assert!(sv.contains("} Pair;"));
+    assert!(sv.contains("output Pair pair;"));
+    assert!(sv.contains("always_comb begin"));
+    assert!(sv.contains("always_ff @(posedge clock) begin"));
+    assert!(sv.contains("State state = State'("));
+    assert!(sv.contains("assign q = state;"));
+    assert!(!sv.contains("initial begin"));
+    assert!(!sv.contains("always @(*)"));
+    // The enum values come from the typedef, not from localparams
+    assert!(!sv.contains("localparam State$Idle"));
+}
+
+#[test]
+fn test_vhdl_generation() {
+    let uut = make_sequencer();
+    let vhdl = generate_hdl(HDLLanguage::VHDL, &uut);
+    assert!(vhdl.contains("package rust_hdl_pkg is"));
+    assert!(vhdl.contains("entity top is"));
+    assert!(vhdl.contains("clock : in unsigned(0 downto 0)"));
+    assert!(vhdl.contains("pair : out unsigned(6 downto 0)"));
+    assert!(vhdl.contains("constant \\State$Idle\\ : unsigned(1 downto 0) := \"00\";"));
+    assert!(vhdl.contains("process(all)"));
+    assert!(vhdl.contains("when \\State$Run\\ =>"));
+    assert!(vhdl.contains("when others =>"));
+    assert!(vhdl.contains("if rising_edge(clock(0)) then"));
+    assert!(vhdl.contains("signal state : unsigned(1 downto 0) := \"00\";"));
+    assert!(vhdl.contains("state : entity work.\\top$state\\"));
+    // Children have to be analyzed before their parents
+    let child = vhdl.find("entity \\top$counter\\ is").unwrap();
+    let parent = vhdl.find("entity top is").unwrap();
+    assert!(child < parent);
+    // Nothing should be left for an external implementation
+    assert!(!vhdl.contains("component"));
+}
+
+#[derive(LogicBlock)]
+struct CustomKernels {
+    pub clock: Signal<In, Clock>,
+    pub address: Signal<In, Bits<4>>,
+    pub data: Signal<Out, Bits<8>>,
+    pub pins: Signal<InOut, Bits<4>>,
+    pub sda: Signal<InOut, Bit>,
+    rom: ROM<Bits<8>, 4>,
+    sync_rom: SyncROM<Bits<8>, 4>,
+    ram: RAM<Bits<8>, 4>,
+    edge_ff: EdgeDFF<Bits<8>>,
+    tristate: TristateBuffer<Bits<4>>,
+    open_drain: OpenDrainBuffer,
+}
+
+impl Default for CustomKernels {
+    fn default() -> Self {
+        Self {
+            clock: Default::default(),
+            address: Default::default(),
+            data: Default::default(),
+            pins: Default::default(),
+            sda: Default::default(),
+            rom: (0..16).map(|x| Bits::<8>::from(x * 3)).into(),
+            sync_rom: (0..16).map(|x| Bits::<8>::from(x + 1)).into(),
+            ram: (0..4).map(|x| Bits::<8>::from(x + 0x20)).into(),
+            edge_ff: EdgeDFF::new(0x5a.into()),
+            tristate: Default::default(),
+            open_drain: Default::default(),
+        }
+    }
+}
+
+impl Logic for CustomKernels {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.rom.address.next = self.address.val();
+        self.sync_rom.clock.next = self.clock.val();
+        self.sync_rom.address.next = self.address.val();
+        self.ram.read_clock.next = self.clock.val();
+        self.ram.write_clock.next = self.clock.val();
+        self.ram.read_address.next = self.address.val();
+        self.ram.write_address.next = self.address.val();
+        self.ram.write_data.next = self.rom.data.val();
+        self.ram.write_enable.next = self.address.val().get_bit(0);
+        self.edge_ff.clk.next = self.clock.val();
+        self.edge_ff.d.next = self.sync_rom.data.val() ^ self.ram.read_data.val();
+        self.data.next = self.edge_ff.q.val();
+        Signal::<InOut, Bits<4>>::link(&mut self.pins, &mut self.tristate.bus);
+        self.tristate.write_enable.next = self.address.val().get_bit(1);
+        self.tristate.write_data.next = self.address.val();
+        Signal::<InOut, Bit>::link(&mut self.sda, &mut self.open_drain.bus);
+        self.open_drain.control.drive_low.next = self.tristate.read_data.val().get_bit(0);
+    }
+}
+
+#[test]
+fn test_vhdl_custom_kernels() {
+    let mut uut = CustomKernels::default();
+    uut.clock.connect();
+    uut.address.connect();
+    uut.connect_all();
+    let vhdl = generate_hdl(HDLLanguage::VHDL, &uut);
+    // Every instance refers to an entity in the output, and nothing is left for an
+    // external implementation
+    assert!(!vhdl.contains("component"));
+    let mut instances = 0;
+    for line in vhdl.lines() {
+        if let Some(kind) = line.split("entity work.").nth(1) {
+            let kind = kind.trim();
+            assert!(
+                vhdl.contains(&format!("entity {} is", kind)),
+                "No entity for {}",
+                kind
+            );
+            instances += 1;
+        }
+    }
+    assert_eq!(instances, 6);
+    // The kernels carry their contents and initial values over
+    assert!(vhdl.contains("when x\"5\" => data <= x\"0F\";"));
+    assert!(vhdl.contains("when others => data <= x\"00\";"));
+    assert!(vhdl.contains("constant mem : mem_t := (\n            0 => x\"01\",\n"));
+    assert!(vhdl.contains("15 => x\"10\","));
+    assert!(vhdl.contains("signal mem : mem_t := (\n"));
+    assert!(vhdl.contains("3 => x\"23\","));
+    assert!(vhdl.contains("            others => x\"00\");"));
+    assert!(vhdl.contains("mem(to_integer(write_address)) <= write_data;"));
+    assert!(vhdl.contains("signal state : unsigned(7 downto 0) := x\"5A\";"));
+    assert!(vhdl.contains("if rising_edge(clk(0)) then"));
+    // `bus` is a reserved word in VHDL
+    assert!(vhdl.contains("\\bus\\ <= write_data when write_enable(0) = '1' else (others => 'Z');"));
+    assert!(vhdl.contains("read_data <= \\bus\\;"));
+    assert!(vhdl.contains("\\bus\\ <= \"0\" when \\control$drive_low\\(0) = '1' else \"Z\";"));
+    assert!(vhdl.contains("\\control$line_state\\ <= \\bus\\;"));
+}
+
+#[derive(LogicBlock, Default)]
+struct Inverter {
+    pub a: Signal<In, Bit>,
+    pub y: Signal<Out, Bit>,
+}
+
+impl Logic for Inverter {
+    fn update(&mut self) {
+        self.y.next = !self.a.val();
+    }
+    fn connect(&mut self) {
+        self.y.connect();
+    }
+    fn hdl(&self) -> Verilog {
+        Verilog::Custom("assign y = ~a;".into())
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct UsesInverter {
+    pub a: Signal<In, Bit>,
+    pub y: Signal<Out, Bit>,
+    inverter: Inverter,
+}
+
+impl Logic for UsesInverter {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.inverter.a.next = self.a.val();
+        self.y.next = self.inverter.y.val();
+    }
+}
+
+#[test]
+#[should_panic(expected = "no VHDL translation")]
+fn test_vhdl_rejects_untranslated_custom_verilog() {
+    let mut uut = UsesInverter::default();
+    uut.a.connect();
+    uut.connect_all();
+    let _ = generate_hdl(HDLLanguage::VHDL, &uut);
+}
+
+#[test]
+fn test_vhdl_identifiers() {
+    assert_eq!(vhdl_ident("clock"), "clock");
+    assert_eq!(vhdl_ident("top$clock"), "\\top$clock\\");
+    assert_eq!(vhdl_ident("signal"), "\\signal\\");
+    assert_eq!(vhdl_ident("a__b"), "\\a__b\\");
+    assert_eq!(vhdl_type(8, true), "signed(7 downto 0)");
+    assert_eq!(vhdl_bit_string(&Bits::<8>::from(0x1b).into(), 8), "x\"1B\"");
+    assert_eq!(vhdl_bit_string(&Bits::<3>::from(5).into(), 3), "\"101\"");
+}
@@ -0,0 +1,217 @@
+use rust_hdl::prelude::*;
+
+// Three controllers share a bridge with a pair of FIFO ports (joined
+// by a FIFO), so the order in which the controllers were granted the
+// bus can be read back from the FIFO.
+#[derive(LogicBlock)]
+struct ArbiterTest {
+    pub c0: SoCBusResponder<16, 8>,
+    pub c1: SoCBusResponder<16, 8>,
+    pub c2: SoCBusResponder<16, 8>,
+    pub lock0: Signal<In, Bit>,
+    pub lock1: Signal<In, Bit>,
+    pub lock2: Signal<In, Bit>,
+    arbiter: SoCBusArbiter<16, 8, 3>,
+    bridge: Bridge<16, 8, 2>,
+    mosi: MOSIFIFOPort<16, 4, 5, 1>,
+    miso: MISOFIFOPort<16, 4, 5, 1>,
+    link: FIFOLink<Bits<16>>,
+}
+
+impl Logic for ArbiterTest {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.arbiter.clock.next = self.c0.clock.val();
+        SoCBusResponder::<16, 8>::link(&mut self.c0, &mut self.arbiter.controllers[0]);
+        SoCBusResponder::<16, 8>::link(&mut self.c1, &mut self.arbiter.controllers[1]);
+        SoCBusResponder::<16, 8>::link(&mut self.c2, &mut self.arbiter.controllers[2]);
+        self.arbiter.lock[0].next = self.lock0.val();
+        self.arbiter.lock[1].next = self.lock1.val();
+        self.arbiter.lock[2].next = self.lock2.val();
+        SoCBusController::<16, 8>::join(&mut self.arbiter.downstream, &mut self.bridge.upstream);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[0], &mut self.mosi.bus);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[1], &mut self.miso.bus);
+        FIFOReadController::<Bits<16>>::join(&mut self.link.read, &mut self.mosi.fifo_bus);
+        FIFOWriteController::<Bits<16>>::join(&mut self.link.write, &mut self.miso.fifo_bus);
+    }
+}
+
+fn arbiter_test(policy: SoCBusArbiterPolicy) -> ArbiterTest {
+    let mut uut = ArbiterTest {
+        c0: Default::default(),
+        c1: Default::default(),
+        c2: Default::default(),
+        lock0: Default::default(),
+        lock1: Default::default(),
+        lock2: Default::default(),
+        arbiter: SoCBusArbiter::new(policy),
+        bridge: Bridge::new(["mosi", "miso"]),
+        mosi: Default::default(),
+        miso: Default::default(),
+        link: Default::default(),
+    };
+    uut.c0.link_connect_dest();
+    uut.c1.link_connect_dest();
+    uut.c2.link_connect_dest();
+    uut.lock0.connect();
+    uut.lock1.connect();
+    uut.lock2.connect();
+    uut.connect_all();
+    uut
+}
+
+// Write 4 words (tagged with the controller) to the FIFO as a single
+// transaction (locked or not), with a gap of some clocks before each word.
+macro_rules! burst {
+    ($sim: ident, $uut: ident, $bus: ident, $lock: ident, $locked: expr, $tag: expr, $gap: expr) => {
+        $uut.$lock.next = $locked;
+        bus_address_strobe!($sim, $uut, $bus, 0);
+        for n in 0..4_u16 {
+            wait_clock_cycles!($sim, $bus.clock, $uut, $gap);
+            $uut = $sim.watch(|x| x.$bus.ready.val(), $uut)?;
+            bus_write_strobe!($sim, $uut, $bus, $tag + n);
+        }
+        wait_clock_true!($sim, $bus.clock, $uut);
+        $uut.$lock.next = false;
+    };
+}
+
+// Controller 2 takes the bus first, and is still using it when controllers
+// 0 and 1 ask for it at the same time.  It then reads back the FIFO, and
+// checks the words with `check`.
+fn run_arbiter_test(
+    policy: SoCBusArbiterPolicy,
+    locked: bool,
+    gaps: [usize; 3],
+    check: fn(&[LiteralType]) -> bool,
+) {
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<ArbiterTest>| {
+        x.c0.clock.next = !x.c0.clock.val();
+        x.c1.clock.next = !x.c1.clock.val();
+        x.c2.clock.next = !x.c2.clock.val();
+    });
+    sim.add_testbench(move |mut sim: Sim<ArbiterTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, c0.clock, x, 20);
+        burst!(sim, x, c0, lock0, locked, 0x0100_u16, gaps[0]);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<ArbiterTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, c1.clock, x, 20);
+        burst!(sim, x, c1, lock1, locked, 0x0200_u16, gaps[1]);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<ArbiterTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, c2.clock, x, 10);
+        burst!(sim, x, c2, lock2, locked, 0x0300_u16, gaps[2]);
+        wait_clock_cycles!(sim, c2.clock, x, 200);
+        bus_address_strobe!(sim, x, c2, 1);
+        let mut words = vec![];
+        for _ in 0..12 {
+            x = sim.watch(|x| x.c2.ready.val(), x)?;
+            words.push(x.c2.to_controller.val().index() as LiteralType);
+            wait_clock_true!(sim, c2.clock, x);
+            x.c2.strobe.next = true;
+            wait_clock_cycle!(sim, c2.clock, x);
+            x.c2.strobe.next = false;
+        }
+        sim_assert!(sim, check(&words), x);
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(arbiter_test(policy)),
+        50_000,
+        std::fs::File::create(vcd_path!(format!("arbiter_{:?}_{}.vcd", policy, locked))).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_arbiter_test_is_synthesizable() {
+    let vlog = generate_verilog(&arbiter_test(SoCBusArbiterPolicy::FixedPriority));
+    yosys_validate("arbiter", &vlog).unwrap();
+}
+
+#[test]
+fn test_arbiter_round_robin() {
+    // Controller 1 is next in turn after controller 2
+    run_arbiter_test(SoCBusArbiterPolicy::RoundRobin, true, [1, 1, 10], |words| {
+        words
+            == [
+                0x300, 0x301, 0x302, 0x303, 0x200, 0x201, 0x202, 0x203, 0x100, 0x101, 0x102, 0x103,
+            ]
+    });
+}
+
+#[test]
+fn test_arbiter_fixed_priority() {
+    // Controller 0 has the highest priority
+    run_arbiter_test(
+        SoCBusArbiterPolicy::FixedPriority,
+        true,
+        [1, 1, 10],
+        |words| {
+            words
+                == [
+                    0x300, 0x301, 0x302, 0x303, 0x100, 0x101, 0x102, 0x103, 0x200, 0x201, 0x202,
+                    0x203,
+                ]
+        },
+    );
+}
+
+#[test]
+fn test_arbiter_unlocked_transactions_with_short_pauses() {
+    // A controller that pauses for less than 16 clocks keeps the bus, even without
+    // the lock, so the transactions are not interleaved
+    run_arbiter_test(SoCBusArbiterPolicy::RoundRobin, false, [1, 1, 8], |words| {
+        words
+            == [
+                0x300, 0x301, 0x302, 0x303, 0x200, 0x201, 0x202, 0x203, 0x100, 0x101, 0x102, 0x103,
+            ]
+    });
+}
+
+#[test]
+fn test_arbiter_does_not_repeat_address_strobes_on_an_idle_bus() {
+    // Two controllers each issue a single address strobe, and then leave the bus idle.
+    // The bus must see each of them once.
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<ArbiterTest>| {
+        x.c0.clock.next = !x.c0.clock.val();
+        x.c1.clock.next = !x.c1.clock.val();
+        x.c2.clock.next = !x.c2.clock.val();
+    });
+    sim.add_testbench(move |mut sim: Sim<ArbiterTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, c0.clock, x, 20);
+        bus_address_strobe!(sim, x, c0, 0);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<ArbiterTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, c1.clock, x, 20);
+        bus_address_strobe!(sim, x, c1, 0);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<ArbiterTest>| {
+        let mut x = sim.init()?;
+        let mut strobes = 0;
+        for _ in 0..2000 {
+            wait_clock_cycle!(sim, c2.clock, x);
+            if x.arbiter.downstream.address_strobe.val() {
+                strobes += 1;
+            }
+        }
+        sim_assert_eq!(sim, strobes, 2, x);
+        sim.done(x)
+    });
+    sim.run(
+        Box::new(arbiter_test(SoCBusArbiterPolicy::RoundRobin)),
+        50_000,
+    )
+    .unwrap();
+}
@@ -0,0 +1,148 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock)]
+struct AXI4LiteTest {
+    pub clock: Signal<In, Clock>,
+    axi_bridge: AXI4LiteBridge<16, 8, 12>,
+    bridge: Bridge<16, 8, 3>,
+    port: MOSIPort<16>,
+    mosi: MOSIFIFOPort<16, 4, 5, 1>,
+    miso: MISOFIFOPort<16, 4, 5, 1>,
+    link: FIFOLink<Bits<16>>,
+}
+
+impl Logic for AXI4LiteTest {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, axi_bridge);
+        SoCBusController::<16, 8>::join(&mut self.axi_bridge.bus, &mut self.bridge.upstream);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[0], &mut self.port.bus);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[1], &mut self.mosi.bus);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[2], &mut self.miso.bus);
+        self.port.ready.next = true;
+        // Whatever is written to the mosi port can be read back from the miso port
+        FIFOReadController::<Bits<16>>::join(&mut self.link.read, &mut self.mosi.fifo_bus);
+        FIFOWriteController::<Bits<16>>::join(&mut self.link.write, &mut self.miso.fifo_bus);
+    }
+}
+
+fn axi4lite_test() -> AXI4LiteTest {
+    let mut uut = AXI4LiteTest {
+        clock: Default::default(),
+        axi_bridge: Default::default(),
+        bridge: Bridge::new(["port", "mosi", "miso"]),
+        port: Default::default(),
+        mosi: Default::default(),
+        miso: Default::default(),
+        link: Default::default(),
+    };
+    uut.clock.connect();
+    uut.axi_bridge.axi.link_connect_dest();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_axi4lite_bridge_test_is_synthesizable() {
+    let vlog = generate_verilog(&axi4lite_test());
+    yosys_validate("axi4lite_test", &vlog).unwrap();
+}
+
+#[test]
+fn test_axi4lite_bridge_works() {
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<AXI4LiteTest>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<AXI4LiteTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, clock, x, 10);
+        // The port at address 0 (byte address 0x0)
+        axi4lite_write!(sim, clock, x, axi_bridge.axi, 0x000, 0xCAFE);
+        sim_assert_eq!(sim, x.port.port_out.val(), 0xCAFE, x);
+        let val = axi4lite_read!(sim, clock, x, axi_bridge.axi, 0x000);
+        sim_assert_eq!(sim, val, 0, x);
+        // The FIFO loop from address 1 (byte address 0x4) to address 2 (byte address 0x8)
+        for val in [0x1234_u32, 0xBEEF, 0x0042] {
+            axi4lite_write!(sim, clock, x, axi_bridge.axi, 0x004, 0xFFFF_0000 | val);
+        }
+        for val in [0x1234_u32, 0xBEEF, 0x0042] {
+            let read = axi4lite_read!(sim, clock, x, axi_bridge.axi, 0x008);
+            sim_assert_eq!(sim, read, val as LiteralType, x);
+        }
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(axi4lite_test()),
+        100_000,
+        std::fs::File::create(vcd_path!("axi4lite_bridge.vcd")).unwrap(),
+    )
+    .unwrap();
+}
+
+#[derive(LogicBlock)]
+struct AXI4StreamTest {
+    pub clock: Signal<In, Clock>,
+    to_fifo: AXI4StreamToFIFO<Bits<16>>,
+    fifo: SyncFIFO<Bits<16>, 3, 4, 1>,
+    from_fifo: FIFOToAXI4Stream<Bits<16>>,
+}
+
+impl Logic for AXI4StreamTest {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, fifo);
+        FIFOWriteController::<Bits<16>>::join(&mut self.to_fifo.fifo, &mut self.fifo.bus_write);
+        FIFOReadController::<Bits<16>>::join(&mut self.from_fifo.fifo, &mut self.fifo.bus_read);
+    }
+}
+
+fn axi4stream_test() -> AXI4StreamTest {
+    let mut uut = AXI4StreamTest {
+        clock: Default::default(),
+        to_fifo: Default::default(),
+        fifo: Default::default(),
+        from_fifo: Default::default(),
+    };
+    uut.clock.connect();
+    uut.to_fifo.axis.link_connect_dest();
+    uut.from_fifo.axis.link_connect_dest();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_axi4stream_bridges_test_is_synthesizable() {
+    let vlog = generate_verilog(&axi4stream_test());
+    yosys_validate("axi4stream_test", &vlog).unwrap();
+}
+
+#[test]
+fn test_axi4stream_through_fifo_works() {
+    let data = (0..32).map(|x| 0x7100_u16 + x * 3).collect::<Vec<_>>();
+    let expected = data.clone();
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<AXI4StreamTest>| {
+        x.clock.next = !x.clock.val()
+    });
+    sim.add_testbench(move |mut sim: Sim<AXI4StreamTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, clock, x, 10);
+        axi4stream_send!(sim, clock, x, to_fifo.axis, &data);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<AXI4StreamTest>| {
+        let mut x = sim.init()?;
+        // Start late, so that the FIFO fills and the sender is held off
+        wait_clock_cycles!(sim, clock, x, 50);
+        let received = axi4stream_receive!(sim, clock, x, from_fifo.axis, expected.len());
+        for (got, want) in received.iter().zip(expected.iter()) {
+            sim_assert_eq!(sim, *got, *want as LiteralType, x);
+        }
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(axi4stream_test()),
+        100_000,
+        std::fs::File::create(vcd_path!("axi4stream_fifo.vcd")).unwrap(),
+    )
+    .unwrap();
+}
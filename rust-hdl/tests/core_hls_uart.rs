@@ -0,0 +1,81 @@
+use rust_hdl::prelude::*;
+
+const UART_CONFIG: UARTConfig = UARTConfig {
+    clock_speed: 32_000_000,
+    baud_rate: 1_000_000,
+    parity: UARTParity::Even,
+    stop_bits: 1,
+};
+
+#[derive(LogicBlock)]
+struct HLSUARTTest {
+    pub clock: Signal<In, Clock>,
+    pub uart: HLSUART<4, 5>,
+}
+
+impl Logic for HLSUARTTest {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, uart);
+    }
+}
+
+fn hls_uart_test() -> HLSUARTTest {
+    let mut uut = HLSUARTTest {
+        clock: Default::default(),
+        uart: HLSUART::new(UART_CONFIG),
+    };
+    uut.clock.connect();
+    uut.uart.rx.connect();
+    uut.uart.bus_write.data.connect();
+    uut.uart.bus_write.write.connect();
+    uut.uart.bus_read.read.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_hls_uart_is_synthesizable() {
+    let vlog = generate_verilog(&hls_uart_test());
+    yosys_validate("hls_uart_test", &vlog).unwrap();
+}
+
+#[test]
+fn test_hls_uart_talks_to_terminal() {
+    let outbound = [0x48_u8, 0x65, 0x6C, 0x6C, 0x6F, 0x0A, 0x00, 0xFF];
+    let inbound = [0x4F_u8, 0x4B, 0x0D, 0x0A, 0x80, 0x01, 0x7E, 0x55];
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<HLSUARTTest>| x.clock.next = !x.clock.val());
+    // The FPGA side writes to and reads from the FIFOs
+    sim.add_testbench(move |mut sim: Sim<HLSUARTTest>| {
+        let mut x = sim.init()?;
+        hls_fifo_write!(sim, clock, x, uart.bus_write, &outbound);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<HLSUARTTest>| {
+        let mut x = sim.init()?;
+        hls_fifo_read!(sim, clock, x, uart.bus_read, &inbound);
+        sim.done(x)
+    });
+    // The terminal talks to the serial lines
+    sim.add_testbench(move |mut sim: Sim<HLSUARTTest>| {
+        let mut x = sim.init()?;
+        x.uart.rx.next = true;
+        wait_clock_cycles!(sim, clock, x, 100);
+        uart_send!(sim, clock, x, uart.rx, UART_CONFIG, &inbound);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<HLSUARTTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, clock, x, 10);
+        let received = uart_receive!(sim, clock, x, uart.tx, UART_CONFIG, outbound.len());
+        sim_assert_eq!(sim, received, outbound.to_vec(), x);
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(hls_uart_test()),
+        200_000,
+        std::fs::File::create(vcd_path!("hls_uart.vcd")).unwrap(),
+    )
+    .unwrap();
+}
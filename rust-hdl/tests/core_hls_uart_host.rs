@@ -0,0 +1,250 @@
+use rust_hdl::prelude::*;
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+
+const UART_CONFIG: UARTConfig = UARTConfig {
+    clock_speed: 32_000_000,
+    baud_rate: 1_000_000,
+    parity: UARTParity::None,
+    stop_bits: 1,
+};
+
+#[derive(LogicBlock)]
+struct UARTHostTest {
+    pub clock: Signal<In, Clock>,
+    host: UARTHost<8>,
+    bridge: Bridge<16, 8, 2>,
+    mosi: MOSIFIFOPort<16, 4, 5, 1>,
+    miso: MISOFIFOPort<16, 4, 5, 1>,
+    link: FIFOLink<Bits<16>>,
+}
+
+impl Logic for UARTHostTest {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, host);
+        SoCBusController::<16, 8>::join(&mut self.host.bus, &mut self.bridge.upstream);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[0], &mut self.mosi.bus);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[1], &mut self.miso.bus);
+        // Whatever is written to the mosi port can be read back from the miso port
+        FIFOReadController::<Bits<16>>::join(&mut self.link.read, &mut self.mosi.fifo_bus);
+        FIFOWriteController::<Bits<16>>::join(&mut self.link.write, &mut self.miso.fifo_bus);
+    }
+}
+
+fn uart_host_test() -> UARTHostTest {
+    let mut uut = UARTHostTest {
+        clock: Default::default(),
+        host: UARTHost::new(UART_CONFIG, WordOrder::MostSignificantFirst),
+        bridge: Bridge::new(["mosi", "miso"]),
+        mosi: Default::default(),
+        miso: Default::default(),
+        link: Default::default(),
+    };
+    uut.clock.connect();
+    uut.host.rx.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_uart_host_is_synthesizable() {
+    let vlog = generate_verilog(&uart_host_test());
+    yosys_validate("uart_host_test", &vlog).unwrap();
+}
+
+// Stands in for the serial port on the PC.  It owns the simulation, and every
+// clock cycle it drives the next bit waiting to go out on the `rx` line and
+// samples the `tx` line, so the client runs in lock step with the circuit.
+struct SimulatedPort {
+    sim: Sim<UARTHostTest>,
+    x: Option<Box<UARTHostTest>>,
+    error: Option<SimError>,
+    to_fpga: VecDeque<bool>,
+    frame: Option<(u64, Vec<bool>)>,
+    from_fpga: VecDeque<u8>,
+}
+
+// How long a read waits for the first byte before giving up
+const READ_TIMEOUT_CYCLES: u64 = 10_000;
+
+fn clock_cycle(
+    sim: &mut Sim<UARTHostTest>,
+    mut x: Box<UARTHostTest>,
+) -> Result<Box<UARTHostTest>, SimError> {
+    wait_clock_cycle!(sim, clock, x);
+    Ok(x)
+}
+
+impl SimulatedPort {
+    fn new(sim: Sim<UARTHostTest>, x: Box<UARTHostTest>) -> Self {
+        Self {
+            sim,
+            x: Some(x),
+            error: None,
+            to_fpga: Default::default(),
+            frame: None,
+            from_fpga: Default::default(),
+        }
+    }
+    fn finish(self) -> Result<(Sim<UARTHostTest>, Box<UARTHostTest>), SimError> {
+        match (self.error, self.x) {
+            (Some(err), _) => Err(err),
+            (None, Some(x)) => Ok((self.sim, x)),
+            (None, None) => Err(SimError::SimTerminated),
+        }
+    }
+    fn cycle(&mut self) -> std::io::Result<()> {
+        let ended = || std::io::Error::new(ErrorKind::BrokenPipe, "simulation ended");
+        let mut x = self.x.take().ok_or_else(ended)?;
+        x.host.rx.next = self.to_fpga.pop_front().unwrap_or(true);
+        x = match clock_cycle(&mut self.sim, x) {
+            Ok(x) => x,
+            Err(err) => {
+                self.error = Some(err);
+                return Err(ended());
+            }
+        };
+        let line = x.host.tx.val();
+        self.x = Some(x);
+        // Wait for the start bit, and then sample each bit in the middle
+        let clocks_per_bit = UART_CONFIG.clocks_per_bit();
+        match &mut self.frame {
+            None => {
+                if !line {
+                    self.frame = Some((0, vec![]));
+                }
+            }
+            Some((ticks, bits)) => {
+                *ticks += 1;
+                if *ticks % clocks_per_bit == clocks_per_bit / 2 {
+                    bits.push(line);
+                    if bits.len() == UART_CONFIG.frame_bits() {
+                        let byte = UART_CONFIG.decode(bits).ok_or_else(|| {
+                            std::io::Error::new(ErrorKind::InvalidData, "bad UART frame")
+                        })?;
+                        self.from_fpga.push_back(byte);
+                        self.frame = None;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for SimulatedPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut idle = 0;
+        while self.from_fpga.is_empty() {
+            if idle == READ_TIMEOUT_CYCLES {
+                return Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    "no reply from the FPGA",
+                ));
+            }
+            self.cycle()?;
+            idle += 1;
+        }
+        let count = buf.len().min(self.from_fpga.len());
+        for (slot, byte) in buf.iter_mut().zip(self.from_fpga.drain(..count)) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Write for SimulatedPort {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for byte in buf {
+            for bit in UART_CONFIG.frame(*byte) {
+                for _ in 0..UART_CONFIG.clocks_per_bit() {
+                    self.to_fpga.push_back(bit);
+                }
+            }
+        }
+        while !self.to_fpga.is_empty() {
+            self.cycle()?;
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn client_exchange<T: Read + Write>(client: &mut UARTHostClient<T>) -> std::io::Result<Vec<u16>> {
+    let mut results = vec![];
+    client.ping(0x42)?;
+    results.push(client.poll(1)? as u16);
+    client.write_data_to_address(0, &[0xDEAD, 0xBEEF, 0x0102])?;
+    results.push(client.poll(1)? as u16);
+    results.extend(client.read_data_from_address(1, 2)?);
+    client.enable_streaming(1)?;
+    results.extend(client.stream_read(1)?);
+    client.disable_streaming()?;
+    client.ping(0x13)?;
+    Ok(results)
+}
+
+#[test]
+fn test_uart_host_client_works() {
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<UARTHostTest>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<UARTHostTest>| {
+        let mut x = sim.init()?;
+        x.host.rx.next = true;
+        wait_clock_cycles!(sim, clock, x, 100);
+        let mut client =
+            UARTHostClient::new(SimulatedPort::new(sim, x), WordOrder::MostSignificantFirst);
+        let results = client_exchange(&mut client);
+        let (sim, x) = client.into_inner().finish()?;
+        match results {
+            Ok(results) => {
+                sim_assert_eq!(sim, results, vec![0, 1, 0xDEAD, 0xBEEF, 0x0102], x);
+            }
+            Err(err) => {
+                println!("HALT client failed: {}", err);
+                return sim.halt(x);
+            }
+        }
+        sim.done(x)
+    });
+    // The time limit bounds the test, so a stalled exchange fails instead of hanging
+    sim.run(Box::new(uart_host_test()), 500_000).unwrap();
+}
+
+#[test]
+fn test_uart_host_client_rejects_bad_lengths() {
+    let mut client = UARTHostClient::new(
+        std::io::Cursor::new(vec![]),
+        WordOrder::MostSignificantFirst,
+    );
+    assert_eq!(
+        client.write_data_to_address(0, &[]).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        client
+            .write_data_to_address(0, &vec![0; 1 << 16])
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        client.read_data_from_address(1, 0).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        client
+            .read_data_from_address(1, 1 << 16)
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidInput
+    );
+    // Nothing was sent to the port
+    assert!(client.into_inner().into_inner().is_empty());
+}
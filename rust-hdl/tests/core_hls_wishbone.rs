@@ -0,0 +1,272 @@
+use rust_hdl::prelude::*;
+
+// A classic Wishbone peripheral with 4 read/write registers
+#[derive(LogicBlock, Default)]
+struct WishboneRegisters<const A: usize> {
+    pub clock: Signal<In, Clock>,
+    pub bus: WishboneResponder<A>,
+    reg0: DFF<Bits<32>>,
+    reg1: DFF<Bits<32>>,
+    reg2: DFF<Bits<32>>,
+    reg3: DFF<Bits<32>>,
+    ack: DFF<Bit>,
+    write: Signal<Local, Bit>,
+}
+
+impl<const A: usize> Logic for WishboneRegisters<A> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, reg0, reg1, reg2, reg3, ack);
+        self.ack.d.next = self.bus.cyc.val() & self.bus.stb.val() & !self.ack.q.val();
+        self.bus.ack.next = self.ack.q.val();
+        self.bus.err.next = false;
+        self.write.next = self.bus.cyc.val() & self.bus.stb.val() & self.bus.we.val();
+        self.bus.dat_r.next = 0.into();
+        if self.bus.adr.val() == 0 {
+            self.bus.dat_r.next = self.reg0.q.val();
+            if self.write.val() {
+                self.reg0.d.next = self.bus.dat_w.val();
+            }
+        }
+        if self.bus.adr.val() == 1 {
+            self.bus.dat_r.next = self.reg1.q.val();
+            if self.write.val() {
+                self.reg1.d.next = self.bus.dat_w.val();
+            }
+        }
+        if self.bus.adr.val() == 2 {
+            self.bus.dat_r.next = self.reg2.q.val();
+            if self.write.val() {
+                self.reg2.d.next = self.bus.dat_w.val();
+            }
+        }
+        if self.bus.adr.val() == 3 {
+            self.bus.dat_r.next = self.reg3.q.val();
+            if self.write.val() {
+                self.reg3.d.next = self.bus.dat_w.val();
+            }
+        }
+    }
+}
+
+// Two controllers (one classic, one pipelined) share a register block, and
+// a pair of SoC bus ports (joined by a FIFO) through the interconnect.
+#[derive(LogicBlock)]
+struct WishboneSystem {
+    pub clock: Signal<In, Clock>,
+    pub classic: WishboneResponder<8>,
+    pub pipelined: WishbonePipelinedResponder<8>,
+    pipe: WishbonePipelinedToClassic<8>,
+    interconnect: WishboneInterconnect<2, 2, 8>,
+    regs: WishboneRegisters<8>,
+    to_soc: WishboneToSoCBus<16, 8, 8>,
+    bridge: Bridge<16, 8, 2>,
+    mosi: MOSIFIFOPort<16, 4, 5, 1>,
+    miso: MISOFIFOPort<16, 4, 5, 1>,
+    link: FIFOLink<Bits<16>>,
+}
+
+impl Logic for WishboneSystem {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, interconnect, regs, to_soc);
+        WishboneResponder::<8>::link(&mut self.classic, &mut self.interconnect.controllers[0]);
+        WishbonePipelinedResponder::<8>::link(&mut self.pipelined, &mut self.pipe.upstream);
+        WishboneController::<8>::join(
+            &mut self.pipe.downstream,
+            &mut self.interconnect.controllers[1],
+        );
+        WishboneController::<8>::join(&mut self.interconnect.responders[0], &mut self.regs.bus);
+        WishboneController::<8>::join(
+            &mut self.interconnect.responders[1],
+            &mut self.to_soc.wishbone,
+        );
+        SoCBusController::<16, 8>::join(&mut self.to_soc.bus, &mut self.bridge.upstream);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[0], &mut self.mosi.bus);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[1], &mut self.miso.bus);
+        FIFOReadController::<Bits<16>>::join(&mut self.link.read, &mut self.mosi.fifo_bus);
+        FIFOWriteController::<Bits<16>>::join(&mut self.link.write, &mut self.miso.fifo_bus);
+    }
+}
+
+fn wishbone_system() -> WishboneSystem {
+    let mut uut = WishboneSystem {
+        clock: Default::default(),
+        classic: Default::default(),
+        pipelined: Default::default(),
+        pipe: Default::default(),
+        interconnect: WishboneInterconnect::new([(0x00, 0x04), (0x10, 0x02)]),
+        regs: Default::default(),
+        to_soc: Default::default(),
+        bridge: Bridge::new(["mosi", "miso"]),
+        mosi: Default::default(),
+        miso: Default::default(),
+        link: Default::default(),
+    };
+    uut.clock.connect();
+    uut.classic.link_connect_dest();
+    uut.pipelined.link_connect_dest();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_wishbone_system_is_synthesizable() {
+    let vlog = generate_verilog(&wishbone_system());
+    yosys_validate("wishbone_system", &vlog).unwrap();
+}
+
+#[test]
+fn test_wishbone_system_works() {
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<WishboneSystem>| {
+        x.clock.next = !x.clock.val()
+    });
+    // The two controllers run at the same time, so that they have to share the bus
+    sim.add_testbench(move |mut sim: Sim<WishboneSystem>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, clock, x, 10);
+        wishbone_write!(
+            sim,
+            clock,
+            x,
+            classic,
+            0x00,
+            &[0x1111_1111_u32, 0x2222_2222]
+        );
+        let vals = wishbone_read!(sim, clock, x, classic, 0x00, 2);
+        sim_assert_eq!(sim, vals, [0x1111_1111_u64, 0x2222_2222], x);
+        // Writes to 0x10 go into the FIFO, and can be read back from 0x11
+        for val in [0xABCD_u32, 0x1234, 0xFFFF_0042] {
+            wishbone_write!(sim, clock, x, classic, 0x10, &[val]);
+        }
+        for val in [0xABCD_u64, 0x1234, 0x0042] {
+            let read = wishbone_read!(sim, clock, x, classic, 0x11, 1);
+            sim_assert_eq!(sim, read[0], val, x);
+        }
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<WishboneSystem>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, clock, x, 10);
+        wishbone_pipelined_write!(
+            sim,
+            clock,
+            x,
+            pipelined,
+            0x02,
+            &[0x3333_3333_u32, 0x4444_4444]
+        );
+        let vals = wishbone_pipelined_read!(sim, clock, x, pipelined, 0x02, 2);
+        sim_assert_eq!(sim, vals, [0x3333_3333_u64, 0x4444_4444], x);
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(wishbone_system()),
+        20_000,
+        std::fs::File::create(vcd_path!("wishbone_system.vcd")).unwrap(),
+    )
+    .unwrap();
+}
+
+// A Wishbone register block hosted in a router tree, next to a regular port
+#[derive(LogicBlock)]
+struct SoCWishboneTest {
+    upstream: SoCBusResponder<16, 8>,
+    router: Router<16, 8, 2>,
+    bridge: Bridge<16, 8, 1>,
+    port: MOSIPort<16>,
+    to_wishbone: SoCBusToWishbone<16, 8, 2>,
+    regs: WishboneRegisters<2>,
+}
+
+impl Logic for SoCWishboneTest {
+    #[hdl_gen]
+    fn update(&mut self) {
+        SoCBusResponder::<16, 8>::link(&mut self.upstream, &mut self.router.upstream);
+        SoCBusController::<16, 8>::join(&mut self.router.nodes[0], &mut self.bridge.upstream);
+        SoCBusController::<16, 8>::join(&mut self.router.nodes[1], &mut self.to_wishbone.upstream);
+        SoCPortController::<16>::join(&mut self.bridge.nodes[0], &mut self.port.bus);
+        self.port.ready.next = true;
+        WishboneController::<2>::join(&mut self.to_wishbone.wishbone, &mut self.regs.bus);
+        self.regs.clock.next = self.to_wishbone.clock_out.val();
+    }
+}
+
+fn soc_wishbone_test() -> SoCWishboneTest {
+    let bridge = Bridge::new(["port"]);
+    let to_wishbone = SoCBusToWishbone::default();
+    let mut uut = SoCWishboneTest {
+        upstream: Default::default(),
+        router: Router::new(["bridge", "regs"], [&bridge, &to_wishbone]),
+        bridge,
+        port: Default::default(),
+        to_wishbone,
+        regs: Default::default(),
+    };
+    uut.upstream.link_connect_dest();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_soc_wishbone_test_is_synthesizable() {
+    let vlog = generate_verilog(&soc_wishbone_test());
+    yosys_validate("soc_wishbone", &vlog).unwrap();
+}
+
+#[test]
+fn test_wishbone_registers_in_router_tree() {
+    let uut = soc_wishbone_test();
+    assert_eq!(
+        uut.router.ports(),
+        [
+            "bridge_port",
+            "regs_write_0",
+            "regs_read_0",
+            "regs_write_1",
+            "regs_read_1",
+            "regs_write_2",
+            "regs_read_2",
+            "regs_write_3",
+            "regs_read_3"
+        ]
+    );
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<SoCWishboneTest>| {
+        x.upstream.clock.next = !x.upstream.clock.val()
+    });
+    sim.add_testbench(move |mut sim: Sim<SoCWishboneTest>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, upstream.clock, x, 10);
+        // Write each register (the write port of register n is at 1 + 2*n)
+        for n in 0..4_u16 {
+            bus_address_strobe!(sim, x, upstream, 1 + 2 * n);
+            bus_write_strobe!(sim, x, upstream, 0xA000_u16 + n);
+        }
+        // Then the regular port
+        bus_address_strobe!(sim, x, upstream, 0);
+        bus_write_strobe!(sim, x, upstream, 0x5A5A_u16);
+        sim_assert_eq!(sim, x.port.port_out.val(), 0x5A5A, x);
+        // And read the registers back in reverse order (the read port is at 2 + 2*n)
+        for n in (0..4_u16).rev() {
+            bus_address_strobe!(sim, x, upstream, 2 + 2 * n);
+            sim_assert_eq!(
+                sim,
+                x.upstream.to_controller.val(),
+                (0xA000 + n) as LiteralType,
+                x
+            );
+            x.upstream.strobe.next = true;
+            wait_clock_cycle!(sim, upstream.clock, x);
+            x.upstream.strobe.next = false;
+        }
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(uut),
+        10_000,
+        std::fs::File::create(vcd_path!("soc_wishbone.vcd")).unwrap(),
+    )
+    .unwrap();
+}
@@ -0,0 +1,106 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct LetBindings {
+    pub a: Signal<In, Bits<8>>,
+    pub b: Signal<In, Bits<8>>,
+    pub sel: Signal<In, Bit>,
+    pub s: Signal<In, Signed<8>>,
+    pub sum: Signal<Out, Bits<8>>,
+    pub choice: Signal<Out, Bits<8>>,
+    pub nibble: Signal<Out, Bits<4>>,
+    pub parity: Signal<Out, Bits<4>>,
+    pub neg: Signal<Out, Signed<8>>,
+}
+
+impl Logic for LetBindings {
+    #[hdl_gen]
+    fn update(&mut self) {
+        let total = self.a.val() + self.b.val();
+        let total = total + 1;
+        self.sum.next = total;
+        if self.sel.val() {
+            let flip = !self.a.val();
+            self.choice.next = flip;
+        } else {
+            self.choice.next = self.b.val();
+        }
+        let nibble = self.a.val().get_bits::<4>(4);
+        self.nibble.next = nibble;
+        self.parity.next = 0.into();
+        for i in 0..4 {
+            let bit = self.a.val().get_bit(i) ^ self.b.val().get_bit(i);
+            self.parity.next = self.parity.val().replace_bit(i, bit);
+        }
+        let s = -self.s.val();
+        self.neg.next = s;
+    }
+}
+
+fn make_uut() -> LetBindings {
+    let mut uut = LetBindings::default();
+    uut.a.connect();
+    uut.b.connect();
+    uut.sel.connect();
+    uut.s.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_let_bindings_are_local_signals() {
+    let vlog = generate_verilog(&make_uut());
+    assert!(vlog.contains("// Let bindings"));
+    assert!(vlog.contains("reg  [7:0] _total;"));
+    assert!(vlog.contains("reg  [7:0] _total$1;"));
+    assert!(vlog.contains("reg  [3:0] _nibble;"));
+    assert!(vlog.contains("reg  _bit;"));
+    assert!(vlog.contains("reg signed [7:0] _s;"));
+    assert!(vlog.contains("_total$1 = _total + 32'h1;"));
+    assert!(vlog.contains("sum = _total$1;"));
+    yosys_validate("let_bindings", &vlog).unwrap();
+}
+
+#[test]
+fn test_let_bindings_do_not_infer_latches() {
+    let vlog = generate_verilog(&make_uut());
+    // Every binding is cleared before the kernel runs
+    let update = &vlog[vlog.find("always @(*) begin").unwrap()..];
+    let flip = update.find("_flip = 8'h0;").unwrap();
+    assert!(flip < update.find("if (sel)").unwrap());
+    assert!(update.contains("_flip = ~a;"));
+}
+
+#[test]
+fn test_let_bindings_in_other_languages() {
+    let sv = generate_hdl(HDLLanguage::SystemVerilog, &make_uut());
+    assert!(sv.contains("logic [7:0] _flip;"));
+    assert!(sv.contains("logic signed [7:0] _s;"));
+    let vhdl = generate_hdl(HDLLanguage::VHDL, &make_uut());
+    assert!(vhdl.contains("variable \\_total$1\\ : unsigned(7 downto 0);"));
+    assert!(vhdl.contains("variable \\_s\\ : signed(7 downto 0);"));
+    assert!(vhdl.contains("\\_total$1\\ :="));
+}
+
+#[test]
+fn test_let_bindings_simulate() {
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |mut sim: Sim<LetBindings>| {
+        let mut x = sim.init()?;
+        x.a.next = 0x5A.into();
+        x.b.next = 0x0F.into();
+        x.sel.next = true;
+        x.s.next = 5.into();
+        x = sim.wait(1, x)?;
+        sim_assert_eq!(sim, x.sum.val(), 0x6A, x);
+        sim_assert_eq!(sim, x.choice.val(), 0xA5, x);
+        sim_assert_eq!(sim, x.nibble.val(), 0x5, x);
+        sim_assert_eq!(sim, x.parity.val(), 0x5, x);
+        sim_assert_eq!(sim, x.neg.val(), Signed::<8>::from(-5), x);
+        x.sel.next = false;
+        x = sim.wait(1, x)?;
+        sim_assert_eq!(sim, x.choice.val(), 0x0F, x);
+        sim.done(x)
+    });
+    sim.run(Box::new(make_uut()), 10).unwrap();
+}
@@ -102,6 +102,6 @@ fn test_write_modules_nested_ports() {
         sig.visit_block(&q);
     }
     let mut jnk = File::create(vcd_path!("test.vcd")).unwrap();
-    let dev = write_vcd_header(&mut jnk, &uut);
-    let _dev = write_vcd_dump(dev, &uut);
+    let dev = write_vcd_header(&mut jnk, &uut).unwrap();
+    let _dev = write_vcd_dump(dev, &uut).unwrap();
 }
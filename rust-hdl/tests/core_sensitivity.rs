@@ -0,0 +1,159 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct CountingCounter {
+    pub clock: Signal<In, Clock>,
+    pub enable: Signal<In, Bit>,
+    pub count: Signal<Out, Bits<8>>,
+    counter: DFF<Bits<8>>,
+    _evals: usize,
+}
+
+impl Logic for CountingCounter {
+    fn update(&mut self) {
+        self._evals += 1;
+        dff_setup!(self, clock, counter);
+        if self.enable.val() {
+            self.counter.d.next = self.counter.q.val() + 1;
+        }
+        self.count.next = self.counter.q.val();
+    }
+
+    fn connect(&mut self) {
+        self.count.connect();
+        self.counter.clock.connect();
+        self.counter.d.connect();
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct CounterPair {
+    pub fast_clock: Signal<In, Clock>,
+    pub slow_clock: Signal<In, Clock>,
+    pub enable: Signal<In, Bit>,
+    pub fast: CountingCounter,
+    pub slow: CountingCounter,
+}
+
+impl Logic for CounterPair {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.fast.clock.next = self.fast_clock.val();
+        self.slow.clock.next = self.slow_clock.val();
+        self.fast.enable.next = self.enable.val();
+        self.slow.enable.next = self.enable.val();
+    }
+}
+
+#[cfg(test)]
+fn make_counter_pair() -> CounterPair {
+    let mut uut = CounterPair::default();
+    uut.fast_clock.connect();
+    uut.slow_clock.connect();
+    uut.enable.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_sensitivity_skips_idle_blocks() {
+    let mut sim = Simulation::new();
+    sim.set_event_driven(true);
+    sim.add_clock(5, |x: &mut Box<CounterPair>| {
+        x.fast_clock.next = !x.fast_clock.val()
+    });
+    sim.add_clock(500, |x: &mut Box<CounterPair>| {
+        x.slow_clock.next = !x.slow_clock.val()
+    });
+    sim.add_testbench(move |mut sim: Sim<CounterPair>| {
+        let mut x = sim.init()?;
+        x.enable.next = true;
+        wait_clock_cycles!(sim, fast_clock, x, 100);
+        sim_assert!(sim, x.fast.count.val() > 95, x);
+        sim_assert!(sim, x.slow.count.val() <= 2, x);
+        // The idle counter should only be evaluated when its clock toggles
+        sim_assert!(sim, x.slow._evals * 10 < x.fast._evals, x);
+        sim.done(x)
+    });
+    sim.run(Box::new(make_counter_pair()), 10_000).unwrap();
+}
+
+#[test]
+fn test_sensitivity_matches_full_update() {
+    let mut full = make_counter_pair();
+    let mut sensitive = make_counter_pair();
+    let mut sens = Sensitivity::new(&sensitive);
+    for cycle in 0..200 {
+        for uut in [&mut full, &mut sensitive] {
+            uut.fast_clock.next = !uut.fast_clock.val();
+            if cycle % 7 == 0 {
+                uut.slow_clock.next = !uut.slow_clock.val();
+            }
+            uut.enable.next = cycle % 3 != 0;
+        }
+        assert!(simulate(&mut full, 100));
+        sens.sweep();
+        let mut converged = false;
+        for _ in 0..100 {
+            if !sens.update(&mut sensitive) {
+                converged = true;
+                break;
+            }
+        }
+        assert!(converged);
+        assert_eq!(full.fast.count.val(), sensitive.fast.count.val());
+        assert_eq!(full.slow.count.val(), sensitive.slow.count.val());
+    }
+    assert!(sensitive.fast._evals < full.fast._evals);
+}
+
+#[derive(LogicBlock, Default)]
+struct Increment {
+    pub a: Signal<In, Bits<8>>,
+    pub y: Signal<Out, Bits<8>>,
+}
+
+impl Logic for Increment {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.y.next = self.a.val() + 1;
+    }
+}
+
+// The stages are declared in the opposite order to the one in which the data
+// flows through them.
+#[derive(LogicBlock, Default)]
+struct IncrementChain {
+    pub a: Signal<In, Bits<8>>,
+    pub y: Signal<Out, Bits<8>>,
+    stage3: Increment,
+    stage2: Increment,
+    stage1: Increment,
+    stage0: Increment,
+}
+
+impl Logic for IncrementChain {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.stage0.a.next = self.a.val();
+        self.stage1.a.next = self.stage0.y.val();
+        self.stage2.a.next = self.stage1.y.val();
+        self.stage3.a.next = self.stage2.y.val();
+        self.y.next = self.stage3.y.val();
+    }
+}
+
+#[test]
+fn test_sensitivity_settles_combinational_chain_in_one_pass() {
+    let mut uut = IncrementChain::default();
+    uut.a.connect();
+    uut.connect_all();
+    let mut sens = Sensitivity::new(&uut);
+    for value in [3, 10, 250] {
+        uut.a.next = value.into();
+        sens.sweep();
+        assert!(sens.update(&mut uut));
+        assert_eq!(uut.y.val(), Bits::<8>::from(value + 4));
+        assert!(!sens.update(&mut uut));
+    }
+}
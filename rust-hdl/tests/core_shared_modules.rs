@@ -0,0 +1,141 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct Inverter<const N: usize> {
+    pub d: Signal<In, Bits<N>>,
+    pub q: Signal<Out, Bits<N>>,
+}
+
+impl<const N: usize> Logic for Inverter<N> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.q.next = !self.d.val();
+    }
+}
+
+#[derive(LogicBlock)]
+struct Shared {
+    pub clock: Signal<In, Clock>,
+    pub enable: Signal<In, Bit>,
+    pub fast: Signal<Out, Bit>,
+    pub slow: Signal<Out, Bit>,
+    pub a: Signal<In, Bits<8>>,
+    pub b: Signal<In, Bits<16>>,
+    pub x: Signal<Out, Bits<8>>,
+    pub y: Signal<Out, Bits<8>>,
+    pub z: Signal<Out, Bits<16>>,
+    pub not_a: Signal<Out, Bits<8>>,
+    pub not_b: Signal<Out, Bits<16>>,
+    strobe_fast: Strobe<32>,
+    strobe_slow: Strobe<32>,
+    reg_x: DFF<Bits<8>>,
+    reg_y: DFF<Bits<8>>,
+    reg_z: DFF<Bits<16>>,
+    inv_a: Inverter<8>,
+    inv_b: Inverter<16>,
+}
+
+impl Default for Shared {
+    fn default() -> Self {
+        Self {
+            clock: Default::default(),
+            enable: Default::default(),
+            fast: Default::default(),
+            slow: Default::default(),
+            a: Default::default(),
+            b: Default::default(),
+            x: Default::default(),
+            y: Default::default(),
+            z: Default::default(),
+            not_a: Default::default(),
+            not_b: Default::default(),
+            strobe_fast: Strobe::new(1_000_000, 1000.0),
+            strobe_slow: Strobe::new(1_000_000, 10.0),
+            reg_x: Default::default(),
+            reg_y: Default::default(),
+            reg_z: Default::default(),
+            inv_a: Default::default(),
+            inv_b: Default::default(),
+        }
+    }
+}
+
+impl Logic for Shared {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, strobe_fast, strobe_slow);
+        dff_setup!(self, clock, reg_x, reg_y, reg_z);
+        self.strobe_fast.enable.next = self.enable.val();
+        self.strobe_slow.enable.next = self.enable.val();
+        self.fast.next = self.strobe_fast.strobe.val();
+        self.slow.next = self.strobe_slow.strobe.val();
+        self.reg_x.d.next = self.a.val();
+        self.reg_y.d.next = !self.a.val();
+        self.reg_z.d.next = self.b.val();
+        self.x.next = self.reg_x.q.val();
+        self.y.next = self.reg_y.q.val();
+        self.z.next = self.reg_z.q.val();
+        self.inv_a.d.next = self.a.val();
+        self.inv_b.d.next = self.b.val();
+        self.not_a.next = self.inv_a.q.val();
+        self.not_b.next = self.inv_b.q.val();
+    }
+}
+
+fn make_uut() -> Shared {
+    let mut uut = Shared::default();
+    uut.clock.connect();
+    uut.enable.connect();
+    uut.a.connect();
+    uut.b.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_identical_blocks_share_a_module() {
+    let vlog = generate_verilog(&make_uut());
+    // The top, the strobes, the inverters, and the flip flops for each width
+    assert_eq!(vlog.matches("\nmodule ").count(), 6);
+    assert!(vlog.contains("module Strobe(enable,strobe,clock);"));
+    assert!(vlog.contains("Strobe strobe_fast("));
+    assert!(vlog.contains("Strobe #(\n        .threshold(32'h186a0)\n    ) strobe_slow("));
+    assert!(vlog.contains("DFF$Bits$8 reg_x("));
+    assert!(vlog.contains("DFF$Bits$8 reg_y("));
+    yosys_validate("shared_modules", &vlog).unwrap();
+}
+
+#[test]
+fn test_differing_widths_and_constants_are_parameters() {
+    let vlog = generate_verilog(&make_uut());
+    let inverter = &vlog[vlog.find("module Inverter(").unwrap()..];
+    assert!(inverter.contains("parameter d$WIDTH = 8;"));
+    assert!(inverter.contains("input wire  [d$WIDTH-1:0] d;"));
+    assert!(inverter.contains("output reg  [q$WIDTH-1:0] q;"));
+    assert!(vlog.contains("Inverter inv_a("));
+    assert!(vlog.contains("Inverter #(\n        .d$WIDTH(16),\n        .q$WIDTH(16)\n    ) inv_b("));
+    // The constant of the strobe can be overridden
+    assert!(vlog.contains("parameter  threshold = 32'h3e8;"));
+}
+
+#[test]
+fn test_blocks_that_differ_in_code_are_not_shared() {
+    let vlog = generate_verilog(&make_uut());
+    // The initial value of a flip flop is part of its code, so flip flops of different
+    // widths get modules of their own.  A module that is not shared keeps the name of
+    // its block, and the shared ones are named after their types.
+    assert!(vlog.contains("top$reg_z reg_z("));
+    assert!(vlog.contains("module top$reg_z(d,q,clock);"));
+    assert!(vlog.contains("DFF$Bits$32 counter("));
+    assert!(!vlog.contains("DFF$Bits$16"));
+    assert!(!vlog.contains("parameter literal"));
+}
+
+#[test]
+fn test_shared_modules_in_system_verilog() {
+    let sv = generate_hdl(HDLLanguage::SystemVerilog, &make_uut());
+    assert_eq!(sv.matches("\nmodule ").count(), 6);
+    assert!(sv.contains("parameter logic [31:0] threshold = 32'h3e8;"));
+    assert!(sv.contains(".threshold(32'h186a0)"));
+    assert!(sv.contains("input logic [d$WIDTH-1:0] d;"));
+}
@@ -0,0 +1,314 @@
+use rust_hdl::prelude::*;
+
+#[derive(Copy, Clone, PartialEq, Debug, LogicState)]
+enum Mode {
+    Idle,
+    Up,
+    Down,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, LogicStruct)]
+struct Tally {
+    pub ups: Bits<6>,
+    pub downs: Bits<6>,
+}
+
+#[derive(LogicBlock, Default)]
+struct Ticker {
+    pub clock: Signal<In, Clock>,
+    pub count: Signal<Out, Bits<8>>,
+    pub tally: Signal<Out, Tally>,
+    mode: DFF<Mode>,
+    counter: DFF<Bits<8>>,
+    ups: DFF<Bits<6>>,
+    downs: DFF<Bits<6>>,
+}
+
+impl Logic for Ticker {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, mode, counter, ups, downs);
+        self.count.next = self.counter.q.val();
+        self.tally.next.ups = self.ups.q.val();
+        self.tally.next.downs = self.downs.q.val();
+        match self.mode.q.val() {
+            Mode::Idle => {
+                self.mode.d.next = Mode::Up;
+            }
+            Mode::Up => {
+                self.counter.d.next = self.counter.q.val() + 3;
+                if self.counter.q.val() > 200 {
+                    self.mode.d.next = Mode::Down;
+                    self.ups.d.next = self.ups.q.val() + 1;
+                }
+            }
+            _ => {
+                self.counter.d.next = self.counter.q.val() - 1;
+                if self.counter.q.val() < 10 {
+                    self.mode.d.next = Mode::Up;
+                    self.downs.d.next = self.downs.q.val() + 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn make_ticker() -> Box<Ticker> {
+    let mut uut = Ticker::default();
+    uut.clock.connect();
+    uut.connect_all();
+    Box::new(uut)
+}
+
+// Records the state of the ticker every 100ps, until 20ns.
+#[cfg(test)]
+fn ticker_sim(sim: &mut Simulation<Ticker>, log: std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    sim.add_phased_clock(5, 3, |x: &mut Box<Ticker>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<Ticker>| {
+        let mut x = sim.init()?;
+        while sim.time() < 20_000 {
+            x = sim.wait(100 - sim.time() % 100, x)?;
+            log.lock().unwrap().push(format!(
+                "{} {} {:?} {:?}",
+                sim.time(),
+                x.count.val().index(),
+                x.tally.val(),
+                x.mode.q.val()
+            ));
+        }
+        sim.done(x)
+    });
+}
+
+#[test]
+fn test_snapshot_resumes_simulation() {
+    let full = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let mut sim = Simulation::new();
+    ticker_sim(&mut sim, full.clone());
+    sim.run(make_ticker(), 100_000).unwrap();
+    let mut sim = Simulation::new();
+    ticker_sim(&mut sim, Default::default());
+    let snapshot = sim.run_to_snapshot(make_ticker(), 10_042).unwrap();
+    assert!(snapshot.time() < 10_042);
+    assert!(snapshot.signal("uut.counter.q").is_some());
+    assert!(snapshot.signal("uut.tally").is_some());
+    // The restored simulation must pick up exactly where the first one left off
+    let resumed = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let mut sim = Simulation::from_snapshot(&snapshot);
+    ticker_sim(&mut sim, resumed.clone());
+    sim.run(make_ticker(), 100_000).unwrap();
+    let full = full.lock().unwrap().clone();
+    let resumed = resumed.lock().unwrap().clone();
+    assert!(resumed[0].starts_with(&format!("{} ", (snapshot.time() / 100 + 1) * 100)));
+    assert!(full.ends_with(&resumed));
+}
+
+#[test]
+fn test_snapshot_save_and_load() {
+    let mut sim = Simulation::new();
+    ticker_sim(&mut sim, Default::default());
+    let snapshot = sim.run_to_snapshot(make_ticker(), 7_777).unwrap();
+    let mut saved = vec![];
+    snapshot.save(&mut saved).unwrap();
+    let text = String::from_utf8(saved.clone()).unwrap();
+    assert!(text.starts_with(&format!("time {}\nclock ", snapshot.time())));
+    assert!(text.contains("signal uut.mode.q s"));
+    let loaded = Snapshot::load(&saved[..]).unwrap();
+    assert_eq!(loaded, snapshot);
+}
+
+#[test]
+fn test_snapshot_must_match_circuit() {
+    let mut sim = Simulation::new();
+    ticker_sim(&mut sim, Default::default());
+    let snapshot = sim.run_to_snapshot(make_ticker(), 1_000).unwrap();
+    let mut saved = vec![];
+    snapshot.save(&mut saved).unwrap();
+    let text = String::from_utf8(saved)
+        .unwrap()
+        .replace("uut.count ", "uut.total ");
+    let snapshot = Snapshot::load(text.as_bytes()).unwrap();
+    let mut sim = Simulation::from_snapshot(&snapshot);
+    ticker_sim(&mut sim, Default::default());
+    match sim.run(make_ticker(), 100_000) {
+        Err(SimError::SnapshotMismatch { reason }) => {
+            assert_eq!(reason, "uut.count is missing from the snapshot")
+        }
+        x => panic!("Expected a snapshot mismatch, got {:?}", x),
+    }
+    let mut sim: Simulation<Ticker> = Simulation::from_snapshot(&snapshot);
+    sim.add_testbench(|sim: Sim<Ticker>| {
+        let x = sim.init()?;
+        sim.done(x)
+    });
+    assert!(matches!(
+        sim.run(make_ticker(), 100_000),
+        Err(SimError::SnapshotMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_async_snapshot_resumes_simulation() {
+    fn ticker_sim(
+        sim: &mut AsyncSimulation<Ticker>,
+        log: std::rc::Rc<std::cell::RefCell<Vec<String>>>,
+    ) {
+        sim.add_phased_clock(5, 3, |x: &mut Box<Ticker>| x.clock.next = !x.clock.val());
+        sim.add_testbench(move |mut sim: AsyncSim<Ticker>| async move {
+            let mut x = sim.init()?;
+            while sim.time() < 20_000 {
+                x = sim.wait(100 - sim.time() % 100, x).await?;
+                log.borrow_mut().push(format!(
+                    "{} {} {:?}",
+                    sim.time(),
+                    x.count.val().index(),
+                    x.tally.val()
+                ));
+            }
+            sim.done(x)
+        });
+    }
+    let full = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+    let mut sim = AsyncSimulation::new();
+    ticker_sim(&mut sim, full.clone());
+    sim.run(make_ticker(), 100_000).unwrap();
+    let mut sim = AsyncSimulation::new();
+    ticker_sim(&mut sim, Default::default());
+    let snapshot = sim.run_to_snapshot(make_ticker(), 12_345).unwrap();
+    let resumed = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+    let mut sim = AsyncSimulation::from_snapshot(&snapshot);
+    ticker_sim(&mut sim, resumed.clone());
+    sim.run(make_ticker(), 100_000).unwrap();
+    assert!(!resumed.borrow().is_empty());
+    assert!(full.borrow().ends_with(&resumed.borrow()));
+}
+
+#[derive(LogicBlock, Default)]
+struct Scratchpad {
+    pub clock: Signal<In, Clock>,
+    pub address: Signal<In, Bits<4>>,
+    pub data_in: Signal<In, Bits<8>>,
+    pub write: Signal<In, Bit>,
+    pub data_out: Signal<Out, Bits<8>>,
+    ram: RAM<Bits<8>, 4>,
+}
+
+impl Logic for Scratchpad {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.ram.read_clock.next = self.clock.val();
+        self.ram.write_clock.next = self.clock.val();
+        self.ram.read_address.next = self.address.val();
+        self.ram.write_address.next = self.address.val();
+        self.ram.write_data.next = self.data_in.val();
+        self.ram.write_enable.next = self.write.val();
+        self.data_out.next = self.ram.read_data.val();
+    }
+}
+
+#[cfg(test)]
+fn make_scratchpad() -> Box<Scratchpad> {
+    let mut uut = Scratchpad::default();
+    uut.clock.connect();
+    uut.address.connect();
+    uut.data_in.connect();
+    uut.write.connect();
+    uut.connect_all();
+    Box::new(uut)
+}
+
+#[test]
+fn test_snapshot_restores_ram_contents() {
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<Scratchpad>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<Scratchpad>| {
+        let mut x = sim.init()?;
+        for n in 0..16 {
+            x.address.next = n.into();
+            x.data_in.next = (3 * n + 1).into();
+            x.write.next = true;
+            wait_clock_cycle!(sim, clock, x);
+        }
+        x.write.next = false;
+        x = sim.wait(10_000, x)?;
+        sim.done(x)
+    });
+    let snapshot = sim.run_to_snapshot(make_scratchpad(), 1_000).unwrap();
+    let mut saved = vec![];
+    snapshot.save(&mut saved).unwrap();
+    let text = String::from_utf8(saved.clone()).unwrap();
+    assert!(text.contains("\nstate uut.ram "));
+    let loaded = Snapshot::load(&saved[..]).unwrap();
+    assert_eq!(loaded, snapshot);
+    // The memory of the new circuit starts out empty, so the values must come from the snapshot
+    let mut sim = Simulation::from_snapshot(&loaded);
+    sim.add_clock(5, |x: &mut Box<Scratchpad>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<Scratchpad>| {
+        let mut x = sim.init()?;
+        for n in 0..16 {
+            x.address.next = n.into();
+            wait_clock_cycle!(sim, clock, x);
+            wait_clock_true!(sim, clock, x);
+            sim_assert_eq!(sim, x.data_out.val(), 3 * n + 1, x);
+        }
+        sim.done(x)
+    });
+    sim.run(make_scratchpad(), 100_000).unwrap();
+}
+
+// A block that implements Block by hand, without accept_mut
+struct Opaque {
+    ticker: Ticker,
+}
+
+impl Logic for Opaque {
+    fn update(&mut self) {}
+}
+
+impl Block for Opaque {
+    fn connect_all(&mut self) {
+        self.ticker.connect_all();
+    }
+
+    fn update_all(&mut self) {
+        self.ticker.update_all();
+    }
+
+    fn has_changed(&self) -> bool {
+        self.ticker.has_changed()
+    }
+
+    fn accept(&self, name: &str, probe: &mut dyn Probe) {
+        self.ticker.accept(name, probe);
+    }
+}
+
+#[test]
+fn test_snapshot_requires_accept_mut() {
+    let reason = "uut does not implement accept_mut, so its state cannot be restored";
+    let uut = || {
+        Box::new(Opaque {
+            ticker: *make_ticker(),
+        })
+    };
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<Opaque>| {
+        x.ticker.clock.next = !x.ticker.clock.val()
+    });
+    match sim.run_to_snapshot(uut(), 1_000) {
+        Err(SimError::SnapshotMismatch { reason: x }) => assert_eq!(x, reason),
+        x => panic!("Expected a snapshot mismatch, got {:?}", x),
+    }
+    let mut sim = Simulation::new();
+    ticker_sim(&mut sim, Default::default());
+    let snapshot = sim.run_to_snapshot(make_ticker(), 1_000).unwrap();
+    let mut sim = Simulation::from_snapshot(&snapshot);
+    sim.add_clock(5, |x: &mut Box<Opaque>| {
+        x.ticker.clock.next = !x.ticker.clock.val()
+    });
+    match sim.run(uut(), 2_000) {
+        Err(SimError::SnapshotMismatch { reason: x }) => assert_eq!(x, reason),
+        x => panic!("Expected a snapshot mismatch, got {:?}", x),
+    }
+}
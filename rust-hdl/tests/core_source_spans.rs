@@ -0,0 +1,136 @@
+use rust_hdl::core::check_error::CheckError;
+use rust_hdl::prelude::*;
+
+const FILE: &str = "rust-hdl/tests/core_source_spans.rs";
+
+// The line of this file that follows the given marker comment
+fn line_of(marker: &str) -> u32 {
+    let tag = format!("// span: {}", marker);
+    include_str!("core_source_spans.rs")
+        .lines()
+        .position(|x| x.trim() == tag)
+        .unwrap() as u32
+        + 2
+}
+
+fn at(marker: &str) -> Option<SourceLocation> {
+    Some(SourceLocation::new(FILE, line_of(marker)))
+}
+
+#[derive(LogicBlock, Default)]
+struct Spans {
+    pub a: Signal<In, Bits<8>>,
+    pub sel: Signal<In, Bit>,
+    pub y: Signal<Out, Bits<8>>,
+    pub z: Signal<Out, Bits<8>>,
+}
+
+impl Logic for Spans {
+    #[hdl_gen]
+    fn update(&mut self) {
+        // span: increment
+        self.y.next = self.a.val() + 1;
+        // span: select
+        if self.sel.val() {
+            // span: then
+            self.z.next = self.a.val();
+        } else {
+            // span: else
+            self.z.next = 0.into();
+        }
+    }
+}
+
+#[test]
+fn test_verilog_refers_to_kernel_lines() {
+    let mut uut = Spans::default();
+    uut.a.connect();
+    uut.sel.connect();
+    uut.connect_all();
+    let vlog = generate_verilog(&uut);
+    let [increment, select, then, otherwise] = ["increment", "select", "then", "else"].map(line_of);
+    assert!(vlog.contains(&format!(
+        "(* src = \"{FILE}:{increment}\" *) y = a + 32'h1;"
+    )));
+    assert!(vlog.contains(&format!("// {FILE}:{select}\n")));
+    assert!(vlog.contains(&format!("(* src = \"{FILE}:{then}\" *) z = a;")));
+    assert!(vlog.contains(&format!("(* src = \"{FILE}:{otherwise}\" *) z = 32'h0;")));
+    yosys_validate("source_spans", &vlog).unwrap();
+    let vhdl = generate_hdl(HDLLanguage::VHDL, &uut);
+    assert!(vhdl.contains(&format!("-- {FILE}:{increment}")));
+}
+
+#[derive(LogicBlock, Default)]
+struct Undriven {
+    pub y: Signal<Out, Bits<8>>,
+    local: Signal<Local, Bits<8>>,
+}
+
+impl Logic for Undriven {
+    #[hdl_gen]
+    fn update(&mut self) {
+        // span: open
+        self.y.next = self.local.val();
+    }
+}
+
+#[test]
+fn test_open_signals_refer_to_kernel_lines() {
+    let mut uut = Undriven::default();
+    uut.connect_all();
+    let Err(CheckError::OpenSignal(map)) = check_all(&uut) else {
+        panic!("Open signal should have been found");
+    };
+    let open = map.values().find(|x| x.name == "local").unwrap();
+    assert_eq!(open.source, at("open"));
+}
+
+#[derive(LogicBlock, Default)]
+struct WritesInput {
+    pub a: Signal<In, Bit>,
+    pub y: Signal<Out, Bit>,
+}
+
+impl Logic for WritesInput {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.y.next = true;
+        // span: input
+        self.a.next = false;
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Loop {
+    pub a: Signal<In, Bits<8>>,
+    pub y: Signal<Out, Bits<8>>,
+    foo: Signal<Local, Bits<8>>,
+}
+
+impl Logic for Loop {
+    #[hdl_gen]
+    fn update(&mut self) {
+        // span: loop
+        self.y.next = self.foo.val();
+        self.foo.next = self.a.val();
+    }
+}
+
+#[test]
+fn test_check_errors_refer_to_kernel_lines() {
+    let mut uut = TopWrap::new(WritesInput::default());
+    uut.connect_all();
+    let Err(CheckError::WritesToInputs(list)) = check_all(&uut) else {
+        panic!("Write to input should have been found");
+    };
+    assert_eq!(list[0].name, "a");
+    assert_eq!(list[0].source, at("input"));
+    let mut uut = Loop::default();
+    uut.a.connect();
+    uut.connect_all();
+    let Err(CheckError::LogicLoops(list)) = check_all(&uut) else {
+        panic!("Logic loop should have been found");
+    };
+    assert_eq!(list[0].name, "foo");
+    assert_eq!(list[0].source, at("loop"));
+}
@@ -0,0 +1,127 @@
+use rust_hdl::core::check_error::CheckError;
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct Accumulator<const N: usize> {
+    pub clock: Signal<In, Clock>,
+    pub sum: Signal<Out, Bits<N>>,
+    acc: DFF<Bits<N>>,
+}
+
+impl<const N: usize> Logic for Accumulator<N> {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, acc);
+        self.acc.d.next = self.acc.q.val() + 3;
+        self.sum.next = self.acc.q.val();
+    }
+}
+
+#[test]
+fn test_adder_delay_grows_with_width() {
+    let model = DelayModel::default();
+    let narrow = estimate_timing(&Accumulator::<4>::default(), &model).unwrap();
+    let wide = estimate_timing(&Accumulator::<32>::default(), &model).unwrap();
+    assert_eq!(narrow.len(), 1);
+    assert_eq!(wide.len(), 1);
+    assert_eq!(
+        narrow[0].delay,
+        model.clock_to_out + model.carry(4) + model.setup
+    );
+    assert_eq!(
+        wide[0].delay,
+        model.clock_to_out + model.carry(32) + model.setup
+    );
+    assert_eq!(
+        wide[0].path,
+        ["top.acc.dff", "top.acc.q", "top.acc.d", "top.acc.dff"]
+    );
+    assert_eq!(wide[0].clock, "top.clock");
+}
+
+#[derive(LogicBlock, Default)]
+struct Selector {
+    pub clock: Signal<In, Clock>,
+    pub sel: Signal<In, Bits<2>>,
+    a: DFF<Bits<8>>,
+    b: DFF<Bits<8>>,
+    out: DFF<Bits<8>>,
+}
+
+impl Logic for Selector {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, a, b, out);
+        self.a.d.next = self.a.q.val();
+        self.b.d.next = self.b.q.val();
+        if self.sel.val().get_bit(0) {
+            if self.sel.val().get_bit(1) {
+                self.out.d.next = self.a.q.val();
+            } else {
+                self.out.d.next = self.b.q.val();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_nested_conditionals_add_muxes() {
+    let model = DelayModel::default();
+    let paths = estimate_timing(&Selector::default(), &model).unwrap();
+    assert_eq!(paths.len(), 1);
+    assert_eq!(
+        paths[0].delay,
+        model.clock_to_out + 2 * model.mux(2) + model.setup
+    );
+    assert_eq!(paths[0].path.last().unwrap(), "top.out.dff");
+}
+
+#[derive(LogicBlock, Default)]
+struct TwoDomains {
+    pub fast_clock: Signal<In, Clock>,
+    pub slow_clock: Signal<In, Clock>,
+    pub fast: Accumulator<4>,
+    pub slow: Accumulator<24>,
+}
+
+impl Logic for TwoDomains {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.fast.clock.next = self.fast_clock.val();
+        self.slow.clock.next = self.slow_clock.val();
+    }
+}
+
+#[test]
+fn test_paths_are_reported_per_clock_domain() {
+    let paths = estimate_timing(&TwoDomains::default(), &DelayModel::default()).unwrap();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0].clock, "top.fast_clock");
+    assert_eq!(paths[0].path.first().unwrap(), "top.fast.acc.dff");
+    assert_eq!(paths[1].clock, "top.slow_clock");
+    assert_eq!(paths[1].path.first().unwrap(), "top.slow.acc.dff");
+    assert!(paths[1].delay > paths[0].delay);
+    assert!(paths[1].max_frequency() < paths[0].max_frequency());
+}
+
+#[derive(LogicBlock, Default)]
+struct Loop {
+    pub a: Signal<Out, Bit>,
+    pub b: Signal<Out, Bit>,
+}
+
+impl Logic for Loop {
+    #[hdl_gen]
+    fn update(&mut self) {
+        self.a.next = !self.b.val();
+        self.b.next = self.a.val();
+    }
+}
+
+#[test]
+fn test_estimate_rejects_logic_loops() {
+    assert!(matches!(
+        estimate_timing(&Loop::default(), &DelayModel::default()),
+        Err(CheckError::LogicLoops(_))
+    ));
+}
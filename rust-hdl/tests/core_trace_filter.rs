@@ -0,0 +1,175 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct Inner {
+    pub clock: Signal<In, Clock>,
+    pub count: Signal<Out, Bits<4>>,
+    counter: DFF<Bits<4>>,
+}
+
+impl Logic for Inner {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, counter);
+        self.counter.d.next = self.counter.q.val() + 1;
+        self.count.next = self.counter.q.val();
+    }
+}
+
+#[derive(LogicBlock, Default)]
+struct Outer {
+    pub clock: Signal<In, Clock>,
+    pub left_count: Signal<Out, Bits<4>>,
+    pub right_count: Signal<Out, Bits<4>>,
+    left: Inner,
+    right: Inner,
+}
+
+impl Logic for Outer {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, left, right);
+        self.left_count.next = self.left.count.val();
+        self.right_count.next = self.right.count.val();
+    }
+}
+
+#[cfg(test)]
+fn make_sim() -> (Simulation<Outer>, Box<Outer>) {
+    let mut uut = Outer::default();
+    uut.clock.connect();
+    uut.connect_all();
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<Outer>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<Outer>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, clock, x, 8);
+        sim.done(x)
+    });
+    (sim, Box::new(uut))
+}
+
+#[cfg(test)]
+fn traced_vars(filter: &TraceFilter) -> (vcd::Header, usize) {
+    let (mut sim, uut) = make_sim();
+    let mut trace = vec![];
+    sim.run_traced_filtered(uut, 1000, &mut trace, filter)
+        .unwrap();
+    let mut parser = vcd::Parser::new(&trace[..]);
+    let header = parser.parse_header().unwrap();
+    let changes = parser.count();
+    (header, changes)
+}
+
+#[test]
+fn test_default_filter_traces_everything() {
+    let (header, _) = traced_vars(&TraceFilter::default());
+    assert!(header.find_var(&["uut", "clock"]).is_some());
+    assert!(header.find_var(&["uut", "left", "count"]).is_some());
+    assert!(header.find_var(&["uut", "right", "counter", "q"]).is_some());
+}
+
+#[test]
+fn test_path_filter() {
+    let (all, all_changes) = traced_vars(&TraceFilter::default());
+    let (header, changes) = traced_vars(&TraceFilter::default().path("uut.left"));
+    assert!(header.find_var(&["uut", "left", "count"]).is_some());
+    assert!(header.find_var(&["uut", "left", "counter", "q"]).is_some());
+    assert!(header.find_var(&["uut", "clock"]).is_none());
+    assert!(header.find_scope(&["uut", "right"]).is_none());
+    assert!(all.find_var(&["uut", "right", "count"]).is_some());
+    assert!(changes < all_changes);
+    let (header, _) = traced_vars(
+        &TraceFilter::default()
+            .path("uut.right.count")
+            .path("uut.clock"),
+    );
+    assert!(header.find_var(&["uut", "right", "count"]).is_some());
+    assert!(header.find_var(&["uut", "right", "clock"]).is_none());
+    assert!(header.find_var(&["uut", "clock"]).is_some());
+    assert!(header.find_var(&["uut", "left_count"]).is_none());
+}
+
+#[test]
+fn test_depth_filter() {
+    let (header, _) = traced_vars(&TraceFilter::default().max_depth(0));
+    assert!(header.find_var(&["uut", "clock"]).is_some());
+    assert!(header.find_var(&["uut", "left_count"]).is_some());
+    assert!(header.find_scope(&["uut", "left"]).is_none());
+    let (header, _) = traced_vars(&TraceFilter::default().max_depth(1));
+    assert!(header.find_var(&["uut", "left", "count"]).is_some());
+    assert!(header.find_scope(&["uut", "left", "counter"]).is_none());
+}
+
+#[test]
+#[ignore = "requires vcd2fst and fst2vcd"]
+fn test_fst_trace() {
+    let (mut sim, uut) = make_sim();
+    let name = std::env::temp_dir().join("trace_filter.fst");
+    sim.run_to_fst(
+        uut,
+        1000,
+        name.to_str().unwrap(),
+        &TraceFilter::default().max_depth(1),
+    )
+    .unwrap();
+    // Convert the trace back to VCD, and check that it holds the filtered signals
+    let vcd = std::process::Command::new("fst2vcd")
+        .arg(&name)
+        .output()
+        .unwrap()
+        .stdout;
+    let mut parser = vcd::Parser::new(&vcd[..]);
+    let header = parser.parse_header().unwrap();
+    assert!(header.find_var(&["uut", "left", "count"]).is_some());
+    assert!(header.find_scope(&["uut", "left", "counter"]).is_none());
+    assert!(parser.count() > 0);
+}
+
+// A trace that fails once `limit` bytes have been written to it (like a pipe to a
+// converter that exited)
+struct FailingTrace {
+    limit: usize,
+}
+
+impl std::io::Write for FailingTrace {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.len() > self.limit {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        }
+        self.limit -= buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_trace_write_errors_stop_the_simulation() {
+    let (mut sim, uut) = make_sim();
+    let mut trace = vec![];
+    sim.run_traced(uut, 1000, &mut trace).unwrap();
+    // The trace fails in the header, in the initial dump, and part way through
+    let end = b"$enddefinitions";
+    let header = trace.windows(end.len()).position(|x| x == end).unwrap();
+    for limit in [0, header + 20, trace.len() / 2] {
+        let (mut sim, uut) = make_sim();
+        let result = sim.run_traced(uut, 1000, FailingTrace { limit });
+        assert!(matches!(result, Err(SimError::TraceFailed { .. })));
+    }
+}
+
+#[test]
+fn test_trace_errors_are_reported() {
+    // Whether or not vcd2fst is installed, the trace cannot be written here
+    let name = std::env::temp_dir().join("no_such_dir").join("trace.fst");
+    let (mut sim, uut) = make_sim();
+    let result = sim.run_to_fst(uut, 1000, name.to_str().unwrap(), &TraceFilter::default());
+    assert!(matches!(result, Err(SimError::TraceFailed { .. })));
+    let name = std::env::temp_dir().join("no_such_dir").join("trace.vcd");
+    let (mut sim, uut) = make_sim();
+    let result = sim.run_to_file(uut, 1000, name.to_str().unwrap());
+    assert!(matches!(result, Err(SimError::TraceFailed { .. })));
+}
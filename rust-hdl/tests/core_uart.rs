@@ -0,0 +1,209 @@
+use rust_hdl::prelude::*;
+
+fn uart_config(parity: UARTParity, stop_bits: usize) -> UARTConfig {
+    UARTConfig {
+        clock_speed: 32_000_000,
+        baud_rate: 1_000_000,
+        parity,
+        stop_bits,
+    }
+}
+
+#[derive(LogicBlock)]
+struct UARTLoopback {
+    pub clock: Signal<In, Clock>,
+    pub rx: Signal<In, Bit>,
+    pub tx: Signal<Out, Bit>,
+    transmitter: UARTTransmitter,
+    receiver: UARTReceiver,
+    fifo: SynchronousFIFO<Bits<8>, 4, 5, 1>,
+}
+
+impl UARTLoopback {
+    fn new(config: UARTConfig) -> Self {
+        Self {
+            clock: Default::default(),
+            rx: Default::default(),
+            tx: Default::default(),
+            transmitter: UARTTransmitter::new(config),
+            receiver: UARTReceiver::new(config),
+            fifo: Default::default(),
+        }
+    }
+}
+
+impl Logic for UARTLoopback {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, transmitter, receiver, fifo);
+        // Echo every good byte back (plus one)
+        self.receiver.rx.next = self.rx.val();
+        self.fifo.data_in.next = self.receiver.data.val() + 1;
+        self.fifo.write.next = self.receiver.data_valid.val()
+            & !self.receiver.framing_error.val()
+            & !self.receiver.parity_error.val();
+        self.transmitter.data.next = self.fifo.data_out.val();
+        self.transmitter.start_send.next = !self.fifo.empty.val() & !self.transmitter.busy.val();
+        self.fifo.read.next = !self.fifo.empty.val() & !self.transmitter.busy.val();
+        self.tx.next = self.transmitter.tx.val();
+    }
+}
+
+fn loopback(config: UARTConfig) -> UARTLoopback {
+    let mut uut = UARTLoopback::new(config);
+    uut.clock.connect();
+    uut.rx.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_uart_loopback_is_synthesizable() {
+    let vlog = generate_verilog(&loopback(uart_config(UARTParity::Even, 1)));
+    yosys_validate("uart_loopback", &vlog).unwrap();
+}
+
+fn test_uart_echo(config: UARTConfig, name: &str) {
+    let data = [0x00_u8, 0x41, 0x5A, 0xA5, 0xFE, 0x7F];
+    let expected = data.iter().map(|x| x.wrapping_add(1)).collect::<Vec<_>>();
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<UARTLoopback>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<UARTLoopback>| {
+        let mut x = sim.init()?;
+        x.rx.next = true;
+        wait_clock_cycles!(sim, clock, x, 100);
+        uart_send!(sim, clock, x, rx, config, &data);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<UARTLoopback>| {
+        let mut x = sim.init()?;
+        wait_clock_cycles!(sim, clock, x, 100);
+        let received = uart_receive!(sim, clock, x, tx, config, expected.len());
+        sim_assert_eq!(sim, received, expected, x);
+        sim.done(x)
+    });
+    sim.run_traced(
+        Box::new(loopback(config)),
+        200_000,
+        std::fs::File::create(vcd_path!(format!("uart_echo_{}.vcd", name))).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_uart_echo_8n1() {
+    test_uart_echo(uart_config(UARTParity::None, 1), "8n1");
+}
+
+#[test]
+fn test_uart_echo_8e1() {
+    test_uart_echo(uart_config(UARTParity::Even, 1), "8e1");
+}
+
+#[test]
+fn test_uart_echo_8o2() {
+    test_uart_echo(uart_config(UARTParity::Odd, 2), "8o2");
+}
+
+#[derive(LogicBlock)]
+struct UARTErrorCheck {
+    pub clock: Signal<In, Clock>,
+    receiver: UARTReceiver,
+}
+
+impl Logic for UARTErrorCheck {
+    #[hdl_gen]
+    fn update(&mut self) {
+        clock!(self, clock, receiver);
+    }
+}
+
+#[test]
+fn test_uart_receiver_reports_errors() {
+    let config = uart_config(UARTParity::Even, 1);
+    let mut uut = UARTErrorCheck {
+        clock: Default::default(),
+        receiver: UARTReceiver::new(config),
+    };
+    uut.clock.connect();
+    uut.receiver.rx.connect();
+    uut.connect_all();
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<UARTErrorCheck>| {
+        x.clock.next = !x.clock.val()
+    });
+    sim.add_testbench(move |mut sim: Sim<UARTErrorCheck>| {
+        let mut x = sim.init()?;
+        x.receiver.rx.next = true;
+        wait_clock_cycles!(sim, clock, x, 100);
+        // A good frame
+        uart_send!(sim, clock, x, receiver.rx, config, &[0x3C_u8]);
+        // A frame with the wrong parity (as sent by an odd parity device)
+        uart_send!(
+            sim,
+            clock,
+            x,
+            receiver.rx,
+            uart_config(UARTParity::Odd, 1),
+            &[0x3C_u8]
+        );
+        // A frame with a low stop bit, followed by an idle line
+        for bit in config.frame(0x81).iter().take(10) {
+            x.receiver.rx.next = *bit;
+            wait_clock_cycles!(sim, clock, x, config.clocks_per_bit());
+        }
+        x.receiver.rx.next = false;
+        wait_clock_cycles!(sim, clock, x, config.clocks_per_bit());
+        x.receiver.rx.next = true;
+        wait_clock_cycles!(sim, clock, x, config.clocks_per_bit() * 4);
+        sim.done(x)
+    });
+    sim.add_testbench(move |mut sim: Sim<UARTErrorCheck>| {
+        let mut x = sim.init()?;
+        let mut results = vec![];
+        for _ in 0..3 {
+            x = sim.watch(|x| x.receiver.data_valid.val(), x)?;
+            results.push((
+                x.receiver.data.val().index() as u8,
+                x.receiver.parity_error.val(),
+                x.receiver.framing_error.val(),
+            ));
+            wait_clock_cycle!(sim, clock, x);
+        }
+        sim_assert_eq!(
+            sim,
+            results,
+            vec![
+                (0x3C, false, false),
+                (0x3C, true, false),
+                (0x81, false, true)
+            ],
+            x
+        );
+        sim.done(x)
+    });
+    sim.run(Box::new(uut), 200_000).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "at least 16 clocks per bit")]
+fn test_uart_receiver_rejects_fast_baud_rates() {
+    let _ = UARTReceiver::new(UARTConfig {
+        clock_speed: 12_000_000,
+        baud_rate: 1_000_000,
+        parity: UARTParity::None,
+        stop_bits: 1,
+    });
+}
+
+#[test]
+#[should_panic(expected = "timing error")]
+fn test_uart_receiver_rejects_inexact_baud_rates() {
+    // 40 clocks per bit is sampled every 3 clocks (48 clocks per bit)
+    let _ = UARTReceiver::new(UARTConfig {
+        clock_speed: 40_000_000,
+        baud_rate: 1_000_000,
+        parity: UARTParity::None,
+        stop_bits: 1,
+    });
+}
@@ -0,0 +1,141 @@
+use rust_hdl::prelude::*;
+use rust_hdl_core::verilator::{cosim_ports, verilator_encode_name, verilator_wrapper};
+
+#[derive(LogicInterface, Default)]
+struct WideBus {
+    pub data: Signal<Out, Bits<80>>,
+    pub strobe: Signal<Out, Bit>,
+}
+
+#[derive(LogicBlock, Default)]
+struct CosimCounter {
+    pub clock: Signal<In, Clock>,
+    pub enable: Signal<In, Bit>,
+    pub count: Signal<Out, Bits<40>>,
+    pub bus: WideBus,
+    counter: DFF<Bits<40>>,
+}
+
+impl Logic for CosimCounter {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, counter);
+        if self.enable.val() {
+            self.counter.d.next = self.counter.q.val() + 1;
+        }
+        self.count.next = self.counter.q.val();
+        self.bus.data.next = bit_cast::<80, 40>(self.counter.q.val()) << 40;
+        self.bus.strobe.next = self.counter.q.val().get_bit(0);
+    }
+}
+
+// The Rust model inverts the output, but the Verilog does not.
+#[derive(LogicBlock, Default)]
+struct Mismatch {
+    pub a: Signal<In, Bits<4>>,
+    pub b: Signal<Out, Bits<4>>,
+}
+
+impl Logic for Mismatch {
+    fn update(&mut self) {
+        self.b.next = if self.a.val() == 3 {
+            0.into()
+        } else {
+            self.a.val()
+        };
+    }
+    fn connect(&mut self) {
+        self.b.connect();
+    }
+    fn hdl(&self) -> Verilog {
+        Verilog::Custom("assign b = a;".into())
+    }
+}
+
+#[cfg(test)]
+fn make_counter() -> CosimCounter {
+    let mut uut = CosimCounter::default();
+    uut.clock.connect();
+    uut.enable.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_verilator_names_are_encoded() {
+    assert_eq!(verilator_encode_name("count"), "count");
+    assert_eq!(verilator_encode_name("bus$data"), "bus__024data");
+    assert_eq!(verilator_encode_name("a__b"), "a___05Fb");
+    let ports = cosim_ports(&make_counter());
+    let names = ports
+        .iter()
+        .map(|x| {
+            (
+                x.path.as_str(),
+                x.verilated_name.as_str(),
+                x.bits,
+                x.is_input,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            ("uut.clock", "clock", 1, true),
+            ("uut.enable", "enable", 1, true),
+            ("uut.count", "count", 40, false),
+            ("uut.bus.data", "bus__024data", 80, false),
+            ("uut.bus.strobe", "bus__024strobe", 1, false),
+        ]
+    );
+    let wrapper = verilator_wrapper(&ports);
+    assert!(wrapper.contains("case 0: m->clock = w[0]; break;"));
+    assert!(wrapper.contains("w[0] = (IData) m->count; w[1] = (IData) (m->count >> 32);"));
+    assert!(wrapper.contains("for (int i=0;i<3;i++) w[i] = m->bus__024data[i];"));
+    assert!(!wrapper.contains("m->count = "));
+}
+
+#[test]
+#[ignore = "requires verilator"]
+fn test_verilator_cosim_matches() {
+    let uut = make_counter();
+    let cosim = VerilatorCosim::new("cosim_counter", &uut).unwrap();
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<CosimCounter>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<CosimCounter>| {
+        let mut x = sim.init()?;
+        for i in 0..100 {
+            x.enable.next = i % 3 != 0;
+            wait_clock_cycle!(sim, clock, x);
+        }
+        sim.done(x)
+    });
+    sim.run_cosim(Box::new(uut), 10_000, cosim).unwrap();
+}
+
+#[test]
+#[ignore = "requires verilator"]
+fn test_verilator_cosim_reports_divergence() {
+    let mut uut = Mismatch::default();
+    uut.a.connect();
+    uut.connect_all();
+    let cosim = VerilatorCosim::new("cosim_mismatch", &uut).unwrap();
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |mut sim: Sim<Mismatch>| {
+        let mut x = sim.init()?;
+        for i in 0..8 {
+            x.a.next = i.into();
+            x = sim.wait(10, x)?;
+        }
+        sim.done(x)
+    });
+    assert_eq!(
+        sim.run_cosim(Box::new(uut), 1_000, cosim),
+        Err(SimError::CosimDivergence {
+            time: 30,
+            signal: "uut.b".into(),
+            rust: "0x00000000".into(),
+            verilog: "0x00000003".into(),
+        })
+    );
+}
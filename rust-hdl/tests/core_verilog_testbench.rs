@@ -0,0 +1,82 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct Accumulator {
+    pub clock: Signal<In, Clock>,
+    pub data: Signal<In, Bits<8>>,
+    pub sum: Signal<Out, Bits<16>>,
+    acc: DFF<Bits<16>>,
+}
+
+impl Logic for Accumulator {
+    #[hdl_gen]
+    fn update(&mut self) {
+        dff_setup!(self, clock, acc);
+        self.acc.d.next = self.acc.q.val() + bit_cast::<16, 8>(self.data.val());
+        self.sum.next = self.acc.q.val();
+    }
+}
+
+#[cfg(test)]
+fn make_accumulator() -> Accumulator {
+    let mut uut = Accumulator::default();
+    uut.clock.connect();
+    uut.data.connect();
+    uut.connect_all();
+    uut
+}
+
+#[cfg(test)]
+fn accumulator_testbench() -> String {
+    let mut sim = Simulation::new();
+    sim.add_clock(5, |x: &mut Box<Accumulator>| x.clock.next = !x.clock.val());
+    sim.add_testbench(move |mut sim: Sim<Accumulator>| {
+        let mut x = sim.init()?;
+        for i in 0..10 {
+            x.data.next = (i * 3).into();
+            wait_clock_cycle!(sim, clock, x);
+        }
+        sim_assert_eq!(sim, x.sum.val(), 135, x);
+        sim.done(x)
+    });
+    let mut tb = vec![];
+    sim.run_to_testbench(Box::new(make_accumulator()), 10_000, &mut tb)
+        .unwrap();
+    String::from_utf8(tb).unwrap()
+}
+
+#[test]
+fn test_testbench_replays_stimulus() {
+    let tb = accumulator_testbench();
+    assert!(tb.contains("module top_tb;"));
+    assert!(tb.contains("reg clock;"));
+    assert!(tb.contains("reg [7:0] data;"));
+    assert!(tb.contains("wire [15:0] sum;"));
+    assert!(tb.contains("top uut(.clock(clock), .data(data), .sum(sum));"));
+    assert!(tb.contains("data = 8'h1b;"));
+    assert!(tb.contains("#4.9;"));
+    assert!(tb.contains("if (sum !== 16'h87) begin"));
+    assert!(tb.contains("on uut.sum: expected 16'h87"));
+}
+
+#[test]
+fn test_testbench_not_written_on_failure() {
+    let mut sim = Simulation::new();
+    sim.add_testbench(move |sim: Sim<Accumulator>| {
+        let x = sim.init()?;
+        sim.halt(x)
+    });
+    let mut tb = vec![];
+    assert!(sim
+        .run_to_testbench(Box::new(make_accumulator()), 10_000, &mut tb)
+        .is_err());
+    assert!(tb.is_empty());
+}
+
+#[test]
+#[ignore = "requires iverilog"]
+fn test_testbench_passes_with_iverilog() {
+    let tb = accumulator_testbench();
+    let vlog = generate_verilog(&make_accumulator());
+    iverilog_run_testbench("accumulator_tb", &vlog, &tb).unwrap();
+}
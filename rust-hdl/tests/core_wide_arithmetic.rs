@@ -0,0 +1,66 @@
+use rust_hdl::prelude::*;
+
+#[derive(LogicBlock, Default)]
+struct ComplexMultiply {
+    pub a_re: Signal<In, Signed<8>>,
+    pub a_im: Signal<In, Signed<8>>,
+    pub b_re: Signal<In, Signed<8>>,
+    pub b_im: Signal<In, Signed<8>>,
+    pub re: Signal<Out, Signed<17>>,
+    pub im: Signal<Out, Signed<17>>,
+    pub count: Signal<In, Bits<8>>,
+    pub total: Signal<Out, Bits<9>>,
+    pub square: Signal<Out, Bits<16>>,
+}
+
+impl Logic for ComplexMultiply {
+    #[hdl_gen]
+    fn update(&mut self) {
+        let rr: Signed<16> = self.a_re.val().wide_mul(self.b_re.val());
+        let ii: Signed<16> = self.a_im.val().wide_mul(self.b_im.val());
+        let ri: Signed<16> = self.a_re.val().wide_mul(self.b_im.val());
+        let ir: Signed<16> = self.a_im.val().wide_mul(self.b_re.val());
+        self.re.next = rr.wide_sub(ii);
+        self.im.next = ri.wide_add(ir);
+        self.total.next = self.count.val().wide_add(bits::<8>(255));
+        self.square.next = self.count.val().wide_mul(self.count.val());
+    }
+}
+
+fn complex_multiply() -> ComplexMultiply {
+    let mut uut = ComplexMultiply::default();
+    uut.a_re.connect();
+    uut.a_im.connect();
+    uut.b_re.connect();
+    uut.b_im.connect();
+    uut.count.connect();
+    uut.connect_all();
+    uut
+}
+
+#[test]
+fn test_wide_arithmetic_verilog() {
+    let vlog = generate_verilog(&complex_multiply());
+    assert!(vlog.contains("function signed [15:0] wide_s8_mul_8;"));
+    assert!(vlog.contains("function signed [16:0] wide_s16_sub_16;"));
+    assert!(vlog.contains("function [8:0] wide_u8_add_8;"));
+    assert!(vlog.contains("_rr = wide_s8_mul_8(a_re, b_re);"));
+    assert!(vlog.contains("re = wide_s16_sub_16(_rr, _ii);"));
+    assert!(vlog.contains("square = wide_u8_mul_8(count, count);"));
+    yosys_validate("wide_arithmetic", &vlog).unwrap();
+}
+
+#[test]
+fn test_wide_arithmetic_simulation() {
+    let mut uut = complex_multiply();
+    uut.a_re.next = signed(-128);
+    uut.a_im.next = signed(-128);
+    uut.b_re.next = signed(-128);
+    uut.b_im.next = signed(127);
+    uut.count.next = 255.into();
+    simulate(&mut uut, 10);
+    assert_eq!(uut.re.val(), signed::<17>(128 * 128 + 128 * 127));
+    assert_eq!(uut.im.val(), signed::<17>(-128 * 127 + 128 * 128));
+    assert_eq!(uut.total.val(), 510);
+    assert_eq!(uut.square.val(), 255 * 255);
+}
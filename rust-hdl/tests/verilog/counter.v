@@ -0,0 +1,25 @@
+// A free running counter with a synchronous reset and a load port.
+module counter #(
+    parameter WIDTH = 8,
+    parameter STEP = 1
+) (
+    input wire clk,
+    input wire reset,
+    input wire load,
+    input wire [WIDTH-1:0] value,
+    output reg [WIDTH-1:0] count,
+    output wire [$clog2(WIDTH)-1:0] low
+);
+
+    assign low = count[$clog2(WIDTH)-1:0];
+
+    always @(posedge clk) begin
+        if (reset)
+            count <= 0;
+        else if (load)
+            count <= value;
+        else
+            count <= count + STEP;
+    end
+
+endmodule